@@ -0,0 +1,168 @@
+//! Grid throughput with thousands of concurrent workers: `cargo bench --bench grid`.
+//!
+//! Each worker runs the grid calls a request makes on the engine's hot path (lease,
+//! latency, outcome, success or failure) and claims a new node every `SESSION_REQUESTS`
+//! requests, as `per_session` rotation would. Prints requests per second through the
+//! grid for 1k to 5k workers, with and without `network.diversity`, next to a baseline
+//! that keeps every node behind one `Mutex`, as the grid did before per-node atomics.
+
+use spectre::engine::grid::{GridManager, NodeLease, SelectionPolicy};
+use spectre::engine::{ShutdownState, Verdict};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const NODES: usize = 500;
+const SESSION_REQUESTS: usize = 20;
+const RUN: Duration = Duration::from_secs(3);
+
+/// The same bookkeeping under a single lock: every call takes it.
+#[derive(Default)]
+struct BaselineNode {
+    failures: usize,
+    latency_ms: f64,
+    requests: usize,
+    successes: usize,
+    leased: usize,
+}
+
+struct BaselineGrid {
+    inner: Mutex<(usize, Vec<BaselineNode>)>,
+}
+
+impl BaselineGrid {
+    fn new(nodes: usize) -> Self {
+        Self { inner: Mutex::new((0, (0..nodes).map(|_| BaselineNode::default()).collect())) }
+    }
+
+    fn claim(&self) -> usize {
+        let mut inner = self.inner.lock().unwrap();
+        let (cursor, nodes) = &mut *inner;
+        let total = nodes.len();
+        let start = *cursor;
+        let picked = (0..total).map(|k| (start + k) % total).find(|&i| nodes[i].failures < 3).unwrap_or(start % total);
+        *cursor = picked + 1;
+        picked
+    }
+
+    fn with(&self, node: usize, f: impl FnOnce(&mut BaselineNode)) {
+        f(&mut self.inner.lock().unwrap().1[node]);
+    }
+}
+
+fn proxies() -> Vec<String> {
+    (0..NODES).map(|i| format!("http://10.{}.{}.1:8080", i / 250, i % 250)).collect()
+}
+
+async fn measure(workers: usize, spawn: impl Fn(usize, Arc<AtomicUsize>, Arc<AtomicBool>) -> tokio::task::JoinHandle<()>) -> f64 {
+    let requests = Arc::new(AtomicUsize::new(0));
+    let stop = Arc::new(AtomicBool::new(false));
+    let handles: Vec<_> = (0..workers).map(|worker| spawn(worker, requests.clone(), stop.clone())).collect();
+    let started = Instant::now();
+    tokio::time::sleep(RUN).await;
+    stop.store(true, Ordering::Relaxed);
+    let done = requests.load(Ordering::Relaxed);
+    let elapsed = started.elapsed().as_secs_f64();
+    for handle in handles {
+        let _ = handle.await;
+    }
+    done as f64 / elapsed
+}
+
+async fn run(workers: usize, policy: SelectionPolicy, diversity: bool) -> f64 {
+    let proxies = proxies();
+    let groups: HashMap<String, String> = proxies.iter().map(|p| (p.clone(), p.clone())).collect();
+    let grid = Arc::new(GridManager::new(proxies, false).with_policy(policy).with_diversity(diversity));
+    grid.set_groups(&groups);
+    let shutdown = ShutdownState::default();
+
+    measure(workers, |worker, requests, stop| {
+        let (grid, shutdown) = (grid.clone(), shutdown.clone());
+        tokio::spawn(async move {
+            let mut n = worker;
+            while !stop.load(Ordering::Relaxed) {
+                let Some((node, _, claim)) = grid.claim_next_node() else {
+                    tokio::task::yield_now().await;
+                    continue;
+                };
+                for _ in 0..SESSION_REQUESTS {
+                    let Some(lease) = NodeLease::acquire(&grid, &node, &shutdown).await else {
+                        return;
+                    };
+                    grid.record_latency(&node, Duration::from_millis(80 + (n % 40) as u64));
+                    n = n.wrapping_add(1);
+                    if n % 50 == 0 {
+                        grid.record_outcome(&node, None, 0);
+                        grid.report_failure(&node);
+                    } else {
+                        let verdict = if n % 10 == 0 { Verdict::Blocked("bench".into()) } else { Verdict::Success };
+                        grid.record_outcome(&node, Some(&verdict), 4096);
+                        grid.report_success(&node);
+                    }
+                    drop(lease);
+                    requests.fetch_add(1, Ordering::Relaxed);
+                    tokio::task::yield_now().await;
+                }
+                drop(claim);
+            }
+        })
+    })
+    .await
+}
+
+async fn run_baseline(workers: usize) -> f64 {
+    let grid = Arc::new(BaselineGrid::new(NODES));
+    measure(workers, |worker, requests, stop| {
+        let grid = grid.clone();
+        tokio::spawn(async move {
+            let mut n = worker;
+            while !stop.load(Ordering::Relaxed) {
+                let node = grid.claim();
+                for _ in 0..SESSION_REQUESTS {
+                    grid.with(node, |s| s.leased += 1);
+                    let latency = (80 + n % 40) as f64;
+                    grid.with(node, |s| s.latency_ms = s.latency_ms * 0.8 + latency * 0.2);
+                    n = n.wrapping_add(1);
+                    let failed = n % 50 == 0;
+                    grid.with(node, |s| {
+                        s.requests += 1;
+                        if failed {
+                            s.failures += 1;
+                        } else {
+                            s.successes += 1;
+                            s.failures = 0;
+                        }
+                    });
+                    grid.with(node, |s| s.leased -= 1);
+                    requests.fetch_add(1, Ordering::Relaxed);
+                    tokio::task::yield_now().await;
+                }
+            }
+        })
+    })
+    .await
+}
+
+#[tokio::main(flavor = "multi_thread")]
+async fn main() {
+    println!("{} nodes, {} requests per session, {}s per case", NODES, SESSION_REQUESTS, RUN.as_secs());
+    for workers in [1000, 2000, 5000] {
+        let baseline = run_baseline(workers).await;
+        println!("{:<24} {:>5} workers: {:>12.0} requests/s", "Mutex baseline", workers, baseline);
+        for (policy, diversity) in [
+            (SelectionPolicy::RoundRobin, false),
+            (SelectionPolicy::RoundRobin, true),
+            (SelectionPolicy::Weighted, false),
+        ] {
+            let rate = run(workers, policy, diversity).await;
+            println!(
+                "{:<24} {:>5} workers: {:>12.0} requests/s ({:.2}x baseline)",
+                format!("{:?}{}", policy, if diversity { " +diversity" } else { "" }),
+                workers,
+                rate,
+                rate / baseline
+            );
+        }
+    }
+}
@@ -0,0 +1,329 @@
+use crate::dates::UtcDateTime;
+use crate::engine::SpectreLogger;
+use anyhow::{Context, Result};
+use chromiumoxide::cdp::browser_protocol::network::{
+    self, EventLoadingFailed, EventLoadingFinished, EventRequestWillBeSent, EventResponseReceived, Headers, Response,
+};
+use chromiumoxide::cdp::js_protocol::runtime::{self, ConsoleApiCalledType, EventConsoleApiCalled, EventExceptionThrown, RemoteObject};
+use chromiumoxide::Page;
+use futures::StreamExt;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// What the solver's page did while a solve ran. A solve that stalls says nothing about why:
+// the challenge script may have failed to load, been blocked, or thrown. With
+// `[browser.activity]` set, every response (URL, status, resource type), every failed
+// request and every console error, warning and uncaught exception goes into the JSONL log
+// as it happens, and each solve's traffic can be written out as a HAR file besides.
+
+/// `[browser.activity]`.
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct ActivityConfig {
+    /// Responses and failed requests, as `BROWSER_NET` events.
+    #[serde(default = "crate::stealth::on")]
+    pub network: bool,
+    /// Console errors and warnings and uncaught exceptions, as `BROWSER_CONSOLE` events.
+    #[serde(default = "crate::stealth::on")]
+    pub console: bool,
+    /// Also write each solve's traffic to `<har_dir>/<worker>-<unix ms>.har`.
+    #[serde(default)]
+    pub har_dir: Option<String>,
+}
+
+/// A request seen starting, until its response body finishes or it fails.
+struct Pending {
+    wall_time: f64,
+    started: f64,
+    request: Value,
+    response: Option<Value>,
+}
+
+#[derive(Default)]
+struct Har {
+    pending: HashMap<String, Pending>,
+    entries: Vec<Value>,
+}
+
+impl Har {
+    fn start(&mut self, event: &EventRequestWillBeSent) {
+        let id = event.request_id.inner().clone();
+        // A redirect reuses the request id; the hop before it ends here
+        if let Some(redirect) = &event.redirect_response {
+            if let Some(mut hop) = self.pending.remove(&id) {
+                hop.response = Some(har_response(redirect));
+                self.finish(hop, *event.timestamp.inner(), None);
+            }
+        }
+        let request = &event.request;
+        self.pending.insert(
+            id,
+            Pending {
+                wall_time: *event.wall_time.inner(),
+                started: *event.timestamp.inner(),
+                request: json!({
+                    "method": request.method,
+                    "url": request.url,
+                    "httpVersion": "",
+                    "headers": har_headers(&request.headers),
+                    "queryString": [],
+                    "cookies": [],
+                    "headersSize": -1,
+                    "bodySize": -1,
+                }),
+                response: None,
+            },
+        );
+    }
+
+    fn respond(&mut self, event: &EventResponseReceived) {
+        if let Some(pending) = self.pending.get_mut(event.request_id.inner()) {
+            pending.response = Some(har_response(&event.response));
+        }
+    }
+
+    fn finish(&mut self, pending: Pending, timestamp: f64, error: Option<&str>) {
+        let time = ((timestamp - pending.started) * 1000.0).max(0.0);
+        let response = pending.response.unwrap_or_else(|| {
+            json!({
+                "status": 0,
+                "statusText": "",
+                "httpVersion": "",
+                "headers": [],
+                "cookies": [],
+                "content": { "size": 0, "mimeType": "" },
+                "redirectURL": "",
+                "headersSize": -1,
+                "bodySize": -1,
+            })
+        });
+        let mut entry = json!({
+            "startedDateTime": iso8601(pending.wall_time),
+            "time": time,
+            "request": pending.request,
+            "response": response,
+            "cache": {},
+            "timings": { "send": 0, "wait": time, "receive": 0 },
+        });
+        if let Some(error) = error {
+            entry["_error"] = json!(error);
+        }
+        self.entries.push(entry);
+    }
+
+    /// The HAR 1.2 document; requests still running are included as they stand.
+    fn document(mut self) -> Value {
+        let pending: Vec<Pending> = self.pending.drain().map(|(_, p)| p).collect();
+        for p in pending {
+            let started = p.started;
+            self.finish(p, started, Some("unfinished"));
+        }
+        self.entries.sort_by(|a, b| a["startedDateTime"].as_str().cmp(&b["startedDateTime"].as_str()));
+        json!({
+            "log": {
+                "version": "1.2",
+                "creator": { "name": "spectre", "version": env!("CARGO_PKG_VERSION") },
+                "pages": [],
+                "entries": self.entries,
+            }
+        })
+    }
+}
+
+/// Headers whose values are credentials; the HAR keeps the name and drops the value.
+const REDACTED_HEADERS: [&str; 4] = ["authorization", "cookie", "set-cookie", "proxy-authorization"];
+
+fn har_headers(headers: &Headers) -> Value {
+    let pairs: Vec<Value> = headers
+        .inner()
+        .as_object()
+        .map(|map| {
+            map.iter()
+                .map(|(name, value)| {
+                    let value = if REDACTED_HEADERS.iter().any(|h| name.eq_ignore_ascii_case(h)) {
+                        "[redacted]"
+                    } else {
+                        value.as_str().unwrap_or_default()
+                    };
+                    json!({ "name": name, "value": value })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    Value::Array(pairs)
+}
+
+fn har_response(response: &Response) -> Value {
+    json!({
+        "status": response.status,
+        "statusText": response.status_text,
+        "httpVersion": response.protocol.clone().unwrap_or_default(),
+        "headers": har_headers(&response.headers),
+        "cookies": [],
+        "content": { "size": response.encoded_data_length, "mimeType": response.mime_type },
+        "redirectURL": "",
+        "headersSize": -1,
+        "bodySize": -1,
+    })
+}
+
+/// `2024-05-01T12:00:00.123Z`, from seconds since the epoch.
+fn iso8601(wall_time: f64) -> String {
+    let wall_time = wall_time.max(0.0);
+    UtcDateTime::from_unix(wall_time as u64).iso8601((wall_time.fract() * 1000.0) as u32)
+}
+
+/// A console message's arguments as the console would print them.
+fn console_text(args: &[RemoteObject]) -> String {
+    args.iter()
+        .map(|arg| match (&arg.value, &arg.description) {
+            (Some(Value::String(s)), _) => s.clone(),
+            (Some(value), _) => value.to_string(),
+            (None, Some(description)) => description.clone(),
+            (None, None) => arg.unserializable_value.as_ref().map(|v| v.inner().clone()).unwrap_or_default(),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Records one solve's page activity until `finish`; dropping it stops recording.
+pub struct ActivityRecorder {
+    task: tokio::task::JoinHandle<()>,
+    har: Option<(Arc<Mutex<Har>>, PathBuf)>,
+}
+
+impl ActivityRecorder {
+    pub async fn start(page: &Page, config: &ActivityConfig, logger: SpectreLogger, worker_id: &str) -> Result<Self> {
+        page.execute(network::EnableParams::default()).await?;
+        page.execute(runtime::EnableParams::default()).await?;
+        let mut sent = page.event_listener::<EventRequestWillBeSent>().await?;
+        let mut received = page.event_listener::<EventResponseReceived>().await?;
+        let mut finished = page.event_listener::<EventLoadingFinished>().await?;
+        let mut failed = page.event_listener::<EventLoadingFailed>().await?;
+        let mut console = page.event_listener::<EventConsoleApiCalled>().await?;
+        let mut exceptions = page.event_listener::<EventExceptionThrown>().await?;
+
+        let har = config.har_dir.as_ref().map(|dir| (Arc::new(Mutex::new(Har::default())), PathBuf::from(dir)));
+        let state = har.as_ref().map(|(state, _)| state.clone());
+        let (log_network, log_console) = (config.network, config.console);
+        let worker_id = worker_id.to_string();
+        let task = tokio::spawn(async move {
+            // Failed requests are logged with their URL, which only requestWillBeSent carries
+            let mut urls: HashMap<String, String> = HashMap::new();
+            loop {
+                tokio::select! {
+                    Some(event) = sent.next() => {
+                        urls.insert(event.request_id.inner().clone(), event.request.url.clone());
+                        if let Some(state) = &state {
+                            state.lock().unwrap().start(&event);
+                        }
+                    }
+                    Some(event) = received.next() => {
+                        if log_network {
+                            let meta = json!({
+                                "url": event.response.url,
+                                "status": event.response.status,
+                                "type": event.r#type.as_ref(),
+                                "mime": event.response.mime_type,
+                            });
+                            logger.log(&worker_id, "BROWSER_NET", "Response", Some(&meta.to_string()));
+                        }
+                        if let Some(state) = &state {
+                            state.lock().unwrap().respond(&event);
+                        }
+                    }
+                    Some(event) = finished.next() => {
+                        let id = event.request_id.inner();
+                        urls.remove(id);
+                        if let Some(state) = &state {
+                            let mut har = state.lock().unwrap();
+                            if let Some(pending) = har.pending.remove(id) {
+                                har.finish(pending, *event.timestamp.inner(), None);
+                            }
+                        }
+                    }
+                    Some(event) = failed.next() => {
+                        let id = event.request_id.inner();
+                        let url = urls.remove(id).unwrap_or_default();
+                        if log_network {
+                            let meta = json!({
+                                "url": url,
+                                "type": event.r#type.as_ref(),
+                                "error": event.error_text,
+                                "blocked": event.blocked_reason.as_ref().map(|r| r.as_ref().to_string()),
+                            });
+                            logger.log(&worker_id, "BROWSER_NET", "Request failed", Some(&meta.to_string()));
+                        }
+                        if let Some(state) = &state {
+                            let mut har = state.lock().unwrap();
+                            if let Some(pending) = har.pending.remove(id) {
+                                har.finish(pending, *event.timestamp.inner(), Some(&event.error_text));
+                            }
+                        }
+                    }
+                    Some(event) = console.next() => {
+                        let level = match event.r#type {
+                            ConsoleApiCalledType::Error | ConsoleApiCalledType::Assert => "error",
+                            ConsoleApiCalledType::Warning => "warning",
+                            _ => continue,
+                        };
+                        if log_console {
+                            let source = event.stack_trace.as_ref().and_then(|s| s.call_frames.first()).map(|f| f.url.clone());
+                            let meta = json!({ "level": level, "text": console_text(&event.args), "url": source });
+                            logger.log(&worker_id, "BROWSER_CONSOLE", "Console message", Some(&meta.to_string()));
+                        }
+                    }
+                    Some(event) = exceptions.next() => {
+                        if log_console {
+                            let details = &event.exception_details;
+                            let text = details
+                                .exception
+                                .as_ref()
+                                .and_then(|e| e.description.clone())
+                                .unwrap_or_else(|| details.text.clone());
+                            let meta = json!({
+                                "level": "exception",
+                                "text": text,
+                                "url": details.url,
+                                "line": details.line_number,
+                            });
+                            logger.log(&worker_id, "BROWSER_CONSOLE", "Uncaught exception", Some(&meta.to_string()));
+                        }
+                    }
+                    else => break,
+                }
+            }
+        });
+        Ok(Self { task, har })
+    }
+
+    /// Stops recording. Writes the HAR when `har_dir` is set and returns its path.
+    pub async fn finish(self, worker_id: &str) -> Result<Option<PathBuf>> {
+        self.task.abort();
+        let Some((state, dir)) = &self.har else {
+            return Ok(None);
+        };
+        let har = std::mem::take(&mut *state.lock().unwrap());
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis();
+        let path = dir.join(format!("{}-{}.har", worker_id, timestamp));
+        write_har(&path, &har.document()).await?;
+        Ok(Some(path))
+    }
+}
+
+async fn write_har(path: &Path, document: &Value) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        tokio::fs::create_dir_all(dir).await.with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    tokio::fs::write(path, serde_json::to_vec_pretty(document)?)
+        .await
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+impl Drop for ActivityRecorder {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
@@ -6,21 +6,22 @@ use axum::{
 use serde::{Deserialize, Serialize};
 use std::sync::{Arc, Mutex};
 use tokio::net::TcpListener;
-use crate::engine::CoreEngine;
+use crate::embed::EngineBuilder;
+use crate::engine::{Config, EngineStats};
 
 #[derive(Clone)]
 pub struct AppState {
-    #[allow(dead_code)]
-    pub engine: Arc<Option<CoreEngine>>, // Placeholder for now, real implementation would manage multiple engines
+    /// Config every scan starts from; the request sets the target and its overrides.
+    pub base_config: Arc<Config>,
+    /// Counters of the current (or last) scan, through which `/gtfo` halts it.
+    pub engine: Arc<Mutex<Option<EngineStats>>>,
     pub scan_status: Arc<Mutex<String>>,
 }
 
 #[derive(Deserialize)]
 pub struct ScanRequest {
     pub target: String,
-    #[allow(dead_code)]
     pub method: Option<String>,
-    #[allow(dead_code)]
     pub concurrency: Option<usize>,
 }
 
@@ -35,15 +36,17 @@ pub struct StatusResponse {
     pub status: String,
 }
 
-pub async fn start_api() {
+pub async fn start_api(base_config: Config) {
     let state = AppState {
-        engine: Arc::new(None),
+        base_config: Arc::new(base_config),
+        engine: Arc::new(Mutex::new(None)),
         scan_status: Arc::new(Mutex::new("Idle".to_string())),
     };
 
     let app = Router::new()
         .route("/scan", post(start_scan))
         .route("/status", get(get_status))
+        .route("/gtfo", post(gtfo))
         .with_state(state);
 
     let listener = TcpListener::bind("0.0.0.0:3000").await.unwrap();
@@ -63,15 +66,37 @@ async fn start_scan(
         });
     }
 
+    let mut config = (*state.base_config).clone();
+    config.general.target_url = payload.target.clone();
+    if let Some(method) = payload.method {
+        config.general.method = method;
+    }
+    if let Some(concurrency) = payload.concurrency {
+        config.general.concurrency = concurrency;
+    }
+    let mut engine = match EngineBuilder::from_config(config).build() {
+        Ok(engine) => engine,
+        Err(e) => {
+            return Json(ScanResponse { id: "error".to_string(), status: format!("Invalid scan: {}", e) });
+        }
+    };
+    if let Err(e) = engine.start() {
+        return Json(ScanResponse { id: "error".to_string(), status: e.to_string() });
+    }
+    let stats = engine.raw_stats();
+    *state.engine.lock().unwrap() = Some(stats.clone());
     *status = "Running".to_string();
     let status_clone = state.scan_status.clone();
-    let target = payload.target.clone();
+    let target = payload.target;
 
-    // Spawn dummy scan task (Integration with CoreEngine comes next)
     tokio::spawn(async move {
-        tokio::time::sleep(tokio::time::Duration::from_secs(10)).await;
+        let outcome = engine.wait().await;
         let mut s = status_clone.lock().unwrap();
-        *s = format!("Completed scan on {}", target);
+        *s = match outcome {
+            _ if stats.shutdown.is_halted() => format!("Halted scan on {}", target),
+            Ok(()) => format!("Completed scan on {}", target),
+            Err(e) => format!("Scan on {} failed: {}", target, e),
+        };
     });
 
     Json(ScanResponse {
@@ -80,6 +105,17 @@ async fn start_scan(
     })
 }
 
+/// Emergency stop for the current run: cancels requests and browsers, freezes the state.
+async fn gtfo(State(state): State<AppState>) -> Json<StatusResponse> {
+    let running = *state.scan_status.lock().unwrap() == "Running";
+    let status = match state.engine.lock().unwrap().as_ref() {
+        Some(stats) if running && stats.shutdown.halt("api") => "Halted",
+        Some(_) if running => "Already halted",
+        _ => "No scan running",
+    };
+    Json(StatusResponse { status: status.to_string() })
+}
+
 async fn get_status(State(state): State<AppState>) -> Json<StatusResponse> {
     let status = state.scan_status.lock().unwrap();
     Json(StatusResponse {
@@ -0,0 +1,184 @@
+use crate::engine::config::ChromeConfig;
+use crate::engine::solver::{BrowserSolver, ProfileDir};
+use anyhow::{anyhow, Context, Result};
+use base64::Engine as _;
+use chromiumoxide::browser::{Browser, BrowserConfig};
+use chromiumoxide::cdp::browser_protocol::emulation::SetScriptExecutionDisabledParams;
+use chromiumoxide::cdp::browser_protocol::page::{CaptureScreenshotFormat, Viewport};
+use chromiumoxide::page::ScreenshotParams;
+use chromiumoxide::Page;
+use futures::StreamExt;
+use rquest::header::HeaderMap;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+// Saved HTML responses. A few bodies per verdict are written to disk during the run; when
+// the report is HTML, each is rendered offline in headless Chrome and embedded as a PNG
+// thumbnail, so block pages and real pages can be told apart at a glance.
+
+/// Page size the artifacts are rendered at before scaling down.
+const RENDER_SIZE: (u32, u32) = (1280, 800);
+/// Time the page gets to lay out; nothing can load over the network anyway.
+const SETTLE: Duration = Duration::from_millis(500);
+/// Longest a single saved page may take to load before it's skipped.
+const NAV_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct ArtifactConfig {
+    /// Sessions get their own subdirectory.
+    #[serde(default = "default_dir")]
+    pub dir: String,
+    /// HTML bodies kept per verdict (success, blocked, challenge).
+    #[serde(default = "default_per_verdict")]
+    pub per_verdict: usize,
+    /// Render the saved pages to thumbnails in the HTML report.
+    #[serde(default = "default_screenshots")]
+    pub screenshots: bool,
+    #[serde(default = "default_thumbnail_width")]
+    pub thumbnail_width: u32,
+}
+
+fn default_dir() -> String {
+    "logs/artifacts".to_string()
+}
+
+fn default_per_verdict() -> usize {
+    5
+}
+
+fn default_screenshots() -> bool {
+    true
+}
+
+fn default_thumbnail_width() -> u32 {
+    320
+}
+
+/// A saved response as the report lists it.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ArtifactSummary {
+    pub path: String,
+    pub url: String,
+    pub status: u16,
+    pub verdict: String,
+    /// `data:image/png;base64,...`; only for the HTML report.
+    #[serde(skip)]
+    pub thumbnail: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ArtifactStore {
+    config: ArtifactConfig,
+    dir: PathBuf,
+    saved: Arc<Mutex<(HashMap<String, usize>, Vec<ArtifactSummary>)>>,
+}
+
+impl ArtifactStore {
+    pub fn new(config: ArtifactConfig, session_id: &str) -> Result<Self> {
+        let dir = PathBuf::from(&config.dir).join(session_id);
+        std::fs::create_dir_all(&dir).context(format!("Failed to create artifact dir {}", dir.display()))?;
+        Ok(Self { config, dir, saved: Arc::default() })
+    }
+
+    pub fn screenshots(&self) -> bool {
+        self.config.screenshots
+    }
+
+    /// Writes `body` if it is HTML and `verdict` hasn't used up its quota.
+    pub fn save(&self, verdict: &str, url: &str, status: u16, headers: &HeaderMap, body: &str) {
+        let is_html = headers
+            .get(rquest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(|ct| ct.to_lowercase().contains("html"))
+            .unwrap_or_else(|| body.trim_start().starts_with('<'));
+        if !is_html || body.is_empty() {
+            return;
+        }
+        let mut saved = self.saved.lock().unwrap();
+        let count = saved.0.entry(verdict.to_string()).or_default();
+        if *count >= self.config.per_verdict {
+            return;
+        }
+        *count += 1;
+        let path = self.dir.join(format!("{}_{:02}.html", verdict, count));
+        if std::fs::write(&path, body).is_err() {
+            return;
+        }
+        saved.1.push(ArtifactSummary {
+            path: path.display().to_string(),
+            url: url.to_string(),
+            status,
+            verdict: verdict.to_string(),
+            thumbnail: None,
+        });
+    }
+
+    pub fn snapshot(&self) -> Vec<ArtifactSummary> {
+        self.saved.lock().unwrap().1.clone()
+    }
+
+    /// Fills in each artifact's thumbnail. The saved bodies are untrusted, so Chrome keeps
+    /// its sandbox, scripts are disabled, and networking (WebRTC UDP included) is pointed at
+    /// a dead proxy so a block page can't call home to the target. Artifacts that fail to
+    /// render, or don't load within `NAV_TIMEOUT`, keep `thumbnail: None`.
+    pub async fn render_thumbnails(&self, artifacts: &mut [ArtifactSummary], chrome: &ChromeConfig) -> Result<()> {
+        if artifacts.is_empty() {
+            return Ok(());
+        }
+        let profile = ProfileDir::create()?;
+        let args = [
+            "--disable-gpu".to_string(),
+            format!("--window-size={},{}", RENDER_SIZE.0, RENDER_SIZE.1),
+            "--proxy-server=http://127.0.0.1:9".to_string(),
+            "--proxy-bypass-list=<-loopback>".to_string(),
+            "--force-webrtc-ip-handling-policy=disable_non_proxied_udp".to_string(),
+        ];
+        let mut config = BrowserConfig::builder().user_data_dir(&profile.path).viewport(None).args(args);
+        if let Some(path) = BrowserSolver::find_chrome_binary(chrome) {
+            config = config.chrome_executable(path);
+        }
+        let config = config.build().map_err(|e| anyhow!("Invalid browser config: {}", e))?;
+        let (mut browser, mut handler) = Browser::launch(config).await.context("Failed to launch browser for thumbnails")?;
+        let handler = tokio::spawn(async move { while handler.next().await.is_some() {} });
+        let rendered = Self::render_all(&browser, artifacts, self.config.thumbnail_width).await;
+        // The profile goes once Chrome has exited; removed earlier, Chrome's last writes leave it behind
+        let _ = browser.close().await;
+        let _ = browser.wait().await;
+        handler.abort();
+        drop(profile);
+        rendered
+    }
+
+    async fn render_all(browser: &Browser, artifacts: &mut [ArtifactSummary], width: u32) -> Result<()> {
+        let page = browser.new_page("about:blank").await?;
+        page.execute(SetScriptExecutionDisabledParams::new(true))
+            .await
+            .context("Failed to disable scripts for thumbnails")?;
+        let scale = width as f64 / RENDER_SIZE.0 as f64;
+        for artifact in artifacts.iter_mut() {
+            let Some(url) = std::fs::canonicalize(&artifact.path)
+                .ok()
+                .and_then(|p| rquest::Url::from_file_path(p).ok())
+            else {
+                continue;
+            };
+            if let Ok(png) = Self::render(&page, url.as_str(), scale).await {
+                artifact.thumbnail = Some(format!(
+                    "data:image/png;base64,{}",
+                    base64::engine::general_purpose::STANDARD.encode(png)
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    async fn render(page: &Page, url: &str, scale: f64) -> Result<Vec<u8>> {
+        tokio::time::timeout(NAV_TIMEOUT, page.goto(url)).await??;
+        tokio::time::sleep(SETTLE).await;
+        let clip = Viewport { x: 0.0, y: 0.0, width: RENDER_SIZE.0 as f64, height: RENDER_SIZE.1 as f64, scale };
+        let params = ScreenshotParams::builder().format(CaptureScreenshotFormat::Png).clip(clip).build();
+        Ok(page.screenshot(params).await?)
+    }
+}
@@ -0,0 +1,89 @@
+use rquest::header::HeaderMap;
+use rquest::Url;
+
+// Redirects to login and consent pages. The client follows redirects, so a session that
+// lost its login (or never had one) used to land on a 200 sign-in form and be counted as
+// a pass. Responses whose redirect ends on such a page get `Verdict::AuthRequired` instead,
+// with their own counter and a configurable reaction.
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuthAction {
+    /// Recorded as "Auth Required" like any other verdict; the worker carries on.
+    #[default]
+    Count,
+    /// Counted, but left out of findings and sinks.
+    Skip,
+    /// Counted, then `login_step` runs next to re-establish the session.
+    Login,
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct AuthConfig {
+    /// Opt-in; when off, redirected responses are judged on their content alone.
+    #[serde(default = "default_detect")]
+    pub detect: bool,
+    /// Case-insensitive substrings of the redirect target's host + path that mark a
+    /// login or consent page.
+    #[serde(default = "default_patterns")]
+    pub patterns: Vec<String>,
+    #[serde(default)]
+    pub action: AuthAction,
+    /// Name of the `[[scenario]]` step that logs in, for `action = "login"`.
+    #[serde(default)]
+    pub login_step: Option<String>,
+}
+
+fn default_detect() -> bool {
+    false
+}
+
+fn default_patterns() -> Vec<String> {
+    [
+        "/login", "/log-in", "/signin", "/sign-in", "/sign_in", "/logon", "/auth/", "/oauth", "/sso", "/saml",
+        "/consent", "login.", "consent.", "accounts.",
+    ]
+    .iter()
+    .map(|p| p.to_string())
+    .collect()
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        Self { detect: default_detect(), patterns: default_patterns(), action: AuthAction::default(), login_step: None }
+    }
+}
+
+impl AuthConfig {
+    /// The login/consent URL the request was sent to, if it was redirected to one: either
+    /// followed (`response_url` differs from `requested`) or handed back as a 3xx `Location`.
+    pub fn login_redirect(&self, requested: &str, response_url: &str, status: u16, headers: &HeaderMap) -> Option<String> {
+        if !self.detect {
+            return None;
+        }
+        let target = if (300..400).contains(&status) {
+            let location = headers.get(rquest::header::LOCATION)?.to_str().ok()?;
+            Url::parse(response_url).ok()?.join(location).ok()?
+        } else {
+            let response = Url::parse(response_url).ok()?;
+            let request = Url::parse(requested).ok()?;
+            if response.host_str() == request.host_str() && response.path() == request.path() {
+                return None;
+            }
+            response
+        };
+        // Pages that were requested as login pages aren't redirects to one
+        if self.is_login_page(requested) {
+            return None;
+        }
+        self.is_login_page(target.as_str()).then(|| target.to_string())
+    }
+
+    fn is_login_page(&self, url: &str) -> bool {
+        let Ok(url) = Url::parse(url) else {
+            return false;
+        };
+        let haystack = format!("{}{}", url.host_str().unwrap_or(""), url.path()).to_lowercase();
+        self.patterns.iter().any(|p| haystack.contains(&p.to_lowercase()))
+    }
+}
@@ -0,0 +1,120 @@
+use crate::waf::WafType;
+use anyhow::{anyhow, Result};
+use rand::Rng;
+use std::collections::HashMap;
+use std::time::Duration;
+
+// Vendor-tuned reactions to blocks and challenges. `[vendor_policies.<vendor>]` tables
+// set them; `vendor_policy_defaults = true` starts from shipped defaults for the common
+// cases instead of the standard behaviour.
+
+/// Policy key for a 429 from a vendor without its own policy.
+pub const RATE_LIMIT_KEY: &str = "rate_limit";
+
+const VENDOR_KEYS: &[&str] = &[
+    "cloudflare",
+    "cloudfront",
+    "akamai",
+    "imperva",
+    "azure",
+    "bunkerweb",
+    "datadome",
+    "perimeterx",
+    "kasada",
+    RATE_LIMIT_KEY,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PolicyAction {
+    /// Challenges go to the browser solver and the clearance is reused by the identity.
+    Solve,
+    /// Burn the identity on the first block or challenge, without solving.
+    Rotate,
+    /// Keep the identity and retry after an exponentially growing pause.
+    Backoff,
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct VendorPolicy {
+    pub action: PolicyAction,
+    /// Per-identity solve limit for this vendor; overrides `[solver]`.
+    #[serde(default)]
+    pub max_solves: Option<usize>,
+    #[serde(default = "default_base_delay_ms")]
+    pub base_delay_ms: u64,
+    #[serde(default = "default_max_delay_ms")]
+    pub max_delay_ms: u64,
+}
+
+fn default_base_delay_ms() -> u64 {
+    1000
+}
+
+fn default_max_delay_ms() -> u64 {
+    60_000
+}
+
+impl VendorPolicy {
+    fn new(action: PolicyAction) -> Self {
+        Self {
+            action,
+            max_solves: None,
+            base_delay_ms: default_base_delay_ms(),
+            max_delay_ms: default_max_delay_ms(),
+        }
+    }
+
+    /// Pause before retry number `attempt` (0-based): base * 2^attempt, capped, with up to
+    /// 20% jitter so workers backing off together don't retry in lockstep.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let exp = self.base_delay_ms.saturating_mul(1u64 << attempt.min(20));
+        let capped = exp.min(self.max_delay_ms);
+        let jitter = rand::thread_rng().gen_range(0..=capped / 5);
+        Duration::from_millis(capped + jitter)
+    }
+}
+
+pub struct VendorPolicies {
+    policies: HashMap<String, VendorPolicy>,
+}
+
+impl VendorPolicies {
+    /// The config's tables, over the shipped defaults when `with_defaults` is set.
+    pub fn new(overrides: &HashMap<String, VendorPolicy>, with_defaults: bool) -> Result<Self> {
+        let mut policies = HashMap::new();
+        if with_defaults {
+            policies.insert(
+                "cloudflare".to_string(),
+                VendorPolicy { max_solves: Some(1), ..VendorPolicy::new(PolicyAction::Solve) },
+            );
+            policies.insert("datadome".to_string(), VendorPolicy::new(PolicyAction::Rotate));
+            policies.insert(RATE_LIMIT_KEY.to_string(), VendorPolicy::new(PolicyAction::Backoff));
+        }
+
+        for (key, policy) in overrides {
+            let key = key.to_lowercase();
+            if !VENDOR_KEYS.contains(&key.as_str()) {
+                return Err(anyhow!(
+                    "Unknown vendor '{}' in vendor_policies (known: {})",
+                    key,
+                    VENDOR_KEYS.join(", ")
+                ));
+            }
+            if policy.base_delay_ms == 0 || policy.max_delay_ms < policy.base_delay_ms {
+                return Err(anyhow!("vendor_policies.{}: need 0 < base_delay_ms <= max_delay_ms", key));
+            }
+            policies.insert(key, policy.clone());
+        }
+        Ok(Self { policies })
+    }
+
+    /// The vendor's own policy, else the rate-limit policy for a 429.
+    pub fn lookup(&self, vendor: &WafType, status: u16) -> Option<(&str, &VendorPolicy)> {
+        let vendor_key = vendor.to_string().to_lowercase();
+        self.policies
+            .get_key_value(vendor_key.as_str())
+            .or_else(|| if status == 429 { self.policies.get_key_value(RATE_LIMIT_KEY) } else { None })
+            .map(|(k, p)| (k.as_str(), p))
+    }
+}
@@ -0,0 +1,275 @@
+use anyhow::{anyhow, Result};
+use chromiumoxide::cdp::browser_protocol::input::{
+    DispatchKeyEventParams, DispatchKeyEventType, DispatchMouseEventParams, DispatchMouseEventType, MouseButton,
+};
+use chromiumoxide::Page;
+use rand::Rng;
+use std::ops::Range;
+use std::time::Duration;
+
+// Human-like input for the solver's page. Behavioural bot managers score the pointer and
+// scroll stream, not just its presence: straight lines at constant speed, instant scroll
+// jumps and a page left untouched until the cookie appears all read as automation. The
+// engine drives trusted CDP input events along Bezier paths with eased speed, the odd
+// overshoot and correction, wheel flicks that decay like momentum scrolling, reading pauses
+// in between and, when a profile asks for it, typing with per-key cadence.
+// `[profiles.<name>.behavior]` picks a preset: "casual", "reader" or "fast".
+
+/// `[profiles.<name>.behavior]`.
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+pub struct BehaviorConfig {
+    #[serde(default)]
+    pub preset: BehaviorPreset,
+    /// Click into a field and type into it, e.g. a search box the real user would use.
+    #[serde(default)]
+    pub typing: Option<TypingConfig>,
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct TypingConfig {
+    /// CSS selector of the field.
+    pub selector: String,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BehaviorPreset {
+    /// A few unhurried movements and a scroll or two.
+    #[default]
+    Casual,
+    /// Slow, steady scrolling down the page with long pauses.
+    Reader,
+    /// Quick, direct movements and big flicks.
+    Fast,
+}
+
+/// The timing of one preset. Ranges are sampled per gesture.
+struct Tempo {
+    /// Pointer movements before and between scrolls.
+    moves: Range<usize>,
+    move_ms: Range<u64>,
+    /// Chance that a movement overshoots its target and corrects.
+    overshoot: f64,
+    /// Wheel flicks, and the first wheel delta of each.
+    flicks: Range<usize>,
+    flick_delta: Range<f64>,
+    /// Pauses between gestures.
+    dwell_ms: Range<u64>,
+    /// Delay between key presses.
+    key_ms: Range<u64>,
+}
+
+impl BehaviorPreset {
+    fn tempo(self) -> Tempo {
+        match self {
+            BehaviorPreset::Casual => Tempo {
+                moves: 2..4,
+                move_ms: 400..900,
+                overshoot: 0.2,
+                flicks: 1..3,
+                flick_delta: 100.0..240.0,
+                dwell_ms: 800..2500,
+                key_ms: 90..220,
+            },
+            BehaviorPreset::Reader => Tempo {
+                moves: 1..3,
+                move_ms: 600..1200,
+                overshoot: 0.1,
+                flicks: 4..8,
+                flick_delta: 60.0..140.0,
+                dwell_ms: 2000..5000,
+                key_ms: 120..300,
+            },
+            BehaviorPreset::Fast => Tempo {
+                moves: 2..5,
+                move_ms: 200..450,
+                overshoot: 0.35,
+                flicks: 1..3,
+                flick_delta: 300.0..600.0,
+                dwell_ms: 300..1000,
+                key_ms: 50..120,
+            },
+        }
+    }
+}
+
+fn sample<T: rand::distributions::uniform::SampleUniform + PartialOrd + Copy>(range: &Range<T>) -> T {
+    rand::thread_rng().gen_range(range.clone())
+}
+
+/// Minimum-jerk easing: slow start, fast middle, slow landing, as a hand moves.
+fn ease(t: f64) -> f64 {
+    t * t * t * (10.0 - 15.0 * t + 6.0 * t * t)
+}
+
+/// Drives one page. Tracks the pointer, since CDP doesn't report it.
+pub struct Behavior<'a> {
+    page: &'a Page,
+    tempo: Tempo,
+    viewport: (f64, f64),
+    pointer: (f64, f64),
+}
+
+impl<'a> Behavior<'a> {
+    pub fn new(page: &'a Page, preset: BehaviorPreset, viewport: (u32, u32)) -> Self {
+        let viewport = (viewport.0.max(200) as f64, viewport.1.max(200) as f64);
+        let pointer = {
+            let mut rng = rand::thread_rng();
+            (rng.gen_range(0.2..0.8) * viewport.0, rng.gen_range(0.2..0.8) * viewport.1)
+        };
+        Self { page, tempo: preset.tempo(), viewport, pointer }
+    }
+
+    /// A visit's worth of activity: movements, then scrolls with movements and pauses in
+    /// between, then the configured typing.
+    pub async fn perform(&mut self, config: &BehaviorConfig) -> Result<()> {
+        for _ in 0..sample(&self.tempo.moves) {
+            let target = self.random_point();
+            self.move_to(target).await?;
+            self.dwell().await;
+        }
+        for _ in 0..sample(&self.tempo.flicks) {
+            self.flick().await?;
+            self.dwell().await;
+            if rand::thread_rng().gen_bool(0.5) {
+                let target = self.random_point();
+                self.move_to(target).await?;
+            }
+        }
+        if let Some(typing) = &config.typing {
+            self.type_into(&typing.selector, &typing.text).await?;
+        }
+        Ok(())
+    }
+
+    fn random_point(&self) -> (f64, f64) {
+        let mut rng = rand::thread_rng();
+        (rng.gen_range(0.1..0.9) * self.viewport.0, rng.gen_range(0.1..0.9) * self.viewport.1)
+    }
+
+    async fn dwell(&self) {
+        tokio::time::sleep(Duration::from_millis(sample(&self.tempo.dwell_ms))).await;
+    }
+
+    async fn mouse(&self, kind: DispatchMouseEventType, (x, y): (f64, f64), delta_y: Option<f64>) -> Result<()> {
+        let mut event = DispatchMouseEventParams::builder().r#type(kind.clone()).x(x).y(y);
+        match kind {
+            DispatchMouseEventType::MousePressed | DispatchMouseEventType::MouseReleased => {
+                event = event.button(MouseButton::Left).click_count(1);
+            }
+            DispatchMouseEventType::MouseWheel => event = event.delta_x(0.0).delta_y(delta_y.unwrap_or(0.0)),
+            _ => {}
+        }
+        self.page.execute(event.build().map_err(|e| anyhow!(e))?).await?;
+        Ok(())
+    }
+
+    /// Moves the pointer along a cubic Bezier curve with eased speed, sometimes past the
+    /// target and back.
+    pub async fn move_to(&mut self, target: (f64, f64)) -> Result<()> {
+        let overshoot = rand::thread_rng().gen_bool(self.tempo.overshoot);
+        if overshoot {
+            let (dx, dy) = (target.0 - self.pointer.0, target.1 - self.pointer.1);
+            let past = rand::thread_rng().gen_range(0.05..0.12);
+            let beyond = (target.0 + dx * past, target.1 + dy * past);
+            self.path(beyond, sample(&self.tempo.move_ms)).await?;
+            tokio::time::sleep(Duration::from_millis(rand::thread_rng().gen_range(60..180))).await;
+            // The correction is short and quick
+            self.path(target, sample(&self.tempo.move_ms) / 3).await
+        } else {
+            self.path(target, sample(&self.tempo.move_ms)).await
+        }
+    }
+
+    async fn path(&mut self, to: (f64, f64), duration_ms: u64) -> Result<()> {
+        let from = self.pointer;
+        let (c1, c2) = {
+            let mut rng = rand::thread_rng();
+            let spread = ((to.0 - from.0).hypot(to.1 - from.1) * 0.3).max(20.0);
+            let mut control = |t: f64| {
+                (
+                    from.0 + (to.0 - from.0) * t + rng.gen_range(-spread..spread),
+                    from.1 + (to.1 - from.1) * t + rng.gen_range(-spread..spread),
+                )
+            };
+            (control(0.3), control(0.7))
+        };
+        // One event per frame or so
+        let steps = (duration_ms / 16).max(5);
+        for i in 1..=steps {
+            let t = ease(i as f64 / steps as f64);
+            let u = 1.0 - t;
+            let x = u * u * u * from.0 + 3.0 * u * u * t * c1.0 + 3.0 * u * t * t * c2.0 + t * t * t * to.0;
+            let y = u * u * u * from.1 + 3.0 * u * u * t * c1.1 + 3.0 * u * t * t * c2.1 + t * t * t * to.1;
+            let (jitter, frame) = {
+                let mut rng = rand::thread_rng();
+                ((rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0)), rng.gen_range(12..22))
+            };
+            let point = if i == steps { to } else { (x + jitter.0, y + jitter.1) };
+            let point = (point.0.clamp(0.0, self.viewport.0 - 1.0), point.1.clamp(0.0, self.viewport.1 - 1.0));
+            self.mouse(DispatchMouseEventType::MouseMoved, point, None).await?;
+            self.pointer = point;
+            tokio::time::sleep(Duration::from_millis(frame)).await;
+        }
+        Ok(())
+    }
+
+    /// One wheel flick: a burst of deltas decaying like momentum scrolling. Mostly down,
+    /// now and then back up.
+    async fn flick(&self) -> Result<()> {
+        let (mut delta, decay) = {
+            let mut rng = rand::thread_rng();
+            let direction = if rng.gen_bool(0.15) { -1.0 } else { 1.0 };
+            (direction * sample(&self.tempo.flick_delta), rng.gen_range(0.75..0.88))
+        };
+        while delta.abs() >= 4.0 {
+            self.mouse(DispatchMouseEventType::MouseWheel, self.pointer, Some(delta.round())).await?;
+            delta *= decay;
+            tokio::time::sleep(Duration::from_millis(rand::thread_rng().gen_range(16..32))).await;
+        }
+        Ok(())
+    }
+
+    /// Moves to the field, clicks it and types `text` key by key, pausing longer between
+    /// words.
+    pub async fn type_into(&mut self, selector: &str, text: &str) -> Result<()> {
+        let rect: Option<[f64; 4]> = self
+            .page
+            .evaluate(format!(
+                "(() => {{ const el = document.querySelector({}); if (!el) return null; el.scrollIntoView({{block: 'center'}}); \
+                 const r = el.getBoundingClientRect(); return [r.x, r.y, r.width, r.height]; }})()",
+                serde_json::to_string(selector)?
+            ))
+            .await?
+            .into_value()?;
+        let [x, y, width, height] = rect.ok_or_else(|| anyhow!("no element matches '{}'", selector))?;
+        let target = {
+            let mut rng = rand::thread_rng();
+            (x + width * rng.gen_range(0.2..0.6), y + height * rng.gen_range(0.3..0.7))
+        };
+        self.move_to(target).await?;
+        self.mouse(DispatchMouseEventType::MousePressed, target, None).await?;
+        tokio::time::sleep(Duration::from_millis(rand::thread_rng().gen_range(50..120))).await;
+        self.mouse(DispatchMouseEventType::MouseReleased, target, None).await?;
+        self.dwell().await;
+
+        for c in text.chars() {
+            let key = c.to_string();
+            for kind in [DispatchKeyEventType::KeyDown, DispatchKeyEventType::KeyUp] {
+                let mut event = DispatchKeyEventParams::builder().r#type(kind.clone()).key(key.clone());
+                // The key-down carries the text, which is what inserts it
+                if kind == DispatchKeyEventType::KeyDown {
+                    event = event.text(key.clone());
+                }
+                self.page.execute(event.build().map_err(|e| anyhow!(e))?).await?;
+            }
+            let mut pause = sample(&self.tempo.key_ms);
+            if c == ' ' {
+                pause += rand::thread_rng().gen_range(100..400);
+            }
+            tokio::time::sleep(Duration::from_millis(pause)).await;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,78 @@
+use crate::engine::{CacheInspector, CacheStatus, ClientFactory, Config, TemplateEngine};
+use anyhow::Result;
+use std::time::Duration;
+
+// Cache-hit ratio measurement: repeat cacheable URLs like a returning visitor would and
+// check the CDN actually serves them from cache. Requests go out through the run's exit
+// nodes in turn, as the workers' would, since the edge that answers depends on the exit.
+
+#[derive(Debug, Default, Clone)]
+pub struct UrlCacheResult {
+    pub url: String,
+    pub hits: usize,
+    pub misses: usize,
+    pub unknown: usize,
+    pub errors: usize,
+}
+
+impl UrlCacheResult {
+    /// Hit ratio over responses that carried a cache verdict.
+    pub fn hit_ratio(&self) -> f64 {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            0.0
+        } else {
+            self.hits as f64 / total as f64
+        }
+    }
+}
+
+pub struct CacheRatioProbe {
+    config: Config,
+}
+
+impl CacheRatioProbe {
+    pub fn new(config: Config) -> Self {
+        Self { config }
+    }
+
+    /// Target URL plus every scenario step URL, with template placeholders emptied.
+    pub fn urls(&self) -> Vec<String> {
+        let mut urls = vec![TemplateEngine::inject(&self.config.general.target_url, "")];
+        for step in &self.config.scenario {
+            let url = TemplateEngine::inject(&step.url, "");
+            if !urls.contains(&url) {
+                urls.push(url);
+            }
+        }
+        urls
+    }
+
+    pub async fn run(&self, repeats: usize, pause: Duration) -> Result<Vec<UrlCacheResult>> {
+        let factory = ClientFactory::for_config(&self.config)?;
+        let nodes = self.config.exit_nodes().await?;
+        let profile = self.config.default_profile()?;
+        let clients = nodes
+            .iter()
+            .map(|node| factory.create_client(&profile, node.as_deref(), None))
+            .collect::<Result<Vec<_>>>()?;
+        let mut results = Vec::new();
+
+        for url in self.urls() {
+            let mut result = UrlCacheResult { url: url.clone(), ..Default::default() };
+            for i in 0..repeats {
+                match clients[i % clients.len()].get(&url).send().await {
+                    Ok(resp) => match CacheInspector::classify(resp.headers()) {
+                        Some(CacheStatus::Hit) => result.hits += 1,
+                        Some(CacheStatus::Miss) => result.misses += 1,
+                        None => result.unknown += 1,
+                    },
+                    Err(_) => result.errors += 1,
+                }
+                tokio::time::sleep(pause).await;
+            }
+            results.push(result);
+        }
+        Ok(results)
+    }
+}
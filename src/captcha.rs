@@ -0,0 +1,297 @@
+use crate::engine::{ResponseAnalyzer, SolverStats};
+use anyhow::{anyhow, Context, Result};
+use chromiumoxide::Page;
+use serde_json::{json, Value};
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+
+// CAPTCHA-solving services for the browser solver. Interactive challenges (reCAPTCHA v2,
+// hCaptcha, a Turnstile that wants a click) never clear by waiting, so without help the
+// solve just times out. With `[captcha]` set, the solver looks for a widget's sitekey once
+// the page has settled, and keeps looking while the page still shows a challenge, since
+// interstitials render their widget late. It hands the sitekey to 2captcha, Anti-Captcha or CapSolver, writes the token
+// into the widget's response field and fires its callback (or submits its form), then
+// polls for clearance as usual. All three speak the same createTask/getTaskResult protocol.
+// Tasks are proxyless: the service solves from its own IP.
+
+/// `[captcha]`.
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct CaptchaConfig {
+    pub provider: CaptchaProvider,
+    /// Falls back to `CAPTCHA_API_KEY`.
+    #[serde(default)]
+    pub api_key: Option<String>,
+    /// Overrides the provider's API base URL, e.g. for a compatible self-hosted service.
+    #[serde(default)]
+    pub endpoint: Option<String>,
+    #[serde(default = "crate::stealth::on")]
+    pub recaptcha: bool,
+    #[serde(default = "crate::stealth::on")]
+    pub hcaptcha: bool,
+    #[serde(default = "crate::stealth::on")]
+    pub turnstile: bool,
+    /// How long to wait for the service's answer.
+    #[serde(default = "default_timeout")]
+    pub timeout_secs: u64,
+    #[serde(default = "default_poll_interval")]
+    pub poll_interval_secs: u64,
+    /// How long to keep looking for a widget on a page that still shows a challenge.
+    #[serde(default = "default_detect")]
+    pub detect_secs: u64,
+}
+
+fn default_timeout() -> u64 {
+    120
+}
+
+fn default_poll_interval() -> u64 {
+    5
+}
+
+fn default_detect() -> u64 {
+    10
+}
+
+/// Longest a single call to the service may take; a hung connection otherwise stalls the
+/// solve until the browser's own timeout.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+/// Gap between looks for a widget.
+const DETECT_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+pub enum CaptchaProvider {
+    #[serde(rename = "2captcha")]
+    TwoCaptcha,
+    #[serde(rename = "anticaptcha", alias = "anti-captcha")]
+    AntiCaptcha,
+    #[serde(rename = "capsolver")]
+    CapSolver,
+}
+
+impl CaptchaProvider {
+    fn endpoint(self) -> &'static str {
+        match self {
+            CaptchaProvider::TwoCaptcha => "https://api.2captcha.com",
+            CaptchaProvider::AntiCaptcha => "https://api.anti-captcha.com",
+            CaptchaProvider::CapSolver => "https://api.capsolver.com",
+        }
+    }
+
+    /// The provider's task type for a widget.
+    fn task_type(self, kind: CaptchaKind) -> &'static str {
+        match (self, kind) {
+            (CaptchaProvider::CapSolver, CaptchaKind::Recaptcha) => "ReCaptchaV2TaskProxyLess",
+            (CaptchaProvider::CapSolver, CaptchaKind::Hcaptcha) => "HCaptchaTaskProxyLess",
+            (CaptchaProvider::CapSolver, CaptchaKind::Turnstile) => "AntiTurnstileTaskProxyLess",
+            (_, CaptchaKind::Recaptcha) => "RecaptchaV2TaskProxyless",
+            (_, CaptchaKind::Hcaptcha) => "HCaptchaTaskProxyless",
+            (_, CaptchaKind::Turnstile) => "TurnstileTaskProxyless",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CaptchaKind {
+    Recaptcha,
+    Hcaptcha,
+    Turnstile,
+}
+
+impl CaptchaKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CaptchaKind::Recaptcha => "recaptcha",
+            CaptchaKind::Hcaptcha => "hcaptcha",
+            CaptchaKind::Turnstile => "turnstile",
+        }
+    }
+}
+
+/// A widget found on the page.
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct CaptchaWidget {
+    pub kind: CaptchaKind,
+    pub sitekey: String,
+}
+
+/// Finds the first widget: its container's `data-sitekey`, else the sitekey in its iframe's URL.
+const DETECT: &str = r#"
+    (() => {
+        const containers = [['.cf-turnstile', 'turnstile'], ['.h-captcha', 'hcaptcha'], ['.g-recaptcha', 'recaptcha']];
+        for (const [selector, kind] of containers) {
+            const el = document.querySelector(selector + '[data-sitekey]');
+            if (el) return { kind, sitekey: el.getAttribute('data-sitekey') };
+        }
+        for (const frame of document.querySelectorAll('iframe[src]')) {
+            const url = new URL(frame.src, location.href);
+            if (url.pathname.includes('/recaptcha/') && url.searchParams.get('k')) {
+                return { kind: 'recaptcha', sitekey: url.searchParams.get('k') };
+            }
+            if (url.hostname.endsWith('hcaptcha.com')) {
+                const sitekey = new URLSearchParams(url.hash.slice(1)).get('sitekey');
+                if (sitekey) return { kind: 'hcaptcha', sitekey };
+            }
+            const turnstile = url.hostname === 'challenges.cloudflare.com' && url.pathname.match(/\/(0x[0-9A-Za-z_-]{10,})\//);
+            if (turnstile) return { kind: 'turnstile', sitekey: turnstile[1] };
+        }
+        return null;
+    })()
+"#;
+
+/// Writes the token into every response field the widget reads, then hands it on the way
+/// the page expects: the detected widget's `data-callback`, else its form. The widget is
+/// found again by kind and sitekey, then by the iframe carrying the sitekey. Takes (kind,
+/// sitekey, token).
+const INJECT: &str = r#"
+    ((kind, sitekey, token) => {
+        const fields = {
+            recaptcha: ['g-recaptcha-response'],
+            hcaptcha: ['h-captcha-response', 'g-recaptcha-response'],
+            turnstile: ['cf-turnstile-response'],
+        }[kind];
+        for (const name of fields) {
+            for (const field of document.querySelectorAll(`[name="${name}"], #${name}`)) {
+                field.value = token;
+                field.innerHTML = token;
+            }
+        }
+        const key = CSS.escape(sitekey);
+        const container = { recaptcha: '.g-recaptcha', hcaptcha: '.h-captcha', turnstile: '.cf-turnstile' }[kind];
+        const frame = Array.from(document.querySelectorAll('iframe[src]')).find((f) => f.src.includes(sitekey));
+        const widget = document.querySelector(`${container}[data-sitekey="${key}"]`)
+            || document.querySelector(`[data-sitekey="${key}"]`)
+            || (frame && (frame.closest('[data-sitekey]') || frame));
+        const callback = widget && widget.getAttribute('data-callback');
+        if (callback && typeof window[callback] === 'function') {
+            window[callback](token);
+            return 'callback';
+        }
+        const form = widget && widget.closest('form');
+        if (form) {
+            form.submit();
+            return 'form';
+        }
+        return 'field';
+    })
+"#;
+
+pub struct CaptchaSolver {
+    config: CaptchaConfig,
+    api_key: String,
+    endpoint: String,
+    client: rquest::Client,
+    stats: SolverStats,
+}
+
+impl CaptchaSolver {
+    pub fn new(config: CaptchaConfig, stats: SolverStats) -> Result<Self> {
+        let api_key = config
+            .api_key
+            .clone()
+            .or_else(|| std::env::var("CAPTCHA_API_KEY").ok())
+            .filter(|k| !k.trim().is_empty())
+            .ok_or_else(|| anyhow!("captcha.api_key not set and CAPTCHA_API_KEY missing"))?;
+        let endpoint = config.endpoint.clone().unwrap_or_else(|| config.provider.endpoint().to_string());
+        rquest::Url::parse(&endpoint).with_context(|| format!("Invalid captcha.endpoint '{}'", endpoint))?;
+        if config.poll_interval_secs == 0 {
+            return Err(anyhow!("captcha.poll_interval_secs must be at least 1"));
+        }
+        Ok(Self {
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            config,
+            api_key,
+            client: rquest::Client::builder().timeout(REQUEST_TIMEOUT).build()?,
+            stats,
+        })
+    }
+
+    fn enabled(&self, kind: CaptchaKind) -> bool {
+        match kind {
+            CaptchaKind::Recaptcha => self.config.recaptcha,
+            CaptchaKind::Hcaptcha => self.config.hcaptcha,
+            CaptchaKind::Turnstile => self.config.turnstile,
+        }
+    }
+
+    /// The page's widget, if it has one of an enabled kind. Looks again every
+    /// `DETECT_INTERVAL` for up to `detect_secs` while the page still shows a challenge;
+    /// a page without one has nothing left to render.
+    pub async fn detect(&self, page: &Page) -> Result<Option<CaptchaWidget>> {
+        let deadline = Instant::now() + Duration::from_secs(self.config.detect_secs);
+        loop {
+            let widget: Option<CaptchaWidget> = page.evaluate(DETECT).await?.into_value()?;
+            if let Some(widget) = widget.filter(|w| self.enabled(w.kind)) {
+                return Ok(Some(widget));
+            }
+            let challenged = ResponseAnalyzer::challenge_marker(&page.content().await?).is_some();
+            if !challenged || Instant::now() >= deadline {
+                return Ok(None);
+            }
+            tokio::time::sleep(DETECT_INTERVAL).await;
+        }
+    }
+
+    /// Has the service solve `widget` and injects the token. Returns how the token was
+    /// handed to the page: `callback`, `form` or `field`.
+    pub async fn solve(&self, page: &Page, widget: &CaptchaWidget) -> Result<String> {
+        let page_url = page.url().await?.ok_or_else(|| anyhow!("page has no URL"))?;
+        self.stats.captcha_attempts.fetch_add(1, Ordering::Relaxed);
+        let token = match self.request_token(widget, &page_url).await {
+            Ok(token) => token,
+            Err(e) => {
+                self.stats.captcha_failures.fetch_add(1, Ordering::Relaxed);
+                return Err(e);
+            }
+        };
+        let call = format!("{}({}, {}, {})", INJECT, json!(widget.kind.as_str()), json!(widget.sitekey), json!(token));
+        let handoff: String = page.evaluate(call).await?.into_value()?;
+        self.stats.captcha_solves.fetch_add(1, Ordering::Relaxed);
+        Ok(handoff)
+    }
+
+    async fn request_token(&self, widget: &CaptchaWidget, page_url: &str) -> Result<String> {
+        let created = self
+            .call(
+                "createTask",
+                json!({
+                    "clientKey": self.api_key,
+                    "task": {
+                        "type": self.config.provider.task_type(widget.kind),
+                        "websiteURL": page_url,
+                        "websiteKey": widget.sitekey,
+                    },
+                }),
+            )
+            .await?;
+        let task_id = created.get("taskId").cloned().ok_or_else(|| anyhow!("createTask returned no taskId"))?;
+
+        let deadline = Instant::now() + Duration::from_secs(self.config.timeout_secs);
+        while Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_secs(self.config.poll_interval_secs)).await;
+            let result = self.call("getTaskResult", json!({ "clientKey": self.api_key, "taskId": task_id })).await?;
+            if result.get("status").and_then(Value::as_str) != Some("ready") {
+                continue;
+            }
+            let solution = result.get("solution");
+            return ["gRecaptchaResponse", "token"]
+                .iter()
+                .find_map(|field| solution?.get(*field)?.as_str())
+                .map(str::to_string)
+                .ok_or_else(|| anyhow!("solution carries no token"));
+        }
+        Err(anyhow!("no answer within {}s", self.config.timeout_secs))
+    }
+
+    /// One API call; a non-zero `errorId` is an error.
+    async fn call(&self, method: &str, body: Value) -> Result<Value> {
+        let url = format!("{}/{}", self.endpoint, method);
+        let resp: Value = self.client.post(&url).json(&body).send().await?.json().await?;
+        if resp.get("errorId").and_then(Value::as_i64).unwrap_or(0) != 0 {
+            let code = resp.get("errorCode").and_then(Value::as_str).unwrap_or("unknown");
+            let description = resp.get("errorDescription").and_then(Value::as_str).unwrap_or("");
+            return Err(anyhow!("{} failed: {} {}", method, code, description));
+        }
+        Ok(resp)
+    }
+}
@@ -0,0 +1,161 @@
+use crate::engine::Verdict;
+use anyhow::{anyhow, Result};
+use rquest::header::HeaderMap;
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+// User-defined verdict categories (`[[analyzer.categories]]`) beyond success / blocked /
+// challenge: "Geo blocked", "Maintenance", "Paywall"... Each has its own match conditions,
+// counter, TUI color and reaction. They are checked after `[[analyzer.rules]]` and before
+// the built-in heuristics.
+
+/// What a worker does after a response lands in the category.
+#[derive(Debug, Clone, Copy, Default, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CategoryAction {
+    /// Record it and carry on with the same identity.
+    #[default]
+    Count,
+    /// Drop the session and continue on a fresh identity and node.
+    Rotate,
+    /// Wait `pause_secs`, then carry on.
+    Pause,
+    /// Drain and end the run.
+    Stop,
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct VerdictCategory {
+    pub name: String,
+    /// Status codes that qualify; any when empty.
+    #[serde(default)]
+    pub status: Vec<u16>,
+    /// Case-insensitive body phrase.
+    #[serde(default)]
+    pub body: Option<String>,
+    /// Response header that must be present...
+    #[serde(default)]
+    pub header: Option<String>,
+    /// ...with a value containing this, when set.
+    #[serde(default)]
+    pub value: Option<String>,
+    /// TUI color: a name (`magenta`, `lightblue`) or `#rrggbb`.
+    #[serde(default)]
+    pub color: Option<String>,
+    #[serde(default)]
+    pub action: CategoryAction,
+    #[serde(default = "default_pause_secs")]
+    pub pause_secs: u64,
+}
+
+fn default_pause_secs() -> u64 {
+    30
+}
+
+/// A category's count, as the stats and the report list it.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct CategoryCount {
+    pub name: String,
+    pub count: usize,
+}
+
+impl VerdictCategory {
+    fn validate(&self) -> Result<()> {
+        if self.name.trim().is_empty() {
+            return Err(anyhow!("Verdict category with an empty name"));
+        }
+        if self.status.is_empty() && self.body.is_none() && self.header.is_none() {
+            return Err(anyhow!("Category '{}' needs a status, body phrase or header to match on", self.name));
+        }
+        if self.body.as_deref().is_some_and(|b| b.trim().is_empty()) {
+            return Err(anyhow!("Category '{}' has an empty body phrase", self.name));
+        }
+        if self.value.is_some() && self.header.is_none() {
+            return Err(anyhow!("Category '{}' sets a header value without a header", self.name));
+        }
+        if let Some(color) = &self.color {
+            if color.parse::<ratatui::style::Color>().is_err() {
+                return Err(anyhow!("Category '{}' has an unknown color '{}'", self.name, color));
+            }
+        }
+        Ok(())
+    }
+
+    /// Every condition that is set must hold.
+    fn matches(&self, status: u16, headers: &HeaderMap, body_lower: &str) -> bool {
+        if !self.status.is_empty() && !self.status.contains(&status) {
+            return false;
+        }
+        if let Some(phrase) = &self.body {
+            if !body_lower.contains(&phrase.to_lowercase()) {
+                return false;
+            }
+        }
+        if let Some(name) = &self.header {
+            let found = headers.get_all(name.as_str()).iter().any(|v| match &self.value {
+                Some(want) => v.to_str().map(|v| v.to_lowercase().contains(&want.to_lowercase())).unwrap_or(false),
+                None => true,
+            });
+            if !found {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// The configured categories and their counters, shared by the workers, TUI and report.
+#[derive(Debug, Clone, Default)]
+pub struct Categories {
+    list: Arc<Vec<VerdictCategory>>,
+    counts: Arc<Vec<AtomicUsize>>,
+}
+
+impl Categories {
+    pub fn new(list: Vec<VerdictCategory>) -> Result<Self> {
+        let mut names = HashSet::new();
+        for category in &list {
+            category.validate()?;
+            if !names.insert(category.name.to_lowercase()) {
+                return Err(anyhow!("Verdict category '{}' is defined twice", category.name));
+            }
+        }
+        let counts = list.iter().map(|_| AtomicUsize::new(0)).collect();
+        Ok(Self { list: Arc::new(list), counts: Arc::new(counts) })
+    }
+
+    /// `Verdict::Custom` for the first matching category, in config order.
+    pub fn first_match(&self, status: u16, headers: &HeaderMap, body: &str) -> Option<Verdict> {
+        if self.list.is_empty() {
+            return None;
+        }
+        let body_lower = body.to_lowercase();
+        self.list
+            .iter()
+            .find(|c| c.matches(status, headers, &body_lower))
+            .map(|c| Verdict::Custom(c.name.clone()))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&VerdictCategory> {
+        self.list.iter().find(|c| c.name == name)
+    }
+
+    pub fn record(&self, name: &str) {
+        if let Some(i) = self.list.iter().position(|c| c.name == name) {
+            self.counts[i].fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Categories with their counts and colors, in config order.
+    pub fn counts(&self) -> Vec<(&VerdictCategory, usize)> {
+        self.list.iter().zip(self.counts.iter()).map(|(c, n)| (c, n.load(Ordering::Relaxed))).collect()
+    }
+
+    pub fn snapshot(&self) -> Vec<CategoryCount> {
+        self.counts()
+            .into_iter()
+            .map(|(c, count)| CategoryCount { name: c.name.clone(), count })
+            .collect()
+    }
+}
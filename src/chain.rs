@@ -0,0 +1,156 @@
+use crate::engine::ProxyEndpoint;
+use crate::raw::{RawClient, RawStream, RawTarget};
+use anyhow::{anyhow, Context, Result};
+use base64::Engine as _;
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
+use std::sync::RwLock;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+// Proxy chaining (proxy-over-proxy). A node with `via` is reached through a first hop,
+// typically a corporate egress proxy in front of the proxy farm. Each engine listens on a
+// loopback port per chained node and relays each connection through an HTTP CONNECT
+// tunnel opened on the first hop. Clients, browsers and health probes are handed the
+// loopback address in place of the exit's (same scheme and credentials), so they still
+// speak to the exit end to end.
+
+/// Longest CONNECT response head accepted from the first hop.
+const MAX_HEAD: usize = 8 * 1024;
+/// Longest the first hop gets to accept the connection and answer CONNECT.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(15);
+
+#[derive(Debug)]
+struct Tunnel {
+    /// First hop, as configured.
+    via: String,
+    addr: SocketAddr,
+    listener: JoinHandle<()>,
+}
+
+impl Drop for Tunnel {
+    fn drop(&mut self) {
+        self.listener.abort();
+    }
+}
+
+/// One engine's tunnels, keyed by the node as configured (before `{session_id}` is filled
+/// in). Engines never share them, and dropping the set closes its listeners.
+#[derive(Debug, Default)]
+pub struct ChainTunnels {
+    tunnels: RwLock<HashMap<String, Tunnel>>,
+}
+
+impl ChainTunnels {
+    /// Starts the tunnel for `node` through `via`, unless it already has one through that hop.
+    pub async fn open(&self, node: &str, via: &str) -> Result<SocketAddr> {
+        let exit = ProxyEndpoint::parse(node)?;
+        let hop = ProxyEndpoint::parse(via)?;
+        if hop.is_socks() {
+            return Err(anyhow!("Chain hop {} must be an http(s) proxy that supports CONNECT", hop.server()));
+        }
+        // The client would check the exit's certificate against the loopback address
+        if exit.scheme == "https" {
+            return Err(anyhow!("https exits can't be chained; use the exit's http or socks5 endpoint"));
+        }
+        if let Some(tunnel) = self.tunnels.read().unwrap().get(node).filter(|t| t.via == via) {
+            return Ok(tunnel.addr);
+        }
+        let listener = TcpListener::bind("127.0.0.1:0").await.context("Failed to bind chain tunnel listener")?;
+        let addr = listener.local_addr()?;
+        let listener = tokio::spawn(async move {
+            while let Ok((inbound, _)) = listener.accept().await {
+                let (hop, host, port) = (hop.clone(), exit.host.clone(), exit.port);
+                tokio::spawn(async move {
+                    if let Err(e) = relay(inbound, &hop, &host, port).await {
+                        log::debug!("Chain {} -> {}:{} failed: {:#}", hop.server(), host, port, e);
+                    }
+                });
+            }
+        });
+        // A tunnel through a different hop (the node's `via` changed on reload) is replaced
+        self.tunnels.write().unwrap().insert(node.to_string(), Tunnel { via: via.to_string(), addr, listener });
+        Ok(addr)
+    }
+
+    /// Closes the tunnels of nodes no longer in `chained`.
+    pub fn retain(&self, chained: &HashSet<&str>) {
+        self.tunnels.write().unwrap().retain(|node, _| chained.contains(node.as_str()));
+    }
+
+    /// `url`, the proxy URL for `node` (session id filled in), pointed at the node's tunnel
+    /// when it is chained, else unchanged.
+    pub fn route(&self, node: &str, url: &str) -> String {
+        let Some(addr) = self.tunnels.read().unwrap().get(node).map(|t| t.addr) else {
+            return url.to_string();
+        };
+        let Ok(endpoint) = ProxyEndpoint::parse(url) else {
+            return url.to_string();
+        };
+        let userinfo = match (&endpoint.username, &endpoint.password) {
+            (Some(user), Some(pass)) => format!("{}:{}@", urlencoding::encode(user), urlencoding::encode(pass)),
+            (Some(user), None) => format!("{}@", urlencoding::encode(user)),
+            _ => String::new(),
+        };
+        format!("{}://{}{}", endpoint.scheme, userinfo, addr)
+    }
+
+    /// Tunnels up, for the startup log.
+    pub fn len(&self) -> usize {
+        self.tunnels.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+async fn relay(mut inbound: TcpStream, via: &ProxyEndpoint, host: &str, port: u16) -> Result<()> {
+    let hop = RawTarget { host: via.host.clone(), port: via.port, tls: via.scheme == "https", path: String::new() };
+    let opened = tokio::time::timeout(CONNECT_TIMEOUT, async {
+        let mut upstream = RawClient::default().connect(&hop).await?;
+        connect(&mut upstream, via, host, port).await?;
+        Ok::<_, anyhow::Error>(upstream)
+    })
+    .await;
+    let mut upstream = opened.map_err(|_| anyhow!("{} did not answer CONNECT within {}s", via.server(), CONNECT_TIMEOUT.as_secs()))??;
+    tokio::io::copy_bidirectional(&mut inbound, &mut upstream).await?;
+    Ok(())
+}
+
+/// Sends CONNECT for `host:port` and reads the first hop's answer. The head is read a byte
+/// at a time so nothing the exit sends after it is swallowed.
+async fn connect(stream: &mut Box<dyn RawStream>, via: &ProxyEndpoint, host: &str, port: u16) -> Result<()> {
+    let mut request = format!("CONNECT {0}:{1} HTTP/1.1\r\nHost: {0}:{1}\r\n", host, port);
+    if let Some(user) = &via.username {
+        let credentials = format!("{}:{}", user, via.password.as_deref().unwrap_or(""));
+        request.push_str(&format!(
+            "Proxy-Authorization: Basic {}\r\n",
+            base64::engine::general_purpose::STANDARD.encode(credentials)
+        ));
+    }
+    request.push_str("\r\n");
+    stream.write_all(request.as_bytes()).await?;
+    stream.flush().await?;
+
+    let mut head = Vec::new();
+    let mut byte = [0u8; 1];
+    while !head.ends_with(b"\r\n\r\n") {
+        if head.len() >= MAX_HEAD {
+            return Err(anyhow!("CONNECT response from {} too long", via.server()));
+        }
+        if stream.read(&mut byte).await? == 0 {
+            return Err(anyhow!("{} closed the connection during CONNECT", via.server()));
+        }
+        head.push(byte[0]);
+    }
+    let head = String::from_utf8_lossy(&head);
+    let status = head.split_whitespace().nth(1).and_then(|s| s.parse::<u16>().ok());
+    match status {
+        Some(200..=299) => Ok(()),
+        Some(407) => Err(anyhow!("{} rejected the chain credentials (407)", via.server())),
+        _ => Err(anyhow!("{} refused CONNECT {}:{}: {}", via.server(), host, port, head.lines().next().unwrap_or(""))),
+    }
+}
@@ -0,0 +1,165 @@
+use anyhow::{anyhow, Context, Result};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncWriteExt;
+
+// Opt-in Chrome download. When no Chrome is configured or installed, `[browser.download]`
+// fetches a pinned Chrome for Testing build into a cache directory and the solver uses
+// that. Google publishes no checksums for these archives, so the config carries the
+// SHA-256 of each platform's zip and nothing unverified is ever unpacked.
+
+pub const PINNED_VERSION: &str = "131.0.6778.85";
+
+const DOWNLOAD_BASE: &str = "https://storage.googleapis.com/chrome-for-testing-public";
+
+/// `[browser.download]`.
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct DownloadConfig {
+    #[serde(default = "default_version")]
+    pub version: String,
+    /// SHA-256 of the zip per Chrome for Testing platform (`linux64`, `mac-arm64`,
+    /// `mac-x64`, `win64`, `win32`). Only this machine's entry is needed.
+    #[serde(default)]
+    pub sha256: HashMap<String, String>,
+    /// Defaults to `$XDG_CACHE_HOME/spectre/chrome`, else `~/.cache/spectre/chrome`.
+    #[serde(default)]
+    pub cache_dir: Option<String>,
+}
+
+fn default_version() -> String {
+    PINNED_VERSION.to_string()
+}
+
+/// This machine's Chrome for Testing platform.
+pub fn platform() -> Option<&'static str> {
+    match (std::env::consts::OS, std::env::consts::ARCH) {
+        ("linux", "x86_64") => Some("linux64"),
+        ("macos", "aarch64") => Some("mac-arm64"),
+        ("macos", "x86_64") => Some("mac-x64"),
+        ("windows", "x86_64") => Some("win64"),
+        ("windows", "x86") => Some("win32"),
+        _ => None,
+    }
+}
+
+/// The binary's path inside an unpacked archive.
+fn binary_in(platform: &str) -> PathBuf {
+    let root = PathBuf::from(format!("chrome-{}", platform));
+    match platform {
+        "mac-arm64" | "mac-x64" => root
+            .join("Google Chrome for Testing.app")
+            .join("Contents")
+            .join("MacOS")
+            .join("Google Chrome for Testing"),
+        "win64" | "win32" => root.join("chrome.exe"),
+        _ => root.join("chrome"),
+    }
+}
+
+impl DownloadConfig {
+    pub fn cache_dir(&self) -> PathBuf {
+        if let Some(dir) = &self.cache_dir {
+            return PathBuf::from(dir);
+        }
+        let base = std::env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+            .unwrap_or_else(|| PathBuf::from(".cache"));
+        base.join("spectre").join("chrome")
+    }
+
+    fn install_dir(&self, platform: &str) -> PathBuf {
+        self.cache_dir().join(&self.version).join(platform)
+    }
+
+    /// What `fetch` would download for this machine: (platform, URL, expected SHA-256).
+    pub fn plan(&self) -> Result<(&'static str, String, String)> {
+        let platform = platform().ok_or_else(|| {
+            anyhow!("no Chrome for Testing build for {}/{}", std::env::consts::OS, std::env::consts::ARCH)
+        })?;
+        let sha256 = self.sha256.get(platform).ok_or_else(|| {
+            anyhow!("browser.download.sha256 has no entry for {}; add the SHA-256 of the {} zip", platform, self.version)
+        })?;
+        if sha256.len() != 64 || !sha256.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(anyhow!("browser.download.sha256.{} is not a SHA-256 hex digest", platform));
+        }
+        let url = format!("{}/{}/{}/chrome-{}.zip", DOWNLOAD_BASE, self.version, platform, platform);
+        Ok((platform, url, sha256.to_lowercase()))
+    }
+
+    /// The downloaded binary, if this version is in the cache.
+    pub fn installed(&self) -> Option<PathBuf> {
+        let platform = platform()?;
+        let path = self.install_dir(platform).join(binary_in(platform));
+        path.is_file().then_some(path)
+    }
+
+    /// Downloads, verifies and unpacks the pinned build unless it is cached; returns the binary.
+    pub async fn fetch(&self) -> Result<PathBuf> {
+        if let Some(path) = self.installed() {
+            return Ok(path);
+        }
+        let (platform, url, expected) = self.plan()?;
+        log::info!("Downloading Chrome for Testing {} ({})", self.version, platform);
+        let dir = self.install_dir(platform);
+        let archive = dir.with_extension("zip.partial");
+        let actual = match download(&url, &archive).await {
+            Ok(actual) => actual,
+            Err(e) => {
+                let _ = tokio::fs::remove_file(&archive).await;
+                return Err(e);
+            }
+        };
+        if actual != expected {
+            let _ = tokio::fs::remove_file(&archive).await;
+            return Err(anyhow!("Chrome archive checksum mismatch: expected {}, got {}", expected, actual));
+        }
+
+        let install = dir.clone();
+        let unpacked = tokio::task::spawn_blocking(move || install_from(&archive, &install)).await?;
+        unpacked?;
+        let path = dir.join(binary_in(platform));
+        if !tokio::fs::try_exists(&path).await.unwrap_or(false) {
+            return Err(anyhow!("archive has no {}", binary_in(platform).display()));
+        }
+        log::info!("Chrome for Testing {} installed at {}", self.version, path.display());
+        Ok(path)
+    }
+}
+
+/// Streams `url` to `path`, hashing as it goes, so the archive (over 150 MB) is never held
+/// in memory. Returns the SHA-256 hex digest.
+async fn download(url: &str, path: &Path) -> Result<String> {
+    let mut resp = rquest::Client::builder().build()?.get(url).send().await?;
+    if !resp.status().is_success() {
+        return Err(anyhow!("{} answered {}", url, resp.status()));
+    }
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await.with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    let mut file = tokio::fs::File::create(path).await.with_context(|| format!("Failed to create {}", path.display()))?;
+    let mut hasher = Sha256::new();
+    while let Some(chunk) = resp.chunk().await.context("Chrome download interrupted")? {
+        hasher.update(&chunk);
+        file.write_all(&chunk).await?;
+    }
+    file.flush().await?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Unpacks the verified archive next to `dir` and moves it in, so a half-written tree is
+/// never used, then deletes the archive. Blocking: runs off the async runtime.
+fn install_from(archive: &Path, dir: &Path) -> Result<()> {
+    let staging = dir.with_extension("partial");
+    let _ = std::fs::remove_dir_all(&staging);
+    std::fs::create_dir_all(&staging).with_context(|| format!("Failed to create {}", staging.display()))?;
+    let file = std::fs::File::open(archive).with_context(|| format!("Failed to open {}", archive.display()))?;
+    let mut zip = zip::ZipArchive::new(file).context("Chrome archive is not a zip")?;
+    // Keeps the executable bits on unix
+    zip.extract(&staging).context("Failed to unpack Chrome")?;
+    let _ = std::fs::remove_dir_all(dir);
+    std::fs::rename(&staging, dir).with_context(|| format!("Failed to move Chrome into {}", dir.display()))?;
+    let _ = std::fs::remove_file(archive);
+    Ok(())
+}
@@ -1,8 +1,11 @@
-use clap::Parser;
+use clap::{Parser, Subcommand};
 
 #[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
+#[command(author, version, about, long_about = None, subcommand_negates_reqs = true)]
 pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     /// MANDATORY: confirms that you have authorization to test the target.
     #[arg(long, required = true)]
     pub authorized: bool,
@@ -47,6 +50,10 @@ pub struct Cli {
     #[arg(long, value_delimiter = ',', num_args = 0..)]
     pub tamper: Option<Vec<String>>,
 
+    /// Preflight: validate config, DNS, Chrome and proxies, print a readiness report and exit.
+    #[arg(long)]
+    pub check: bool,
+
     /// Enable WAF detection before starting attacks.
     #[arg(long)]
     pub detect: bool,
@@ -59,7 +66,147 @@ pub struct Cli {
     #[arg(long)]
     pub time_limit: Option<u64>,
 
+    /// Wordlist file; entries replace `{word}` in the target URL (one pass).
+    #[arg(long)]
+    pub wordlist: Option<String>,
+
+    /// Global request rate cap (requests per second, across all workers).
+    #[arg(long)]
+    pub max_rps: Option<f64>,
+
+    /// Cache-busting: unique query parameter + no-cache headers on every request.
+    #[arg(long)]
+    pub cache_bust: bool,
+
+    /// Cache-hit ratio mode: repeat cacheable URLs and report the CDN hit ratio, then exit.
+    #[arg(long)]
+    pub cache_ratio: bool,
+
+    /// Requests per URL in cache-hit ratio mode.
+    #[arg(long, default_value_t = 10)]
+    pub cache_repeats: usize,
+
+    /// Allow connecting to the target directly, without a proxy.
+    #[arg(long)]
+    pub direct: bool,
+
+    /// Mirror a sample of requests to this secondary target URL (e.g. a staging WAF policy).
+    #[arg(long)]
+    pub shadow: Option<String>,
+
+    /// Percentage of requests mirrored to the shadow target.
+    #[arg(long)]
+    pub shadow_percent: Option<f64>,
+
+    /// Listen for JSON control commands on this Unix socket (e.g. /tmp/spectre.sock).
+    #[arg(long)]
+    pub control_socket: Option<String>,
+
+    /// Origin exposure check: comma-separated candidate origin IPs to hit directly (Host/SNI kept).
+    #[arg(long, value_delimiter = ',')]
+    pub origin_scan: Option<Vec<String>>,
+
+    /// Requests sent per path during the origin exposure check.
+    #[arg(long, default_value_t = 5)]
+    pub origin_samples: usize,
+
+    /// Opt-in gate for intrusive probe modules (e.g. --smuggle-probe).
+    #[arg(long)]
+    pub enable_intrusive: bool,
+
+    /// Timing-based CL.TE / TE.CL desync probe against the target, then exit. Requires --enable-intrusive.
+    #[arg(long)]
+    pub smuggle_probe: bool,
+
+    /// Raw-socket variant mode: send the request with unusual header casing/folding/ordering, then exit.
+    #[arg(long)]
+    pub raw_variants: bool,
+
+    /// Requests sent per variant in raw-socket variant mode.
+    #[arg(long, default_value_t = 3)]
+    pub variant_samples: usize,
+
+    /// Header-mutation campaign: mutate one header dimension at a time and report which trigger challenges, then exit.
+    #[arg(long)]
+    pub header_fuzz: bool,
+
+    /// Profile whose TLS fingerprint the header campaign uses.
+    #[arg(long, default_value = "desktop")]
+    pub fuzz_profile: String,
+
+    /// Requests sent per mutation in the header campaign.
+    #[arg(long, default_value_t = 3)]
+    pub fuzz_samples: usize,
+
+    /// Print the exact on-the-wire request head (order and casing) for this profile, then exit.
+    #[arg(long)]
+    pub dump_headers: Option<String>,
+
+    /// Print the resolved custom TLS spec and its JA3 for this profile, then exit.
+    #[arg(long)]
+    pub tls_fingerprint: Option<String>,
+
+    /// Show the HTTP/2 (Akamai) fingerprint for this profile, then exit.
+    #[arg(long)]
+    pub h2_fingerprint: Option<String>,
+
+    /// Fingerprint echo service to query with --h2-fingerprint (e.g. https://tls.peet.ws/api/all).
+    #[arg(long)]
+    pub echo_url: Option<String>,
+
+    /// Rebuild a report from a crash journal (written with --report, else printed as JSON), then exit.
+    #[arg(long)]
+    pub recover: Option<String>,
+
+    /// Verify a report against its `.sig` file, then exit.
+    #[arg(long)]
+    pub verify_report: Option<String>,
+
+    /// Hex ed25519 public key the report must be signed with (for --verify-report).
+    #[arg(long)]
+    pub public_key: Option<String>,
+
+    /// Convert a binary ring-buffer recording to JSONL on stdout, then exit.
+    #[arg(long)]
+    pub convert_recording: Option<String>,
+
+    /// Single-line progress bar on stderr instead of the full TUI.
+    #[arg(long)]
+    pub progress: bool,
+
+    /// Skip the startup check for a newer release.
+    #[arg(long)]
+    pub no_version_check: bool,
+
     /// Run in REST API mode.
     #[arg(long)]
     pub api: bool,
 }
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Replace this binary with the latest GitHub release.
+    SelfUpdate {
+        /// Only report whether a newer release exists.
+        #[arg(long)]
+        check: bool,
+    },
+    /// Chart success rate, challenge rate and latency per target across past runs.
+    Trends {
+        /// Directory searched (recursively) for reports, journals and sink summaries.
+        #[arg(long, default_value = "logs")]
+        dir: String,
+        /// SQLite sink database whose `summaries` table is read as well.
+        #[arg(long)]
+        db: Option<String>,
+        /// Only this target URL.
+        #[arg(long)]
+        target: Option<String>,
+        /// Days per data point.
+        #[arg(long, default_value_t = 7)]
+        bucket_days: u64,
+        /// Write the trends to a .html (charts) or .json file instead of printing a table.
+        #[arg(long)]
+        out: Option<String>,
+    },
+}
@@ -0,0 +1,129 @@
+use crate::engine::{EngineStats, StatsSnapshot};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::Ordering;
+
+// Local control channel: newline-delimited JSON commands over a Unix socket, e.g.
+//   echo '{"cmd":"stats"}' | nc -U /tmp/spectre.sock
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "cmd", rename_all = "kebab-case")]
+pub enum ControlCommand {
+    Stats,
+    Pause,
+    Resume,
+    SetConcurrency { value: usize },
+    /// Emergency stop: cancel everything now and freeze the run.
+    Gtfo,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum ControlResponse {
+    Stats(StatsSnapshot),
+    Ok { ok: bool, message: String },
+    Error { ok: bool, error: String },
+}
+
+pub fn handle_command(line: &str, stats: &EngineStats) -> ControlResponse {
+    let cmd: ControlCommand = match serde_json::from_str(line) {
+        Ok(c) => c,
+        Err(e) => {
+            return ControlResponse::Error {
+                ok: false,
+                error: format!("Invalid command: {}", e),
+            }
+        }
+    };
+
+    match cmd {
+        ControlCommand::Stats => ControlResponse::Stats(stats.snapshot()),
+        ControlCommand::Pause => {
+            stats.control.paused.store(true, Ordering::Relaxed);
+            ControlResponse::Ok { ok: true, message: "paused".into() }
+        }
+        ControlCommand::Resume => {
+            stats.control.paused.store(false, Ordering::Relaxed);
+            ControlResponse::Ok { ok: true, message: "resumed".into() }
+        }
+        ControlCommand::SetConcurrency { value } => {
+            let applied = stats.control.set_concurrency(value);
+            ControlResponse::Ok {
+                ok: true,
+                message: format!("concurrency set to {}", applied),
+            }
+        }
+        ControlCommand::Gtfo => {
+            if stats.shutdown.halt("control socket") {
+                ControlResponse::Ok { ok: true, message: "halted".into() }
+            } else {
+                ControlResponse::Ok { ok: true, message: "already halted".into() }
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+pub async fn serve(path: String, stats: EngineStats) -> anyhow::Result<()> {
+    use anyhow::{anyhow, Context};
+    use std::os::unix::fs::{FileTypeExt, MetadataExt};
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::UnixListener;
+
+    // A stale socket from a previous run would make bind fail. Only a socket nobody
+    // listens on is replaced; anything else at the path is left alone.
+    if let Ok(meta) = std::fs::symlink_metadata(&path) {
+        if !meta.file_type().is_socket() {
+            return Err(anyhow!("Control socket path {} exists and is not a socket", path));
+        }
+        if std::os::unix::net::UnixStream::connect(&path).is_ok() {
+            return Err(anyhow!("Control socket {} is in use by another process", path));
+        }
+        std::fs::remove_file(&path).context(format!("Failed to remove stale control socket {}", path))?;
+    }
+    let listener = UnixListener::bind(&path).context(format!("Failed to bind control socket: {}", path))?;
+    let _cleanup = SocketCleanup { path: path.clone(), ino: std::fs::symlink_metadata(&path)?.ino() };
+    log::info!("Control socket listening on {}", path);
+
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let stats = stats.clone();
+        tokio::spawn(async move {
+            let (reader, mut writer) = stream.into_split();
+            let mut lines = BufReader::new(reader).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let response = handle_command(&line, &stats);
+                let mut out = serde_json::to_string(&response).unwrap_or_else(|_| "{}".to_string());
+                out.push('\n');
+                if writer.write_all(out.as_bytes()).await.is_err() {
+                    break;
+                }
+            }
+        });
+    }
+}
+
+/// Removes the control socket when `serve` ends, if the path is still the socket it bound.
+#[cfg(unix)]
+struct SocketCleanup {
+    path: String,
+    ino: u64,
+}
+
+#[cfg(unix)]
+impl Drop for SocketCleanup {
+    fn drop(&mut self) {
+        use std::os::unix::fs::{FileTypeExt, MetadataExt};
+        let ours = std::fs::symlink_metadata(&self.path).is_ok_and(|m| m.file_type().is_socket() && m.ino() == self.ino);
+        if ours {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+pub async fn serve(path: String, _stats: EngineStats) -> anyhow::Result<()> {
+    Err(anyhow::anyhow!("Control socket {} is only supported on Unix platforms", path))
+}
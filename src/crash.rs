@@ -0,0 +1,70 @@
+use crate::engine::SpectreLogger;
+use crate::tui;
+use std::backtrace::Backtrace;
+use std::panic::PanicHookInfo;
+use std::sync::{Once, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Process-wide panic hook: puts the terminal back, records the panic in the session log,
+// and writes `logs/crash_<ts>.txt` with the message and a backtrace before the default
+// hook prints its usual message.
+
+static LOGGER: OnceLock<SpectreLogger> = OnceLock::new();
+static INSTALL: Once = Once::new();
+
+/// Installs the hook; later calls are no-ops.
+pub fn install() {
+    INSTALL.call_once(|| {
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            tui::restore_terminal();
+            let report = crash_report(info);
+            if let Some(logger) = LOGGER.get() {
+                let meta = serde_json::to_string(&report).unwrap_or_else(|_| "null".into());
+                logger.log("Engine", "PANIC", "Process panicked", Some(&meta));
+                logger.flush();
+            }
+            match write_report(&report) {
+                Some(path) => eprintln!("[\x1b[31m!\x1b[0m] Spectre crashed; crash report written to {}", path),
+                None => eprintln!("[\x1b[31m!\x1b[0m] Spectre crashed; could not write a crash report"),
+            }
+            previous(info);
+        }));
+    });
+}
+
+/// Session log the hook records panics in; the first engine's logger wins.
+pub fn register_logger(logger: &SpectreLogger) {
+    let _ = LOGGER.set(logger.clone());
+}
+
+fn crash_report(info: &PanicHookInfo) -> String {
+    let message = info
+        .payload()
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| info.payload().downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "non-string panic payload".to_string());
+    let location = info
+        .location()
+        .map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column()))
+        .unwrap_or_else(|| "unknown".to_string());
+    let thread = std::thread::current().name().unwrap_or("unnamed").to_string();
+    format!(
+        "spectre {} panicked\nthread: {}\nlocation: {}\nmessage: {}\nsession log: {}\n\nbacktrace:\n{}\n",
+        env!("CARGO_PKG_VERSION"),
+        thread,
+        location,
+        message,
+        LOGGER.get().map(|l| l.path()).unwrap_or("-"),
+        Backtrace::force_capture()
+    )
+}
+
+fn write_report(report: &str) -> Option<String> {
+    let ts = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+    std::fs::create_dir_all("logs").ok()?;
+    let path = format!("logs/crash_{}.txt", ts);
+    std::fs::write(&path, report).ok()?;
+    Some(path)
+}
@@ -0,0 +1,72 @@
+// UTC calendar dates from Unix time, for trend buckets, HAR timestamps and request signing.
+// std has no calendar and three call sites don't justify a date crate, so the civil-date
+// conversion lives here once instead of being sliced out of another format.
+
+/// A point in time broken down into UTC calendar fields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UtcDateTime {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+}
+
+impl UtcDateTime {
+    pub fn from_unix(secs: u64) -> Self {
+        let (days, rem) = ((secs / 86_400) as i64, secs % 86_400);
+        // Days since epoch -> civil date (Howard Hinnant's algorithm)
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z - era * 146_097;
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+        Self {
+            year,
+            month: month as u32,
+            day: day as u32,
+            hour: (rem / 3600) as u32,
+            minute: (rem % 3600 / 60) as u32,
+            second: (rem % 60) as u32,
+        }
+    }
+
+    /// `2024-05-01`.
+    pub fn date(&self) -> String {
+        format!("{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+
+    /// `2024-05-01T12:00:00.123Z`.
+    pub fn iso8601(&self, millis: u32) -> String {
+        format!("{}T{:02}:{:02}:{:02}.{:03}Z", self.date(), self.hour, self.minute, self.second, millis.min(999))
+    }
+
+    /// `20240501T120000Z` and `20240501`, as SigV4 wants them.
+    pub fn compact(&self) -> (String, String) {
+        let date = format!("{:04}{:02}{:02}", self.year, self.month, self.day);
+        (format!("{}T{:02}{:02}{:02}Z", date, self.hour, self.minute, self.second), date)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::UtcDateTime;
+
+    #[test]
+    fn epoch_is_first_of_january_1970() {
+        assert_eq!(UtcDateTime::from_unix(0).iso8601(0), "1970-01-01T00:00:00.000Z");
+    }
+
+    #[test]
+    fn handles_leap_days_and_time_of_day() {
+        assert_eq!(UtcDateTime::from_unix(951_782_400).date(), "2000-02-29");
+        let t = UtcDateTime::from_unix(1_700_000_000);
+        assert_eq!(t.iso8601(42), "2023-11-14T22:13:20.042Z");
+        assert_eq!(t.compact(), ("20231114T221320Z".to_string(), "20231114".to_string()));
+    }
+}
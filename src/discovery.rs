@@ -0,0 +1,197 @@
+use crate::engine::{BodyReader, EngineStats, RateLimiter};
+use crate::safety::SafetyConfig;
+use anyhow::{anyhow, Result};
+use regex::Regex;
+use rquest::header::HeaderMap;
+use rquest::Url;
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+// Endpoint discovery. Successful HTML and JS responses are scanned for same-origin API
+// URLs in `fetch(...)`, XHR `open(...)`, axios calls and `/api/...`-style path literals;
+// same-origin `<script src>` bundles are fetched once each and scanned the same way.
+// The report lists what was found as proposed targets; nothing is requested beyond the
+// scripts themselves.
+
+/// Bytes of a fetched script that get scanned; bundles beyond this are truncated.
+const SCRIPT_BYTES: usize = 2 * 1024 * 1024;
+
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct DiscoveryConfig {
+    /// Stop recording new endpoints past this many.
+    #[serde(default = "default_max_endpoints")]
+    pub max_endpoints: usize,
+    /// Fetch same-origin `<script src>` files and scan them too.
+    #[serde(default = "default_fetch_scripts")]
+    pub fetch_scripts: bool,
+}
+
+fn default_max_endpoints() -> usize {
+    200
+}
+
+fn default_fetch_scripts() -> bool {
+    true
+}
+
+/// An endpoint as the report lists it.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct DiscoveredEndpoint {
+    /// Absolute URL; `${...}` template parts become `{param}`.
+    pub url: String,
+    /// Methods seen in XHR/axios calls; empty when only the URL was found.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub methods: Vec<String>,
+    /// Page or script it was first found in.
+    pub source: String,
+    /// `fetch`, `xhr`, `axios` or `literal`.
+    pub via: String,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    endpoints: BTreeMap<String, DiscoveredEndpoint>,
+    scripts_seen: HashSet<String>,
+    pending_scripts: VecDeque<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Discovery {
+    origin: Url,
+    config: DiscoveryConfig,
+    safety: SafetyConfig,
+    state: Arc<Mutex<State>>,
+}
+
+lazy_static::lazy_static! {
+    static ref FETCH: Regex = Regex::new(r#"\bfetch\(\s*["'`]([^"'`\s]+)["'`]"#).unwrap();
+    static ref XHR: Regex = Regex::new(r#"(?i)\.open\(\s*["'](GET|POST|PUT|PATCH|DELETE)["']\s*,\s*["'`]([^"'`\s]+)["'`]"#).unwrap();
+    static ref AXIOS: Regex = Regex::new(r#"(?i)\b(?:axios|\$http)\.(get|post|put|patch|delete)\(\s*["'`]([^"'`\s]+)["'`]"#).unwrap();
+    static ref LITERAL: Regex = Regex::new(r#"["'`](/(?:api|graphql|rest|v\d+)(?:[/?][^"'`\s<>]*)?)["'`]"#).unwrap();
+    static ref SCRIPT_SRC: Regex = Regex::new(r#"(?i)<script\b[^>]*\bsrc\s*=\s*["']([^"']+)["']"#).unwrap();
+    static ref TEMPLATE: Regex = Regex::new(r"\$\{[^}]*\}").unwrap();
+}
+
+/// Extensions that are assets, not endpoints.
+const STATIC_EXTENSIONS: &[&str] = &[
+    ".js", ".mjs", ".css", ".map", ".png", ".jpg", ".jpeg", ".gif", ".svg", ".ico", ".webp", ".woff", ".woff2", ".ttf",
+];
+
+impl Discovery {
+    pub fn new(target_url: &str, config: DiscoveryConfig) -> Result<Self> {
+        let origin = Url::parse(target_url).map_err(|e| anyhow!("Invalid target URL for discovery: {}", e))?;
+        Ok(Self { origin, config, safety: SafetyConfig::default(), state: Arc::default() })
+    }
+
+    /// Scripts on hosts outside `safety.allowed_hosts` are dropped instead of fetched.
+    pub fn with_safety(mut self, safety: SafetyConfig) -> Self {
+        self.safety = safety;
+        self
+    }
+
+    /// Scans a response body if it looks like HTML or JavaScript.
+    pub fn scan(&self, page_url: &str, headers: &HeaderMap, body: &str) {
+        let content_type = headers
+            .get(rquest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("")
+            .to_lowercase();
+        let scannable = content_type.is_empty() || ["html", "javascript", "ecmascript"].iter().any(|t| content_type.contains(t));
+        if scannable {
+            self.scan_text(page_url, body);
+        }
+    }
+
+    fn scan_text(&self, page_url: &str, body: &str) {
+        let Ok(base) = Url::parse(page_url) else {
+            return;
+        };
+        let mut found: Vec<(String, Option<String>, &'static str)> = Vec::new();
+        found.extend(FETCH.captures_iter(body).map(|c| (c[1].to_string(), None, "fetch")));
+        found.extend(XHR.captures_iter(body).map(|c| (c[2].to_string(), Some(c[1].to_uppercase()), "xhr")));
+        found.extend(AXIOS.captures_iter(body).map(|c| (c[2].to_string(), Some(c[1].to_uppercase()), "axios")));
+        found.extend(LITERAL.captures_iter(body).map(|c| (c[1].to_string(), None, "literal")));
+
+        let mut state = self.state.lock().unwrap();
+        for (raw, method, via) in found {
+            let Some(url) = self.same_origin(&base, &TEMPLATE.replace_all(&raw, "{param}")) else {
+                continue;
+            };
+            let path = url.path().to_lowercase();
+            if STATIC_EXTENSIONS.iter().any(|ext| path.ends_with(ext)) {
+                continue;
+            }
+            // `join` percent-encodes the placeholder's braces
+            let key = url.to_string().replace("%7Bparam%7D", "{param}");
+            if !state.endpoints.contains_key(&key) && state.endpoints.len() >= self.config.max_endpoints {
+                continue;
+            }
+            let entry = state.endpoints.entry(key.clone()).or_insert_with(|| DiscoveredEndpoint {
+                url: key,
+                methods: Vec::new(),
+                source: page_url.to_string(),
+                via: via.to_string(),
+            });
+            if let Some(m) = method {
+                if !entry.methods.contains(&m) {
+                    entry.methods.push(m);
+                }
+            }
+        }
+        if self.config.fetch_scripts {
+            for c in SCRIPT_SRC.captures_iter(body) {
+                if let Some(url) = self.same_origin(&base, &c[1]) {
+                    let url = url.to_string();
+                    if state.scripts_seen.insert(url.clone()) {
+                        state.pending_scripts.push_back(url);
+                    }
+                }
+            }
+        }
+    }
+
+    /// `raw` resolved against `base`, if it lands on the target's origin.
+    fn same_origin(&self, base: &Url, raw: &str) -> Option<Url> {
+        let mut url = base.join(raw.trim()).ok()?;
+        url.set_fragment(None);
+        (url.origin() == self.origin.origin()).then_some(url)
+    }
+
+    /// Fetches one pending script with `client` and scans it. Returns whether there was one.
+    /// The fetch waits for `limiter` and is counted like any worker request.
+    pub async fn fetch_next_script(&self, client: &rquest::Client, limiter: Option<&RateLimiter>, stats: &EngineStats) -> Result<bool> {
+        let Some(url) = self.state.lock().unwrap().pending_scripts.pop_front() else {
+            return Ok(false);
+        };
+        if !self.safety.allows(&url) {
+            return Ok(true);
+        }
+        if let Some(limiter) = limiter {
+            limiter.acquire().await;
+        }
+        stats.total_requests.fetch_add(1, Ordering::Relaxed);
+        stats.in_flight.fetch_add(1, Ordering::Relaxed);
+        let sent_at = Instant::now();
+        let sent = client.get(&url).send().await;
+        stats.in_flight.fetch_sub(1, Ordering::Relaxed);
+        let resp = match sent {
+            Ok(resp) => resp,
+            Err(e) => {
+                stats.failed_requests.fetch_add(1, Ordering::Relaxed);
+                return Err(e.into());
+            }
+        };
+        stats.latency_total_ms.fetch_add(sent_at.elapsed().as_millis() as u64, Ordering::Relaxed);
+        stats.latency_samples.fetch_add(1, Ordering::Relaxed);
+        let body = BodyReader::read(resp, SCRIPT_BYTES, SCRIPT_BYTES).await?;
+        self.scan_text(&url, &String::from_utf8_lossy(&body.data));
+        Ok(true)
+    }
+
+    /// Endpoints found so far, by URL.
+    pub fn snapshot(&self) -> Vec<DiscoveredEndpoint> {
+        self.state.lock().unwrap().endpoints.values().cloned().collect()
+    }
+}
@@ -0,0 +1,167 @@
+use anyhow::{anyhow, Context, Result};
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+
+// Name resolution overrides shared by the HTTP clients and the solver's Chrome. Static
+// mappings pin a hostname to an address (e.g. an origin behind its CDN hostname); DoH
+// resolves the run's known hosts up front so lookups don't go through the local resolver.
+// `IpFamily` restricts every client to IPv4 or IPv6 so block rates can be compared per family.
+
+/// `network.ip_family`: which address family outbound connections use.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IpFamily {
+    /// Whatever the resolver returns, raced happy-eyeballs style (IPv6 first).
+    #[default]
+    Auto,
+    V4,
+    V6,
+}
+
+impl IpFamily {
+    pub fn allows(&self, ip: &IpAddr) -> bool {
+        match self {
+            IpFamily::Auto => true,
+            IpFamily::V4 => ip.is_ipv4(),
+            IpFamily::V6 => ip.is_ipv6(),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IpFamily::Auto => "auto",
+            IpFamily::V4 => "v4",
+            IpFamily::V6 => "v6",
+        }
+    }
+
+    /// DoH record types to ask for, in order.
+    fn record_types(&self) -> &'static [&'static str] {
+        match self {
+            IpFamily::Auto => &["A", "AAAA"],
+            IpFamily::V4 => &["A"],
+            IpFamily::V6 => &["AAAA"],
+        }
+    }
+
+    /// Resolver that drops addresses of the other family; `None` for `auto`.
+    pub fn resolver(&self) -> Option<Arc<FamilyResolver>> {
+        (*self != IpFamily::Auto).then(|| Arc::new(FamilyResolver { family: *self }))
+    }
+}
+
+/// System resolver filtered to one family. With a single family left there is nothing
+/// for happy eyeballs to race, so connections never fall back to the other one.
+#[derive(Debug)]
+pub struct FamilyResolver {
+    family: IpFamily,
+}
+
+impl rquest::dns::Resolve for FamilyResolver {
+    fn resolve(&self, name: rquest::dns::Name) -> rquest::dns::Resolving {
+        let family = self.family;
+        Box::pin(async move {
+            let host = name.as_str().to_string();
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), 0))
+                .await?
+                .filter(|a| family.allows(&a.ip()))
+                .collect();
+            if addrs.is_empty() {
+                return Err(format!("{} has no {} address", host, family.as_str()).into());
+            }
+            let addrs: rquest::dns::Addrs = Box::new(addrs.into_iter());
+            Ok(addrs)
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+pub struct DnsConfig {
+    /// `host = "ip"` mappings, applied on top of `hosts_file`.
+    #[serde(default)]
+    pub hosts: HashMap<String, String>,
+    /// File in `/etc/hosts` format.
+    #[serde(default)]
+    pub hosts_file: Option<String>,
+    /// DNS-over-HTTPS JSON endpoint, e.g. `https://cloudflare-dns.com/dns-query`.
+    #[serde(default)]
+    pub doh: Option<String>,
+}
+
+impl DnsConfig {
+    /// Static mappings as client resolve overrides (port 0: the URL's port is kept).
+    pub fn static_overrides(&self) -> Result<Vec<(String, SocketAddr)>> {
+        let mut map: HashMap<String, IpAddr> = HashMap::new();
+        if let Some(path) = &self.hosts_file {
+            let content = std::fs::read_to_string(path).context(format!("Failed to read dns.hosts_file {}", path))?;
+            for line in content.lines() {
+                let line = line.split('#').next().unwrap_or("").trim();
+                let mut fields = line.split_whitespace();
+                let Some(ip) = fields.next() else { continue };
+                let ip: IpAddr = ip.parse().context(format!("Invalid address '{}' in {}", ip, path))?;
+                for name in fields {
+                    map.insert(name.to_lowercase(), ip);
+                }
+            }
+        }
+        for (host, ip) in &self.hosts {
+            let ip: IpAddr = ip.parse().context(format!("Invalid address '{}' for dns.hosts.{}", ip, host))?;
+            map.insert(host.to_lowercase(), ip);
+        }
+        Ok(map.into_iter().map(|(host, ip)| (host, SocketAddr::new(ip, 0))).collect())
+    }
+
+    /// Resolves `hosts` through DoH, skipping any with a static mapping. Empty without `doh`.
+    /// `client` should come from the run's client factory (see `Config::doh_client`).
+    pub async fn doh_overrides(
+        &self,
+        client: &rquest::Client,
+        hosts: &[String],
+        family: IpFamily,
+    ) -> Result<Vec<(String, SocketAddr)>> {
+        let Some(endpoint) = &self.doh else {
+            return Ok(Vec::new());
+        };
+        let pinned = self.static_overrides()?;
+        let mut out = Vec::new();
+        for host in hosts {
+            let host = host.to_lowercase();
+            if pinned.iter().any(|(h, _)| *h == host) || host.parse::<IpAddr>().is_ok() {
+                continue;
+            }
+            let ip = Self::doh_lookup(client, endpoint, &host, family).await?;
+            out.push((host, SocketAddr::new(ip, 0)));
+        }
+        Ok(out)
+    }
+
+    /// First A record (then AAAA) for `host` from a DoH JSON API, limited to `family`.
+    async fn doh_lookup(client: &rquest::Client, endpoint: &str, host: &str, family: IpFamily) -> Result<IpAddr> {
+        for record_type in family.record_types() {
+            let body: serde_json::Value = client
+                .get(endpoint)
+                .query(&[("name", host), ("type", *record_type)])
+                .header("accept", "application/dns-json")
+                .send()
+                .await
+                .context(format!("DoH query for {} failed", host))?
+                .json()
+                .await
+                .context(format!("DoH answer for {} is not JSON", host))?;
+            let found = body["Answer"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter(|a| matches!(a["type"].as_u64(), Some(1) | Some(28)))
+                .find_map(|a| a["data"].as_str().and_then(|d| d.parse::<IpAddr>().ok()));
+            if let Some(ip) = found {
+                return Ok(ip);
+            }
+        }
+        match family {
+            IpFamily::Auto => Err(anyhow!("DoH returned no address for {}", host)),
+            _ => Err(anyhow!("DoH returned no {} address for {}", family.as_str(), host)),
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use crate::engine::grid::DIRECT_NODE;
+use crate::engine::Verdict;
+use crate::rules::{self, AnalyzerRule};
+use crate::success::SuccessCriteria;
+use anyhow::{anyhow, Result};
+use rquest::header::HeaderMap;
+use std::collections::HashMap;
+
+// Per-domain policies for scenarios that span several hosts (app + api + cdn). A
+// `[domains."api.example.com"]` table decides, for requests to that host, whether the
+// session's cookies go along, which profile and exit node to use, and which extra analyzer
+// rules apply. Hosts without a table follow the session as before.
+
+/// Whether requests to the domain carry the session's clearance cookies.
+#[derive(Debug, Clone, Copy, Default, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CookieScope {
+    #[default]
+    Shared,
+    /// Own client, no clearance cookies from other domains' solves.
+    Isolated,
+}
+
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+pub struct DomainPolicy {
+    #[serde(default)]
+    pub cookies: CookieScope,
+    /// Profile for this domain instead of the session's.
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// Exit node for this domain: one of `network.proxies`, or `"direct"`.
+    #[serde(default)]
+    pub proxy: Option<String>,
+    /// Draw this domain's exit from a `[network.pools]` list instead.
+    #[serde(default)]
+    pub pool: Option<String>,
+    /// Checked before `[[analyzer.rules]]` for responses from this domain.
+    #[serde(default)]
+    pub rules: Vec<AnalyzerRule>,
+    /// Replaces the top-level `[success]` for this domain.
+    #[serde(default)]
+    pub success: Option<SuccessCriteria>,
+}
+
+impl DomainPolicy {
+    /// Whether this domain can't reuse the session's client as-is.
+    pub fn needs_own_client(&self, session_profile: &str, session_node: &str) -> bool {
+        self.cookies == CookieScope::Isolated
+            || self.profile.as_deref().is_some_and(|p| p != session_profile)
+            || self.proxy.as_deref().is_some_and(|p| p != session_node)
+    }
+
+    pub fn first_match(&self, status: u16, headers: &HeaderMap, body: &str) -> Option<Verdict> {
+        rules::first_match(&self.rules, status, headers, body)
+    }
+}
+
+/// `[domains]`, keyed by lowercase host; `*.example.com` keys match any subdomain.
+#[derive(Debug, Clone, Default)]
+pub struct DomainPolicies {
+    policies: HashMap<String, DomainPolicy>,
+}
+
+impl DomainPolicies {
+    /// Checks every policy's profile, proxy, pool and rules against the rest of the config.
+    /// `direct_allowed` is `NetworkConfig::direct_allowed`: a policy can't pin the direct
+    /// node when the grid itself may not use it.
+    pub fn new(
+        policies: &HashMap<String, DomainPolicy>,
+        profiles: &[&str],
+        proxies: &[&str],
+        pools: &[&str],
+        direct_allowed: bool,
+    ) -> Result<Self> {
+        for (domain, policy) in policies {
+            if let Some(profile) = &policy.profile {
+                if !profiles.contains(&profile.as_str()) {
+                    return Err(anyhow!("domains.\"{}\".profile: no profile named '{}'", domain, profile));
+                }
+            }
+            if let Some(proxy) = &policy.proxy {
+                if proxy == DIRECT_NODE {
+                    if !direct_allowed {
+                        return Err(anyhow!(
+                            "domains.\"{}\".proxy = \"direct\" needs network.allow_direct and no network.geo filter",
+                            domain
+                        ));
+                    }
+                } else if !proxies.contains(&proxy.as_str()) {
+                    return Err(anyhow!("domains.\"{}\".proxy is not in network.proxies", domain));
+                }
+            }
+            if let Some(pool) = &policy.pool {
+                if policy.proxy.is_some() {
+                    return Err(anyhow!("domains.\"{}\" sets both proxy and pool", domain));
+                }
+                if !pools.contains(&pool.as_str()) {
+                    return Err(anyhow!("domains.\"{}\".pool: no network.pools entry named '{}'", domain, pool));
+                }
+            }
+            if let Some(success) = &policy.success {
+                success.validate(&format!("domains.\"{}\".success", domain))?;
+            }
+        }
+        let mut compiled = HashMap::new();
+        for (domain, policy) in policies {
+            let mut policy = policy.clone();
+            policy.rules = std::mem::take(&mut policy.rules)
+                .into_iter()
+                .map(AnalyzerRule::compiled)
+                .collect::<Result<_>>()
+                .map_err(|e| anyhow!("domains.\"{}\": {:#}", domain, e))?;
+            compiled.insert(domain.trim().to_lowercase(), policy);
+        }
+        Ok(Self { policies: compiled })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.policies.is_empty()
+    }
+
+    /// The policy for `url`'s host, with the key it matched: exact host first, then the
+    /// longest matching `*.` wildcard.
+    pub fn lookup(&self, url: &str) -> Option<(&str, &DomainPolicy)> {
+        if self.policies.is_empty() {
+            return None;
+        }
+        let host = rquest::Url::parse(url).ok()?.host_str()?.to_lowercase();
+        if let Some((key, policy)) = self.policies.get_key_value(&host) {
+            return Some((key.as_str(), policy));
+        }
+        self.policies
+            .iter()
+            .filter(|(key, _)| {
+                key.strip_prefix("*.")
+                    .is_some_and(|suffix| host.len() > suffix.len() && host.ends_with(suffix) && host[..host.len() - suffix.len()].ends_with('.'))
+            })
+            .max_by_key(|(key, _)| key.len())
+            .map(|(key, policy)| (key.as_str(), policy))
+    }
+}
@@ -0,0 +1,167 @@
+use crate::engine::config::ProfileEntry;
+use crate::engine::{Config, CoreEngine, EngineStats, ShutdownPhase, StatsSnapshot};
+use crate::geo::ProxySpec;
+use anyhow::{anyhow, Result};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+// --- Embedding API ---
+pub struct EngineBuilder {
+    config: Config,
+    handle_signals: bool,
+}
+
+impl EngineBuilder {
+    pub fn new(target_url: &str) -> Self {
+        Self::from_config(Config::minimal(target_url))
+    }
+
+    pub fn from_config(config: Config) -> Self {
+        Self {
+            config,
+            handle_signals: false,
+        }
+    }
+
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.config.general.concurrency = concurrency;
+        self
+    }
+
+    pub fn proxies(mut self, proxies: Vec<String>) -> Self {
+        self.config.network.proxies = proxies.into_iter().map(ProxySpec::from).collect();
+        self
+    }
+
+    pub fn allow_direct(mut self, allow: bool) -> Self {
+        self.config.network.allow_direct = allow;
+        self
+    }
+
+    pub fn profile(mut self, key: &str, emulation: &str) -> Self {
+        self.config.profiles.insert(key.to_string(), ProfileEntry::Emulation(emulation.to_string()));
+        self
+    }
+
+    pub fn method(mut self, method: &str) -> Self {
+        self.config.general.method = method.to_string();
+        self
+    }
+
+    /// Adds a header in `Key: Value` form (supports `{payload}` templating).
+    pub fn header(mut self, header: &str) -> Self {
+        self.config.general.headers.push(header.to_string());
+        self
+    }
+
+    pub fn body(mut self, body: &str) -> Self {
+        self.config.general.raw_body = Some(body.to_string());
+        self
+    }
+
+    pub fn payload_file(mut self, path: &str) -> Self {
+        self.config.general.payload_file = Some(path.to_string());
+        self
+    }
+
+    pub fn tampers(mut self, tampers: Vec<String>) -> Self {
+        self.config.general.tampers = tampers;
+        self
+    }
+
+    pub fn time_limit(mut self, limit: Duration) -> Self {
+        self.config.general.time_limit = Some(limit.as_secs());
+        self
+    }
+
+    pub fn report_file(mut self, path: &str) -> Self {
+        self.config.general.report_file = Some(path.to_string());
+        self
+    }
+
+    /// Let the engine install its own Ctrl-C handler (off by default when embedded).
+    pub fn handle_signals(mut self, enabled: bool) -> Self {
+        self.handle_signals = enabled;
+        self
+    }
+
+    pub fn build(self) -> Result<Engine> {
+        let mut core = CoreEngine::try_new(self.config)?;
+        core.set_signal_handling(self.handle_signals);
+        Ok(Engine {
+            core: Arc::new(core),
+            handle: None,
+        })
+    }
+}
+
+/// Handle to an embedded engine run.
+pub struct Engine {
+    core: Arc<CoreEngine>,
+    handle: Option<JoinHandle<Result<()>>>,
+}
+
+impl Engine {
+    /// Spawns the worker pool on the current tokio runtime.
+    pub fn start(&mut self) -> Result<()> {
+        if self.handle.is_some() {
+            return Err(anyhow!("Engine already started"));
+        }
+        let core = self.core.clone();
+        self.handle = Some(tokio::spawn(async move { core.run().await }));
+        Ok(())
+    }
+
+    pub fn stats(&self) -> StatsSnapshot {
+        self.core.get_stats().snapshot()
+    }
+
+    /// Live counters, for callers that want more than the snapshot (findings, shadow, baselines).
+    pub fn raw_stats(&self) -> EngineStats {
+        self.core.get_stats()
+    }
+
+    /// Emits a snapshot every `interval` until the run finishes or the receiver is dropped.
+    pub fn stats_stream(&self, interval: Duration) -> mpsc::Receiver<StatsSnapshot> {
+        let (tx, rx) = mpsc::channel(16);
+        let stats = self.core.get_stats();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                let done = matches!(stats.shutdown.phase(), ShutdownPhase::Done | ShutdownPhase::Halted);
+                if tx.send(stats.snapshot()).await.is_err() || done {
+                    break;
+                }
+            }
+        });
+        rx
+    }
+
+    /// Drains workers gracefully, flushes the report and waits for the run to end.
+    pub async fn stop(mut self) -> Result<()> {
+        self.core.get_stats().shutdown.request();
+        self.wait_inner().await
+    }
+
+    /// Emergency stop: cancels in-flight requests and browsers without flushing anything.
+    /// The counters stay readable through `stats` / `raw_stats`.
+    pub async fn halt(mut self) -> Result<()> {
+        self.core.get_stats().shutdown.halt("embedder");
+        self.wait_inner().await
+    }
+
+    /// Waits for the run to end on its own (time limit or external stop).
+    pub async fn wait(mut self) -> Result<()> {
+        self.wait_inner().await
+    }
+
+    async fn wait_inner(&mut self) -> Result<()> {
+        match self.handle.take() {
+            Some(handle) => handle.await?,
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,883 @@
+use super::config::{
+    default_sticky_length, random_session_id, CooldownConfig, StickyConfig, StickyId, StickyRotation,
+    SESSION_ID_PLACEHOLDER,
+};
+use super::{ProxyEndpoint, ShutdownState, Verdict};
+use crate::chain::ChainTunnels;
+use crate::tor::TorConfig;
+use anyhow::{anyhow, Result};
+use rand::Rng;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+// The proxy grid: the nodes a run sends traffic through, how each new session's node is
+// picked, and the leases, cooldowns, health and latency per node that drive the pick.
+
+/// `network.selection`: how the grid picks the node for a new session.
+#[derive(Debug, Clone, Copy, Default, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SelectionPolicy {
+    #[default]
+    RoundRobin,
+    /// Random, proportional to success rate / latency (both EWMAs).
+    Weighted,
+    /// The node with the lowest latency EWMA; unmeasured nodes are tried first.
+    LowestLatency,
+    Random,
+}
+
+/// Smoothing for the per-node EWMAs: each observation moves the average 20% of the way.
+const EWMA_ALPHA: f64 = 0.2;
+/// Success-rate floor for weighting, so a node that had a bad streak still gets probed.
+const MIN_SUCCESS_WEIGHT: f64 = 0.05;
+
+/// A shared `f64` (the EWMAs), stored as its bits. NaN means "no value yet".
+#[derive(Debug)]
+struct AtomicF64(AtomicU64);
+
+impl AtomicF64 {
+    fn new(value: f64) -> Self {
+        Self(AtomicU64::new(value.to_bits()))
+    }
+
+    fn load(&self) -> f64 {
+        f64::from_bits(self.0.load(Ordering::Relaxed))
+    }
+
+    /// Applies `f` atomically; concurrent observations are retried, not lost.
+    fn update(&self, f: impl Fn(f64) -> f64) {
+        let _ = self.0.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |bits| Some(f(f64::from_bits(bits)).to_bits()));
+    }
+}
+
+lazy_static::lazy_static! {
+    /// Zero point for the grid's atomic timestamps.
+    static ref GRID_EPOCH: Instant = Instant::now();
+}
+
+/// `t` as ms since `GRID_EPOCH`, offset by one so 0 can mean "unset".
+fn grid_ms(t: Instant) -> u64 {
+    t.saturating_duration_since(*GRID_EPOCH).as_millis() as u64 + 1
+}
+
+fn grid_instant(ms: u64) -> Option<Instant> {
+    (ms > 0).then(|| *GRID_EPOCH + Duration::from_millis(ms - 1))
+}
+
+/// One proxy's state. Every field is an atomic or a lock private to the node, so workers
+/// on different nodes never wait on each other, and workers on the same node only for
+/// the length of an atomic update.
+#[derive(Debug)]
+struct Node {
+    url: String,
+    failures: AtomicUsize,
+    /// `grid_ms` the current cooldown ends at; 0 = none.
+    cooldown_until: AtomicU64,
+    /// Cooldowns since the node last succeeded; sets the next cooldown's length.
+    cooldowns: AtomicUsize,
+    /// Failed its last health probe; out of rotation until a probe succeeds.
+    dead: AtomicBool,
+    /// Hit `cooldown.evict_after`; out of rotation for the rest of the run.
+    evicted: AtomicBool,
+    /// `NodeLimits::max_concurrent`; `usize::MAX` = uncapped.
+    max_concurrent: AtomicUsize,
+    /// `NodeLimits::max_requests_per_minute`; 0 = uncapped.
+    max_per_minute: AtomicU32,
+    /// Active sessions in the node's `network.diversity` group, shared with the other
+    /// nodes of that group; `None` for the direct node.
+    group: RwLock<Option<Arc<AtomicUsize>>>,
+    /// Proxy lists the node belongs to: `DEFAULT_POOL` and/or `[network.pools]` names.
+    pools: RwLock<Vec<String>>,
+    /// Requests currently holding a `NodeLease`.
+    leased: AtomicUsize,
+    /// Lease start times within the last minute, oldest first; only kept under a per-minute cap.
+    recent: Mutex<VecDeque<Instant>>,
+    /// EWMA of response latency (and health probe round trips), in ms.
+    latency_ms: AtomicF64,
+    /// EWMA of outcomes, 1.0 = every recent request passed.
+    success_rate: AtomicF64,
+    requests: AtomicUsize,
+    successes: AtomicUsize,
+    /// Blocked or challenged responses.
+    blocks: AtomicUsize,
+    /// Transport failures.
+    errors: AtomicUsize,
+    /// Response body bytes received.
+    bytes: AtomicU64,
+    /// `grid_ms` of the last request; 0 = never used.
+    last_used: AtomicU64,
+}
+
+impl Node {
+    fn new(url: String) -> Self {
+        Self {
+            url,
+            failures: AtomicUsize::new(0),
+            cooldown_until: AtomicU64::new(0),
+            cooldowns: AtomicUsize::new(0),
+            dead: AtomicBool::new(false),
+            evicted: AtomicBool::new(false),
+            max_concurrent: AtomicUsize::new(usize::MAX),
+            max_per_minute: AtomicU32::new(0),
+            group: RwLock::new(None),
+            pools: RwLock::new(vec![DEFAULT_POOL.to_string()]),
+            leased: AtomicUsize::new(0),
+            recent: Mutex::new(VecDeque::new()),
+            latency_ms: AtomicF64::new(f64::NAN),
+            success_rate: AtomicF64::new(1.0),
+            requests: AtomicUsize::new(0),
+            successes: AtomicUsize::new(0),
+            blocks: AtomicUsize::new(0),
+            errors: AtomicUsize::new(0),
+            bytes: AtomicU64::new(0),
+            last_used: AtomicU64::new(0),
+        }
+    }
+
+    fn latency(&self) -> Option<f64> {
+        Some(self.latency_ms.load()).filter(|l| !l.is_nan())
+    }
+
+    fn observe_latency(&self, latency: Duration) {
+        let ms = latency.as_secs_f64() * 1000.0;
+        self.latency_ms.update(|avg| if avg.is_nan() { ms } else { avg + EWMA_ALPHA * (ms - avg) });
+    }
+
+    fn observe_outcome(&self, success: bool) {
+        let x = if success { 1.0 } else { 0.0 };
+        self.success_rate.update(|rate| rate + EWMA_ALPHA * (x - rate));
+    }
+
+    fn cooling(&self, now: Instant) -> bool {
+        grid_instant(self.cooldown_until.load(Ordering::Acquire)).is_some_and(|c| now < c)
+    }
+
+    /// Clears a cooldown that has run out; true for the one caller that cleared it.
+    fn expire_cooldown(&self, now: Instant) -> bool {
+        let until = self.cooldown_until.load(Ordering::Acquire);
+        if !grid_instant(until).is_some_and(|c| now >= c) {
+            return false;
+        }
+        if self.cooldown_until.compare_exchange(until, 0, Ordering::AcqRel, Ordering::Acquire).is_err() {
+            return false;
+        }
+        self.failures.store(0, Ordering::Relaxed);
+        true
+    }
+
+    fn available(&self, now: Instant) -> bool {
+        !self.dead.load(Ordering::Relaxed) && !self.evicted.load(Ordering::Relaxed) && !self.cooling(now)
+    }
+
+    fn in_pool(&self, pool: &str) -> bool {
+        self.pools.read().unwrap().iter().any(|p| p == pool)
+    }
+
+    /// Whether the node's diversity group already has an active session.
+    fn group_taken(&self) -> bool {
+        self.group.read().unwrap().as_ref().is_some_and(|g| g.load(Ordering::Acquire) > 0)
+    }
+
+    /// Joins the node's group. `exclusive` only succeeds while the group is free, and does
+    /// so atomically, so two workers can't both take the last free group.
+    fn try_claim(&self, exclusive: bool) -> Option<SessionClaim> {
+        let group = self.group.read().unwrap().clone();
+        match group {
+            None => Some(SessionClaim { group: None }),
+            Some(active) if exclusive => active
+                .compare_exchange(0, 1, Ordering::AcqRel, Ordering::Acquire)
+                .ok()
+                .map(|_| SessionClaim { group: Some(active) }),
+            Some(active) => {
+                active.fetch_add(1, Ordering::AcqRel);
+                Some(SessionClaim { group: Some(active) })
+            }
+        }
+    }
+
+    fn set_limits(&self, limits: NodeLimits) {
+        self.max_concurrent.store(limits.max_concurrent.unwrap_or(usize::MAX), Ordering::Relaxed);
+        self.max_per_minute.store(limits.max_requests_per_minute.unwrap_or(0), Ordering::Relaxed);
+    }
+
+    /// Takes a slot if the caps allow a request now; otherwise how long to wait before
+    /// asking again.
+    fn try_lease(&self, now: Instant) -> Result<(), Duration> {
+        let per_minute = self.max_per_minute.load(Ordering::Relaxed) as usize;
+        if per_minute == 0 {
+            return self.take_slot();
+        }
+        let mut recent = self.recent.lock().unwrap();
+        while recent.front().is_some_and(|t| now.duration_since(*t) >= Duration::from_secs(60)) {
+            recent.pop_front();
+        }
+        if recent.len() >= per_minute {
+            let oldest = recent.front().copied().unwrap_or(now);
+            return Err((oldest + Duration::from_secs(60)).saturating_duration_since(now).max(LEASE_POLL));
+        }
+        self.take_slot()?;
+        recent.push_back(now);
+        Ok(())
+    }
+
+    fn take_slot(&self) -> Result<(), Duration> {
+        let max = self.max_concurrent.load(Ordering::Relaxed);
+        self.leased
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |leased| (leased < max).then_some(leased + 1))
+            .map(|_| ())
+            .map_err(|_| LEASE_POLL)
+    }
+
+    fn release(&self) {
+        let _ = self.leased.fetch_update(Ordering::AcqRel, Ordering::Acquire, |leased| Some(leased.saturating_sub(1)));
+    }
+}
+
+/// Node id used for the no-proxy pseudo-node.
+pub const DIRECT_NODE: &str = "direct";
+
+/// Pool name of the main proxy list (`network.proxies` and its sources).
+pub const DEFAULT_POOL: &str = "";
+
+/// Per-node request caps, so residential exits aren't tripped into provider-side throttling.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct NodeLimits {
+    pub max_concurrent: Option<usize>,
+    pub max_requests_per_minute: Option<u32>,
+}
+
+impl NodeLimits {
+    /// A cap of 0 would park every request on the node for good.
+    pub fn validate(&self, node: &str) -> Result<()> {
+        if self.max_concurrent == Some(0) {
+            return Err(anyhow!("max_concurrent for {} must be at least 1", ProxyEndpoint::redact(node)));
+        }
+        if self.max_requests_per_minute == Some(0) {
+            return Err(anyhow!("max_requests_per_minute for {} must be at least 1", ProxyEndpoint::redact(node)));
+        }
+        Ok(())
+    }
+}
+
+/// How often a worker waiting on a node at its concurrency cap checks again.
+const LEASE_POLL: Duration = Duration::from_millis(50);
+
+/// One request's slot on a node; released when dropped.
+pub struct NodeLease {
+    /// `None` for nodes the grid doesn't know (e.g. dropped by a reload): uncapped.
+    node: Option<Arc<Node>>,
+}
+
+impl NodeLease {
+    /// Waits until `node` is under its caps, then takes a slot on it. `None` once a
+    /// shutdown is requested while waiting.
+    pub async fn acquire(grid: &GridManager, node: &str, shutdown: &ShutdownState) -> Option<Self> {
+        let Some(node) = grid.node(node) else {
+            return Some(Self { node: None });
+        };
+        loop {
+            if shutdown.is_requested() {
+                return None;
+            }
+            match node.try_lease(Instant::now()) {
+                Ok(()) => return Some(Self { node: Some(node) }),
+                // Re-checks shutdown at least every second during a long per-minute wait
+                Err(wait) => tokio::time::sleep(wait.min(Duration::from_secs(1))).await,
+            }
+        }
+    }
+}
+
+impl Drop for NodeLease {
+    fn drop(&mut self) {
+        if let Some(node) = &self.node {
+            node.release();
+        }
+    }
+}
+
+/// A session's hold on its node's diversity group, taken by `GridManager::claim_next_node`.
+#[derive(Debug)]
+pub struct SessionClaim {
+    group: Option<Arc<AtomicUsize>>,
+}
+
+impl Drop for SessionClaim {
+    fn drop(&mut self) {
+        if let Some(active) = &self.group {
+            let _ = active.fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| Some(n.saturating_sub(1)));
+        }
+    }
+}
+
+/// One node's counters, as `GridManager::snapshot` reports them.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct NodeSnapshot {
+    /// Node id with the password masked.
+    pub node: String,
+    /// `live`, `cooldown`, `dead` or `evicted`.
+    pub state: String,
+    pub requests: usize,
+    pub successes: usize,
+    pub blocks: usize,
+    pub errors: usize,
+    pub bytes: u64,
+    /// Latency EWMA; `None` until the node has carried a request or passed a probe.
+    pub latency_ms: Option<f64>,
+    pub success_rate: f64,
+    /// Seconds since the node last carried a request.
+    pub idle_secs: Option<u64>,
+    /// Requests in flight through the node right now.
+    #[serde(default)]
+    pub in_flight: usize,
+}
+
+/// The node list, replaced whole when the proxy list is reloaded.
+#[derive(Debug, Default)]
+struct NodeTable {
+    list: Vec<Arc<Node>>,
+    by_url: HashMap<String, usize>,
+}
+
+impl NodeTable {
+    fn new(list: Vec<Arc<Node>>) -> Self {
+        let by_url = list.iter().enumerate().map(|(i, n)| (n.url.clone(), i)).collect();
+        Self { list, by_url }
+    }
+
+    fn get(&self, url: &str) -> Option<&Arc<Node>> {
+        self.by_url.get(url).map(|&i| &self.list[i])
+    }
+}
+
+/// The proxy grid. Methods take `&self` and workers share it through an `Arc`: per-request
+/// calls read the node table under a briefly held read lock and update the node with
+/// atomics. Only a proxy reload takes the write lock.
+#[derive(Debug, Default)]
+pub struct GridManager {
+    nodes: RwLock<Arc<NodeTable>>,
+    /// Round-robin cursor.
+    index: AtomicUsize,
+    policy: SelectionPolicy,
+    cooldown: CooldownConfig,
+    diversity: bool,
+    /// Active-session counters per diversity group. Entries outlive reloads, so claims
+    /// taken before one are still given back.
+    groups: Mutex<HashMap<String, Arc<AtomicUsize>>>,
+    sticky: StickyConfig,
+    /// Gateway session ids handed out so far.
+    issued_ids: AtomicUsize,
+    tor: TorConfig,
+    /// Local tunnels of the chained nodes (`via`).
+    chains: ChainTunnels,
+}
+
+impl GridManager {
+    pub fn new(proxies: Vec<String>, allow_direct: bool) -> Self {
+        let mut nodes: Vec<Arc<Node>> = proxies.into_iter().map(|p| Arc::new(Node::new(p))).collect();
+        if allow_direct {
+            nodes.push(Arc::new(Node::new(DIRECT_NODE.to_string())));
+        }
+        Self {
+            nodes: RwLock::new(Arc::new(NodeTable::new(nodes))),
+            index: AtomicUsize::new(0),
+            policy: SelectionPolicy::RoundRobin,
+            cooldown: CooldownConfig::default(),
+            diversity: false,
+            groups: Mutex::new(HashMap::new()),
+            sticky: StickyConfig::default(),
+            issued_ids: AtomicUsize::new(0),
+            tor: TorConfig::default(),
+            chains: ChainTunnels::default(),
+        }
+    }
+
+    pub fn with_chains(mut self, chains: ChainTunnels) -> Self {
+        self.chains = chains;
+        self
+    }
+
+    pub fn chains(&self) -> &ChainTunnels {
+        &self.chains
+    }
+
+    pub fn with_policy(mut self, policy: SelectionPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    pub fn with_cooldown(mut self, cooldown: CooldownConfig) -> Self {
+        self.cooldown = cooldown;
+        self
+    }
+
+    pub fn with_diversity(mut self, diversity: bool) -> Self {
+        self.diversity = diversity;
+        self
+    }
+
+    pub fn with_sticky(mut self, sticky: StickyConfig) -> Self {
+        self.sticky = sticky;
+        self
+    }
+
+    pub fn with_tor(mut self, tor: TorConfig) -> Self {
+        self.tor = tor;
+        self
+    }
+
+    fn table(&self) -> Arc<NodeTable> {
+        self.nodes.read().unwrap().clone()
+    }
+
+    fn node(&self, node_id: &str) -> Option<Arc<Node>> {
+        self.nodes.read().unwrap().get(node_id).cloned()
+    }
+
+    /// Whether `node` is a rotating gateway that needs a `{session_id}`.
+    pub fn is_gateway(node: &str) -> bool {
+        node.contains(SESSION_ID_PLACEHOLDER)
+    }
+
+    /// A fresh session id for `node`, or `None` when it isn't a gateway.
+    pub fn issue_session_id(&self, node: &str) -> Option<StickyId> {
+        if !Self::is_gateway(node) {
+            return None;
+        }
+        self.issued_ids.fetch_add(1, Ordering::Relaxed);
+        Some(StickyId { id: random_session_id(self.sticky.length), issued: Instant::now() })
+    }
+
+    /// Whether `sticky` should be replaced before the session's next request.
+    pub fn session_id_due(&self, sticky: &StickyId) -> bool {
+        match self.sticky.rotate {
+            StickyRotation::PerRequest => true,
+            StickyRotation::Interval => sticky.issued.elapsed() >= Duration::from_secs(self.sticky.minutes.max(1) * 60),
+            StickyRotation::OnBlock => false,
+        }
+    }
+
+    pub fn issued_session_ids(&self) -> usize {
+        self.issued_ids.load(Ordering::Relaxed)
+    }
+
+    /// `proxy_for`, with a gateway's `{session_id}` filled in. Without `sticky` a one-off
+    /// id is used, e.g. for health probes.
+    pub fn proxy_with_session(&self, node: &str, sticky: Option<&StickyId>) -> Option<String> {
+        if !Self::is_gateway(node) {
+            return self.proxy_for(node, node);
+        }
+        let id = match sticky {
+            Some(s) => s.id.clone(),
+            None => random_session_id(default_sticky_length()),
+        };
+        self.proxy_for(node, &node.replace(SESSION_ID_PLACEHOLDER, &id))
+    }
+
+    /// Maps a node id to the proxy URL a client should use (`None` for the direct node).
+    /// http(s) and socks5(h) nodes alike; `ProxyEndpoint` handles the scheme. Tor nodes
+    /// map to their SOCKS port, chained nodes to their local tunnel. `url` is the node
+    /// with any session id filled in.
+    fn proxy_for(&self, node: &str, url: &str) -> Option<String> {
+        if node == DIRECT_NODE {
+            None
+        } else {
+            Some(self.chains.route(node, &crate::tor::socks_url(url)))
+        }
+    }
+
+    pub fn get_next_node(&self) -> Option<String> {
+        self.get_next_node_with_note().map(|(node, _)| node)
+    }
+
+    /// Like `get_next_node`, plus a short note on why this node was picked.
+    pub fn get_next_node_with_note(&self) -> Option<(String, String)> {
+        self.claim_next_node().map(|(node, note, _)| (node, note))
+    }
+
+    /// Picks the node for a new session and claims its diversity group in the same step.
+    /// The claim is given back when the returned `SessionClaim` is dropped.
+    pub fn claim_next_node(&self) -> Option<(String, String, SessionClaim)> {
+        self.claim_next_node_in(DEFAULT_POOL)
+    }
+
+    /// `claim_next_node` over the nodes of one `[network.pools]` list.
+    pub fn claim_next_node_in(&self, pool: &str) -> Option<(String, String, SessionClaim)> {
+        let table = self.table();
+        let picked = self.pick(&table, pool, self.diversity);
+        if picked.is_some() || !self.diversity {
+            return picked;
+        }
+        // Every free node shares a group with an active session; reuse one rather than stall
+        self.pick(&table, pool, false).map(|(node, note, claim)| (node, format!("{}, diversity relaxed", note), claim))
+    }
+
+    fn pick(&self, table: &NodeTable, pool: &str, diverse: bool) -> Option<(String, String, SessionClaim)> {
+        match self.policy {
+            SelectionPolicy::RoundRobin => self.pick_round_robin(table, pool, diverse),
+            policy => self.pick_scored(table, pool, policy, diverse),
+        }
+    }
+
+    fn pick_round_robin(&self, table: &NodeTable, pool: &str, diverse: bool) -> Option<(String, String, SessionClaim)> {
+        let total = table.list.len();
+        if total == 0 {
+            return None;
+        }
+        let now = Instant::now();
+        'rotation: loop {
+            let start = self.index.load(Ordering::Acquire);
+            let mut recovered = false;
+            let Some(skipped) = (0..total).find(|&k| {
+                let node = &table.list[start.wrapping_add(k) % total];
+                recovered = node.in_pool(pool) && node.expire_cooldown(now);
+                node.in_pool(pool) && node.available(now) && (!diverse || !node.group_taken())
+            }) else {
+                return None;
+            };
+            // The cursor moves to just past the pick, so skipped nodes don't shift the
+            // rotation. A worker that lost the race rescans from where the winner left it.
+            let next = start.wrapping_add(skipped + 1);
+            if self.index.compare_exchange(start, next, Ordering::AcqRel, Ordering::Acquire).is_err() {
+                continue 'rotation;
+            }
+            let slot = start.wrapping_add(skipped) % total;
+            let node = &table.list[slot];
+            // Another worker took the group between the scan and the claim
+            let Some(claim) = node.try_claim(diverse) else {
+                continue 'rotation;
+            };
+            let note = format!(
+                "round-robin slot {}/{}, failures {}/{}, {} skipped (cooldown/dead){}",
+                slot + 1,
+                total,
+                node.failures.load(Ordering::Relaxed),
+                self.cooldown.failures,
+                skipped,
+                if recovered { ", cooldown just expired" } else { "" }
+            );
+            return Some((node.url.clone(), note, claim));
+        }
+    }
+
+    /// Weighted, lowest-latency and random selection over the live nodes.
+    fn pick_scored(&self, table: &NodeTable, pool: &str, policy: SelectionPolicy, diverse: bool) -> Option<(String, String, SessionClaim)> {
+        let now = Instant::now();
+        for node in &table.list {
+            node.expire_cooldown(now);
+        }
+        let mut eligible: Vec<&Arc<Node>> =
+            table.list.iter().filter(|n| n.in_pool(pool) && n.available(now) && (!diverse || !n.group_taken())).collect();
+        let candidates = eligible.len();
+        // Unmeasured nodes count as the slowest measured one until they have a sample
+        let slowest = table.list.iter().filter_map(|n| n.latency()).fold(1000.0, f64::max);
+        let latency = |n: &Arc<Node>| n.latency().unwrap_or(slowest).max(1.0);
+        let mut rng = rand::thread_rng();
+        // Another worker can claim a group between the filter and our claim; the next
+        // candidate is tried then
+        while !eligible.is_empty() {
+            let picked = match policy {
+                SelectionPolicy::Random => rng.gen_range(0..eligible.len()),
+                SelectionPolicy::LowestLatency => match eligible.iter().position(|n| n.latency().is_none()) {
+                    Some(unmeasured) => unmeasured,
+                    None => (0..eligible.len()).min_by(|&a, &b| latency(eligible[a]).total_cmp(&latency(eligible[b])))?,
+                },
+                SelectionPolicy::Weighted | SelectionPolicy::RoundRobin => {
+                    // A node at 100 ms and 90% success gets twice the traffic of one at 200 ms and 90%
+                    let weights: Vec<f64> = eligible
+                        .iter()
+                        .map(|&n| n.success_rate.load().max(MIN_SUCCESS_WEIGHT) / latency(n))
+                        .collect();
+                    let dist = rand::distributions::WeightedIndex::new(&weights).ok()?;
+                    rand::distributions::Distribution::sample(&dist, &mut rng)
+                }
+            };
+            let node = eligible[picked];
+            let Some(claim) = node.try_claim(diverse) else {
+                eligible.swap_remove(picked);
+                continue;
+            };
+            let note = format!(
+                "{:?} pick, latency {}, success {:.0}%, {}/{} nodes eligible, failures {}/{}",
+                policy,
+                node.latency().map(|l| format!("{:.0} ms", l)).unwrap_or_else(|| "unmeasured".into()),
+                node.success_rate.load() * 100.0,
+                candidates,
+                table.list.len(),
+                node.failures.load(Ordering::Relaxed),
+                self.cooldown.failures
+            );
+            return Some((node.url.clone(), note, claim));
+        }
+        None
+    }
+
+    /// Every node id that isn't evicted, for health probing.
+    pub fn node_ids(&self) -> Vec<String> {
+        self.table().list.iter().filter(|n| !n.evicted.load(Ordering::Relaxed)).map(|n| n.url.clone()).collect()
+    }
+
+    /// Whether `node_id` can take requests now: known, live, not evicted and not cooling
+    /// down. A cooldown that has run out is cleared.
+    pub fn is_usable(&self, node_id: &str) -> bool {
+        let now = Instant::now();
+        self.node(node_id).is_some_and(|node| {
+            node.expire_cooldown(now);
+            node.available(now)
+        })
+    }
+
+    /// Applies a health probe result (`None` = failed). Returns whether the node changed
+    /// between live and dead.
+    pub fn record_probe(&self, node_id: &str, latency: Option<Duration>) -> bool {
+        let Some(node) = self.node(node_id).filter(|n| !n.evicted.load(Ordering::Relaxed)) else {
+            return false;
+        };
+        let dead = latency.is_none();
+        if let Some(l) = latency {
+            node.observe_latency(l);
+        }
+        node.dead.swap(dead, Ordering::AcqRel) != dead
+    }
+
+    /// Swaps in a new proxy list along with its caps, diversity groups and pools. New nodes
+    /// are fully set up before the swap, so no worker picks one without its settings. Nodes
+    /// that stay keep their stats, cooldowns and leases; the direct node stays if it was
+    /// there. Only the reload task calls this. Returns (added, removed).
+    pub fn replace_nodes(
+        &self,
+        proxies: Vec<String>,
+        limits: &HashMap<String, NodeLimits>,
+        groups: &HashMap<String, String>,
+        pools: &HashMap<String, Vec<String>>,
+    ) -> (usize, usize) {
+        let old = self.table();
+        let added = proxies.iter().filter(|p| old.get(p).is_none()).count();
+        let mut list: Vec<Arc<Node>> =
+            proxies.into_iter().map(|p| old.get(&p).cloned().unwrap_or_else(|| Arc::new(Node::new(p)))).collect();
+        if let Some(direct) = old.get(DIRECT_NODE) {
+            list.push(direct.clone());
+        }
+        Self::apply_limits(&list, limits);
+        self.apply_groups(&list, groups);
+        Self::apply_pools(&list, pools);
+        let table = NodeTable::new(list);
+        let removed = old.list.iter().filter(|n| table.get(&n.url).is_none()).count();
+        *self.nodes.write().unwrap() = Arc::new(table);
+        self.index.store(0, Ordering::Release);
+        (added, removed)
+    }
+
+    /// Sets each listed node's diversity group; the direct node has none.
+    pub fn set_groups(&self, groups: &HashMap<String, String>) {
+        self.apply_groups(&self.table().list, groups);
+    }
+
+    fn apply_groups(&self, list: &[Arc<Node>], groups: &HashMap<String, String>) {
+        let mut registry = self.groups.lock().unwrap();
+        for node in list {
+            let group = groups
+                .get(&node.url)
+                .filter(|g| !g.is_empty())
+                .map(|g| registry.entry(g.clone()).or_default().clone());
+            *node.group.write().unwrap() = group;
+        }
+    }
+
+    /// Sets each listed node's pool memberships; unlisted nodes (the direct node) are in
+    /// the main list only.
+    pub fn set_pools(&self, pools: &HashMap<String, Vec<String>>) {
+        Self::apply_pools(&self.table().list, pools);
+    }
+
+    fn apply_pools(list: &[Arc<Node>], pools: &HashMap<String, Vec<String>>) {
+        for node in list {
+            *node.pools.write().unwrap() = pools.get(&node.url).cloned().unwrap_or_else(|| vec![DEFAULT_POOL.to_string()]);
+        }
+    }
+
+    /// Sets each listed node's caps; nodes not in `limits` (the direct node) stay uncapped.
+    pub fn set_limits(&self, limits: &HashMap<String, NodeLimits>) {
+        Self::apply_limits(&self.table().list, limits);
+    }
+
+    fn apply_limits(list: &[Arc<Node>], limits: &HashMap<String, NodeLimits>) {
+        for node in list {
+            node.set_limits(limits.get(&node.url).copied().unwrap_or_default());
+        }
+    }
+
+    /// Per-node counters in grid order.
+    pub fn snapshot(&self) -> Vec<NodeSnapshot> {
+        let now = Instant::now();
+        self.table()
+            .list
+            .iter()
+            .map(|n| NodeSnapshot {
+                node: ProxyEndpoint::redact(&n.url),
+                state: if n.evicted.load(Ordering::Relaxed) {
+                    "evicted"
+                } else if n.dead.load(Ordering::Relaxed) {
+                    "dead"
+                } else if n.cooling(now) {
+                    "cooldown"
+                } else {
+                    "live"
+                }
+                .to_string(),
+                requests: n.requests.load(Ordering::Relaxed),
+                successes: n.successes.load(Ordering::Relaxed),
+                blocks: n.blocks.load(Ordering::Relaxed),
+                errors: n.errors.load(Ordering::Relaxed),
+                bytes: n.bytes.load(Ordering::Relaxed),
+                latency_ms: n.latency(),
+                success_rate: n.success_rate.load(),
+                idle_secs: grid_instant(n.last_used.load(Ordering::Relaxed)).map(|t| now.saturating_duration_since(t).as_secs()),
+                in_flight: n.leased.load(Ordering::Relaxed),
+            })
+            .collect()
+    }
+
+    /// Counts one request through `node_id`; `None` means it failed at the transport level.
+    pub fn record_outcome(&self, node_id: &str, verdict: Option<&Verdict>, bytes: usize) {
+        let Some(node) = self.node(node_id) else {
+            return;
+        };
+        node.requests.fetch_add(1, Ordering::Relaxed);
+        node.bytes.fetch_add(bytes as u64, Ordering::Relaxed);
+        node.last_used.store(grid_ms(Instant::now()), Ordering::Relaxed);
+        match verdict {
+            Some(Verdict::Success) => node.successes.fetch_add(1, Ordering::Relaxed),
+            Some(Verdict::Blocked(_)) | Some(Verdict::Challenge(_)) => node.blocks.fetch_add(1, Ordering::Relaxed),
+            Some(Verdict::Oversized(_)) | Some(Verdict::AuthRequired(_)) | Some(Verdict::Custom(_)) => 0,
+            None => node.errors.fetch_add(1, Ordering::Relaxed),
+        };
+    }
+
+    pub fn live_nodes(&self) -> usize {
+        self.table()
+            .list
+            .iter()
+            .filter(|n| !n.dead.load(Ordering::Relaxed) && !n.evicted.load(Ordering::Relaxed))
+            .count()
+    }
+
+    pub fn evicted_nodes(&self) -> usize {
+        self.table().list.iter().filter(|n| n.evicted.load(Ordering::Relaxed)).count()
+    }
+
+    /// Time from sending a request through `proxy_url` to its response headers.
+    pub fn record_latency(&self, proxy_url: &str, latency: Duration) {
+        if let Some(node) = self.node(proxy_url) {
+            node.observe_latency(latency);
+        }
+    }
+
+    /// Counts a failure against `proxy_url`, starting a cooldown once the node has failed
+    /// `cooldown.failures` times in a row. Returns the number of cooldowns it went through
+    /// when this failure evicted it. A Tor node also gets a new identity.
+    pub fn report_failure(&self, proxy_url: &str) -> Option<usize> {
+        let cooldown = &self.cooldown;
+        let node = self.node(proxy_url).filter(|n| !n.evicted.load(Ordering::Relaxed))?;
+        if crate::tor::is_tor(&node.url) {
+            crate::tor::renew(&node.url, &self.tor);
+        }
+        let failures = node.failures.fetch_add(1, Ordering::AcqRel) + 1;
+        node.observe_outcome(false);
+        let now = Instant::now();
+        let until = node.cooldown_until.load(Ordering::Acquire);
+        // Failures from requests already in flight don't start another cycle
+        if failures < cooldown.failures.max(1) || grid_instant(until).is_some_and(|c| now < c) {
+            return None;
+        }
+        let cycles = node.cooldowns.load(Ordering::Acquire) + 1;
+        if cooldown.evict_after > 0 && cycles >= cooldown.evict_after && node.url != DIRECT_NODE {
+            if node.evicted.swap(true, Ordering::AcqRel) {
+                return None;
+            }
+            node.cooldowns.store(cycles, Ordering::Release);
+            node.cooldown_until.store(0, Ordering::Release);
+            return Some(cycles);
+        }
+        // Of several failures racing past the threshold, the one that sets the cooldown counts it
+        let next = grid_ms(now + cooldown.duration(cycles));
+        if node.cooldown_until.compare_exchange(until, next, Ordering::AcqRel, Ordering::Acquire).is_ok() {
+            node.cooldowns.store(cycles, Ordering::Release);
+        }
+        None
+    }
+
+    pub fn report_success(&self, proxy_url: &str) {
+        if let Some(node) = self.node(proxy_url) {
+            node.failures.store(0, Ordering::Relaxed);
+            node.cooldowns.store(0, Ordering::Relaxed);
+            node.observe_outcome(true);
+        }
+    }
+}
+
+/// The run's grid. `run` swaps a fresh one in at startup; workers hold their own `Arc`,
+/// so the lock here is only taken by readers outside the hot path (stats, TUI).
+#[derive(Debug, Default, Clone)]
+pub struct GridSlot(Arc<RwLock<Arc<GridManager>>>);
+
+impl GridSlot {
+    pub fn get(&self) -> Arc<GridManager> {
+        self.0.read().unwrap().clone()
+    }
+
+    pub fn set(&self, grid: GridManager) -> Arc<GridManager> {
+        let grid = Arc::new(grid);
+        *self.0.write().unwrap() = grid.clone();
+        grid
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn picks(grid: &GridManager, rounds: usize) -> HashMap<String, usize> {
+        let mut counts = HashMap::new();
+        for _ in 0..rounds {
+            let (node, _, _) = grid.claim_next_node().unwrap();
+            *counts.entry(node).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    #[test]
+    fn round_robin_spreads_evenly_across_threads() {
+        let proxies: Vec<String> = (0..4).map(|i| format!("http://10.0.0.{}:8080", i)).collect();
+        let grid = Arc::new(GridManager::new(proxies.clone(), false));
+        let threads: Vec<_> = (0..8)
+            .map(|_| {
+                let grid = grid.clone();
+                std::thread::spawn(move || picks(&grid, 100))
+            })
+            .collect();
+        let mut total: HashMap<String, usize> = HashMap::new();
+        for thread in threads {
+            for (node, n) in thread.join().unwrap() {
+                *total.entry(node).or_insert(0) += n;
+            }
+        }
+        for proxy in &proxies {
+            assert_eq!(total[proxy], 200, "{}", proxy);
+        }
+    }
+
+    #[test]
+    fn round_robin_skips_a_dead_node_without_doubling_its_neighbour() {
+        let proxies: Vec<String> = (0..4).map(|i| format!("http://10.0.0.{}:8080", i)).collect();
+        let grid = GridManager::new(proxies.clone(), false);
+        grid.record_probe(&proxies[1], None);
+        let counts = picks(&grid, 300);
+        assert_eq!(counts.get(&proxies[1]), None);
+        for proxy in [&proxies[0], &proxies[2], &proxies[3]] {
+            assert_eq!(counts[proxy], 100, "{}", proxy);
+        }
+    }
+}
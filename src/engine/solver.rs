@@ -0,0 +1,1225 @@
+use super::config::{
+    default_capture_dir, default_crash_retries, ChromeConfig, SolverConfig, WaitConfig, WaitStrategy,
+};
+use super::{ProxyEndpoint, SolverStats, SpectreLogger};
+use crate::activity::ActivityRecorder;
+use crate::behavior::Behavior;
+use crate::captcha::CaptchaSolver;
+use crate::persona::{BrowserPersona, SolverBackend};
+use crate::stealth::StealthConfig;
+use crate::success::SuccessCriteria;
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use chromiumoxide::browser::{Browser as CdpBrowser, BrowserConfig};
+use chromiumoxide::cdp::browser_protocol::browser::{Bounds, GetWindowForTargetParams, SetWindowBoundsParams};
+use chromiumoxide::cdp::browser_protocol::emulation::{
+    SetDeviceMetricsOverrideParams, SetTimezoneOverrideParams, SetTouchEmulationEnabledParams,
+};
+use chromiumoxide::cdp::browser_protocol::fetch::{
+    AuthChallengeResponse, AuthChallengeResponseResponse, ContinueRequestParams, ContinueWithAuthParams,
+    EnableParams as FetchEnableParams, EventAuthRequired, EventRequestPaused,
+};
+use chromiumoxide::cdp::browser_protocol::input::{DispatchMouseEventParams, DispatchMouseEventType, MouseButton};
+use chromiumoxide::cdp::browser_protocol::inspector::{EnableParams as InspectorEnableParams, EventTargetCrashed};
+use chromiumoxide::cdp::browser_protocol::network::{
+    EventLoadingFailed, EventLoadingFinished, EventRequestWillBeSent, SetUserAgentOverrideParams,
+};
+use chromiumoxide::cdp::browser_protocol::page::{
+    AddScriptToEvaluateOnNewDocumentParams, CaptureScreenshotFormat, RemoveScriptToEvaluateOnNewDocumentParams,
+    ScriptIdentifier,
+};
+use chromiumoxide::cdp::browser_protocol::target::{BrowserContextId, CreateBrowserContextParams, CreateTargetParams};
+use chromiumoxide::page::ScreenshotParams;
+use chromiumoxide::Page;
+use futures::StreamExt;
+use rand::Rng;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::Semaphore;
+
+// The browser solver: Chrome launched (or attached to) over CDP, the pool of warm browsers
+// kept per proxy and persona, and the solve itself, from navigation to clearance cookies.
+// The Firefox backend (firefox.rs) reuses the clearance check.
+
+pub struct BrowserSolver;
+
+lazy_static::lazy_static! {
+    static ref BROWSER_LIMITER: Semaphore = Semaphore::new(1);
+}
+
+static ACTIVE_BROWSERS: AtomicUsize = AtomicUsize::new(0);
+
+/// Set by `BrowserSolver::kill_all`; solves launched afterwards close their browser at once.
+static BROWSERS_HALTED: AtomicBool = AtomicBool::new(false);
+
+struct ActiveBrowserGuard {
+    pid: Option<u32>,
+    /// The launching pool's `running` set.
+    running: Arc<Mutex<HashSet<u32>>>,
+}
+
+impl ActiveBrowserGuard {
+    fn new(running: Arc<Mutex<HashSet<u32>>>) -> Self {
+        ACTIVE_BROWSERS.fetch_add(1, Ordering::Relaxed);
+        Self { pid: None, running }
+    }
+
+    /// Registers the launched browser so `kill_all` can reach it, and so a later run can
+    /// kill it should this one die without closing it.
+    fn track(&mut self, pid: Option<u32>, profile: Option<&Path>) -> Result<()> {
+        self.pid = pid;
+        if let Some(pid) = pid {
+            self.running.lock().unwrap().insert(pid);
+            if let Some(profile) = profile {
+                crate::reaper::register(pid, profile);
+            }
+        }
+        if BROWSERS_HALTED.load(Ordering::SeqCst) {
+            return Err(anyhow!("Emergency stop: browser closed"));
+        }
+        Ok(())
+    }
+}
+
+impl Drop for ActiveBrowserGuard {
+    fn drop(&mut self) {
+        ACTIVE_BROWSERS.fetch_sub(1, Ordering::Relaxed);
+        if let Some(pid) = self.pid {
+            self.running.lock().unwrap().remove(&pid);
+            crate::reaper::unregister(pid);
+        }
+    }
+}
+
+impl BrowserSolver {
+    /// Number of Chrome instances currently launched by the solver.
+    pub fn active_count() -> usize {
+        ACTIVE_BROWSERS.load(Ordering::Relaxed)
+    }
+
+    /// Kills every solver browser and makes later launches close straight away. Chrome is
+    /// spawned with kill-on-drop, so the idle ones in `pool` die as they are dropped here and
+    /// the busy ones as their cancelled workers drop them; no PID is signalled from outside.
+    /// Returns the number of browsers that were running.
+    pub fn kill_all(pool: &BrowserPool) -> usize {
+        BROWSERS_HALTED.store(true, Ordering::SeqCst);
+        let running = pool.running.lock().unwrap().len();
+        pool.kill_idle();
+        running
+    }
+
+    /// Lifts an earlier run's emergency stop, so this run's solves can launch browsers.
+    pub fn reset_halt() {
+        BROWSERS_HALTED.store(false, Ordering::SeqCst);
+    }
+
+    /// Kills `pool`'s browsers still running once every worker has finished, which should
+    /// be none: their handles are gone, so this is the one place a tracked PID is signalled
+    /// directly. Another engine's browsers are left alone. Returns how many there were.
+    pub fn kill_leftovers(pool: &BrowserPool) -> usize {
+        let pids: Vec<u32> = pool.running.lock().unwrap().iter().copied().collect();
+        pids.into_iter().filter(|pid| crate::reaper::kill(*pid)).count()
+    }
+
+    /// `browser.binary_path`, else the first Chrome in the usual install locations, else a
+    /// `browser.download` build already in the cache.
+    pub fn find_chrome_binary(chrome: &ChromeConfig) -> Option<PathBuf> {
+        if let Some(path) = &chrome.binary_path {
+            return Some(PathBuf::from(path));
+        }
+        let possible_paths = [
+            "/usr/bin/chromium",
+            "/usr/bin/chromium-browser",
+            "/usr/bin/google-chrome",
+            "/snap/bin/chromium",
+            "/Applications/Google Chrome.app/Contents/MacOS/Google Chrome",
+            "C:\\Program Files\\Google\\Chrome\\Application\\chrome.exe",
+            "C:\\Program Files (x86)\\Google\\Chrome\\Application\\chrome.exe",
+        ];
+        for path_str in possible_paths {
+            if Path::new(path_str).exists() {
+                return Some(PathBuf::from(path_str));
+            }
+        }
+        chrome.download.as_ref().and_then(|d| d.installed())
+    }
+
+    /// Attaches to a remote browser once and returns its product string, e.g. "HeadlessChrome/131.0.6778.85".
+    pub async fn probe_remote(url: &str) -> Result<String> {
+        let (browser, mut handler) =
+            CdpBrowser::connect(url).await.with_context(|| format!("Failed to attach to remote browser {}", url))?;
+        let handler = tokio::spawn(async move { while handler.next().await.is_some() {} });
+        let version = browser.version().await;
+        handler.abort();
+        Ok(version?.product)
+    }
+
+    /// Cloudflare Turnstile: gives a managed challenge time to pass on its own, then clicks
+    /// the checkbox with real (trusted) input events, and waits for `cf_clearance`. An
+    /// embedded widget on a site Cloudflare doesn't front counts as passed once it has
+    /// issued its token. Returns whether it cleared; `false` as well when there's no Turnstile.
+    async fn clear_turnstile(page: &Page, stats: &SolverStats, logger: &SpectreLogger, worker_id: &str) -> Result<bool> {
+        let Some(widget) = page.evaluate(TURNSTILE_DETECT).await?.into_value::<Option<TurnstileWidget>>()? else {
+            return Ok(false);
+        };
+        let started = Instant::now();
+        stats.turnstile_attempts.fetch_add(1, Ordering::Relaxed);
+        let kind = if widget.managed { "managed" } else { "widget" };
+        logger.log(worker_id, "TURNSTILE_DETECTED", "Cloudflare Turnstile on the page", Some(&format!("{{\"kind\": \"{}\"}}", kind)));
+
+        let mut cleared = Self::await_turnstile(page, widget.managed, TURNSTILE_AUTO_PASS).await;
+        if !cleared {
+            if let Some([x, y, _, height]) = widget.rect {
+                // The checkbox sits at the left edge of the widget, vertically centred
+                let (cx, cy) = {
+                    let mut rng = rand::thread_rng();
+                    (x + rng.gen_range(22.0..34.0), y + height / 2.0 + rng.gen_range(-4.0..4.0))
+                };
+                Self::click(page, cx, cy).await?;
+                logger.log(worker_id, "TURNSTILE_CLICK", "Clicked the Turnstile checkbox", None);
+                cleared = Self::await_turnstile(page, widget.managed, TURNSTILE_AFTER_CLICK).await;
+            }
+        }
+        let elapsed = started.elapsed().as_millis() as u64;
+        if cleared {
+            stats.turnstile_solves.fetch_add(1, Ordering::Relaxed);
+            stats.turnstile_ms.fetch_add(elapsed, Ordering::Relaxed);
+            logger.log(worker_id, "TURNSTILE_SOLVED", "Turnstile cleared", Some(&format!("{{\"duration_ms\": {}}}", elapsed)));
+        } else {
+            logger.log(worker_id, "TURNSTILE_FAIL", "Turnstile did not clear", Some(&format!("{{\"duration_ms\": {}}}", elapsed)));
+        }
+        Ok(cleared)
+    }
+
+    /// Polls until `cf_clearance` is set (or, for an embedded widget, its token is issued).
+    async fn await_turnstile(page: &Page, managed: bool, within: Duration) -> bool {
+        let deadline = Instant::now() + within;
+        while Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(500)).await;
+            if page.get_cookies().await.is_ok_and(|cookies| cookies.iter().any(|c| c.name == "cf_clearance")) {
+                return true;
+            }
+            if !managed {
+                let token = page.evaluate(TURNSTILE_TOKEN).await.ok().and_then(|r| r.into_value::<bool>().ok());
+                if token == Some(true) {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    /// A left click through `Input.dispatchMouseEvent`, which the page sees as trusted.
+    async fn click(page: &Page, x: f64, y: f64) -> Result<()> {
+        let event = |kind: DispatchMouseEventType| {
+            DispatchMouseEventParams::builder()
+                .r#type(kind)
+                .x(x)
+                .y(y)
+                .button(MouseButton::Left)
+                .click_count(1)
+                .build()
+                .map_err(|e| anyhow!(e))
+        };
+        page.execute(event(DispatchMouseEventType::MouseMoved)?).await?;
+        tokio::time::sleep(Duration::from_millis(rand::thread_rng().gen_range(80..200))).await;
+        page.execute(event(DispatchMouseEventType::MousePressed)?).await?;
+        tokio::time::sleep(Duration::from_millis(rand::thread_rng().gen_range(50..120))).await;
+        page.execute(event(DispatchMouseEventType::MouseReleased)?).await?;
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub async fn solve(
+        pool: &Arc<BrowserPool>,
+        url: &str,
+        success: &SuccessCriteria,
+        captcha: Option<&CaptchaSolver>,
+        proxy: Option<&str>,
+        persona: &BrowserPersona,
+        stealth: &StealthConfig,
+        env: &BrowserEnv,
+        logger: &SpectreLogger,
+        worker_id: &str,
+    ) -> Result<String> {
+        let _permit = BROWSER_LIMITER.acquire().await?;
+        if persona.solver == SolverBackend::Firefox {
+            // Every Firefox solve starts its own browser
+            pool.stats.browser_launches.fetch_add(1, Ordering::Relaxed);
+            return crate::firefox::solve(url, success, proxy, persona, env, logger, worker_id).await;
+        }
+
+        // A crashed tab or browser says nothing about the challenge, so the solve starts
+        // over on another browser, up to `solver.crash_retries` times
+        let mut crashes = 0;
+        loop {
+            match Self::attempt(pool, url, success, captcha, proxy, persona, stealth, env, logger, worker_id).await {
+                Attempt::Done(result) => return result,
+                Attempt::Crashed(e) => {
+                    pool.stats.browser_crashes.fetch_add(1, Ordering::Relaxed);
+                    let retry = crashes < pool.crash_retries && !BROWSERS_HALTED.load(Ordering::SeqCst);
+                    logger.log(
+                        worker_id,
+                        "BROWSER_CRASH",
+                        if retry { "Browser crashed, solving again on another" } else { "Browser crashed" },
+                        Some(&serde_json::json!({ "error": e.to_string(), "crashes": crashes + 1 }).to_string()),
+                    );
+                    if !retry {
+                        return Err(e.context("Browser crashed"));
+                    }
+                    crashes += 1;
+                }
+            }
+        }
+    }
+
+    /// One solve on one browser.
+    #[allow(clippy::too_many_arguments)]
+    async fn attempt(
+        pool: &Arc<BrowserPool>,
+        url: &str,
+        success: &SuccessCriteria,
+        captcha: Option<&CaptchaSolver>,
+        proxy: Option<&str>,
+        persona: &BrowserPersona,
+        stealth: &StealthConfig,
+        env: &BrowserEnv,
+        logger: &SpectreLogger,
+        worker_id: &str,
+    ) -> Attempt {
+        // A solve cancelled mid-way (deadline, halt) drops its browser, which kills it
+        let browser = match pool.get(proxy, env, persona).await {
+            Ok(browser) => browser,
+            Err(e) => return Attempt::Done(Err(e)),
+        };
+        if browser.solves > 0 {
+            logger.log(worker_id, "BROWSER_POOL", "Reusing warm browser", Some(&format!("{{\"solves\": {}}}", browser.solves)));
+        }
+        let prepared = async {
+            browser.page.execute(InspectorEnableParams::default()).await?;
+            let crashes = browser.page.event_listener::<EventTargetCrashed>().await?;
+            let script = Self::prepare_page(&browser.page, persona, stealth).await?;
+            Ok::<_, anyhow::Error>((crashes, script))
+        };
+        let prepared = prepared.await;
+        let (mut tab_crashes, script) = match prepared {
+            Ok(prepared) => prepared,
+            Err(e) => return pool.fail(browser, false, e).await,
+        };
+        let recorder = match &env.chrome.activity {
+            Some(activity) => match ActivityRecorder::start(&browser.page, activity, logger.clone(), worker_id).await {
+                Ok(recorder) => Some(recorder),
+                Err(e) => {
+                    logger.log(worker_id, "BROWSER_WARN", "Activity recording failed", Some(&serde_json::json!(e.to_string()).to_string()));
+                    None
+                }
+            },
+            None => None,
+        };
+        let mut tab_crashed = false;
+        let outcome = tokio::select! {
+            outcome = Self::await_clearance(&browser.page, url, persona, success, captcha, &pool.stats, &env.chrome, logger, worker_id) => outcome,
+            Some(_) = tab_crashes.next() => {
+                tab_crashed = true;
+                Err(anyhow!("Tab crashed"))
+            }
+        };
+        if let Some(recorder) = recorder {
+            match recorder.finish(worker_id).await {
+                Ok(Some(path)) => {
+                    logger.log(worker_id, "BROWSER_HAR", "Saved the solve's traffic", Some(&serde_json::json!(path.display().to_string()).to_string()));
+                    pool.captures.lock().unwrap().push(path.display().to_string());
+                }
+                Ok(None) => {}
+                Err(e) => logger.log(worker_id, "BROWSER_WARN", "HAR write failed", Some(&serde_json::json!(e.to_string()).to_string())),
+            }
+        }
+        // A timeout leaves a working browser; any other error may mean it died
+        match outcome {
+            Ok(Some(cookies)) => {
+                pool.release(browser, script).await;
+                Attempt::Done(Ok(cookies))
+            }
+            Ok(None) => {
+                // Before the page is reset, so the capture shows where the solve got stuck
+                match pool.capture(&browser.page, worker_id).await {
+                    Ok(Some(dir)) => logger.log(
+                        worker_id,
+                        "BROWSER_CAPTURE",
+                        "Saved the timed-out page",
+                        Some(&serde_json::json!(dir.display().to_string()).to_string()),
+                    ),
+                    Ok(None) => {}
+                    Err(e) => logger.log(worker_id, "BROWSER_WARN", "Timeout capture failed", Some(&serde_json::json!(e.to_string()).to_string())),
+                }
+                pool.release(browser, script).await;
+                Attempt::Done(Err(anyhow!("Browser timed out waiting for clearance")))
+            }
+            Err(e) => pool.fail(browser, tab_crashed, e).await,
+        }
+    }
+
+    /// Launches Chrome for `proxy` (or attaches to `browser.remote_debugging_url`) with a
+    /// page ready to take per-identity settings.
+    async fn launch(
+        proxy: Option<&str>,
+        env: &BrowserEnv,
+        persona: &BrowserPersona,
+        running: &Arc<Mutex<HashSet<u32>>>,
+    ) -> Result<PooledBrowser> {
+        let mut active = ActiveBrowserGuard::new(running.clone());
+        let upstream = proxy.map(ProxyEndpoint::parse).transpose()?;
+        let mut proxy_endpoint = upstream.clone();
+        let remote = env.chrome.remote_debugging_url.as_deref();
+        // Chrome can't log in to a SOCKS proxy; a loopback relay does it on its behalf, and
+        // wants credentials of its own, answered like an HTTP proxy's
+        if let Some(p) = upstream.as_ref().filter(|p| p.is_socks() && p.has_credentials() && remote.is_none()) {
+            proxy_endpoint = Some(env.relays.http(p).await?);
+        }
+        let (mut browser, mut handler, profile) = match remote {
+            Some(url) => {
+                let (browser, handler) =
+                    CdpBrowser::connect(url).await.with_context(|| format!("Failed to attach to remote browser {}", url))?;
+                (browser, handler, None)
+            }
+            None => {
+                // Pooled browsers live side by side, so each gets its own profile
+                let profile = match &env.chrome.profiles_dir {
+                    Some(root) => ProfileDir::persistent(Path::new(root), &persona.profile, proxy)?,
+                    None => ProfileDir::create()?,
+                };
+                let config = Self::launch_config(proxy_endpoint.as_ref(), upstream.as_ref(), env, persona, &profile)?;
+                let (browser, handler) = CdpBrowser::launch(config).await.context("Failed to launch browser")?;
+                (browser, handler, Some(profile))
+            }
+        };
+        // The handler drives the CDP connection; nothing moves without it
+        let handler = tokio::spawn(async move { while handler.next().await.is_some() {} });
+        active.track(browser.get_mut_child().and_then(|child| child.id()), profile.as_ref().map(|p| p.path.as_path()))?;
+        // A remote Chrome was started without our flags, so its contexts carry the proxy
+        let context_proxy = remote.and(proxy_endpoint.clone());
+        let (page, context) = match profile.as_ref().is_some_and(|p| p.persistent) {
+            true => (browser.new_page("about:blank").await?, None),
+            false => {
+                let (page, context) = Self::context_page(&browser, context_proxy.as_ref()).await?;
+                (page, Some(context))
+            }
+        };
+
+        // Proxy credentials: answer Fetch.authRequired with continueWithAuth
+        let proxy_auth = proxy_endpoint.filter(|p| p.has_credentials());
+        let auth = match &proxy_auth {
+            Some(p) => Some(Self::answer_proxy_auth(&page, p.clone()).await?),
+            None => None,
+        };
+        Ok(PooledBrowser {
+            proxy: proxy.map(str::to_string),
+            persona: profile.as_ref().filter(|p| p.persistent).map(|_| persona.profile.clone()),
+            user_agent: persona.user_agent.clone(),
+            browser: Some(browser),
+            page,
+            context,
+            context_proxy,
+            proxy_auth,
+            solves: 0,
+            handler,
+            auth,
+            profile,
+            active: Some(active),
+        })
+    }
+
+    /// A page in a fresh browser context, which starts with no cookies or site data for any
+    /// origin. `proxy` is set on the context for a remote Chrome, which was started without
+    /// our flags; a local one's contexts use its `--proxy-server`. Chrome disposes of the
+    /// context when this connection closes.
+    async fn context_page(browser: &CdpBrowser, proxy: Option<&ProxyEndpoint>) -> Result<(Page, BrowserContextId)> {
+        let mut context = CreateBrowserContextParams::builder().dispose_on_detach(true);
+        if let Some(p) = proxy {
+            context = context.proxy_server(p.chrome_proxy_server()?);
+        }
+        let context = browser.create_browser_context(context.build()).await?;
+        let target = CreateTargetParams::builder()
+            .url("about:blank")
+            .browser_context_id(context.clone())
+            .build()
+            .map_err(|e| anyhow!(e))?;
+        Ok((browser.new_page(target).await?, context))
+    }
+
+    /// Flags for a locally launched Chrome. `upstream` is the node itself, which differs
+    /// from `proxy_endpoint` when Chrome goes through a relay.
+    fn launch_config(
+        proxy_endpoint: Option<&ProxyEndpoint>,
+        upstream: Option<&ProxyEndpoint>,
+        env: &BrowserEnv,
+        persona: &BrowserPersona,
+        profile: &ProfileDir,
+    ) -> Result<BrowserConfig> {
+        let traits = &persona.traits;
+        let mut args_vec = vec![
+            "--no-sandbox".to_string(),
+            "--disable-gpu".to_string(),
+            format!("--window-size={},{}", traits.screen_width, traits.screen_height),
+            "--disable-blink-features=AutomationControlled".to_string(),
+            // --- CRITICAL FIX START: Force User-Agent at Launch ---
+            format!("--user-agent={}", persona.user_agent),
+            // --- CRITICAL FIX END ---
+        ];
+
+        if let Some(p) = proxy_endpoint {
+            args_vec.extend(p.chrome_args()?);
+        }
+        if let Some(rules) = env.host_resolver_rules(upstream) {
+            args_vec.push(format!("--host-resolver-rules={}", rules));
+        }
+        // Chrome appends to the same key log, so the browser leg decrypts too
+        if let Some(path) = &env.keylog {
+            args_vec.push(format!("--ssl-key-log-file={}", path.display()));
+        }
+        // A browser that was killed rather than closed left its profile marked as crashed
+        if profile.persistent {
+            args_vec.push("--hide-crash-restore-bubble".to_string());
+        }
+
+        let mut config = BrowserConfig::builder()
+            // The defaults include --enable-automation, which sets navigator.webdriver
+            .disable_default_args()
+            .user_data_dir(&profile.path)
+            .args(args_vec);
+        if !env.chrome.headless || env.chrome.devtools {
+            config = config.with_head();
+        }
+        if env.chrome.devtools {
+            config = config.arg("--auto-open-devtools-for-tabs");
+        }
+        if let Some(path) = Self::find_chrome_binary(&env.chrome) {
+            config = config.chrome_executable(path);
+        }
+        config.build().map_err(|e| anyhow!("Invalid browser config: {}", e))
+    }
+
+    /// Intercepts the page's requests to answer proxy auth challenges; everything else is
+    /// let through untouched.
+    async fn answer_proxy_auth(page: &Page, proxy: ProxyEndpoint) -> Result<tokio::task::JoinHandle<()>> {
+        let mut challenges = page.event_listener::<EventAuthRequired>().await?;
+        let mut paused = page.event_listener::<EventRequestPaused>().await?;
+        page.execute(FetchEnableParams::builder().handle_auth_requests(true).build()).await?;
+        let page = page.clone();
+        Ok(tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    Some(event) = challenges.next() => {
+                        let answer = AuthChallengeResponse {
+                            response: AuthChallengeResponseResponse::ProvideCredentials,
+                            username: proxy.username.clone(),
+                            password: proxy.password.clone(),
+                        };
+                        let _ = page.execute(ContinueWithAuthParams::new(event.request_id.clone(), answer)).await;
+                    }
+                    Some(event) = paused.next() => {
+                        let _ = page.execute(ContinueRequestParams::new(event.request_id.clone())).await;
+                    }
+                    else => break,
+                }
+            }
+        }))
+    }
+
+    /// Applies the persona to the page. A pooled page outlives the identity it was launched
+    /// for, so user agent, client hints, time zone, window size, viewport and touch input
+    /// are set on every solve. Returns the stealth script's id, to remove it before the
+    /// page's next solve.
+    async fn prepare_page(page: &Page, persona: &BrowserPersona, stealth: &StealthConfig) -> Result<ScriptIdentifier> {
+        let traits = &persona.traits;
+        // --- CDP STEALTH INJECTION ---
+        // Registered before navigation, so the patches are in place before any page script
+        let script = page
+            .execute(AddScriptToEvaluateOnNewDocumentParams::new(stealth.script(traits, &persona.languages)))
+            .await?
+            .result
+            .identifier;
+
+        // The launch flag only covers the identity the browser was launched for; the pool
+        // hands the browser to identities with the same User-Agent, whose hints may differ
+        let mut user_agent = SetUserAgentOverrideParams::builder()
+            .user_agent(persona.user_agent.clone())
+            .accept_language(persona.accept_language.clone())
+            .platform(persona.platform);
+        if let Some(hints) = &persona.hints {
+            user_agent = user_agent.user_agent_metadata(hints.ua_metadata(traits)?);
+        }
+        page.execute(user_agent.build().map_err(|e| anyhow!(e))?).await?;
+        // An empty zone clears the previous identity's override
+        page.execute(SetTimezoneOverrideParams::new(persona.timezone.clone().unwrap_or_default())).await?;
+
+        // The window outlives `--window-size` too; outerWidth/outerHeight follow it
+        let window = page.execute(GetWindowForTargetParams::default()).await?.result.window_id;
+        let bounds = Bounds::builder()
+            .width(traits.screen_width as i64)
+            .height(traits.screen_height as i64)
+            .build();
+        page.execute(SetWindowBoundsParams::new(window, bounds)).await?;
+
+        // Mobile identities: phone viewport, DPR and touch input instead of a desktop window
+        let mobile = traits.family.is_mobile();
+        page.execute(
+            SetDeviceMetricsOverrideParams::builder()
+                .width(traits.screen_width as i64)
+                .height(traits.screen_height as i64)
+                .device_scale_factor(if mobile { traits.pixel_ratio as f64 } else { 0.0 })
+                .mobile(mobile)
+                .screen_width(traits.screen_width as i64)
+                .screen_height(traits.screen_height as i64)
+                .build()
+                .map_err(|e| anyhow!(e))?,
+        )
+        .await?;
+        let mut touch = SetTouchEmulationEnabledParams::builder().enabled(mobile);
+        if mobile {
+            touch = touch.max_touch_points(5);
+        }
+        page.execute(touch.build().map_err(|e| anyhow!(e))?).await?;
+        Ok(script)
+    }
+
+    /// Navigates and polls until the page meets `success` (without criteria: until it no
+    /// longer looks like a challenge) and cookies are set; `None` on timeout. A Turnstile on
+    /// the settled page gets its own flow first; any CAPTCHA widget still unsolved then
+    /// goes to `captcha`, when set.
+    #[allow(clippy::too_many_arguments)]
+    async fn await_clearance(
+        page: &Page,
+        url: &str,
+        persona: &BrowserPersona,
+        success: &SuccessCriteria,
+        captcha: Option<&CaptchaSolver>,
+        stats: &SolverStats,
+        chrome: &ChromeConfig,
+        logger: &SpectreLogger,
+        worker_id: &str,
+    ) -> Result<Option<String>> {
+        let slow_mo = chrome.slow_mo();
+        logger.log(worker_id, "BROWSER_NAV", "Navigating to Target", Some(&serde_json::json!(url.to_string()).to_string()));
+        
+        page.goto(url).await?;
+        tokio::time::sleep(slow_mo).await;
+
+        let viewport = (persona.traits.screen_width, persona.traits.screen_height);
+        let mut behavior = Behavior::new(page, persona.behavior.preset, viewport);
+        if let Err(e) = behavior.perform(&persona.behavior).await {
+            logger.log(worker_id, "BROWSER_WARN", "Biometric simulation issue", Some(&serde_json::json!(e.to_string()).to_string()));
+        }
+        tokio::time::sleep(slow_mo).await;
+
+        let wait = &chrome.wait;
+        match Self::wait_for_page(page, wait, Duration::from_millis(chrome.poll_interval_ms)).await {
+            Ok(true) => {}
+            Ok(false) => logger.log(
+                worker_id,
+                "BROWSER_WARN",
+                "Page wait timed out",
+                Some(&format!("{{\"strategy\": \"{}\", \"timeout_secs\": {}}}", wait.strategy.as_str(), wait.timeout_secs)),
+            ),
+            Err(e) => logger.log(worker_id, "BROWSER_WARN", "Page wait failed", Some(&serde_json::json!(e.to_string()).to_string())),
+        }
+
+        if let Ok(png) = page
+            .screenshot(ScreenshotParams::builder().format(CaptureScreenshotFormat::Png).full_page(true).build())
+            .await
+        {
+             let _ = tokio::fs::write("debug_screenshot.png", png).await;
+        }
+
+        let turnstile_cleared = match Self::clear_turnstile(page, stats, logger, worker_id).await {
+            Ok(cleared) => cleared,
+            Err(e) => {
+                logger.log(worker_id, "BROWSER_WARN", "Turnstile flow failed", Some(&serde_json::json!(e.to_string()).to_string()));
+                false
+            }
+        };
+        tokio::time::sleep(slow_mo).await;
+
+        // The service's answer can take a minute; the clearance poll starts after it
+        if let Some(captcha) = captcha.filter(|_| !turnstile_cleared) {
+            match captcha.detect(page).await {
+                Ok(Some(widget)) => {
+                    let kind = widget.kind.as_str();
+                    logger.log(worker_id, "CAPTCHA_SUBMIT", "Sending widget to the solving service", Some(&format!("{{\"kind\": \"{}\"}}", kind)));
+                    match captcha.solve(page, &widget).await {
+                        Ok(via) => logger.log(worker_id, "CAPTCHA_SOLVED", "Token injected", Some(&format!("{{\"kind\": \"{}\", \"via\": \"{}\"}}", kind, via))),
+                        Err(e) => logger.log(worker_id, "CAPTCHA_FAIL", "Solving service gave no token", Some(&serde_json::json!(e.to_string()).to_string())),
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => logger.log(worker_id, "BROWSER_WARN", "CAPTCHA detection failed", Some(&serde_json::json!(e.to_string()).to_string())),
+            }
+            tokio::time::sleep(slow_mo).await;
+        }
+
+        Self::poll_clearance(page, success, chrome, logger, worker_id).await
+    }
+
+    /// Polls a solver page, Chrome or Firefox, until it passes `success` (or, without
+    /// criteria, shows no interstitial markers) and has cookies to hand back; `None` once
+    /// `browser.solve_timeout_secs` is up.
+    pub(crate) async fn poll_clearance(
+        page: &dyn SolverPage,
+        success: &SuccessCriteria,
+        chrome: &ChromeConfig,
+        logger: &SpectreLogger,
+        worker_id: &str,
+    ) -> Result<Option<String>> {
+        let slow_mo = chrome.slow_mo();
+        let start_time = Instant::now();
+        let timeout = Duration::from_secs(chrome.solve_timeout_secs);
+        let poll_interval = Duration::from_millis(chrome.poll_interval_ms);
+
+        while start_time.elapsed() < timeout {
+            if let (Ok(content), Ok(cookies)) = (page.html().await, page.cookie_pairs().await) {
+                // Check for Success Indicators
+                let passed = if success.is_empty() {
+                    !content.to_lowercase().contains("checking your browser") && !content.contains("bw_id")
+                } else {
+                    let current = page.current_url().await.unwrap_or_default();
+                    let selector_found = match success.selector.as_deref() {
+                        Some(selector) => page.has_element(selector).await,
+                        None => true,
+                    };
+                    selector_found && success.matches_page(&current, cookies.iter().map(|(name, _)| name.as_str()), &content)
+                };
+                if passed {
+                    let cookie_vec: Vec<String> = cookies
+                        .iter()
+                        .map(|(name, value)| format!("{}={}", name, value))
+                        .collect();
+                    
+                    let cookie_str = cookie_vec.join("; ");
+                    if !cookie_str.is_empty() {
+                        logger.log(worker_id, "BROWSER_SUCCESS", "Challenge Solved", Some(&serde_json::json!(cookie_str.to_string()).to_string()));
+                        return Ok(Some(cookie_str));
+                    }
+                }
+            }
+            tokio::time::sleep(poll_interval + slow_mo).await;
+        }
+        Ok(None)
+    }
+
+    /// Waits for `browser.wait` once the page has loaded. Returns false when the strategy
+    /// ran out of time; the solve goes on regardless, since a challenge page may never
+    /// reach the state a cleared page would.
+    async fn wait_for_page(page: &Page, wait: &WaitConfig, poll_interval: Duration) -> Result<bool> {
+        let timeout = Duration::from_secs(wait.timeout_secs);
+        let waited = match wait.strategy {
+            WaitStrategy::Fixed => {
+                tokio::time::sleep(Duration::from_secs(wait.settle_secs)).await;
+                return Ok(true);
+            }
+            WaitStrategy::NetworkIdle => {
+                tokio::time::timeout(timeout, Self::network_idle(page, Duration::from_millis(wait.idle_ms))).await
+            }
+            WaitStrategy::Selector => {
+                let selector = wait.selector.as_deref().unwrap_or_default();
+                let check = format!("{}({})", SELECTOR_VISIBLE, serde_json::to_string(selector)?);
+                tokio::time::timeout(timeout, async {
+                    loop {
+                        if page.evaluate(check.as_str()).await?.into_value::<bool>()? {
+                            return Ok::<_, anyhow::Error>(());
+                        }
+                        tokio::time::sleep(poll_interval).await;
+                    }
+                })
+                .await
+            }
+            WaitStrategy::Url => {
+                let pattern = wait.url_regex()?;
+                tokio::time::timeout(timeout, async {
+                    loop {
+                        if page.url().await?.is_some_and(|current| pattern.is_match(&current)) {
+                            return Ok::<_, anyhow::Error>(());
+                        }
+                        tokio::time::sleep(poll_interval).await;
+                    }
+                })
+                .await
+            }
+        };
+        match waited {
+            Ok(result) => result.map(|()| true),
+            Err(_) => Ok(false),
+        }
+    }
+
+    /// Returns once no request has been in flight for `quiet`. Requests that started
+    /// before the call aren't known, so only the ones seen starting count as in flight.
+    async fn network_idle(page: &Page, quiet: Duration) -> Result<()> {
+        let mut sent = page.event_listener::<EventRequestWillBeSent>().await?;
+        let mut finished = page.event_listener::<EventLoadingFinished>().await?;
+        let mut failed = page.event_listener::<EventLoadingFailed>().await?;
+        let mut in_flight: HashSet<String> = HashSet::new();
+        loop {
+            tokio::select! {
+                Some(event) = sent.next() => {
+                    in_flight.insert(event.request_id.inner().clone());
+                }
+                Some(event) = finished.next() => {
+                    in_flight.remove(event.request_id.inner());
+                }
+                Some(event) = failed.next() => {
+                    in_flight.remove(event.request_id.inner());
+                }
+                _ = tokio::time::sleep(quiet), if in_flight.is_empty() => return Ok(()),
+                else => return Err(anyhow!("page closed while waiting for the network")),
+            }
+        }
+    }
+}
+
+/// Whether the element matching a selector is rendered and visible. Takes the selector.
+pub(crate) const SELECTOR_VISIBLE: &str = r#"
+    ((selector) => {
+        const el = document.querySelector(selector);
+        if (!el) return false;
+        const r = el.getBoundingClientRect();
+        const style = getComputedStyle(el);
+        return r.width > 0 && r.height > 0 && style.visibility !== 'hidden' && style.display !== 'none';
+    })
+"#;
+
+/// How long a Turnstile gets to pass without interaction, and then after the click.
+const TURNSTILE_AUTO_PASS: Duration = Duration::from_secs(8);
+const TURNSTILE_AFTER_CLICK: Duration = Duration::from_secs(15);
+
+/// What `TURNSTILE_DETECT` reports: a managed challenge page (`_cf_chl_opt`) or an
+/// embedded widget, and the widget's [x, y, width, height] when it is visible.
+#[derive(serde::Deserialize)]
+struct TurnstileWidget {
+    managed: bool,
+    rect: Option<[f64; 4]>,
+}
+
+const TURNSTILE_DETECT: &str = r#"
+    (() => {
+        const managed = typeof window._cf_chl_opt !== 'undefined';
+        const el = document.querySelector('iframe[src*="challenges.cloudflare.com"]')
+            || document.querySelector('.cf-turnstile, [id^="cf-chl-widget-"], #turnstile-wrapper');
+        if (!managed && !el) return null;
+        const r = el && el.getBoundingClientRect();
+        return { managed, rect: r && r.width > 0 && r.height > 0 ? [r.x, r.y, r.width, r.height] : null };
+    })()
+"#;
+
+const TURNSTILE_TOKEN: &str =
+    "Array.from(document.querySelectorAll('[name=\"cf-turnstile-response\"]')).some((f) => f.value.length > 0)";
+
+static PROFILE_DIRS: AtomicUsize = AtomicUsize::new(0);
+
+lazy_static::lazy_static! {
+    /// Persistent profiles held by a running browser; Chrome won't share one.
+    static ref PROFILES_IN_USE: Mutex<HashSet<PathBuf>> = Mutex::new(HashSet::new());
+}
+
+/// A browser's profile directory: a throwaway one, removed with the browser, or a
+/// persona's persistent one under `browser.profiles_dir`.
+pub(crate) struct ProfileDir {
+    pub(crate) path: PathBuf,
+    persistent: bool,
+}
+
+impl ProfileDir {
+    pub(crate) fn create() -> Result<Self> {
+        let n = PROFILE_DIRS.fetch_add(1, Ordering::Relaxed);
+        let dir = std::env::temp_dir().join(format!("spectre-chrome-{}-{}", std::process::id(), n));
+        fs::create_dir_all(&dir).context(format!("Failed to create browser profile {}", dir.display()))?;
+        Ok(Self { path: dir, persistent: false })
+    }
+
+    /// `<root>/<persona>@<node>`, or `<root>/<persona>@<node>-<n>` when browsers for the
+    /// same persona and node run side by side: the first one not in use. `<node>` is a hash
+    /// of the proxy (`direct` without one), so cookies bound to one exit IP, such as
+    /// `cf_clearance`, never travel through another.
+    fn persistent(root: &Path, persona: &str, proxy: Option<&str>) -> Result<Self> {
+        let persona: String =
+            persona.chars().map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' }).collect();
+        let node = match proxy {
+            Some(proxy) => format!("{:x}", md5::compute(proxy.as_bytes()))[..12].to_string(),
+            None => "direct".to_string(),
+        };
+        let name = format!("{}@{}", persona, node);
+        let path = {
+            let mut in_use = PROFILES_IN_USE.lock().unwrap();
+            let mut path = root.join(&name);
+            let mut n = 0;
+            while in_use.contains(&path) {
+                n += 1;
+                path = root.join(format!("{}-{}", name, n));
+            }
+            in_use.insert(path.clone());
+            path
+        };
+        let profile = Self { path, persistent: true };
+        fs::create_dir_all(&profile.path).context(format!("Failed to create browser profile {}", profile.path.display()))?;
+        Ok(profile)
+    }
+}
+
+impl Drop for ProfileDir {
+    fn drop(&mut self) {
+        if self.persistent {
+            PROFILES_IN_USE.lock().unwrap().remove(&self.path);
+        } else {
+            let _ = fs::remove_dir_all(&self.path);
+        }
+    }
+}
+
+/// What the clearance poll reads from a solver page, so the Chrome and Firefox backends
+/// share one notion of solved.
+#[async_trait]
+pub trait SolverPage: Sync {
+    async fn html(&self) -> Result<String>;
+    async fn current_url(&self) -> Result<String>;
+    /// Name and value of each cookie the page holds.
+    async fn cookie_pairs(&self) -> Result<Vec<(String, String)>>;
+    async fn has_element(&self, selector: &str) -> bool;
+}
+
+#[async_trait]
+impl SolverPage for Page {
+    async fn html(&self) -> Result<String> {
+        Ok(Page::content(self).await?)
+    }
+
+    async fn current_url(&self) -> Result<String> {
+        Ok(Page::url(self).await?.unwrap_or_default())
+    }
+
+    async fn cookie_pairs(&self) -> Result<Vec<(String, String)>> {
+        Ok(Page::get_cookies(self).await?.into_iter().map(|c| (c.name, c.value)).collect())
+    }
+
+    async fn has_element(&self, selector: &str) -> bool {
+        Page::find_element(self, selector).await.is_ok()
+    }
+}
+
+/// How one attempt at a solve ended.
+enum Attempt {
+    Done(Result<String>),
+    /// The tab or the whole browser crashed; the solve can start over on another browser.
+    Crashed(anyhow::Error),
+}
+
+/// A solver Chrome and its page. Dropping it kills the browser, or for a remote one, closes
+/// the connection and with it the browser context.
+struct PooledBrowser {
+    /// Proxy the browser was launched with; fixed for its life.
+    proxy: Option<String>,
+    /// Persona whose persistent profile the browser runs on; it serves no other.
+    persona: Option<String>,
+    /// `--user-agent` it was launched with. Workers and out-of-process frames report it
+    /// whatever the page's override says, so the browser only serves identities that share it.
+    user_agent: String,
+    /// `None` only once dropped.
+    browser: Option<CdpBrowser>,
+    page: Page,
+    /// The context `page` lives in, replaced between identities; `None` on a persistent
+    /// profile, whose data is kept.
+    context: Option<BrowserContextId>,
+    /// Set on a remote browser's contexts.
+    context_proxy: Option<ProxyEndpoint>,
+    /// Credentials the page answers proxy auth challenges with.
+    proxy_auth: Option<ProxyEndpoint>,
+    solves: usize,
+    /// Drives the CDP connection.
+    handler: tokio::task::JoinHandle<()>,
+    /// Answers the page's proxy auth challenges.
+    auth: Option<tokio::task::JoinHandle<()>>,
+    /// `None` for a remote browser.
+    profile: Option<ProfileDir>,
+    /// `None` only once dropped.
+    active: Option<ActiveBrowserGuard>,
+}
+
+impl PooledBrowser {
+    fn browser(&self) -> &CdpBrowser {
+        self.browser.as_ref().expect("browser is only taken on drop")
+    }
+
+    /// Closes Chrome over CDP and waits for it to exit, so a persistent profile's cookies
+    /// and site data reach the disk; a killed Chrome loses what it hadn't flushed yet.
+    async fn shut_down(&mut self) {
+        if self.persona.is_none() || BROWSERS_HALTED.load(Ordering::SeqCst) {
+            return;
+        }
+        let Some(browser) = self.browser.as_mut() else {
+            return;
+        };
+        let _ = tokio::time::timeout(Duration::from_secs(5), async {
+            let _ = browser.close().await;
+            let _ = browser.wait().await;
+        })
+        .await;
+    }
+}
+
+impl Drop for PooledBrowser {
+    // chromiumoxide kills the process when `browser` is dropped, but doesn't wait for it to
+    // exit. The profile is removed, or freed for the next browser, and the PID forgotten
+    // only once it has, so no Chrome is still writing to a profile that's gone or handed on.
+    fn drop(&mut self) {
+        self.handler.abort();
+        if let Some(auth) = &self.auth {
+            auth.abort();
+        }
+        let Some(mut browser) = self.browser.take() else {
+            return;
+        };
+        let (profile, active) = (self.profile.take(), self.active.take());
+        if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+            runtime.spawn(async move {
+                let _ = browser.kill().await;
+                drop((profile, active));
+            });
+        } else {
+            drop(browser);
+        }
+    }
+}
+
+/// Warm solver browsers (`solver.browser_pool`), keyed by proxy. Launching Chrome takes
+/// seconds, so a browser and its page are kept for the next solve through the same proxy
+/// and User-Agent, on a fresh browser context each time (the data is kept, on a persistent
+/// profile, since the browser only serves its persona). A browser is closed after
+/// `solver.max_solves_per_browser` solves, or replaced once it stops answering.
+pub struct BrowserPool {
+    size: usize,
+    max_solves: usize,
+    /// Fresh attempts after a crash, per solve.
+    crash_retries: usize,
+    /// Idle browsers, least recently used first.
+    idle: Mutex<Vec<PooledBrowser>>,
+    stats: SolverStats,
+    /// Where timed-out solves are captured; `None` when switched off.
+    capture_dir: Option<PathBuf>,
+    /// Files written by `capture`, and HAR recordings, for the uploader.
+    captures: Mutex<Vec<String>>,
+    /// Chrome processes this pool launched that are still running, for the emergency stop
+    /// and the shutdown sweep.
+    running: Arc<Mutex<HashSet<u32>>>,
+}
+
+impl BrowserPool {
+    /// Without `solver.browser_pool`, every solve launches a browser and closes it afterwards.
+    pub fn new(config: Option<&SolverConfig>, stats: SolverStats) -> Self {
+        Self {
+            size: config.map(|c| c.browser_pool).unwrap_or(0),
+            max_solves: config.map(|c| c.max_solves_per_browser.max(1)).unwrap_or(1),
+            crash_retries: config.map(|c| c.crash_retries).unwrap_or_else(default_crash_retries),
+            idle: Mutex::new(Vec::new()),
+            stats,
+            capture_dir: match config {
+                Some(c) if !c.capture_on_timeout => None,
+                Some(c) => Some(PathBuf::from(&c.capture_dir)),
+                None => Some(PathBuf::from(default_capture_dir())),
+            },
+            captures: Mutex::new(Vec::new()),
+            running: Arc::new(Mutex::new(HashSet::new())),
+        }
+    }
+
+    /// Saves what a timed-out solve was looking at: a full-page screenshot and the DOM in
+    /// `<capture_dir>/<worker>/<unix ms>/`. Returns the directory. The files are named
+    /// after worker and time as well, since the uploader keeps only file names.
+    async fn capture(&self, page: &Page, worker_id: &str) -> Result<Option<PathBuf>> {
+        let Some(root) = &self.capture_dir else {
+            return Ok(None);
+        };
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis();
+        let dir = root.join(worker_id).join(timestamp.to_string());
+        let stem = format!("{}-{}", worker_id, timestamp);
+        tokio::fs::create_dir_all(&dir).await.with_context(|| format!("Failed to create {}", dir.display()))?;
+
+        let mut written = Vec::new();
+        let png = page
+            .screenshot(ScreenshotParams::builder().format(CaptureScreenshotFormat::Png).full_page(true).build())
+            .await;
+        // The DOM is worth keeping even when the screenshot fails, and the other way round
+        if let Ok(png) = png {
+            let path = dir.join(format!("{}.png", stem));
+            tokio::fs::write(&path, png).await?;
+            written.push(path.display().to_string());
+        }
+        if let Ok(html) = page.content().await {
+            let path = dir.join(format!("{}.html", stem));
+            tokio::fs::write(&path, html).await?;
+            written.push(path.display().to_string());
+        }
+        if written.is_empty() {
+            return Err(anyhow!("page returned neither a screenshot nor its DOM"));
+        }
+        self.captures.lock().unwrap().extend(written);
+        Ok(Some(dir))
+    }
+
+    /// Every file captured so far.
+    pub fn captures(&self) -> Vec<String> {
+        self.captures.lock().unwrap().clone()
+    }
+
+    /// An idle browser for `proxy`, or a freshly launched one.
+    async fn get(&self, proxy: Option<&str>, env: &BrowserEnv, persona: &BrowserPersona) -> Result<PooledBrowser> {
+        let persona_key = env.chrome.persists_profiles().then_some(persona.profile.as_str());
+        loop {
+            let browser = {
+                let mut idle = self.idle.lock().unwrap();
+                let matches = |b: &PooledBrowser| {
+                    b.proxy.as_deref() == proxy && b.persona.as_deref() == persona_key && b.user_agent == persona.user_agent
+                };
+                let Some(i) = idle.iter().rposition(matches) else {
+                    break;
+                };
+                idle.remove(i)
+            };
+            // Crashed or killed while idle
+            if browser.browser().version().await.is_ok() {
+                self.stats.browser_reuses.fetch_add(1, Ordering::Relaxed);
+                return Ok(browser);
+            }
+            self.retire(browser).await;
+        }
+        let browser = BrowserSolver::launch(proxy, env, persona, &self.running).await?;
+        self.stats.browser_launches.fetch_add(1, Ordering::Relaxed);
+        Ok(browser)
+    }
+
+    /// Takes a browser back after a solve that left it working. It's kept, with a clean
+    /// page, unless the pool is off or the browser has done its share of solves.
+    async fn release(&self, mut browser: PooledBrowser, script: ScriptIdentifier) {
+        browser.solves += 1;
+        if self.size == 0 {
+            browser.shut_down().await;
+            return;
+        }
+        if browser.solves >= self.max_solves || BROWSERS_HALTED.load(Ordering::SeqCst) {
+            self.retire(browser).await;
+            return;
+        }
+        if Self::reset(&mut browser, script).await.is_err() {
+            self.retire(browser).await;
+            return;
+        }
+        let evicted = {
+            let mut idle = self.idle.lock().unwrap();
+            idle.push(browser);
+            // Over capacity: close the least recently used
+            (idle.len() > self.size).then(|| idle.remove(0))
+        };
+        if let Some(mut evicted) = evicted {
+            evicted.shut_down().await;
+        }
+    }
+
+    /// Closes a browser whose solve failed with `error`, telling a crash (the tab, or a
+    /// browser that no longer answers) from a failure of the solve itself.
+    async fn fail(&self, browser: PooledBrowser, tab_crashed: bool, error: anyhow::Error) -> Attempt {
+        let crashed = tab_crashed || browser.browser().version().await.is_err();
+        self.retire(browser).await;
+        if crashed {
+            Attempt::Crashed(error)
+        } else {
+            Attempt::Done(Err(error))
+        }
+    }
+
+    /// Closes a browser that failed or is worn out, gracefully on a persistent profile, so
+    /// a failed solve doesn't cost the profile its unflushed cookies.
+    async fn retire(&self, mut browser: PooledBrowser) {
+        if self.size > 0 {
+            self.stats.browser_recycles.fetch_add(1, Ordering::Relaxed);
+        }
+        browser.shut_down().await;
+    }
+
+    /// Readies a kept browser for the next identity. On a persistent profile the previous
+    /// identity's stealth script is dropped; otherwise the page moves to a fresh context and
+    /// the old one is disposed of, taking every origin's cookies and site data with it, the
+    /// challenge frames' included.
+    async fn reset(browser: &mut PooledBrowser, script: ScriptIdentifier) -> Result<()> {
+        let Some(stale_context) = browser.context.take() else {
+            browser.page.execute(RemoveScriptToEvaluateOnNewDocumentParams::new(script)).await?;
+            browser.page.goto("about:blank").await?;
+            return Ok(());
+        };
+        let (page, context) = BrowserSolver::context_page(browser.browser(), browser.context_proxy.as_ref()).await?;
+        if let Some(auth) = browser.auth.take() {
+            auth.abort();
+        }
+        if let Some(p) = browser.proxy_auth.clone() {
+            browser.auth = Some(BrowserSolver::answer_proxy_auth(&page, p).await?);
+        }
+        let stale = std::mem::replace(&mut browser.page, page);
+        browser.context = Some(context);
+        let _ = stale.close().await;
+        browser.browser().dispose_browser_context(stale_context).await?;
+        Ok(())
+    }
+
+    /// Drops the idle browsers without closing them, which kills their processes.
+    fn kill_idle(&self) {
+        let idle: Vec<PooledBrowser> = self.idle.lock().unwrap().drain(..).collect();
+        drop(idle);
+    }
+
+    /// Closes the idle browsers; returns how many there were.
+    pub async fn close(&self) -> usize {
+        let idle: Vec<PooledBrowser> = self.idle.lock().unwrap().drain(..).collect();
+        let count = idle.len();
+        for mut browser in idle {
+            browser.shut_down().await;
+        }
+        count
+    }
+}
+
+/// Launch settings for the solver's Chrome: `[browser]`, and what it shares with the HTTP
+/// clients.
+#[derive(Debug, Clone, Default)]
+pub struct BrowserEnv {
+    pub keylog: Option<PathBuf>,
+    /// Host -> address pins (DNS overrides, routing `connect_ip`).
+    pub resolve: Vec<(String, SocketAddr)>,
+    pub chrome: ChromeConfig,
+    /// The run's relays to credentialed SOCKS proxies; they close with the run.
+    pub relays: Arc<crate::socks::SocksRelays>,
+}
+
+impl BrowserEnv {
+    /// `--host-resolver-rules` value: the pins first (first match wins), then the proxy's rule.
+    pub fn host_resolver_rules(&self, proxy: Option<&ProxyEndpoint>) -> Option<String> {
+        let mut latest: HashMap<&str, IpAddr> = HashMap::new();
+        for (host, addr) in &self.resolve {
+            latest.insert(host.as_str(), addr.ip());
+        }
+        let mut rules: Vec<String> = latest
+            .into_iter()
+            .map(|(host, ip)| match ip {
+                IpAddr::V4(v4) => format!("MAP {} {}", host, v4),
+                IpAddr::V6(v6) => format!("MAP {} [{}]", host, v6),
+            })
+            .collect();
+        rules.sort();
+        rules.extend(proxy.and_then(|p| p.chrome_resolver_rule()));
+        if rules.is_empty() {
+            None
+        } else {
+            Some(rules.join(" , "))
+        }
+    }
+}
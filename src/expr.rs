@@ -0,0 +1,344 @@
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+
+// Small expression language for scenario step conditions, e.g.
+// `status == 403 && vendor == 'cloudflare'`.
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Num(f64),
+    Str(String),
+}
+
+impl Value {
+    pub fn truthy(&self) -> bool {
+        match self {
+            Value::Null => false,
+            Value::Bool(b) => *b,
+            Value::Num(n) => *n != 0.0,
+            Value::Str(s) => !s.is_empty(),
+        }
+    }
+
+    fn as_num(&self) -> Option<f64> {
+        match self {
+            Value::Num(n) => Some(*n),
+            Value::Str(s) => s.trim().parse().ok(),
+            Value::Bool(b) => Some(if *b { 1.0 } else { 0.0 }),
+            Value::Null => None,
+        }
+    }
+
+    fn loose_eq(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Null, Value::Null) => true,
+            (Value::Null, _) | (_, Value::Null) => false,
+            (Value::Str(a), Value::Str(b)) => a.eq_ignore_ascii_case(b),
+            (Value::Bool(a), Value::Bool(b)) => a == b,
+            _ => match (self.as_num(), other.as_num()) {
+                (Some(a), Some(b)) => a == b,
+                _ => false,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Num(f64),
+    Str(String),
+    Op(&'static str),
+    LParen,
+    RParen,
+}
+
+fn tokenize(src: &str) -> Result<Vec<Token>> {
+    let chars: Vec<char> = src.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '(' {
+            tokens.push(Token::LParen);
+            i += 1;
+        } else if c == ')' {
+            tokens.push(Token::RParen);
+            i += 1;
+        } else if c == '\'' || c == '"' {
+            let quote = c;
+            let start = i + 1;
+            let mut end = start;
+            while end < chars.len() && chars[end] != quote {
+                end += 1;
+            }
+            if end >= chars.len() {
+                return Err(anyhow!("Unterminated string literal at offset {}", i));
+            }
+            tokens.push(Token::Str(chars[start..end].iter().collect()));
+            i = end + 1;
+        } else if c.is_ascii_digit() {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            let text: String = chars[start..i].iter().collect();
+            let num = text.parse().map_err(|_| anyhow!("Invalid number: {}", text))?;
+            tokens.push(Token::Num(num));
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '.') {
+                i += 1;
+            }
+            tokens.push(Token::Ident(chars[start..i].iter().collect()));
+        } else {
+            let two: String = chars[i..(i + 2).min(chars.len())].iter().collect();
+            let op = match two.as_str() {
+                "==" => Some("=="),
+                "!=" => Some("!="),
+                "<=" => Some("<="),
+                ">=" => Some(">="),
+                "&&" => Some("&&"),
+                "||" => Some("||"),
+                _ => None,
+            };
+            if let Some(op) = op {
+                tokens.push(Token::Op(op));
+                i += 2;
+                continue;
+            }
+            let op = match c {
+                '<' => "<",
+                '>' => ">",
+                '!' => "!",
+                _ => return Err(anyhow!("Unexpected character '{}' at offset {}", c, i)),
+            };
+            tokens.push(Token::Op(op));
+            i += 1;
+        }
+    }
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CmpOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone)]
+pub enum Expr {
+    Literal(Value),
+    Var(String),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Cmp(CmpOp, Box<Expr>, Box<Expr>),
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let tok = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        tok
+    }
+
+    fn parse_or(&mut self) -> Result<Expr> {
+        let mut lhs = self.parse_and()?;
+        while self.peek() == Some(&Token::Op("||")) {
+            self.pos += 1;
+            let rhs = self.parse_and()?;
+            lhs = Expr::Or(Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr> {
+        let mut lhs = self.parse_not()?;
+        while self.peek() == Some(&Token::Op("&&")) {
+            self.pos += 1;
+            let rhs = self.parse_not()?;
+            lhs = Expr::And(Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn parse_not(&mut self) -> Result<Expr> {
+        if self.peek() == Some(&Token::Op("!")) {
+            self.pos += 1;
+            return Ok(Expr::Not(Box::new(self.parse_not()?)));
+        }
+        self.parse_cmp()
+    }
+
+    fn parse_cmp(&mut self) -> Result<Expr> {
+        let lhs = self.parse_primary()?;
+        let op = match self.peek() {
+            Some(Token::Op("==")) => CmpOp::Eq,
+            Some(Token::Op("!=")) => CmpOp::Ne,
+            Some(Token::Op("<")) => CmpOp::Lt,
+            Some(Token::Op("<=")) => CmpOp::Le,
+            Some(Token::Op(">")) => CmpOp::Gt,
+            Some(Token::Op(">=")) => CmpOp::Ge,
+            _ => return Ok(lhs),
+        };
+        self.pos += 1;
+        let rhs = self.parse_primary()?;
+        Ok(Expr::Cmp(op, Box::new(lhs), Box::new(rhs)))
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr> {
+        match self.next() {
+            Some(Token::Num(n)) => Ok(Expr::Literal(Value::Num(n))),
+            Some(Token::Str(s)) => Ok(Expr::Literal(Value::Str(s))),
+            Some(Token::Ident(id)) => Ok(match id.as_str() {
+                "true" => Expr::Literal(Value::Bool(true)),
+                "false" => Expr::Literal(Value::Bool(false)),
+                "null" => Expr::Literal(Value::Null),
+                _ => Expr::Var(id),
+            }),
+            Some(Token::LParen) => {
+                let inner = self.parse_or()?;
+                match self.next() {
+                    Some(Token::RParen) => Ok(inner),
+                    _ => Err(anyhow!("Expected ')'")),
+                }
+            }
+            Some(tok) => Err(anyhow!("Unexpected token {:?}", tok)),
+            None => Err(anyhow!("Unexpected end of expression")),
+        }
+    }
+}
+
+impl Expr {
+    pub fn parse(src: &str) -> Result<Self> {
+        let mut parser = Parser { tokens: tokenize(src)?, pos: 0 };
+        let expr = parser.parse_or()?;
+        if parser.pos < parser.tokens.len() {
+            return Err(anyhow!("Trailing tokens in expression: {}", src));
+        }
+        Ok(expr)
+    }
+
+    pub fn eval(&self, ctx: &HashMap<String, Value>) -> Value {
+        match self {
+            Expr::Literal(v) => v.clone(),
+            Expr::Var(name) => ctx.get(name).cloned().unwrap_or(Value::Null),
+            Expr::Not(inner) => Value::Bool(!inner.eval(ctx).truthy()),
+            Expr::And(a, b) => Value::Bool(a.eval(ctx).truthy() && b.eval(ctx).truthy()),
+            Expr::Or(a, b) => Value::Bool(a.eval(ctx).truthy() || b.eval(ctx).truthy()),
+            Expr::Cmp(op, a, b) => {
+                let (lhs, rhs) = (a.eval(ctx), b.eval(ctx));
+                let result = match op {
+                    CmpOp::Eq => lhs.loose_eq(&rhs),
+                    CmpOp::Ne => !lhs.loose_eq(&rhs),
+                    _ => match (lhs.as_num(), rhs.as_num()) {
+                        (Some(l), Some(r)) => match op {
+                            CmpOp::Lt => l < r,
+                            CmpOp::Le => l <= r,
+                            CmpOp::Gt => l > r,
+                            CmpOp::Ge => l >= r,
+                            CmpOp::Eq | CmpOp::Ne => unreachable!(),
+                        },
+                        _ => false,
+                    },
+                };
+                Value::Bool(result)
+            }
+        }
+    }
+
+    pub fn matches(&self, ctx: &HashMap<String, Value>) -> bool {
+        self.eval(ctx).truthy()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Expr, Value};
+    use std::collections::HashMap;
+
+    fn ctx(vars: &[(&str, Value)]) -> HashMap<String, Value> {
+        vars.iter().map(|(k, v)| (k.to_string(), v.clone())).collect()
+    }
+
+    fn check(src: &str, vars: &HashMap<String, Value>) -> bool {
+        Expr::parse(src).unwrap().matches(vars)
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or_and_not_tighter_than_both() {
+        let vars = ctx(&[("a", Value::Bool(true)), ("b", Value::Bool(false)), ("c", Value::Bool(false))]);
+        assert!(check("a || b && c", &vars));
+        assert!(!check("(a || b) && c", &vars));
+        assert!(!check("!a && b", &vars));
+        assert!(check("!(a && b)", &vars));
+        assert!(check("!b || c", &vars));
+        assert!(check("!!a", &vars));
+    }
+
+    #[test]
+    fn compares_numbers_strings_and_mixed_values() {
+        let vars = ctx(&[("status", Value::Num(403.0)), ("vendor", Value::Str("cloudflare".into()))]);
+        assert!(check("status == 403", &vars));
+        assert!(check("status != 200", &vars));
+        assert!(check("status < 404 && status <= 403", &vars));
+        assert!(check("status > 399 && status >= 403", &vars));
+        assert!(!check("status > 403", &vars));
+        // Strings compare case-insensitively, and numeric strings compare as numbers
+        assert!(check("vendor == 'Cloudflare'", &vars));
+        assert!(check("vendor != \"akamai\"", &vars));
+        assert!(check("status == '403'", &vars));
+        assert!(!check("vendor > 1", &vars));
+        assert!(check("status == 403 && vendor == 'cloudflare'", &vars));
+    }
+
+    #[test]
+    fn missing_variables_are_null() {
+        let vars = ctx(&[]);
+        assert_eq!(Expr::parse("token").unwrap().eval(&vars), Value::Null);
+        assert!(!check("token", &vars));
+        assert!(check("!token", &vars));
+        assert!(check("token == null", &vars));
+        assert!(check("token != 'abc'", &vars));
+        // Ordering against a missing value is never true
+        assert!(!check("token < 1", &vars));
+        assert!(!check("token >= 0", &vars));
+    }
+
+    #[test]
+    fn rejects_malformed_expressions() {
+        let malformed = [
+            "",
+            "status ==",
+            "(status == 403",
+            "vendor == 'cloudflare",
+            "status = 403",
+            "status == 403 404",
+            "1.2.3 == 1",
+            "a && || b",
+            ")",
+        ];
+        for src in malformed {
+            assert!(Expr::parse(src).is_err(), "{:?} parsed", src);
+        }
+    }
+}
@@ -0,0 +1,365 @@
+use crate::engine::config::{WaitConfig, WaitStrategy};
+use crate::engine::solver::{BrowserEnv, BrowserSolver, SolverPage, SELECTOR_VISIBLE};
+use crate::engine::{ProxyEndpoint, SpectreLogger};
+use crate::persona::BrowserPersona;
+use crate::success::SuccessCriteria;
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use serde_json::{json, Map, Value};
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+
+// Firefox as a second solver backend. A profile emulating Firefox's TLS and HTTP/2
+// fingerprint that escalates to Chrome switches browsers mid-visit, and some targets block
+// the Chrome path outright. `solver = "firefox"` on a profile solves its challenges in a real
+// Firefox instead, driven over W3C WebDriver: Spectre starts geckodriver on a loopback port
+// (or uses `[browser.firefox] webdriver_url`), opens one session per solve and closes it
+// afterwards. The clearance check and cookie extraction are the Chrome solver's. There is
+// no CDP here, so no mouse activity, Turnstile click or CAPTCHA service, no timeout capture
+// or activity recording, and no host pins.
+
+/// `[browser.firefox]`.
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+pub struct FirefoxConfig {
+    /// A geckodriver or Selenium endpoint that is already running, e.g. `http://127.0.0.1:4444`.
+    /// Spectre starts its own geckodriver per solve otherwise.
+    #[serde(default)]
+    pub webdriver_url: Option<String>,
+    /// geckodriver to start; `geckodriver` on the PATH by default.
+    #[serde(default)]
+    pub geckodriver_path: Option<String>,
+    /// Firefox to launch; geckodriver's own search of the usual locations otherwise.
+    #[serde(default)]
+    pub binary_path: Option<String>,
+}
+
+impl FirefoxConfig {
+    fn geckodriver(&self) -> &str {
+        self.geckodriver_path.as_deref().unwrap_or("geckodriver")
+    }
+
+    /// For preflight: the endpoint's readiness, or the version of the geckodriver that
+    /// would be started.
+    pub async fn probe(&self) -> Result<String> {
+        if let Some(url) = &self.webdriver_url {
+            let status: Value = rquest::Client::builder()
+                .build()?
+                .get(format!("{}/status", url.trim_end_matches('/')))
+                .send()
+                .await?
+                .json()
+                .await?;
+            let ready = status["value"]["ready"].as_bool().unwrap_or(false);
+            return Ok(format!("{} ({})", url, if ready { "ready" } else { "busy" }));
+        }
+        let output = tokio::process::Command::new(self.geckodriver())
+            .arg("--version")
+            .output()
+            .await
+            .with_context(|| format!("{} not found", self.geckodriver()))?;
+        Ok(String::from_utf8_lossy(&output.stdout).lines().next().unwrap_or_default().to_string())
+    }
+}
+
+/// Solves `url` in Firefox and returns the clearance cookies.
+pub async fn solve(
+    url: &str,
+    success: &SuccessCriteria,
+    proxy: Option<&str>,
+    persona: &BrowserPersona,
+    env: &BrowserEnv,
+    logger: &SpectreLogger,
+    worker_id: &str,
+) -> Result<String> {
+    let session = Session::start(proxy, persona, env).await?;
+    let outcome = clearance(&session, url, success, env, logger, worker_id).await;
+    session.close().await;
+    outcome?.ok_or_else(|| anyhow!("Firefox timed out waiting for clearance"))
+}
+
+async fn clearance(
+    session: &Session,
+    url: &str,
+    success: &SuccessCriteria,
+    env: &BrowserEnv,
+    logger: &SpectreLogger,
+    worker_id: &str,
+) -> Result<Option<String>> {
+    let chrome = &env.chrome;
+    logger.log(worker_id, "BROWSER_NAV", "Navigating to Target in Firefox", Some(&json!(url.to_string()).to_string()));
+    // Returns once the page has loaded
+    session.command("POST", "url", Some(json!({ "url": url }))).await?;
+    tokio::time::sleep(chrome.slow_mo()).await;
+
+    let wait = &chrome.wait;
+    match session.wait_for_page(wait, Duration::from_millis(chrome.poll_interval_ms)).await {
+        Ok(true) => {}
+        Ok(false) => logger.log(
+            worker_id,
+            "BROWSER_WARN",
+            "Page wait timed out",
+            Some(&format!("{{\"strategy\": \"{}\", \"timeout_secs\": {}}}", wait.strategy.as_str(), wait.timeout_secs)),
+        ),
+        Err(e) => logger.log(worker_id, "BROWSER_WARN", "Page wait failed", Some(&json!(e.to_string()).to_string())),
+    }
+    tokio::time::sleep(chrome.slow_mo()).await;
+
+    BrowserSolver::poll_clearance(session, success, chrome, logger, worker_id).await
+}
+
+/// Firefox prefs for a persona: no `navigator.webdriver`, its languages, its User-Agent
+/// and platform when it has one, and the proxy.
+fn prefs(persona: &BrowserPersona, proxy: Option<&ProxyEndpoint>) -> Map<String, Value> {
+    let mut prefs = Map::new();
+    prefs.insert("dom.webdriver.enabled".into(), json!(false));
+    prefs.insert("intl.accept_languages".into(), json!(persona.languages.join(",")));
+    if !persona.user_agent.is_empty() {
+        prefs.insert("general.useragent.override".into(), json!(persona.user_agent));
+        prefs.insert("general.platform.override".into(), json!(persona.platform));
+    }
+    if let Some(p) = proxy {
+        prefs.insert("network.proxy.type".into(), json!(1));
+        if p.is_socks() {
+            prefs.insert("network.proxy.socks".into(), json!(p.host));
+            prefs.insert("network.proxy.socks_port".into(), json!(p.port));
+            prefs.insert("network.proxy.socks_version".into(), json!(if p.scheme.starts_with("socks4") { 4 } else { 5 }));
+            prefs.insert("network.proxy.socks_remote_dns".into(), json!(p.remote_dns() || p.scheme == "socks4a"));
+        } else {
+            for scheme in ["http", "ssl"] {
+                prefs.insert(format!("network.proxy.{}", scheme), json!(p.host));
+                prefs.insert(format!("network.proxy.{}_port", scheme), json!(p.port));
+            }
+        }
+    }
+    prefs
+}
+
+/// One WebDriver session, with the geckodriver it runs on when Spectre started one.
+struct Session {
+    client: rquest::Client,
+    /// `<endpoint>/session/<id>`.
+    base: String,
+    driver: Option<tokio::process::Child>,
+    closed: bool,
+}
+
+impl Session {
+    async fn start(proxy: Option<&str>, persona: &BrowserPersona, env: &BrowserEnv) -> Result<Self> {
+        let config = &env.chrome.firefox;
+        let mut endpoint = proxy.map(ProxyEndpoint::parse).transpose()?;
+        if let Some(p) = endpoint.clone().filter(|p| p.has_credentials()) {
+            if !p.is_socks() {
+                return Err(anyhow!("Firefox can't log in to HTTP proxies on its own; use a SOCKS endpoint for Firefox profiles"));
+            }
+            // The relay's socket is on this machine, out of a remote Firefox's reach
+            if config.webdriver_url.is_some() {
+                return Err(anyhow!(
+                    "a remote Firefox can't authenticate to SOCKS proxies or reach the local relay; use a local geckodriver for the solver"
+                ));
+            }
+            // As for Chrome, a relay logs in on Firefox's behalf. Firefox can't answer the
+            // relay's own credentials either, so it's a Unix socket only this user reaches.
+            #[cfg(unix)]
+            {
+                let path = env.relays.unix(&p).await?;
+                // Firefox takes a Unix socket as a file:// URL in place of the host
+                endpoint = Some(ProxyEndpoint {
+                    scheme: p.scheme,
+                    host: format!("file://{}", path.display()),
+                    port: 0,
+                    username: None,
+                    password: None,
+                });
+            }
+            #[cfg(not(unix))]
+            return Err(anyhow!("Firefox reaches credentialed SOCKS proxies through a Unix socket, which this platform lacks"));
+        }
+
+        let client = rquest::Client::builder().build()?;
+        let (url, driver) = match &config.webdriver_url {
+            Some(url) => (url.trim_end_matches('/').to_string(), None),
+            None => {
+                let port = std::net::TcpListener::bind("127.0.0.1:0")?.local_addr()?.port();
+                let driver = tokio::process::Command::new(config.geckodriver())
+                    .args(["--host", "127.0.0.1", "--port", &port.to_string()])
+                    .stdout(Stdio::null())
+                    .stderr(Stdio::null())
+                    .kill_on_drop(true)
+                    .spawn()
+                    .with_context(|| format!("Failed to start {}", config.geckodriver()))?;
+                let url = format!("http://127.0.0.1:{}", port);
+                Self::await_ready(&client, &url).await?;
+                (url, Some(driver))
+            }
+        };
+
+        let traits = &persona.traits;
+        let mut args = vec![format!("--width={}", traits.screen_width), format!("--height={}", traits.screen_height)];
+        if env.chrome.devtools {
+            args.push("-devtools".into());
+        } else if env.chrome.headless {
+            args.push("-headless".into());
+        }
+        let mut options = json!({ "args": args, "prefs": prefs(persona, endpoint.as_ref()) });
+        if let Some(binary) = &config.binary_path {
+            options["binary"] = json!(binary);
+        }
+        if let Some(tz) = &persona.timezone {
+            options["env"] = json!({ "TZ": tz });
+        }
+        let capabilities = json!({
+            "capabilities": {
+                "alwaysMatch": {
+                    "browserName": "firefox",
+                    "pageLoadStrategy": "normal",
+                    "moz:firefoxOptions": options,
+                }
+            }
+        });
+        let created = Self::call(&client, "POST", &format!("{}/session", url), Some(capabilities))
+            .await
+            .context("Failed to start a Firefox session")?;
+        let id = created["sessionId"].as_str().ok_or_else(|| anyhow!("WebDriver returned no session id"))?;
+        Ok(Self { base: format!("{}/session/{}", url, id), client, driver, closed: false })
+    }
+
+    /// geckodriver takes a moment to listen.
+    async fn await_ready(client: &rquest::Client, url: &str) -> Result<()> {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while Instant::now() < deadline {
+            if let Ok(resp) = client.get(format!("{}/status", url)).send().await {
+                if let Ok(status) = resp.json::<Value>().await {
+                    if status["value"]["ready"].as_bool() == Some(true) {
+                        return Ok(());
+                    }
+                }
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        Err(anyhow!("geckodriver didn't come up at {}", url))
+    }
+
+    /// One WebDriver call; returns its `value`. Errors carry WebDriver's error code.
+    async fn call(client: &rquest::Client, method: &str, url: &str, body: Option<Value>) -> Result<Value> {
+        let request = match method {
+            "POST" => client.post(url).json(&body.unwrap_or_else(|| json!({}))),
+            "DELETE" => client.delete(url),
+            _ => client.get(url),
+        };
+        let resp = request.send().await?;
+        let ok = resp.status().is_success();
+        let mut reply: Value = resp.json().await?;
+        let value = reply["value"].take();
+        if !ok {
+            return Err(anyhow!(
+                "WebDriver {}: {}",
+                value["error"].as_str().unwrap_or("error"),
+                value["message"].as_str().unwrap_or_default()
+            ));
+        }
+        Ok(value)
+    }
+
+    /// A command on this session; `path` is relative to it.
+    async fn command(&self, method: &str, path: &str, body: Option<Value>) -> Result<Value> {
+        let url = if path.is_empty() { self.base.clone() } else { format!("{}/{}", self.base, path) };
+        Self::call(&self.client, method, &url, body).await
+    }
+
+    async fn execute(&self, script: &str, args: Value) -> Result<Value> {
+        self.command("POST", "execute/sync", Some(json!({ "script": script, "args": args }))).await
+    }
+
+    /// `browser.wait` over WebDriver. `network_idle` waits for the page's resource count to
+    /// stop growing, since WebDriver doesn't report requests.
+    async fn wait_for_page(&self, wait: &WaitConfig, poll_interval: Duration) -> Result<bool> {
+        let deadline = Instant::now() + Duration::from_secs(wait.timeout_secs);
+        let pattern = match wait.strategy {
+            WaitStrategy::Fixed => {
+                tokio::time::sleep(Duration::from_secs(wait.settle_secs)).await;
+                return Ok(true);
+            }
+            WaitStrategy::Url => Some(wait.url_regex()?),
+            _ => None,
+        };
+        let check = format!("return ({})(arguments[0]);", SELECTOR_VISIBLE);
+        let idle = Duration::from_millis(wait.idle_ms);
+        let (mut resources, mut changed) = (-1, Instant::now());
+        while Instant::now() < deadline {
+            let done = match wait.strategy {
+                WaitStrategy::Selector => self.execute(&check, json!([wait.selector])).await?.as_bool() == Some(true),
+                WaitStrategy::Url => pattern.as_ref().is_some_and(|p| p.is_match(&self.current_url().await.unwrap_or_default())),
+                _ => {
+                    let count = self
+                        .execute("return performance.getEntriesByType('resource').length;", json!([]))
+                        .await?
+                        .as_i64()
+                        .unwrap_or(0);
+                    if count != resources {
+                        (resources, changed) = (count, Instant::now());
+                    }
+                    changed.elapsed() >= idle
+                }
+            };
+            if done {
+                return Ok(true);
+            }
+            tokio::time::sleep(poll_interval.min(idle)).await;
+        }
+        Ok(false)
+    }
+
+    /// Ends the session, which closes Firefox, then stops geckodriver.
+    async fn close(mut self) {
+        let _ = self.command("DELETE", "", None).await;
+        self.closed = true;
+        if let Some(mut driver) = self.driver.take() {
+            let _ = driver.kill().await;
+        }
+    }
+}
+
+impl Drop for Session {
+    // A solve cancelled mid-way still closes Firefox; killing geckodriver alone would leave it running
+    fn drop(&mut self) {
+        if self.closed {
+            return;
+        }
+        let (client, base, driver) = (self.client.clone(), self.base.clone(), self.driver.take());
+        if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+            runtime.spawn(async move {
+                let _ = client.delete(base).send().await;
+                if let Some(mut driver) = driver {
+                    let _ = driver.kill().await;
+                }
+            });
+        }
+    }
+}
+
+#[async_trait]
+impl SolverPage for Session {
+    async fn html(&self) -> Result<String> {
+        Ok(self.command("GET", "source", None).await?.as_str().unwrap_or_default().to_string())
+    }
+
+    async fn current_url(&self) -> Result<String> {
+        Ok(self.command("GET", "url", None).await?.as_str().unwrap_or_default().to_string())
+    }
+
+    async fn cookie_pairs(&self) -> Result<Vec<(String, String)>> {
+        let cookies = self.command("GET", "cookie", None).await?;
+        Ok(cookies
+            .as_array()
+            .map(|all| {
+                all.iter()
+                    .filter_map(|c| Some((c["name"].as_str()?.to_string(), c["value"].as_str()?.to_string())))
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    async fn has_element(&self, selector: &str) -> bool {
+        self.command("POST", "element", Some(json!({ "using": "css selector", "value": selector }))).await.is_ok()
+    }
+}
@@ -0,0 +1,207 @@
+use crate::engine::{BodyReader, ClientFactory, Config, RequestBuilderHelper, ResponseAnalyzer, TemplateEngine};
+use crate::origin::PathVerdicts;
+use crate::significance;
+use anyhow::Result;
+use rquest::header::{HeaderMap, HeaderName, HeaderValue};
+use std::str::FromStr;
+
+// Header-mutation campaign: start from a consistent Chrome header set and change one
+// dimension at a time, so the resulting matrix shows exactly which header trips the WAF.
+
+const CHROME_UA: &str =
+    "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/130.0.0.0 Safari/537.36";
+
+fn baseline_headers() -> Vec<(String, String)> {
+    [
+        ("sec-ch-ua", "\"Chromium\";v=\"130\", \"Google Chrome\";v=\"130\", \"Not?A_Brand\";v=\"99\""),
+        ("sec-ch-ua-mobile", "?0"),
+        ("sec-ch-ua-platform", "\"Windows\""),
+        ("upgrade-insecure-requests", "1"),
+        ("user-agent", CHROME_UA),
+        (
+            "accept",
+            "text/html,application/xhtml+xml,application/xml;q=0.9,image/avif,image/webp,image/apng,*/*;q=0.8,application/signed-exchange;v=b3;q=0.7",
+        ),
+        ("sec-fetch-site", "none"),
+        ("sec-fetch-mode", "navigate"),
+        ("sec-fetch-user", "?1"),
+        ("sec-fetch-dest", "document"),
+        ("accept-encoding", "gzip, deflate, br, zstd"),
+        ("accept-language", "en-US,en;q=0.9"),
+    ]
+    .iter()
+    .map(|(k, v)| (k.to_string(), v.to_string()))
+    .collect()
+}
+
+#[derive(Debug, Clone)]
+pub struct HeaderMutation {
+    pub dimension: &'static str,
+    pub name: String,
+    pub headers: Vec<(String, String)>,
+}
+
+impl HeaderMutation {
+    fn baseline() -> Self {
+        Self { dimension: "baseline", name: "chrome-130".into(), headers: baseline_headers() }
+    }
+
+    fn replace(dimension: &'static str, name: &str, header: &str, value: &str) -> Self {
+        let headers = baseline_headers()
+            .into_iter()
+            .map(|(k, v)| if k == header { (k, value.to_string()) } else { (k, v) })
+            .collect();
+        Self { dimension, name: name.to_string(), headers }
+    }
+
+    fn remove(dimension: &'static str, name: &str, removed: &[&str]) -> Self {
+        let headers = baseline_headers()
+            .into_iter()
+            .filter(|(k, _)| !removed.contains(&k.as_str()))
+            .collect();
+        Self { dimension, name: name.to_string(), headers }
+    }
+
+    /// Baseline first, then one mutated dimension per entry.
+    pub fn catalog() -> Vec<HeaderMutation> {
+        vec![
+            Self::baseline(),
+            // User-Agent versions (sec-ch-ua left at 130 on purpose)
+            Self::replace("user-agent", "chrome-100", "user-agent", &CHROME_UA.replace("130.0.0.0", "100.0.0.0")),
+            Self::replace("user-agent", "chrome-120", "user-agent", &CHROME_UA.replace("130.0.0.0", "120.0.0.0")),
+            Self::replace("user-agent", "chrome-131", "user-agent", &CHROME_UA.replace("130.0.0.0", "131.0.0.0")),
+            Self::replace(
+                "user-agent",
+                "firefox-133",
+                "user-agent",
+                "Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:133.0) Gecko/20100101 Firefox/133.0",
+            ),
+            Self::replace(
+                "user-agent",
+                "safari-17",
+                "user-agent",
+                "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.5 Safari/605.1.15",
+            ),
+            Self::replace("user-agent", "curl", "user-agent", "curl/8.5.0"),
+            Self::replace("user-agent", "python-requests", "user-agent", "python-requests/2.31.0"),
+            // Accept values and orderings
+            Self::replace("accept", "wildcard", "accept", "*/*"),
+            Self::replace(
+                "accept",
+                "wildcard-first",
+                "accept",
+                "*/*;q=0.8,text/html,application/xhtml+xml,application/xml;q=0.9,image/avif,image/webp",
+            ),
+            Self::replace("accept", "json", "accept", "application/json"),
+            Self::remove("accept", "missing", &["accept"]),
+            // Fetch metadata
+            Self::remove("sec-fetch", "no-site", &["sec-fetch-site"]),
+            Self::remove("sec-fetch", "no-mode", &["sec-fetch-mode"]),
+            Self::remove("sec-fetch", "no-user", &["sec-fetch-user"]),
+            Self::remove("sec-fetch", "no-dest", &["sec-fetch-dest"]),
+            Self::remove(
+                "sec-fetch",
+                "none",
+                &["sec-fetch-site", "sec-fetch-mode", "sec-fetch-user", "sec-fetch-dest"],
+            ),
+            // Client hints
+            Self::remove("client-hints", "none", &["sec-ch-ua", "sec-ch-ua-mobile", "sec-ch-ua-platform"]),
+            Self::replace("client-hints", "platform-macos", "sec-ch-ua-platform", "\"macOS\""),
+            Self::replace("client-hints", "mobile", "sec-ch-ua-mobile", "?1"),
+            // Language / encoding
+            Self::remove("accept-language", "missing", &["accept-language"]),
+            Self::replace("accept-language", "wildcard", "accept-language", "*"),
+            Self::replace("accept-encoding", "identity", "accept-encoding", "identity"),
+            Self::remove("accept-encoding", "missing", &["accept-encoding"]),
+        ]
+    }
+
+    fn header_map(&self, extra: &[String], payload: &str) -> HeaderMap {
+        let mut map = HeaderMap::new();
+        let extra = extra
+            .iter()
+            .filter_map(|h| h.split_once(':'))
+            .map(|(k, v)| (k.trim().to_string(), TemplateEngine::inject(v.trim(), payload)));
+        for (k, v) in self.headers.iter().cloned().chain(extra) {
+            if let (Ok(name), Ok(value)) = (HeaderName::from_str(&k), HeaderValue::from_str(&v)) {
+                map.insert(name, value);
+            }
+        }
+        map
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MutationResult {
+    pub dimension: &'static str,
+    pub name: String,
+    pub verdicts: PathVerdicts,
+}
+
+impl MutationResult {
+    /// Blocked or challenged more often than the unmodified baseline.
+    pub fn triggers(&self, baseline: &PathVerdicts) -> bool {
+        self.verdicts.blocked + self.verdicts.challenge > baseline.blocked + baseline.challenge
+    }
+
+    /// Two-proportion p-value of this row's pass rate against the baseline's.
+    pub fn p_value(&self, baseline: &PathVerdicts) -> Option<f64> {
+        significance::two_proportion_p_value(
+            self.verdicts.success,
+            self.verdicts.answered(),
+            baseline.success,
+            baseline.answered(),
+        )
+    }
+}
+
+pub struct HeaderFuzzer {
+    config: Config,
+    profile: String,
+}
+
+impl HeaderFuzzer {
+    pub fn new(config: Config, profile: &str) -> Self {
+        Self { config, profile: profile.to_string() }
+    }
+
+    pub async fn run(&self, samples: usize) -> Result<Vec<MutationResult>> {
+        let factory = ClientFactory::for_config(&self.config)?;
+        // Emulated TLS fingerprint, but only the headers each mutation sets
+        let client = factory.create_bare_client(&self.profile, None)?;
+        let method = RequestBuilderHelper::parse_method(&self.config.general.method);
+
+        let mut results = Vec::new();
+        for mutation in HeaderMutation::catalog() {
+            let mut verdicts = PathVerdicts::default();
+            for i in 0..samples.max(1) {
+                // Benign filler for {payload} so only the header change differs between rows
+                let payload = format!("probe{}", i);
+                let url = TemplateEngine::inject(&self.config.general.target_url, &payload);
+                let mut req = client
+                    .request(method.clone(), &url)
+                    .headers(mutation.header_map(&self.config.general.headers, &payload));
+                if let Some(body) = &self.config.general.raw_body {
+                    req = req.body(TemplateEngine::inject(body, &payload));
+                }
+                let verdict = match req.send().await {
+                    Ok(resp) => {
+                        let status = resp.status().as_u16();
+                        let body = BodyReader::read(
+                            resp,
+                            self.config.general.max_body_bytes,
+                            self.config.general.analyze_body_bytes,
+                        )
+                        .await
+                        .ok();
+                        body.map(|body| ResponseAnalyzer::analyze(status, &String::from_utf8_lossy(&body.data), None))
+                    }
+                    Err(_) => None,
+                };
+                verdicts.record(verdict.as_ref());
+            }
+            results.push(MutationResult { dimension: mutation.dimension, name: mutation.name, verdicts });
+        }
+        Ok(results)
+    }
+}
@@ -0,0 +1,235 @@
+use crate::engine::grid::{NodeLimits, DIRECT_NODE};
+use crate::engine::{ProfileCounters, ProfileTracker, ProxyEndpoint, Verdict};
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, RwLock};
+
+// Proxy metadata. A `network.proxies` entry is either a bare URL or a table carrying the
+// exit's country, ASN, provider and kind, and optionally its own request caps;
+// `[network.geo]` narrows the grid to matching exits (e.g. only US residential) and the
+// report breaks block rates down by each tag, since WAF rules are frequently geo- or
+// ASN-specific.
+
+/// One `network.proxies` entry.
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+#[serde(from = "ProxyEntry")]
+pub struct ProxySpec {
+    pub url: String,
+    pub tags: GeoTags,
+    /// Overrides `network.max_concurrent` / `network.max_requests_per_minute` for this node.
+    pub limits: NodeLimits,
+    /// First hop this node is reached through; overrides `network.via`.
+    pub via: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GeoTags {
+    /// ISO 3166 alpha-2 code, e.g. `US`.
+    pub country: Option<String>,
+    pub asn: Option<u32>,
+    pub provider: Option<String>,
+    /// Free-form exit type: `residential`, `mobile`, `datacenter`...
+    pub kind: Option<String>,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum ProxyEntry {
+    Url(String),
+    Tagged {
+        url: String,
+        #[serde(default)]
+        country: Option<String>,
+        #[serde(default)]
+        asn: Option<u32>,
+        #[serde(default)]
+        provider: Option<String>,
+        #[serde(default)]
+        kind: Option<String>,
+        #[serde(default)]
+        max_concurrent: Option<usize>,
+        #[serde(default)]
+        max_requests_per_minute: Option<u32>,
+        #[serde(default)]
+        via: Option<String>,
+    },
+}
+
+impl From<ProxyEntry> for ProxySpec {
+    fn from(entry: ProxyEntry) -> Self {
+        match entry {
+            ProxyEntry::Url(url) => url.into(),
+            ProxyEntry::Tagged { url, country, asn, provider, kind, max_concurrent, max_requests_per_minute, via } => Self {
+                url,
+                tags: GeoTags {
+                    country: country.map(|c| c.trim().to_uppercase()),
+                    asn,
+                    provider,
+                    kind: kind.map(|k| k.trim().to_lowercase()),
+                },
+                limits: NodeLimits { max_concurrent, max_requests_per_minute },
+                via,
+            },
+        }
+    }
+}
+
+impl From<String> for ProxySpec {
+    fn from(url: String) -> Self {
+        Self { url, tags: GeoTags::default(), limits: NodeLimits::default(), via: None }
+    }
+}
+
+impl ProxySpec {
+    /// Reputation group for `network.diversity`: the ASN when tagged, else the exit's /24
+    /// (/48 for IPv6). Hostname exits group by hostname, which keeps a rotating gateway's
+    /// sessions apart but can't see two hostnames sharing a subnet.
+    pub fn diversity_group(&self) -> String {
+        if let Some(asn) = self.tags.asn {
+            return format!("AS{}", asn);
+        }
+        let Ok(endpoint) = ProxyEndpoint::parse(&self.url) else {
+            return self.url.clone();
+        };
+        match endpoint.host.trim_matches(|c| c == '[' || c == ']').parse::<IpAddr>() {
+            Ok(IpAddr::V4(ip)) => {
+                let [a, b, c, _] = ip.octets();
+                format!("{}.{}.{}.0/24", a, b, c)
+            }
+            Ok(IpAddr::V6(ip)) => {
+                let s = ip.segments();
+                format!("{:x}:{:x}:{:x}::/48", s[0], s[1], s[2])
+            }
+            Err(_) => endpoint.host.to_lowercase(),
+        }
+    }
+}
+
+impl GeoTags {
+    pub fn is_empty(&self) -> bool {
+        *self == GeoTags::default()
+    }
+
+    /// "US AS7922 comcast residential", for logs.
+    pub fn describe(&self) -> String {
+        let parts: Vec<String> = [
+            self.country.clone(),
+            self.asn.map(|a| format!("AS{}", a)),
+            self.provider.clone(),
+            self.kind.clone(),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        if parts.is_empty() {
+            "untagged".into()
+        } else {
+            parts.join(" ")
+        }
+    }
+}
+
+/// `[network.geo]`: every non-empty list must contain the node's tag. Untagged nodes
+/// never match a non-empty list, and the direct node is left out while a filter is set.
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+pub struct GeoFilter {
+    #[serde(default)]
+    pub countries: Vec<String>,
+    #[serde(default)]
+    pub asns: Vec<u32>,
+    #[serde(default)]
+    pub providers: Vec<String>,
+    #[serde(default)]
+    pub kinds: Vec<String>,
+}
+
+impl GeoFilter {
+    pub fn is_empty(&self) -> bool {
+        self.countries.is_empty() && self.asns.is_empty() && self.providers.is_empty() && self.kinds.is_empty()
+    }
+
+    pub fn matches(&self, tags: &GeoTags) -> bool {
+        fn allowed(list: &[String], tag: &Option<String>) -> bool {
+            list.is_empty() || tag.as_ref().is_some_and(|t| list.iter().any(|l| l.eq_ignore_ascii_case(t)))
+        }
+        allowed(&self.countries, &tags.country)
+            && (self.asns.is_empty() || tags.asn.is_some_and(|a| self.asns.contains(&a)))
+            && allowed(&self.providers, &tags.provider)
+            && allowed(&self.kinds, &tags.kind)
+    }
+
+    /// The proxies this filter keeps; an error when it keeps none.
+    pub fn select(&self, proxies: &[ProxySpec]) -> Result<Vec<ProxySpec>> {
+        let kept: Vec<ProxySpec> = proxies.iter().filter(|p| self.matches(&p.tags)).cloned().collect();
+        if kept.is_empty() && !self.is_empty() {
+            return Err(anyhow!("network.geo matches none of the {} configured proxies", proxies.len()));
+        }
+        Ok(kept)
+    }
+}
+
+/// Outcomes per country, network (ASN/provider) and exit kind.
+#[derive(Debug, Default, Clone)]
+pub struct GeoStats {
+    tags: Arc<RwLock<HashMap<String, GeoTags>>>,
+    countries: ProfileTracker,
+    networks: ProfileTracker,
+    kinds: ProfileTracker,
+}
+
+impl GeoStats {
+    /// Tracks the nodes of `proxies`; stays inert when none of them is tagged.
+    pub fn new(proxies: &[ProxySpec]) -> Self {
+        let stats = Self::default();
+        stats.set_proxies(proxies);
+        stats
+    }
+
+    /// Replaces the node tags, e.g. after the proxy list was reloaded. Counters are kept.
+    pub fn set_proxies(&self, proxies: &[ProxySpec]) {
+        *self.tags.write().unwrap() =
+            proxies.iter().filter(|p| !p.tags.is_empty()).map(|p| (p.url.clone(), p.tags.clone())).collect();
+    }
+
+    pub fn tags(&self, node: &str) -> Option<GeoTags> {
+        self.tags.read().unwrap().get(node).cloned()
+    }
+
+    /// `None` means the request failed at the transport level.
+    pub fn record(&self, node: &str, verdict: Option<&Verdict>) {
+        let tags = {
+            let all = self.tags.read().unwrap();
+            if all.is_empty() {
+                return;
+            }
+            all.get(node).cloned()
+        };
+        let tags = tags.as_ref();
+        let untagged = if node == DIRECT_NODE { DIRECT_NODE } else { "untagged" };
+        let country = tags.and_then(|t| t.country.clone()).unwrap_or_else(|| untagged.into());
+        let network = tags
+            .and_then(|t| match (t.asn, &t.provider) {
+                (Some(asn), Some(p)) => Some(format!("AS{} {}", asn, p)),
+                (Some(asn), None) => Some(format!("AS{}", asn)),
+                (None, Some(p)) => Some(p.clone()),
+                (None, None) => None,
+            })
+            .unwrap_or_else(|| untagged.into());
+        let kind = tags.and_then(|t| t.kind.clone()).unwrap_or_else(|| untagged.into());
+        self.countries.record(&country, verdict);
+        self.networks.record(&network, verdict);
+        self.kinds.record(&kind, verdict);
+    }
+
+    /// (dimension, value, counters): countries, then networks, then kinds, each sorted by value.
+    pub fn snapshot(&self) -> Vec<(&'static str, String, ProfileCounters)> {
+        let mut out = Vec::new();
+        for (dimension, tracker) in [("country", &self.countries), ("network", &self.networks), ("kind", &self.kinds)] {
+            let mut rows: Vec<(String, ProfileCounters)> = tracker.snapshot().into_iter().collect();
+            rows.sort_by(|a, b| a.0.cmp(&b.0));
+            out.extend(rows.into_iter().map(|(value, c)| (dimension, value, c)));
+        }
+        out
+    }
+}
@@ -0,0 +1,157 @@
+use anyhow::{anyhow, Result};
+use rquest::{EmulationProvider, Http2Config, PseudoOrder};
+
+// HTTP/2 fingerprint knobs per profile (SETTINGS, connection WINDOW_UPDATE, pseudo-header
+// order), i.e. everything the Akamai h2 fingerprint is computed from.
+
+/// Initial connection window every h2 client starts with (RFC 9113).
+const DEFAULT_CONNECTION_WINDOW: u32 = 65_535;
+
+/// Preset h2 values per browser family, as (header_table_size, enable_push,
+/// max_concurrent_streams, initial_window_size, max_frame_size, max_header_list_size,
+/// connection_window_size, pseudo_order). rquest replaces the whole h2 layer when a
+/// profile overrides it, so the values the profile leaves out are filled from here.
+type Preset = (Option<u32>, Option<bool>, Option<u32>, Option<u32>, Option<u32>, Option<u32>, Option<u32>, &'static str);
+
+static PRESETS: &[(&str, Preset)] = &[
+    ("chrome", (Some(65_536), Some(false), None, Some(6_291_456), None, Some(262_144), Some(15_728_640), "m,a,s,p")),
+    ("edge", (Some(65_536), Some(false), None, Some(6_291_456), None, Some(262_144), Some(15_728_640), "m,a,s,p")),
+    ("firefox", (Some(65_536), Some(false), None, Some(131_072), Some(16_384), None, Some(12_582_912), "m,p,a,s")),
+    ("safari", (None, Some(false), Some(100), Some(2_097_152), None, None, Some(10_485_760), "m,s,a,p")),
+    ("okhttp", (None, None, None, Some(16_777_216), None, None, Some(16_777_216), "m,p,a,s")),
+];
+
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+pub struct Http2Spec {
+    #[serde(default)]
+    pub header_table_size: Option<u32>,
+    #[serde(default)]
+    pub enable_push: Option<bool>,
+    #[serde(default)]
+    pub max_concurrent_streams: Option<u32>,
+    #[serde(default)]
+    pub initial_window_size: Option<u32>,
+    #[serde(default)]
+    pub max_frame_size: Option<u32>,
+    #[serde(default)]
+    pub max_header_list_size: Option<u32>,
+    /// Target connection window; the WINDOW_UPDATE increment is this minus 65535.
+    #[serde(default)]
+    pub connection_window_size: Option<u32>,
+    /// Pseudo-header order as `m`/`a`/`s`/`p` (or `:method`, `:authority`, ...).
+    #[serde(default)]
+    pub pseudo_order: Vec<String>,
+}
+
+impl Http2Spec {
+    /// This spec with every value it leaves unset taken from the emulation's preset.
+    pub fn over_preset(&self, emulation: &str) -> Self {
+        let normalized = emulation.trim().to_lowercase();
+        let Some((_, preset)) = PRESETS.iter().find(|(family, _)| normalized.contains(family)) else {
+            return self.clone();
+        };
+        let (table, push, streams, window, frame, header_list, connection, pseudo) = *preset;
+        Self {
+            header_table_size: self.header_table_size.or(table),
+            enable_push: self.enable_push.or(push),
+            max_concurrent_streams: self.max_concurrent_streams.or(streams),
+            initial_window_size: self.initial_window_size.or(window),
+            max_frame_size: self.max_frame_size.or(frame),
+            max_header_list_size: self.max_header_list_size.or(header_list),
+            connection_window_size: self.connection_window_size.or(connection),
+            pseudo_order: if self.pseudo_order.is_empty() {
+                pseudo.split(',').map(str::to_string).collect()
+            } else {
+                self.pseudo_order.clone()
+            },
+        }
+    }
+
+    fn pseudo(&self) -> Result<Option<[PseudoOrder; 4]>> {
+        if self.pseudo_order.is_empty() {
+            return Ok(None);
+        }
+        let parsed = self
+            .pseudo_order
+            .iter()
+            .map(|p| match p.trim().trim_start_matches(':').to_lowercase().as_str() {
+                "m" | "method" => Ok(PseudoOrder::Method),
+                "a" | "authority" => Ok(PseudoOrder::Authority),
+                "s" | "scheme" => Ok(PseudoOrder::Scheme),
+                "p" | "path" => Ok(PseudoOrder::Path),
+                other => Err(anyhow!("Unknown pseudo-header '{}'", other)),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let order: [PseudoOrder; 4] = parsed
+            .try_into()
+            .map_err(|_| anyhow!("pseudo_order needs exactly the 4 pseudo-headers m, a, s, p"))?;
+        Ok(Some(order))
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        self.pseudo()?;
+        if let Some(window) = self.connection_window_size {
+            if window < DEFAULT_CONNECTION_WINDOW {
+                return Err(anyhow!("connection_window_size must be at least {}", DEFAULT_CONNECTION_WINDOW));
+            }
+        }
+        Ok(())
+    }
+
+    /// Akamai-format fingerprint of the configured values: `SETTINGS|WINDOW_UPDATE|PRIORITY|PSEUDO`.
+    pub fn akamai_fingerprint(&self) -> String {
+        let settings: Vec<String> = [
+            (1, self.header_table_size),
+            (2, self.enable_push.map(|p| p as u32)),
+            (3, self.max_concurrent_streams),
+            (4, self.initial_window_size),
+            (5, self.max_frame_size),
+            (6, self.max_header_list_size),
+        ]
+        .iter()
+        .filter_map(|(id, v)| v.map(|v| format!("{}:{}", id, v)))
+        .collect();
+        let window_update = self
+            .connection_window_size
+            .map(|w| w.saturating_sub(DEFAULT_CONNECTION_WINDOW).to_string())
+            .unwrap_or_else(|| "00".to_string());
+        let pseudo: Vec<String> = self
+            .pseudo_order
+            .iter()
+            .filter_map(|p| p.trim().trim_start_matches(':').chars().next())
+            .map(|c| c.to_ascii_lowercase().to_string())
+            .collect();
+        format!("{}|{}|0|{}", settings.join(";"), window_update, pseudo.join(","))
+    }
+
+    /// Emulation layer carrying only the h2 settings. It replaces the preset's h2 layer,
+    /// so call it on the result of `over_preset`.
+    pub fn emulation(&self) -> Result<EmulationProvider> {
+        let mut h2 = Http2Config::builder();
+        if let Some(v) = self.header_table_size {
+            h2 = h2.header_table_size(v);
+        }
+        if let Some(v) = self.enable_push {
+            h2 = h2.enable_push(v);
+        }
+        if let Some(v) = self.max_concurrent_streams {
+            h2 = h2.max_concurrent_streams(v);
+        }
+        if let Some(v) = self.initial_window_size {
+            h2 = h2.initial_stream_window_size(v);
+        }
+        if let Some(v) = self.max_frame_size {
+            h2 = h2.max_frame_size(v);
+        }
+        if let Some(v) = self.max_header_list_size {
+            h2 = h2.max_header_list_size(v);
+        }
+        if let Some(v) = self.connection_window_size {
+            h2 = h2.initial_connection_window_size(v);
+        }
+        if let Some(order) = self.pseudo()? {
+            h2 = h2.headers_pseudo_order(order);
+        }
+        Ok(EmulationProvider::builder().http2_config(h2.build()).build())
+    }
+}
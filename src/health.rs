@@ -0,0 +1,90 @@
+use crate::engine::grid::{GridManager, DIRECT_NODE};
+use crate::engine::{ClientFactory, ProxyEndpoint};
+use anyhow::{anyhow, Result};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::task::JoinSet;
+
+// Proxy health checks. Without them the grid only learns a node is dead after requests
+// have failed on it; with `[network.health_check]` every node is probed against a neutral
+// URL at startup and then periodically. Failing nodes are taken out of rotation until a
+// later probe succeeds, and the measured latency weights node selection.
+
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct HealthCheckConfig {
+    /// Should answer 2xx/3xx quickly and cheaply; it is never the target.
+    #[serde(default = "default_url")]
+    pub url: String,
+    /// Seconds between probe rounds during the run (0 = startup only).
+    #[serde(default = "default_interval_secs")]
+    pub interval_secs: u64,
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+fn default_url() -> String {
+    "https://www.gstatic.com/generate_204".to_string()
+}
+
+fn default_interval_secs() -> u64 {
+    60
+}
+
+fn default_timeout_secs() -> u64 {
+    10
+}
+
+impl HealthCheckConfig {
+    /// Round-trip time of one check request through `node`, with a `profile` client from
+    /// the run's factory so probes connect the way workers do.
+    pub async fn probe(&self, factory: &ClientFactory, grid: &GridManager, profile: &str, node: &str) -> Result<Duration> {
+        let proxy = grid.proxy_with_session(node, None);
+        let client = factory.create_bare_client(profile, proxy.as_deref())?;
+        let started = Instant::now();
+        let resp = client.get(&self.url).timeout(Duration::from_secs(self.timeout_secs)).send().await?;
+        let status = resp.status();
+        // 407 (bad credentials) and 5xx (proxy can't reach upstream) come from the proxy itself
+        if status.is_success() || status.is_redirection() {
+            Ok(started.elapsed())
+        } else {
+            Err(anyhow!("HTTP {}", status.as_u16()))
+        }
+    }
+
+    /// Probes all nodes concurrently; results come back in `nodes` order.
+    pub async fn probe_all(
+        &self,
+        factory: &Arc<ClientFactory>,
+        grid: &Arc<GridManager>,
+        profile: &str,
+        nodes: &[String],
+    ) -> Vec<(String, Result<Duration>)> {
+        let mut set = JoinSet::new();
+        for (i, node) in nodes.iter().enumerate() {
+            let (config, factory, grid, profile, node) =
+                (self.clone(), factory.clone(), grid.clone(), profile.to_string(), node.clone());
+            set.spawn(async move { (i, config.probe(&factory, &grid, &profile, &node).await) });
+        }
+        let mut results: Vec<Option<Result<Duration>>> = nodes.iter().map(|_| None).collect();
+        while let Some(joined) = set.join_next().await {
+            if let Ok((i, result)) = joined {
+                results[i] = Some(result);
+            }
+        }
+        nodes
+            .iter()
+            .cloned()
+            .zip(results)
+            .map(|(node, r)| (node, r.unwrap_or_else(|| Err(anyhow!("probe task failed")))))
+            .collect()
+    }
+
+    /// Node label for logs: the redacted proxy URL, or `direct`.
+    pub fn label(node: &str) -> String {
+        if node == DIRECT_NODE {
+            DIRECT_NODE.to_string()
+        } else {
+            ProxyEndpoint::redact(node)
+        }
+    }
+}
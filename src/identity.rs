@@ -0,0 +1,231 @@
+use crate::persona::Persona;
+use rand::seq::SliceRandom;
+
+// Per-identity device traits (screen, OS version, GPU, fonts) drawn from pools that are
+// consistent with the emulated browser, so a run doesn't look like N copies of one machine.
+// Weights are rough market shares; they only need to look natural, not be exact.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceFamily {
+    Windows,
+    MacOs,
+    Ios,
+    Android,
+}
+
+impl DeviceFamily {
+    /// Chromium and Firefox presets all claim Windows in their User-Agent.
+    pub fn for_emulation(name: &str) -> Self {
+        if let Some(persona) = Persona::lookup(name) {
+            return persona.family;
+        }
+        let normalized = name.trim().to_lowercase().replace(['-', '.'], "_");
+        if normalized.starts_with("okhttp") {
+            DeviceFamily::Android
+        } else if normalized.starts_with("safari_ios") || normalized.starts_with("safari_ipad") {
+            DeviceFamily::Ios
+        } else if normalized.starts_with("safari") {
+            DeviceFamily::MacOs
+        } else {
+            DeviceFamily::Windows
+        }
+    }
+
+    pub fn is_mobile(&self) -> bool {
+        matches!(self, DeviceFamily::Ios | DeviceFamily::Android)
+    }
+
+    /// The `sec-ch-ua-platform` / `userAgentData.platform` value.
+    pub fn platform(&self) -> &'static str {
+        match self {
+            DeviceFamily::Windows => "Windows",
+            DeviceFamily::MacOs => "macOS",
+            DeviceFamily::Ios => "iOS",
+            DeviceFamily::Android => "Android",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            DeviceFamily::Windows => "windows",
+            DeviceFamily::MacOs => "macos",
+            DeviceFamily::Ios => "ios",
+            DeviceFamily::Android => "android",
+        }
+    }
+}
+
+/// (label, `sec-ch-ua-platform-version`, weight). Windows 10 and 11 share `NT 10.0`
+/// in the UA; only the platform version tells them apart.
+static WINDOWS_VERSIONS: &[(&str, &str, u32)] = &[
+    ("Windows 10 22H2", "10.0.0", 45),
+    ("Windows 11 22H2", "13.0.0", 10),
+    ("Windows 11 23H2", "15.0.0", 30),
+    ("Windows 11 24H2", "19.0.0", 15),
+];
+static MACOS_VERSIONS: &[(&str, &str, u32)] = &[
+    ("macOS 13", "13.6.9", 20),
+    ("macOS 14", "14.7.1", 45),
+    ("macOS 15", "15.1.1", 35),
+];
+static IOS_VERSIONS: &[(&str, &str, u32)] = &[
+    ("iOS 16", "16.7.10", 15),
+    ("iOS 17", "17.6.1", 40),
+    ("iOS 18", "18.1.1", 45),
+];
+static ANDROID_VERSIONS: &[(&str, &str, u32)] = &[
+    ("Android 12", "12.0.0", 20),
+    ("Android 13", "13.0.0", 30),
+    ("Android 14", "14.0.0", 50),
+];
+
+/// (width, height, device pixel ratio, weight) in CSS pixels.
+static DESKTOP_SCREENS: &[(u32, u32, f32, u32)] = &[
+    (1920, 1080, 1.0, 35),
+    (1366, 768, 1.0, 12),
+    (1536, 864, 1.25, 14),
+    (1440, 900, 1.0, 6),
+    (1280, 720, 1.5, 5),
+    (1600, 900, 1.0, 6),
+    (2560, 1440, 1.0, 10),
+    (1280, 800, 1.5, 4),
+    (1680, 1050, 1.0, 3),
+];
+static MAC_SCREENS: &[(u32, u32, f32, u32)] = &[
+    (1440, 900, 2.0, 20),
+    (1512, 982, 2.0, 25),
+    (1728, 1117, 2.0, 15),
+    (1470, 956, 2.0, 20),
+    (1920, 1080, 1.0, 10),
+    (2560, 1440, 1.0, 10),
+];
+static IOS_SCREENS: &[(u32, u32, f32, u32)] = &[
+    (390, 844, 3.0, 30),
+    (393, 852, 3.0, 30),
+    (430, 932, 3.0, 20),
+    (375, 667, 2.0, 10),
+    (414, 896, 2.0, 10),
+];
+static ANDROID_SCREENS: &[(u32, u32, f32, u32)] = &[
+    (412, 915, 2.625, 35),
+    (360, 800, 3.0, 25),
+    (393, 873, 2.75, 20),
+    (384, 854, 2.8125, 10),
+    (412, 892, 3.5, 10),
+];
+
+/// (WebGL unmasked vendor, unmasked renderer, weight).
+static WINDOWS_GPUS: &[(&str, &str, u32)] = &[
+    ("Google Inc. (Intel)", "ANGLE (Intel, Intel(R) UHD Graphics 620 Direct3D11 vs_5_0 ps_5_0, D3D11)", 20),
+    ("Google Inc. (Intel)", "ANGLE (Intel, Intel(R) Iris(R) Xe Graphics Direct3D11 vs_5_0 ps_5_0, D3D11)", 18),
+    ("Google Inc. (NVIDIA)", "ANGLE (NVIDIA, NVIDIA GeForce GTX 1650 Direct3D11 vs_5_0 ps_5_0, D3D11)", 10),
+    ("Google Inc. (NVIDIA)", "ANGLE (NVIDIA, NVIDIA GeForce RTX 3060 Direct3D11 vs_5_0 ps_5_0, D3D11)", 12),
+    ("Google Inc. (NVIDIA)", "ANGLE (NVIDIA, NVIDIA GeForce RTX 4060 Direct3D11 vs_5_0 ps_5_0, D3D11)", 8),
+    ("Google Inc. (AMD)", "ANGLE (AMD, AMD Radeon(TM) Graphics Direct3D11 vs_5_0 ps_5_0, D3D11)", 12),
+    ("Google Inc. (AMD)", "ANGLE (AMD, AMD Radeon RX 6600 Direct3D11 vs_5_0 ps_5_0, D3D11)", 6),
+];
+static MAC_GPUS: &[(&str, &str, u32)] = &[
+    ("Apple Inc.", "Apple M1", 35),
+    ("Apple Inc.", "Apple M2", 30),
+    ("Apple Inc.", "Apple M3", 20),
+    ("Intel Inc.", "Intel(R) Iris(TM) Plus Graphics 655", 15),
+];
+static IOS_GPUS: &[(&str, &str, u32)] = &[("Apple Inc.", "Apple GPU", 1)];
+static ANDROID_GPUS: &[(&str, &str, u32)] = &[
+    ("Qualcomm", "Adreno (TM) 640", 25),
+    ("Qualcomm", "Adreno (TM) 730", 25),
+    ("ARM", "Mali-G78", 25),
+    ("ARM", "Mali-G710", 25),
+];
+
+/// `sec-ch-ua-model` values; only Android Chrome reports a model.
+static ANDROID_MODELS: &[(&str, u32)] = &[
+    ("Pixel 7", 20),
+    ("Pixel 8", 15),
+    ("SM-S911B", 20),
+    ("SM-A546B", 25),
+    ("2201117TG", 10),
+    ("CPH2451", 10),
+];
+
+/// Installed-font counts as fingerprinters bucket them.
+static WINDOWS_FONTS: &[(u32, u32)] = &[(60, 15), (90, 45), (120, 25), (180, 15)];
+static MAC_FONTS: &[(u32, u32)] = &[(230, 30), (260, 45), (300, 25)];
+static MOBILE_FONTS: &[(u32, u32)] = &[(40, 50), (60, 50)];
+
+static DESKTOP_CORES: &[(u32, u32)] = &[(4, 25), (8, 40), (12, 15), (16, 20)];
+static MAC_CORES: &[(u32, u32)] = &[(8, 60), (10, 25), (12, 15)];
+static MOBILE_CORES: &[(u32, u32)] = &[(6, 40), (8, 60)];
+
+fn pick<T: Copy>(pool: &[T], weight: impl Fn(&T) -> u32) -> T {
+    *pool
+        .choose_weighted(&mut rand::thread_rng(), |item| weight(item))
+        .expect("entropy pools are non-empty with positive weights")
+}
+
+/// Device traits of one identity; sampled once per session.
+#[derive(Debug, Clone)]
+pub struct IdentityTraits {
+    pub family: DeviceFamily,
+    pub os_version: &'static str,
+    /// `sec-ch-ua-platform-version` for the OS version above.
+    pub platform_version: &'static str,
+    pub screen_width: u32,
+    pub screen_height: u32,
+    pub pixel_ratio: f32,
+    pub gpu_vendor: &'static str,
+    pub gpu_renderer: &'static str,
+    /// Device model for `sec-ch-ua-model`; empty outside Android.
+    pub device_model: &'static str,
+    /// Font-count bucket; recorded for the logs, the solver can't change installed fonts.
+    pub font_count: u32,
+    pub hardware_concurrency: u32,
+}
+
+impl IdentityTraits {
+    pub fn sample(emulation: &str) -> Self {
+        Self::sample_family(DeviceFamily::for_emulation(emulation))
+    }
+
+    pub fn sample_family(family: DeviceFamily) -> Self {
+        let (versions, screens, gpus, fonts, cores) = match family {
+            DeviceFamily::Windows => (WINDOWS_VERSIONS, DESKTOP_SCREENS, WINDOWS_GPUS, WINDOWS_FONTS, DESKTOP_CORES),
+            DeviceFamily::MacOs => (MACOS_VERSIONS, MAC_SCREENS, MAC_GPUS, MAC_FONTS, MAC_CORES),
+            DeviceFamily::Ios => (IOS_VERSIONS, IOS_SCREENS, IOS_GPUS, MOBILE_FONTS, MOBILE_CORES),
+            DeviceFamily::Android => (ANDROID_VERSIONS, ANDROID_SCREENS, ANDROID_GPUS, MOBILE_FONTS, MOBILE_CORES),
+        };
+        let (os_version, platform_version, _) = pick(versions, |v| v.2);
+        let (screen_width, screen_height, pixel_ratio, _) = pick(screens, |s| s.3);
+        let (gpu_vendor, gpu_renderer, _) = pick(gpus, |g| g.2);
+        let (font_count, _) = pick(fonts, |f| f.1);
+        let (hardware_concurrency, _) = pick(cores, |c| c.1);
+        let device_model = if family == DeviceFamily::Android { pick(ANDROID_MODELS, |m| m.1).0 } else { "" };
+        Self {
+            family,
+            os_version,
+            platform_version,
+            screen_width,
+            screen_height,
+            pixel_ratio,
+            gpu_vendor,
+            gpu_renderer,
+            device_model,
+            font_count,
+            hardware_concurrency,
+        }
+    }
+
+    /// One-line summary for logs.
+    pub fn summary(&self) -> String {
+        format!(
+            "{}, {}x{}@{}, {}, {} fonts, {} cores",
+            self.os_version,
+            self.screen_width,
+            self.screen_height,
+            self.pixel_ratio,
+            self.gpu_renderer,
+            self.font_count,
+            self.hardware_concurrency
+        )
+    }
+}
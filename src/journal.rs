@@ -0,0 +1,91 @@
+use crate::report::{Finding, Report, ScanSummary};
+use anyhow::{anyhow, Context, Result};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+
+// Append-only checkpoint journal. Every interval the engine appends the requests recorded
+// since the last checkpoint plus a full summary snapshot, and fsyncs; after a crash
+// `--recover` rebuilds a report from the last complete checkpoint.
+
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct JournalConfig {
+    /// Defaults to `logs/<session id>.journal.jsonl`, next to the session log.
+    #[serde(default)]
+    pub path: Option<String>,
+    #[serde(default = "default_interval_secs")]
+    pub interval_secs: u64,
+}
+
+fn default_interval_secs() -> u64 {
+    10
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum JournalEntry {
+    Records { records: Vec<Finding> },
+    Checkpoint { summary: ScanSummary, complete: bool },
+}
+
+pub struct Journal {
+    file: File,
+    path: String,
+    written: usize,
+}
+
+impl Journal {
+    pub fn create(path: &str) -> Result<Self> {
+        // One journal per run; a fixed `path` is truncated rather than mixed with an old run
+        let file = File::create(path).context(format!("Failed to create journal {}", path))?;
+        Ok(Self { file, path: path.to_string(), written: 0 })
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Findings already journaled; the engine's findings list only ever grows, so the
+    /// next checkpoint takes everything past this index.
+    pub fn written(&self) -> usize {
+        self.written
+    }
+
+    /// Appends the `fresh` findings and a summary snapshot, then syncs to disk.
+    /// `complete` marks the run's final checkpoint.
+    pub fn checkpoint(&mut self, summary: &ScanSummary, fresh: &[Finding], complete: bool) -> Result<()> {
+        if !fresh.is_empty() {
+            let line = serde_json::json!({ "type": "records", "records": fresh });
+            writeln!(self.file, "{}", line)?;
+        }
+        let line = serde_json::json!({ "type": "checkpoint", "summary": summary, "complete": complete });
+        writeln!(self.file, "{}", line)?;
+        self.file.sync_data()?;
+        self.written += fresh.len();
+        Ok(())
+    }
+
+    /// Rebuilds a report from a journal: the last checkpoint's summary and every journaled
+    /// record. A torn final line (crash mid-write) is dropped.
+    pub fn recover(path: &str) -> Result<Report> {
+        let file = File::open(path).context(format!("Failed to open journal {}", path))?;
+        let lines: Vec<String> = BufReader::new(file).lines().collect::<std::io::Result<_>>()?;
+        let mut findings = Vec::new();
+        let mut last: Option<(ScanSummary, bool)> = None;
+        for (i, line) in lines.iter().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<JournalEntry>(line) {
+                Ok(JournalEntry::Records { records }) => findings.extend(records),
+                Ok(JournalEntry::Checkpoint { summary, complete }) => last = Some((summary, complete)),
+                Err(_) if i + 1 == lines.len() => break,
+                Err(e) => return Err(anyhow!("Corrupt journal entry at line {}: {}", i + 1, e)),
+            }
+        }
+        let (mut summary, complete) = last.ok_or_else(|| anyhow!("No checkpoint in journal {}", path))?;
+        if !complete && summary.stop_reason.is_none() {
+            summary.stop_reason = Some("run did not finish; recovered from journal".to_string());
+        }
+        Ok(Report::new(summary, findings))
+    }
+}
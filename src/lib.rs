@@ -0,0 +1,80 @@
+//! Spectre as a library: embed the stealth-traffic engine in your own harness.
+//!
+//! ```ignore
+//! use spectre::EngineBuilder;
+//! use std::time::Duration;
+//!
+//! let mut engine = EngineBuilder::new("https://staging.example.com/")
+//!     .concurrency(8)
+//!     .allow_direct(true)
+//!     .build()?;
+//! engine.start()?;
+//!
+//! let mut stats = engine.stats_stream(Duration::from_secs(1));
+//! if let Some(snapshot) = stats.recv().await {
+//!     println!("{} requests so far", snapshot.total_requests);
+//! }
+//! engine.stop().await?;
+//! ```
+
+pub mod activity;
+pub mod api;
+pub mod artifacts;
+pub mod auth;
+pub mod backoff;
+pub mod behavior;
+pub mod cache;
+pub mod captcha;
+pub mod categories;
+pub mod chain;
+pub mod chromium;
+pub mod cli;
+pub mod control;
+pub mod crash;
+pub mod dates;
+pub mod discovery;
+pub mod dns;
+pub mod domains;
+pub mod embed;
+pub mod engine;
+pub mod expr;
+pub mod firefox;
+pub mod fuzz;
+pub mod geo;
+pub mod h2;
+pub mod health;
+pub mod identity;
+pub mod journal;
+pub mod origin;
+pub mod payloads;
+pub mod persona;
+pub mod preflight;
+pub mod progress;
+pub mod proxies;
+pub mod raw;
+pub mod reaper;
+pub mod recorder;
+pub mod report;
+pub mod rules;
+pub mod runtime;
+pub mod safety;
+pub mod sandbox;
+pub mod significance;
+pub mod signing;
+pub mod sink;
+pub mod smuggle;
+pub mod socks;
+pub mod stealth;
+pub mod success;
+pub mod tamper;
+pub mod tls;
+pub mod tor;
+pub mod trends;
+pub mod tui;
+pub mod update;
+pub mod upload;
+pub mod variants;
+pub mod waf;
+
+pub use embed::{Engine, EngineBuilder};
+pub use engine::{Config, EngineStats, StatsSnapshot};
@@ -1,33 +1,45 @@
-mod cli;
-mod engine;
-mod tui;
-mod payloads;
-mod tamper;
-mod waf;
-mod report;
-mod api;
-
-use anyhow::Result;
+use anyhow::{Context, Result};
 use clap::Parser;
-use engine::{Config, CoreEngine};
+use spectre::engine::{self, Config, CoreEngine};
+use spectre::{api, cache, cli, control, crash, fuzz, journal, origin, preflight, progress, recorder, runtime, significance, signing, smuggle, trends, tui, update, variants, waf};
 use std::fs;
 use std::sync::Arc;
 use tokio::task;
 
-#[tokio::main]
-async fn main() -> Result<()> {
+fn main() -> Result<()> {
     env_logger::init();
+    crash::install();
 
     // Parse CLI
     let args = cli::Cli::parse();
 
-    // API Mode
-    if args.api {
-        if !args.authorized {
-             eprintln!("\x1b[31m[ERROR] Authorization Required for API Mode (--authorized)\x1b[0m");
-             std::process::exit(1);
+    // Runtime sizing lives in the config file, so `[runtime]` is read before anything else
+    let config_content = fs::read_to_string(&args.config).unwrap_or_default();
+    let runtime = runtime::RuntimeConfig::from_toml(&config_content)?.build()?;
+    runtime.block_on(run(args))
+}
+
+async fn run(args: cli::Cli) -> Result<()> {
+    // Self-update: touches no target, so no authorization needed
+    if let Some(cli::Command::SelfUpdate { check }) = &args.command {
+        match update::self_update(*check).await? {
+            update::UpdateOutcome::UpToDate => eprintln!("[\x1b[32m+\x1b[0m] Spectre {} is up to date", env!("CARGO_PKG_VERSION")),
+            update::UpdateOutcome::Available(r) => eprintln!("[\x1b[33m*\x1b[0m] Spectre {} is available: {}", r.version(), r.html_url),
+            update::UpdateOutcome::Installed(r) => eprintln!("[\x1b[32m+\x1b[0m] Updated to Spectre {}", r.version()),
+        }
+        return Ok(());
+    }
+
+    // Trends: offline, reads only what earlier runs wrote
+    if let Some(cli::Command::Trends { dir, db, target, bucket_days, out }) = &args.command {
+        let report = trends::TrendReport::build(dir, db.as_deref(), target.as_deref(), *bucket_days)?;
+        match out {
+            Some(path) => {
+                report.save(path)?;
+                eprintln!("[\x1b[32m+\x1b[0m] Trends for {} targets ({} runs) written to {}", report.targets.len(), report.runs, path);
+            }
+            None => report.print(),
         }
-        api::start_api().await;
         return Ok(());
     }
 
@@ -43,6 +55,42 @@ Usage: spectre --authorized --target <URL>
         std::process::exit(1);
     }
 
+    // Crash recovery: offline, needs no config
+    if let Some(path) = &args.recover {
+        let report = journal::Journal::recover(path)?;
+        eprintln!(
+            "[\x1b[32m+\x1b[0m] Recovered {} records, {} requests over {}s{}",
+            report.findings.len(),
+            report.summary.total_requests,
+            report.summary.duration_seconds,
+            report.summary.stop_reason.as_deref().map(|r| format!(" ({})", r)).unwrap_or_default()
+        );
+        match &args.report {
+            Some(out) => {
+                report.save(out)?;
+                eprintln!("[\x1b[32m+\x1b[0m] Report written to {}", out);
+            }
+            None => println!("{}", serde_json::to_string_pretty(&report)?),
+        }
+        return Ok(());
+    }
+
+    if let Some(path) = &args.verify_report {
+        let sig = signing::verify_file(path, args.public_key.as_deref())?;
+        eprintln!("[\x1b[32m+\x1b[0m] Valid signature on {}", path);
+        eprintln!("    public key  : {}{}", sig.public_key, if args.public_key.is_some() { " (expected)" } else { " (compare out of band)" });
+        eprintln!("    config hash : {}", sig.config_hash.as_deref().unwrap_or("-"));
+        return Ok(());
+    }
+
+    if let Some(path) = &args.convert_recording {
+        let stdout = std::io::stdout();
+        let mut out = std::io::BufWriter::new(stdout.lock());
+        let count = recorder::convert_to_jsonl(path, &mut out)?;
+        eprintln!("[\x1b[32m+\x1b[0m] Converted {} records", count);
+        return Ok(());
+    }
+
     // Load Config (File + CLI Overrides)
     let config_content = fs::read_to_string(&args.config).unwrap_or_else(|_| "".to_string());
     
@@ -51,23 +99,11 @@ Usage: spectre --authorized --target <URL>
         toml::from_str(&config_content)?
     } else {
         // Minimal Default Config if no file
-        Config {
-            general: engine::GeneralConfig {
-                target_url: "http://localhost".to_string(),
-                concurrency: 1,
-                debug_mode: false,
-                method: "GET".to_string(),
-                headers: vec![],
-                raw_body: None,
-                payload_file: None,
-                tampers: vec![],
-                report_file: None,
-                time_limit: None,
-            },
-            profiles: std::collections::HashMap::new(),
-            network: engine::NetworkConfig { proxies: vec![] },
-        }
+        Config::minimal("http://localhost")
     };
+    if !config_content.is_empty() {
+        config.config_hash = Some(signing::config_hash(&config_content));
+    }
 
     // Apply CLI Overrides
     if let Some(target) = args.target {
@@ -104,6 +140,114 @@ Usage: spectre --authorized --target <URL>
         config.general.time_limit = Some(tl);
     }
 
+    if let Some(socket_path) = args.control_socket {
+        config.control = Some(engine::config::ControlConfig { socket_path });
+    }
+
+    if args.cache_bust {
+        config.general.cache_bust = true;
+    }
+
+    if let Some(wordlist) = args.wordlist {
+        config.general.wordlist = Some(wordlist);
+    }
+    if let Some(rps) = args.max_rps {
+        config.general.max_rps = Some(rps);
+    }
+
+    if args.direct {
+        config.network.allow_direct = true;
+    }
+
+    if let Some(shadow_url) = args.shadow {
+        let sample_percent = args.shadow_percent.unwrap_or(10.0);
+        config.shadow = Some(engine::config::ShadowConfig { target_url: shadow_url, sample_percent });
+    } else if let (Some(sh), Some(pct)) = (config.shadow.as_mut(), args.shadow_percent) {
+        sh.sample_percent = pct;
+    }
+
+    // API Mode: every scan starts from the loaded config
+    if args.api {
+        api::start_api(config).await;
+        return Ok(());
+    }
+
+    // Every mode below talks to the target, so the allowlist applies to all of them
+    config.check_safety()?;
+
+    // Preflight Validation (no traffic loop)
+    if args.check {
+        eprintln!("[\x1b[33m*\x1b[0m] Preflight check for {}...\n", config.general.target_url);
+        let report = preflight::Preflight::run(&config).await;
+        report.print();
+        std::process::exit(if report.is_ready() { 0 } else { 1 });
+    }
+
+    // Header Wire Dump (one-shot mode)
+    if let Some(profile) = &args.dump_headers {
+        let factory = engine::ClientFactory::new(config.profiles.clone())
+            .with_encodings(config.network.decompress, config.network.encodings.clone())?;
+        let lines = engine::WireCapture::capture(&factory, profile).await?;
+        eprintln!("[\x1b[33m*\x1b[0m] Request head for profile '{}' (HTTP/1.1):", profile);
+        for line in lines {
+            eprintln!("    {}", line);
+        }
+        return Ok(());
+    }
+
+    // Custom TLS Fingerprint (one-shot mode)
+    if let Some(profile) = &args.tls_fingerprint {
+        let entry = config
+            .profiles
+            .get(profile)
+            .ok_or_else(|| anyhow::anyhow!("Profile not found: {}", profile))?;
+        match entry.tls() {
+            Some(spec) => {
+                let resolved = spec.resolve()?;
+                eprintln!("[\x1b[33m*\x1b[0m] Custom TLS for profile '{}':", profile);
+                eprintln!("    ciphers    : {}", resolved.ciphers.iter().map(|(_, n)| *n).collect::<Vec<_>>().join(":"));
+                eprintln!("    curves     : {}", resolved.curves.iter().map(|(_, n)| *n).collect::<Vec<_>>().join(":"));
+                eprintln!("    extensions : {:?}", resolved.extensions);
+                eprintln!("    grease     : {}", resolved.grease);
+                eprintln!("    JA3        : {}", resolved.ja3());
+                eprintln!("    JA3 hash   : {}", resolved.ja3_hash());
+            }
+            None => eprintln!("[\x1b[33m*\x1b[0m] Profile '{}' uses the preset emulation '{}'", profile, entry.emulation()),
+        }
+        return Ok(());
+    }
+
+    // HTTP/2 Fingerprint (one-shot mode)
+    if let Some(profile) = &args.h2_fingerprint {
+        let entry = config
+            .profiles
+            .get(profile)
+            .ok_or_else(|| anyhow::anyhow!("Profile not found: {}", profile))?;
+        match entry.http2() {
+            Some(spec) => {
+                spec.validate().context(format!("Invalid http2 spec in profile '{}'", profile))?;
+                let effective = spec.over_preset(entry.emulation());
+                eprintln!("[\x1b[33m*\x1b[0m] Configured h2 fingerprint for '{}': {}", profile, effective.akamai_fingerprint());
+            }
+            None => eprintln!("[\x1b[33m*\x1b[0m] Profile '{}' uses the h2 settings of preset '{}'", profile, entry.emulation()),
+        }
+        // Only an echo service can tell what actually went on the wire
+        if let Some(echo_url) = &args.echo_url {
+            config.safety.check_targets([("echo", echo_url.as_str())])?;
+            let factory = engine::ClientFactory::new(config.profiles.clone());
+            let body = factory.create_client(profile, None, None)?.get(echo_url).send().await?.text().await?;
+            match serde_json::from_str::<serde_json::Value>(&body) {
+                Ok(json) => {
+                    let field = |path: &str| json.pointer(path).and_then(|v| v.as_str()).unwrap_or("-").to_string();
+                    eprintln!("    observed akamai : {}", field("/http2/akamai_fingerprint"));
+                    eprintln!("    observed ja3    : {}", field("/tls/ja3"));
+                }
+                Err(_) => eprintln!("{}", body.chars().take(2000).collect::<String>()),
+            }
+        }
+        return Ok(());
+    }
+
     // WAF Detection
     if args.detect {
         eprintln!("[\x1b[33m*\x1b[0m] Starting WAF Detection on {}...", config.general.target_url);
@@ -116,23 +260,167 @@ Usage: spectre --authorized --target <URL>
         }
     }
 
+    // Origin Exposure Check (one-shot mode)
+    if let Some(candidates) = args.origin_scan {
+        eprintln!("[\x1b[33m*\x1b[0m] Origin exposure check on {} ({} candidates)...", config.general.target_url, candidates.len());
+        let scanner = origin::OriginScanner::new(config.clone(), &candidates)?;
+        let (cdn, results) = scanner.run(args.origin_samples).await?;
+        eprintln!(
+            "    WAF path : {} passed / {} blocked / {} challenged / {} errors",
+            cdn.success, cdn.blocked, cdn.challenge, cdn.errors
+        );
+        for r in &results {
+            let v = &r.verdicts;
+            let tag = if r.bypasses_waf(&cdn) {
+                "\x1b[31mBYPASS\x1b[0m"
+            } else if r.is_reachable() {
+                "\x1b[33mREACHABLE\x1b[0m"
+            } else {
+                "\x1b[32mCLOSED\x1b[0m"
+            };
+            eprintln!(
+                "    {:<15}: {} passed / {} blocked / {} challenged / {} errors [{}]",
+                r.ip, v.success, v.blocked, v.challenge, v.errors, tag
+            );
+        }
+        return Ok(());
+    }
+
+    // Cache-Hit Ratio Measurement (one-shot mode)
+    if args.cache_ratio {
+        let probe = cache::CacheRatioProbe::new(config.clone());
+        eprintln!("[\x1b[33m*\x1b[0m] Measuring CDN cache-hit ratio ({} requests per URL)...", args.cache_repeats);
+        let results = probe.run(args.cache_repeats, std::time::Duration::from_millis(200)).await?;
+        for r in &results {
+            eprintln!(
+                "    {} -> HIT {} / MISS {} / no header {} / errors {} ({:.1}% hit)",
+                r.url, r.hits, r.misses, r.unknown, r.errors, r.hit_ratio() * 100.0
+            );
+        }
+        return Ok(());
+    }
+
+    // Desync Probe (intrusive, one-shot mode)
+    if args.smuggle_probe {
+        if !args.enable_intrusive {
+            eprintln!("\x1b[31m[ERROR] --smuggle-probe is intrusive and requires --enable-intrusive\x1b[0m");
+            std::process::exit(1);
+        }
+        eprintln!("[\x1b[33m*\x1b[0m] Desync timing probe on {}...", config.general.target_url);
+        let probe = smuggle::SmuggleProbe::new(&config.general.target_url, std::time::Duration::from_secs(10), 3)?;
+        for r in probe.run().await? {
+            if r.skipped {
+                eprintln!("    {}: skipped (CL.TE already positive)", r.kind);
+            } else if r.likely_susceptible() {
+                eprintln!(
+                    "[\x1b[31m!\x1b[0m] {}: likely susceptible ({}/{} probes stalled, baseline {} ms)",
+                    r.kind, r.timeouts, r.attempts, r.baseline_ms
+                );
+            } else {
+                eprintln!(
+                    "[\x1b[32m+\x1b[0m] {}: not detected ({}/{} probes stalled, baseline {} ms)",
+                    r.kind, r.timeouts, r.attempts, r.baseline_ms
+                );
+            }
+        }
+        return Ok(());
+    }
+
+    // Raw-Socket Variants (one-shot mode)
+    if args.raw_variants {
+        eprintln!("[\x1b[33m*\x1b[0m] Raw-socket variants on {} ({} requests each)...", config.general.target_url, args.variant_samples);
+        let scanner = variants::RawVariantScanner::new(config.clone());
+        for r in scanner.run(args.variant_samples).await? {
+            let v = &r.verdicts;
+            eprintln!(
+                "    {:<20}: {} passed / {} blocked / {} challenged / {} errors",
+                r.variant.name(), v.success, v.blocked, v.challenge, v.errors
+            );
+        }
+        return Ok(());
+    }
+
+    // Header-Mutation Campaign (one-shot mode)
+    if args.header_fuzz {
+        eprintln!("[\x1b[33m*\x1b[0m] Header mutation campaign on {} ({} requests each)...", config.general.target_url, args.fuzz_samples);
+        let fuzzer = fuzz::HeaderFuzzer::new(config.clone(), &args.fuzz_profile);
+        let results = fuzzer.run(args.fuzz_samples).await?;
+        let baseline = results.first().map(|r| r.verdicts.clone()).unwrap_or_default();
+        eprintln!(
+            "    {:<16} {:<18} {:>6} {:>7} {:>10} {:>6} {:>7}",
+            "DIMENSION", "MUTATION", "PASS", "BLOCK", "CHALLENGE", "ERR", "P"
+        );
+        for r in &results {
+            let v = &r.verdicts;
+            // p-value of the pass rate vs baseline; "-" where there's nothing to test
+            let p = r.p_value(&baseline).map(|p| format!("{:.3}", p)).unwrap_or_else(|| "-".into());
+            let tag = if r.triggers(&baseline) { " \x1b[31m<- triggers\x1b[0m" } else { "" };
+            eprintln!(
+                "    {:<16} {:<18} {:>6} {:>7} {:>10} {:>6} {:>7}{}",
+                r.dimension, r.name, v.success, v.blocked, v.challenge, v.errors, p, tag
+            );
+        }
+        eprintln!("    P < {} means the difference from baseline is unlikely to be noise at this sample size.", significance::ALPHA);
+        return Ok(());
+    }
+
+    // Stale emulation presets stop working quietly, so say when a newer release is out
+    if config.general.version_check && !args.no_version_check {
+        if let Some(notice) = update::startup_notice().await {
+            eprintln!("[\x1b[33m*\x1b[0m] {}", notice);
+        }
+    }
+
+    let control_config = config.control.clone();
+    let max_requests = config.stop.as_ref().and_then(|s| s.max_requests);
+
     // Initialize Engine
     let engine = Arc::new(CoreEngine::new(config));
     let engine_clone = engine.clone();
+    let stats = engine.get_stats();
+    let engine_stats = stats.clone();
 
     // Run Engine in background
-    let _engine_handle = task::spawn(async move {
-        if let Err(e) = engine_clone.run().await {
+    let engine_handle = task::spawn(async move {
+        let result = engine_clone.run().await;
+        if let Err(e) = &result {
             eprintln!("Engine error: {}", e);
+            engine_stats.shutdown.set_phase(engine::ShutdownPhase::Done);
         }
+        result
     });
 
+    // Local control socket
+    if let Some(ctrl) = control_config {
+        let control_stats = stats.clone();
+        task::spawn(async move {
+            if let Err(e) = control::serve(ctrl.socket_path, control_stats).await {
+                log::error!("Control socket error: {}", e);
+            }
+        });
+    }
+
+    // Progress line instead of the TUI
+    if args.progress {
+        progress::ProgressLine::new(stats.clone(), max_requests)
+            .run(std::time::Duration::from_millis(500))
+            .await;
+        // The error was printed when the engine stopped; the exit code is for scripts
+        let failed = !matches!(engine_handle.await, Ok(Ok(())));
+        std::process::exit(if failed { 1 } else { 0 });
+    }
+
     // Run TUI
-    let mut tui_app = tui::TuiApp::new(engine.get_stats());
-    tui_app.run().await?;
+    // Rules promoted in the builder are appended to the config file the run was loaded from
+    let mut tui_app = tui::TuiApp::new(stats.clone()).with_config_path((!config_content.is_empty()).then(|| args.config.clone()));
+    let exit = tui_app.run().await?;
 
-    // --- IMPORTANT: FORCE EXIT ---
-    // This kills the background engine tasks immediately
-    std::process::exit(0);
-}
+    if exit == tui::TuiExit::Forced {
+        std::process::exit(130);
+    }
 
+    // Graceful: the engine has drained and flushed by now
+    stats.shutdown.request();
+    let failed = !matches!(engine_handle.await, Ok(Ok(())));
+    std::process::exit(if failed { 1 } else { 0 });
+}
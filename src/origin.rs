@@ -0,0 +1,138 @@
+use crate::engine::{
+    BodyReader, ClientFactory, Config, RequestBuilderHelper, ResponseAnalyzer, TemplateEngine, Verdict,
+};
+use crate::payloads::{PayloadConfig, PayloadManager};
+use crate::tamper::{TamperEngine, TamperType};
+use anyhow::{anyhow, Context, Result};
+use std::net::{IpAddr, SocketAddr};
+
+// Origin exposure check: replay the same requests straight at candidate origin IPs
+// (keeping the real Host/SNI) and compare verdicts with the CDN/WAF path.
+
+#[derive(Debug, Default, Clone)]
+pub struct PathVerdicts {
+    pub success: usize,
+    pub blocked: usize,
+    pub challenge: usize,
+    /// Answered with an `[[analyzer.categories]]` verdict: neither a pass nor a block.
+    pub other: usize,
+    pub errors: usize,
+}
+
+impl PathVerdicts {
+    pub fn record(&mut self, verdict: Option<&Verdict>) {
+        match verdict {
+            Some(Verdict::Success) => self.success += 1,
+            Some(Verdict::Blocked(_)) | Some(Verdict::Oversized(_)) | Some(Verdict::AuthRequired(_)) => self.blocked += 1,
+            Some(Verdict::Challenge(_)) => self.challenge += 1,
+            Some(Verdict::Custom(_)) => self.other += 1,
+            None => self.errors += 1,
+        }
+    }
+
+    pub fn answered(&self) -> usize {
+        self.success + self.blocked + self.challenge + self.other
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct OriginResult {
+    pub ip: IpAddr,
+    pub verdicts: PathVerdicts,
+}
+
+impl OriginResult {
+    /// The origin answers HTTP at all when addressed directly.
+    pub fn is_reachable(&self) -> bool {
+        self.verdicts.answered() > 0
+    }
+
+    /// The origin lets through requests that the protected path blocks or challenges.
+    pub fn bypasses_waf(&self, cdn: &PathVerdicts) -> bool {
+        self.verdicts.success > cdn.success
+    }
+}
+
+pub struct OriginScanner {
+    config: Config,
+    candidates: Vec<IpAddr>,
+}
+
+impl OriginScanner {
+    pub fn new(config: Config, candidates: &[String]) -> Result<Self> {
+        let candidates = candidates
+            .iter()
+            .map(|c| c.trim().parse::<IpAddr>().context(format!("Invalid origin IP: {}", c)))
+            .collect::<Result<Vec<_>>>()?;
+        if candidates.is_empty() {
+            return Err(anyhow!("No origin candidates given"));
+        }
+        Ok(Self { config, candidates })
+    }
+
+    async fn probe(&self, factory: &ClientFactory, samples: usize) -> Result<PathVerdicts> {
+        let payload_conf = self.config.general.payload_file.as_ref().map(|p| PayloadConfig {
+            file_path: p.clone(),
+        });
+        let payloads = PayloadManager::new(payload_conf)?;
+        let tampers: Vec<TamperType> = self
+            .config
+            .general
+            .tampers
+            .iter()
+            .map(|t| t.parse().unwrap_or(TamperType::None))
+            .collect();
+        let client = factory.create_client("desktop", None, None)?;
+        let mut verdicts = PathVerdicts::default();
+
+        for raw_payload in payloads.get_payloads().iter().cycle().take(samples) {
+            let payload = TamperEngine::apply(raw_payload, &tampers);
+            let url = TemplateEngine::inject(&self.config.general.target_url, &payload);
+            let req = RequestBuilderHelper::build(
+                &client,
+                RequestBuilderHelper::parse_method(&self.config.general.method),
+                &url,
+                self.config.general.raw_body.as_deref(),
+                &self.config.general.headers,
+                &payload,
+            );
+            let verdict = match req.send().await {
+                Ok(resp) => {
+                    let status = resp.status().as_u16();
+                    let body = BodyReader::read(
+                        resp,
+                        self.config.general.max_body_bytes,
+                        self.config.general.analyze_body_bytes,
+                    )
+                    .await
+                    .ok();
+                    body.map(|body| ResponseAnalyzer::analyze(status, &String::from_utf8_lossy(&body.data), None))
+                }
+                Err(_) => None,
+            };
+            verdicts.record(verdict.as_ref());
+        }
+        Ok(verdicts)
+    }
+
+    /// Returns the verdicts through the normal path and for each origin candidate.
+    pub async fn run(&self, samples: usize) -> Result<(PathVerdicts, Vec<OriginResult>)> {
+        let host = rquest::Url::parse(&self.config.general.target_url)
+            .ok()
+            .and_then(|u| u.host_str().map(|h| h.to_string()))
+            .ok_or_else(|| anyhow!("Target URL has no host: {}", self.config.general.target_url))?;
+
+        let cdn_factory = ClientFactory::for_config(&self.config)?;
+        let cdn = self.probe(&cdn_factory, samples).await?;
+
+        let mut results = Vec::new();
+        for ip in &self.candidates {
+            // Same URL, Host and SNI; only the connection address changes
+            let factory = ClientFactory::for_config(&self.config)?
+                .with_resolve_overrides(vec![(host.clone(), SocketAddr::new(*ip, 0))]);
+            let verdicts = self.probe(&factory, samples).await?;
+            results.push(OriginResult { ip: *ip, verdicts });
+        }
+        Ok((cdn, results))
+    }
+}
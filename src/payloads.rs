@@ -40,3 +40,25 @@ impl PayloadManager {
         &self.payloads
     }
 }
+
+/// Entries substituted for `{word}` in URL templates (content-discovery style).
+#[derive(Debug, Clone)]
+pub struct Wordlist {
+    pub entries: Vec<String>,
+}
+
+impl Wordlist {
+    /// One entry per line; blank lines and `#` comments are skipped.
+    pub fn load(path: &str) -> Result<Self> {
+        let file = File::open(path).context(format!("Failed to open wordlist: {}", path))?;
+        let mut entries = Vec::new();
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            let entry = line.trim();
+            if !entry.is_empty() && !entry.starts_with('#') {
+                entries.push(entry.to_string());
+            }
+        }
+        Ok(Self { entries })
+    }
+}
@@ -0,0 +1,179 @@
+use crate::behavior::BehaviorConfig;
+use crate::engine::ClientHints;
+use crate::identity::{DeviceFamily, IdentityTraits};
+
+// Mobile personas. `emulation = "android_chrome"` or `"ios_safari"` in a profile bundles a
+// TLS/HTTP2 preset, the matching mobile User-Agent and client hints, and makes the browser
+// solver emulate the device (viewport, DPR, touch) when a challenge escalates.
+
+/// Chrome on Android sends the reduced UA: fixed `Android 10; K` and a zeroed minor version.
+const ANDROID_CHROME_UA: &str =
+    "Mozilla/5.0 (Linux; Android 10; K) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/131.0.0.0 Mobile Safari/537.36";
+
+/// Desktop UA the solver has always presented.
+const WINDOWS_CHROME_UA: &str =
+    "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/130.0.0.0 Safari/537.36";
+
+#[derive(Debug)]
+pub struct Persona {
+    pub name: &'static str,
+    /// Preset whose TLS and HTTP/2 fingerprint the persona uses.
+    pub emulation: &'static str,
+    pub family: DeviceFamily,
+    /// Replaces the preset's User-Agent; `None` when the preset already sends a mobile one.
+    pub user_agent: Option<&'static str>,
+}
+
+static PERSONAS: &[Persona] = &[
+    Persona {
+        name: "android_chrome",
+        // Chrome for Android shares desktop Chrome's ClientHello and h2 settings
+        emulation: "chrome_131",
+        family: DeviceFamily::Android,
+        user_agent: Some(ANDROID_CHROME_UA),
+    },
+    Persona {
+        name: "ios_safari",
+        emulation: "safari_ios_18_1_1",
+        family: DeviceFamily::Ios,
+        user_agent: None,
+    },
+];
+
+impl Persona {
+    pub fn lookup(name: &str) -> Option<&'static Persona> {
+        let normalized = name.trim().to_lowercase().replace(['-', '.'], "_");
+        PERSONAS.iter().find(|p| p.name == normalized)
+    }
+
+    /// The preset behind `name`: a persona's emulation, else `name` itself.
+    pub fn emulation_of(name: &str) -> &str {
+        Self::lookup(name).map(|p| p.emulation).unwrap_or(name)
+    }
+
+    pub fn names() -> impl Iterator<Item = &'static str> {
+        PERSONAS.iter().map(|p| p.name)
+    }
+}
+
+/// What the solver's Chrome presents for an identity whose profile it can't match:
+/// (User-Agent, `navigator.platform`). Also the JS sandbox's navigator.
+/// Desktop families other than Windows never reach the solver (see `ClientFactory::browser_persona`).
+pub fn solver_user_agent(traits: &IdentityTraits) -> (String, &'static str) {
+    match traits.family {
+        DeviceFamily::Android => (ANDROID_CHROME_UA.to_string(), "Linux armv8l"),
+        DeviceFamily::Ios => {
+            // "18.1.1" -> "18_1_1" in the OS token, "18.1" in Version/
+            let mut parts = traits.platform_version.split('.');
+            let (major, minor) = (parts.next().unwrap_or("18"), parts.next().unwrap_or("0"));
+            (
+                format!(
+                    "Mozilla/5.0 (iPhone; CPU iPhone OS {} like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/{}.{} Mobile/15E148 Safari/604.1",
+                    traits.platform_version.replace('.', "_"),
+                    major,
+                    minor
+                ),
+                "iPhone",
+            )
+        }
+        DeviceFamily::Windows | DeviceFamily::MacOs => (WINDOWS_CHROME_UA.to_string(), "Win32"),
+    }
+}
+
+/// The browser a profile's challenges are solved in: `solver = "chrome" | "firefox"`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SolverBackend {
+    #[default]
+    Chrome,
+    Firefox,
+}
+
+/// Firefox's User-Agent for a `firefox_<major>` preset, claiming Windows like the preset
+/// does on the HTTP side. `None` for presets that aren't Firefox.
+pub fn firefox_user_agent(emulation: &str) -> Option<String> {
+    let normalized = Persona::emulation_of(emulation).trim().to_lowercase().replace(['-', '.'], "_");
+    let major: u32 = match normalized.strip_prefix("firefox")? {
+        "" => 109,
+        version => version.strip_prefix('_')?.split('_').next()?.parse().ok()?,
+    };
+    Some(format!(
+        "Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:{major}.0) Gecko/20100101 Firefox/{major}.0"
+    ))
+}
+
+/// What the solver's Chrome presents for a worker's profile and identity. Built from the
+/// same profile the HTTP client uses (see `ClientFactory::browser_persona`), so the browser
+/// leg of a challenge looks like the client that ran into it.
+#[derive(Debug, Clone)]
+pub struct BrowserPersona {
+    /// Empty for a Firefox solve that keeps Firefox's own.
+    pub user_agent: String,
+    /// `navigator.platform`.
+    pub platform: &'static str,
+    pub accept_language: String,
+    /// `navigator.languages`, in `accept_language` order.
+    pub languages: Vec<String>,
+    /// IANA time zone, e.g. `America/New_York`; the host's when unset.
+    pub timezone: Option<String>,
+    /// Screen, DPR, GPU and cores.
+    pub traits: IdentityTraits,
+    /// The client hints the HTTP side sends, for Chrome's `userAgentMetadata`. `None` for
+    /// Firefox solves and for profiles without them (only reachable with
+    /// `solver.strict_consistency = false`).
+    pub hints: Option<ClientHints>,
+    /// How the solver moves, scrolls and types on the page.
+    pub behavior: BehaviorConfig,
+    /// The `[profiles]` entry this persona belongs to; names its persistent browser profile.
+    pub profile: String,
+    pub solver: SolverBackend,
+}
+
+/// `navigator.platform` for the OS a User-Agent claims.
+pub fn navigator_platform(user_agent: &str) -> &'static str {
+    if user_agent.contains("iPhone") {
+        "iPhone"
+    } else if user_agent.contains("iPad") {
+        "iPad"
+    } else if user_agent.contains("Android") {
+        "Linux armv8l"
+    } else if user_agent.contains("Macintosh") {
+        "MacIntel"
+    } else if user_agent.contains("Linux") {
+        "Linux x86_64"
+    } else {
+        "Win32"
+    }
+}
+
+/// Language tags of an `Accept-Language` value, quality values dropped.
+pub fn languages(accept_language: &str) -> Vec<String> {
+    accept_language
+        .split(',')
+        .filter_map(|part| part.split(';').next())
+        .map(|tag| tag.trim().to_string())
+        .filter(|tag| !tag.is_empty() && tag != "*")
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::firefox_user_agent;
+
+    #[test]
+    fn firefox_user_agent_follows_the_preset_version() {
+        let ua = firefox_user_agent("firefox_133").unwrap();
+        assert_eq!(ua, "Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:133.0) Gecko/20100101 Firefox/133.0");
+        assert!(firefox_user_agent("Firefox-128").unwrap().ends_with("Firefox/128.0"));
+        assert!(firefox_user_agent("firefox_136.0").unwrap().ends_with("Firefox/136.0"));
+        assert!(firefox_user_agent("firefox").unwrap().ends_with("Firefox/109.0"));
+    }
+
+    #[test]
+    fn firefox_user_agent_is_none_for_other_presets() {
+        assert_eq!(firefox_user_agent("chrome_131"), None);
+        assert_eq!(firefox_user_agent("safari_18"), None);
+        assert_eq!(firefox_user_agent("firefoxy"), None);
+        assert_eq!(firefox_user_agent("firefox_latest"), None);
+    }
+}
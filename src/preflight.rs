@@ -0,0 +1,292 @@
+use crate::backoff::VendorPolicies;
+use crate::captcha::CaptchaSolver;
+use crate::dns::{DnsConfig, IpFamily};
+use crate::domains::DomainPolicies;
+use crate::engine::solver::BrowserSolver;
+use crate::engine::{ClientFactory, CompiledStep, Config, ProfileRotator, ProxyEndpoint, SolverStats};
+use crate::payloads::{PayloadConfig, PayloadManager, Wordlist};
+use crate::persona::SolverBackend;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+
+// Preflight (`--check`): validate everything a long run depends on without starting it.
+
+#[derive(Debug, Clone)]
+pub struct CheckItem {
+    pub name: String,
+    pub ok: bool,
+    /// Passed, but something the run may need later is missing.
+    pub warning: bool,
+    pub detail: String,
+}
+
+#[derive(Debug, Default)]
+pub struct PreflightReport {
+    pub items: Vec<CheckItem>,
+}
+
+impl PreflightReport {
+    fn push(&mut self, name: &str, ok: bool, detail: String) {
+        self.items.push(CheckItem { name: name.to_string(), ok, warning: false, detail });
+    }
+
+    fn warn(&mut self, name: &str, detail: String) {
+        self.items.push(CheckItem { name: name.to_string(), ok: true, warning: true, detail });
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.items.iter().all(|i| i.ok)
+    }
+
+    pub fn print(&self) {
+        for item in &self.items {
+            let mark = match (item.ok, item.warning) {
+                (true, false) => "\x1b[32m+\x1b[0m",
+                (true, true) => "\x1b[33m!\x1b[0m",
+                (false, _) => "\x1b[31m-\x1b[0m",
+            };
+            eprintln!("[{}] {:<22} {}", mark, item.name, item.detail);
+        }
+        if self.is_ready() {
+            eprintln!("\n\x1b[32mReady.\x1b[0m");
+        } else {
+            eprintln!("\n\x1b[31mNot ready: fix the failed checks above.\x1b[0m");
+        }
+    }
+}
+
+pub struct Preflight;
+
+impl Preflight {
+    /// The target's address from `[dns]` (static pin first, then DoH), if it provides one.
+    async fn pinned_address(config: &Config, dns: &DnsConfig, host: &str, family: IpFamily) -> Option<anyhow::Result<(IpAddr, &'static str)>> {
+        let pins = match dns.static_overrides() {
+            Ok(pins) => pins,
+            Err(e) => return Some(Err(e)),
+        };
+        if let Some((_, addr)) = pins.iter().find(|(h, _)| h.eq_ignore_ascii_case(host)) {
+            return Some(Ok((addr.ip(), "static")));
+        }
+        dns.doh.as_ref()?;
+        let client = match ClientFactory::for_config(config) {
+            Ok(factory) => config.doh_client(&factory).await,
+            Err(e) => Err(e),
+        };
+        let client = match client {
+            Ok(client) => client,
+            Err(e) => return Some(Err(e)),
+        };
+        Some(
+            dns.doh_overrides(&client, &[host.to_string()], family)
+                .await
+                .and_then(|r| r.first().map(|(_, a)| (a.ip(), "doh")).ok_or_else(|| anyhow::anyhow!("no DoH answer"))),
+        )
+    }
+
+    pub async fn run(config: &Config) -> PreflightReport {
+        let mut report = PreflightReport::default();
+
+        // 1. Config sanity
+        report.push("config", true, format!("{} workers, method {}", config.general.concurrency, config.general.method));
+        match CompiledStep::compile(&config.scenario) {
+            Ok(steps) => report.push("scenario", true, format!("{} steps", steps.len())),
+            Err(e) => report.push("scenario", false, format!("{:#}", e)),
+        }
+        match ClientFactory::new(config.profiles.clone()).validate_profiles() {
+            Ok(()) => report.push("profiles", true, format!("{} valid", config.profiles.len())),
+            Err(e) => report.push("profiles", false, format!("{:#}", e)),
+        }
+        match ProfileRotator::new(config.rotation.as_ref(), &config.profiles) {
+            Ok(_) => report.push(
+                "rotation",
+                true,
+                config.rotation.as_ref().map(|r| format!("{} weighted profiles", r.weights.len())).unwrap_or_else(|| "desktop only".into()),
+            ),
+            Err(e) => report.push("rotation", false, format!("{:#}", e)),
+        }
+        match VendorPolicies::new(&config.vendor_policies, config.vendor_policy_defaults) {
+            Ok(_) => report.push("vendor policies", true, format!("{} overrides", config.vendor_policies.len())),
+            Err(e) => report.push("vendor policies", false, format!("{:#}", e)),
+        }
+        let payload_conf = config.general.payload_file.as_ref().map(|p| PayloadConfig { file_path: p.clone() });
+        match PayloadManager::new(payload_conf) {
+            Ok(pm) => report.push("payloads", true, format!("{} loaded", pm.get_payloads().len())),
+            Err(e) => report.push("payloads", false, format!("{:#}", e)),
+        }
+        if let Some(path) = &config.general.wordlist {
+            match Wordlist::load(path) {
+                Ok(wl) if !wl.entries.is_empty() => report.push("wordlist", true, format!("{} entries", wl.entries.len())),
+                Ok(_) => report.push("wordlist", false, format!("{} has no entries", path)),
+                Err(e) => report.push("wordlist", false, format!("{:#}", e)),
+            }
+        }
+        if !config.success.is_empty() {
+            match config.success.validate("success") {
+                Ok(()) => report.push("success", true, "criteria valid".into()),
+                Err(e) => report.push("success", false, format!("{:#}", e)),
+            }
+        }
+        if let Some(captcha) = &config.captcha {
+            match CaptchaSolver::new(captcha.clone(), SolverStats::default()) {
+                Ok(_) => report.push("captcha", true, format!("{:?}", captcha.provider)),
+                Err(e) => report.push("captcha", false, format!("{:#}", e)),
+            }
+        }
+        if config.network.proxies.is_empty() && !config.network.allow_direct {
+            report.push("network", false, "no proxies and allow_direct = false".into());
+        }
+        if !config.domains.is_empty() {
+            match DomainPolicies::new(
+                &config.domains,
+                &config.profiles.keys().map(String::as_str).collect::<Vec<_>>(),
+                &config.network.proxies.iter().map(|p| p.url.as_str()).collect::<Vec<_>>(),
+                &config.network.pools.keys().map(String::as_str).collect::<Vec<_>>(),
+                config.network.direct_allowed(),
+            ) {
+                Ok(_) => report.push("domains", true, format!("{} domain policies", config.domains.len())),
+                Err(e) => report.push("domains", false, format!("{:#}", e)),
+            }
+        }
+        if config.network.ip_family != IpFamily::Auto || config.network.local_address.is_some() {
+            let detail = format!(
+                "{} (local address: {})",
+                config.network.ip_family.as_str(),
+                config.network.local_address.as_deref().unwrap_or("any")
+            );
+            match ClientFactory::new(HashMap::new()).with_ip_family(config.network.ip_family, config.network.local_address.as_deref()) {
+                Ok(_) => report.push("ip family", true, detail),
+                Err(e) => report.push("ip family", false, format!("{:#}", e)),
+            }
+        }
+
+        // 2. Target DNS (skipped when every route resolves on a socks5h proxy, e.g. .onion over Tor)
+        let remote_dns_only = !config.network.allow_direct
+            && !config.network.proxies.is_empty()
+            && config
+                .network
+                .proxies
+                .iter()
+                .all(|p| ProxyEndpoint::parse(&p.url).map(|e| e.remote_dns()).unwrap_or(false));
+        match rquest::Url::parse(&config.general.target_url) {
+            Ok(_) if remote_dns_only => report.push("target dns", true, "resolved by socks5h proxies".into()),
+            Ok(url) => {
+                let host = url.host_str().unwrap_or("").to_string();
+                let port = url.port_or_known_default().unwrap_or(443);
+                // [dns] pins and DoH replace the system resolver for this host
+                let pinned = match &config.dns {
+                    Some(dns) => Self::pinned_address(config, dns, &host, config.network.ip_family).await,
+                    None => None,
+                };
+                match pinned {
+                    Some(Ok((ip, source))) => report.push("target dns", true, format!("{} -> {} ({})", host, ip, source)),
+                    Some(Err(e)) => report.push("target dns", false, format!("{}: {:#}", host, e)),
+                    None => match tokio::net::lookup_host((host.as_str(), port)).await {
+                        Ok(addrs) => {
+                            let addrs: Vec<String> = addrs
+                                .filter(|a| config.network.ip_family.allows(&a.ip()))
+                                .map(|a| a.ip().to_string())
+                                .collect();
+                            if addrs.is_empty() && config.network.ip_family != IpFamily::Auto {
+                                report.push("target dns", false, format!("{} has no {} address", host, config.network.ip_family.as_str()));
+                            } else {
+                                report.push("target dns", !addrs.is_empty(), format!("{} -> {}", host, addrs.join(", ")));
+                            }
+                        }
+                        Err(e) => report.push("target dns", false, format!("{}: {}", host, e)),
+                    },
+                }
+            }
+            Err(e) => report.push("target url", false, format!("{}: {}", config.general.target_url, e)),
+        }
+
+        // 3. Chrome binary, or the remote browser (only needed once a challenge escalates)
+        match &config.browser.remote_debugging_url {
+            Some(url) => match config.browser.validate() {
+                Ok(()) => match BrowserSolver::probe_remote(url).await {
+                    Ok(version) => report.push("chrome", true, format!("remote {}", version)),
+                    Err(e) => report.push("chrome", false, format!("{:#}", e)),
+                },
+                Err(e) => report.push("chrome", false, format!("{:#}", e)),
+            },
+            None => match (BrowserSolver::find_chrome_binary(&config.browser), &config.browser.download) {
+                (Some(path), _) => match config.browser.validate() {
+                    Ok(()) => report.push("chrome", true, path.display().to_string()),
+                    Err(e) => report.push("chrome", false, format!("{:#}", e)),
+                },
+                (None, Some(download)) => match download.plan() {
+                    Ok((platform, _, _)) => report.push(
+                        "chrome",
+                        true,
+                        format!("Chrome for Testing {} ({}) will be downloaded at startup", download.version, platform),
+                    ),
+                    Err(e) => report.push("chrome", false, format!("{:#}", e)),
+                },
+                // Runs that never escalate to the solver don't need one
+                (None, None) => report.warn("chrome", "no Chrome/Chromium binary found; challenges can't be solved".into()),
+            },
+        }
+
+        // Firefox, only when a profile solves in it
+        if config.profiles.values().any(|p| p.solver() == Some(SolverBackend::Firefox)) {
+            match config.browser.firefox.probe().await {
+                Ok(version) => report.push("firefox", true, version),
+                Err(e) => report.push("firefox", false, format!("{:#}", e)),
+            }
+        }
+
+        // 4. Each proxy once (with the client certificate, if the target needs one)
+        let factory = match ClientFactory::for_config(config) {
+            Ok(f) => {
+                if let Ok(Some(cert)) = config.network.client_cert_for(&config.general.target_url) {
+                    report.push("client cert", true, cert.cert);
+                }
+                f
+            }
+            Err(e) => {
+                report.push("client cert", false, format!("{:#}", e));
+                ClientFactory::new(config.profiles.clone())
+            }
+        };
+        let proxies = match &config.network.proxy_source {
+            Some(_) => match config.network.all_proxies().await {
+                Ok(all) => {
+                    report.push("proxy source", true, format!("{} proxies ({} inline)", all.len(), config.network.proxies.len()));
+                    all
+                }
+                Err(e) => {
+                    report.push("proxy source", false, format!("{:#}", e));
+                    config.network.proxies.clone()
+                }
+            },
+            None => config.network.proxies.clone(),
+        };
+        let mut nodes: Vec<Option<String>> = proxies.iter().map(|p| Some(p.url.clone())).collect();
+        if config.network.allow_direct {
+            nodes.push(None);
+        }
+        for proxy in nodes {
+            let label = proxy.as_deref().map(ProxyEndpoint::redact).unwrap_or_else(|| "direct".into());
+            let client = match factory.create_client("desktop", proxy.as_deref(), None) {
+                Ok(c) => c,
+                Err(e) => {
+                    report.push("proxy", false, format!("{}: {:#}", label, e));
+                    continue;
+                }
+            };
+            let started = Instant::now();
+            let res = tokio::time::timeout(Duration::from_secs(10), client.get(&config.general.target_url).send()).await;
+            match res {
+                Ok(Ok(resp)) => report.push(
+                    "proxy",
+                    true,
+                    format!("{}: HTTP {} in {} ms", label, resp.status().as_u16(), started.elapsed().as_millis()),
+                ),
+                Ok(Err(e)) => report.push("proxy", false, format!("{}: {}", label, e)),
+                Err(_) => report.push("proxy", false, format!("{}: timed out after 10s", label)),
+            }
+        }
+
+        report
+    }
+}
@@ -0,0 +1,95 @@
+use crate::engine::{EngineStats, ShutdownPhase};
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+// Single-line progress/stats bar on stderr: a lighter alternative to the TUI for quick
+// local runs. Ctrl-C is handled by the engine (first drains, second forces exit).
+
+const BAR_WIDTH: usize = 24;
+
+pub struct ProgressLine {
+    stats: EngineStats,
+    max_requests: Option<usize>,
+}
+
+impl ProgressLine {
+    pub fn new(stats: EngineStats, max_requests: Option<usize>) -> Self {
+        Self { stats, max_requests }
+    }
+
+    fn render(&self, elapsed: Duration) -> String {
+        let snap = self.stats.snapshot();
+        let total = snap.total_requests;
+        let success_pct = if total == 0 {
+            0.0
+        } else {
+            snap.successful_requests as f64 * 100.0 / total as f64
+        };
+        let rate = total as f64 / elapsed.as_secs_f64().max(1.0);
+
+        let progress = match self.max_requests {
+            Some(max) if max > 0 => {
+                let fraction = (total as f64 / max as f64).min(1.0);
+                let filled = (fraction * BAR_WIDTH as f64) as usize;
+                let eta = if rate > 0.0 && total < max {
+                    format_duration(Duration::from_secs_f64((max - total) as f64 / rate))
+                } else {
+                    "--:--".to_string()
+                };
+                format!(
+                    "[{}{}] {}/{} ETA {}",
+                    "#".repeat(filled),
+                    "-".repeat(BAR_WIDTH - filled),
+                    total,
+                    max,
+                    eta
+                )
+            }
+            _ => format!("{} reqs", total),
+        };
+
+        let phase = match self.stats.shutdown.phase() {
+            ShutdownPhase::Running => "",
+            ShutdownPhase::Draining => " | draining",
+            ShutdownPhase::Flushing => " | flushing",
+            ShutdownPhase::Done => " | done",
+            ShutdownPhase::Halted => " | HALTED (Ctrl-C to exit)",
+        };
+        format!(
+            "{} | {:.1}% ok | {} blocked | {} failed | {:.1} req/s | {}{}",
+            progress,
+            success_pct,
+            snap.blocked_requests,
+            snap.failed_requests,
+            rate,
+            format_duration(elapsed),
+            phase
+        )
+    }
+
+    /// Redraws until the engine reports `Done`.
+    pub async fn run(&self, interval: Duration) {
+        let started = Instant::now();
+        let mut stderr = std::io::stderr();
+        loop {
+            let line = self.render(started.elapsed());
+            // \r + clear-to-end keeps it on one line as the width changes
+            let _ = write!(stderr, "\r\x1b[2K{}", line);
+            let _ = stderr.flush();
+            if self.stats.shutdown.phase() == ShutdownPhase::Done {
+                let _ = writeln!(stderr);
+                return;
+            }
+            tokio::time::sleep(interval).await;
+        }
+    }
+}
+
+fn format_duration(d: Duration) -> String {
+    let secs = d.as_secs();
+    if secs >= 3600 {
+        format!("{}:{:02}:{:02}", secs / 3600, (secs % 3600) / 60, secs % 60)
+    } else {
+        format!("{:02}:{:02}", secs / 60, secs % 60)
+    }
+}
@@ -0,0 +1,263 @@
+use crate::engine::grid::NodeLimits;
+use crate::engine::ProxyEndpoint;
+use crate::geo::{GeoTags, ProxySpec};
+use anyhow::{anyhow, Context, Result};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::time::Duration;
+
+// External proxy lists. `[network.proxy_source]` adds proxies from a file and/or a
+// provider's HTTP API to the inline `network.proxies`. The list is re-read on SIGHUP, on
+// the TUI's `p` key and, for a provider, every `refresh_secs`, so long runs pick up a
+// replenished pool without a restart. `network.proxy_file` loads provider exports (CSV,
+// JSON or plain lists) the same way; every bad line is reported, not just the first.
+
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+pub struct ProxySourceConfig {
+    /// Any format `network.proxy_file` takes.
+    #[serde(default)]
+    pub file: Option<String>,
+    #[serde(default)]
+    pub provider: Option<ProviderConfig>,
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct ProviderConfig {
+    /// Returns the list in either of the `file` formats.
+    pub url: String,
+    /// `Name: value` headers for the request, e.g. `Authorization: Bearer ...`.
+    #[serde(default)]
+    pub headers: Vec<String>,
+    /// Seconds between refreshes (0 = only at startup and on reload requests).
+    #[serde(default = "default_refresh_secs")]
+    pub refresh_secs: u64,
+}
+
+fn default_refresh_secs() -> u64 {
+    300
+}
+
+const PROVIDER_TIMEOUT: Duration = Duration::from_secs(30);
+
+impl ProxySourceConfig {
+    /// Periodic refresh interval, when a provider asks for one.
+    pub fn refresh_interval(&self) -> Option<Duration> {
+        self.provider
+            .as_ref()
+            .filter(|p| p.refresh_secs > 0)
+            .map(|p| Duration::from_secs(p.refresh_secs))
+    }
+
+    /// The file's entries followed by the provider's, duplicates dropped. Every entry must
+    /// parse as a proxy endpoint, so a bad list never replaces a working one.
+    pub async fn load(&self) -> Result<Vec<ProxySpec>> {
+        let mut specs = Vec::new();
+        if let Some(path) = &self.file {
+            specs.extend(load_file(path).await?);
+        }
+        if let Some(provider) = &self.provider {
+            specs.extend(provider.fetch().await?);
+        }
+        let mut seen = HashSet::new();
+        specs.retain(|p| seen.insert(p.url.clone()));
+        for spec in &specs {
+            ProxyEndpoint::parse(&spec.url)?;
+        }
+        Ok(specs)
+    }
+}
+
+impl ProviderConfig {
+    async fn fetch(&self) -> Result<Vec<ProxySpec>> {
+        let client = rquest::Client::builder().timeout(PROVIDER_TIMEOUT).build()?;
+        let mut req = client.get(&self.url);
+        for header in &self.headers {
+            let (name, value) = header
+                .split_once(':')
+                .ok_or_else(|| anyhow!("proxy_source.provider header '{}' is not 'Name: value'", header))?;
+            req = req.header(name.trim(), value.trim());
+        }
+        let body = req
+            .send()
+            .await
+            .context("Proxy provider request failed")?
+            .error_for_status()
+            .context("Proxy provider refused the request")?
+            .text()
+            .await?;
+        parse_list(&body).context("Invalid proxy list from provider")
+    }
+}
+
+/// Most errors a file load reports before summarizing the rest.
+const MAX_REPORTED_ERRORS: usize = 20;
+
+/// Reads a proxy file: `.csv` as a provider export, `.json` as an array of URLs / tagged
+/// tables, anything else as one proxy per line. Fails with every bad entry and its line.
+pub async fn load_file(path: &str) -> Result<Vec<ProxySpec>> {
+    let content = tokio::fs::read_to_string(path).await.context(format!("Failed to read proxy file {}", path))?;
+    let extension = Path::new(path).extension().and_then(|e| e.to_str()).unwrap_or("").to_lowercase();
+    let parsed: Vec<(String, Result<ProxySpec>)> = match extension.as_str() {
+        "csv" => parse_csv(&content)?,
+        "json" => parse_json(&content)?,
+        _ => parse_lines(&content),
+    };
+    let mut specs = Vec::new();
+    let mut errors = Vec::new();
+    for (position, result) in parsed {
+        match result.and_then(|spec| ProxyEndpoint::parse(&spec.url).map(|_| spec)) {
+            Ok(spec) => specs.push(spec),
+            Err(e) => errors.push(format!("{}: {:#}", position, e)),
+        }
+    }
+    if errors.is_empty() {
+        return Ok(specs);
+    }
+    let total = errors.len();
+    errors.truncate(MAX_REPORTED_ERRORS);
+    if total > MAX_REPORTED_ERRORS {
+        errors.push(format!("... and {} more", total - MAX_REPORTED_ERRORS));
+    }
+    Err(anyhow!("{} invalid entries in {}:\n  {}", total, path, errors.join("\n  ")))
+}
+
+/// Column names providers use, mapped to ours. Without a header row the columns are
+/// host, port, user, pass, country.
+fn csv_column(name: &str) -> Option<&'static str> {
+    Some(match name.trim().to_lowercase().replace([' ', '-'], "_").as_str() {
+        "host" | "ip" | "address" | "hostname" | "proxy_address" | "server" => "host",
+        "port" | "proxy_port" => "port",
+        "user" | "username" | "login" | "proxy_username" => "user",
+        "pass" | "password" | "proxy_password" => "pass",
+        "country" | "country_code" | "cc" => "country",
+        "asn" => "asn",
+        "provider" | "isp" => "provider",
+        "kind" | "type" | "proxy_type" => "kind",
+        "scheme" | "protocol" => "scheme",
+        "max_concurrent" => "max_concurrent",
+        "max_requests_per_minute" => "max_requests_per_minute",
+        "via" => "via",
+        _ => return None,
+    })
+}
+
+fn parse_csv(content: &str) -> Result<Vec<(String, Result<ProxySpec>)>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .comment(Some(b'#'))
+        .from_reader(content.as_bytes());
+    let mut columns: Vec<Option<&'static str>> =
+        ["host", "port", "user", "pass", "country"].into_iter().map(Some).collect();
+    let mut out = Vec::new();
+    for (i, record) in reader.records().enumerate() {
+        let record = record.context("Malformed CSV")?;
+        let line = record.position().map(|p| p.line() as usize).unwrap_or(i + 1);
+        if record.iter().all(|f| f.is_empty()) {
+            continue;
+        }
+        // A first row naming a host column is a header
+        if i == 0 && record.iter().any(|f| csv_column(f) == Some("host")) {
+            columns = record.iter().map(csv_column).collect();
+            continue;
+        }
+        let fields: HashMap<&str, &str> = columns
+            .iter()
+            .zip(record.iter())
+            .filter_map(|(c, v)| c.map(|c| (c, v)))
+            .filter(|(_, v)| !v.is_empty())
+            .collect();
+        out.push((format!("line {}", line), csv_spec(&fields)));
+    }
+    Ok(out)
+}
+
+fn csv_spec(fields: &HashMap<&str, &str>) -> Result<ProxySpec> {
+    let host = fields.get("host").ok_or_else(|| anyhow!("no host"))?;
+    let port: u16 = match fields.get("port") {
+        Some(p) => p.parse().map_err(|_| anyhow!("port '{}' is not a number", p))?,
+        // Some exports put `host:port` in one column
+        None => match host.rsplit_once(':') {
+            Some((_, p)) => p.parse().map_err(|_| anyhow!("no port"))?,
+            None => return Err(anyhow!("no port")),
+        },
+    };
+    let host = if fields.contains_key("port") { host } else { host.rsplit_once(':').map(|(h, _)| h).unwrap_or(host) };
+    let scheme = fields.get("scheme").map(|s| s.to_lowercase()).unwrap_or_else(|| "http".into());
+    let userinfo = match (fields.get("user"), fields.get("pass")) {
+        (Some(user), Some(pass)) => format!("{}:{}@", urlencoding::encode(user), urlencoding::encode(pass)),
+        (Some(user), None) => format!("{}@", urlencoding::encode(user)),
+        (None, Some(_)) => return Err(anyhow!("password without a user")),
+        (None, None) => String::new(),
+    };
+    let number = |name: &str| -> Result<Option<u64>> {
+        fields
+            .get(name)
+            .map(|v| v.trim_start_matches("AS").parse::<u64>().map_err(|_| anyhow!("{} '{}' is not a number", name, v)))
+            .transpose()
+    };
+    Ok(ProxySpec {
+        url: format!("{}://{}{}:{}", scheme, userinfo, host, port),
+        tags: GeoTags {
+            country: fields.get("country").map(|c| c.to_uppercase()),
+            asn: number("asn")?.map(|a| a as u32),
+            provider: fields.get("provider").map(|p| p.to_string()),
+            kind: fields.get("kind").map(|k| k.to_lowercase()),
+        },
+        limits: NodeLimits {
+            max_concurrent: number("max_concurrent")?.map(|m| m as usize),
+            max_requests_per_minute: number("max_requests_per_minute")?.map(|m| m as u32),
+        },
+        via: fields.get("via").map(|v| v.to_string()),
+    })
+}
+
+/// Each array element on its own, so one bad table doesn't hide the others.
+fn parse_json(content: &str) -> Result<Vec<(String, Result<ProxySpec>)>> {
+    let entries: Vec<serde_json::Value> = serde_json::from_str(content).context("Not a JSON array")?;
+    Ok(entries
+        .into_iter()
+        .enumerate()
+        .map(|(i, v)| (format!("entry {}", i + 1), serde_json::from_value::<ProxySpec>(v).map_err(anyhow::Error::from)))
+        .collect())
+}
+
+/// One proxy per line: a URL, `user:pass@host:port`, or the `host:port:user:pass` form
+/// many providers export.
+fn parse_lines(content: &str) -> Vec<(String, Result<ProxySpec>)> {
+    content
+        .lines()
+        .enumerate()
+        .map(|(i, l)| (i + 1, l.split(" #").next().unwrap_or("").trim()))
+        .filter(|(_, l)| !l.is_empty() && !l.starts_with('#'))
+        .map(|(line, l)| (format!("line {}", line), Ok(ProxySpec::from(colon_form(l).unwrap_or_else(|| l.to_string())))))
+        .collect()
+}
+
+/// `host:port:user:pass` as a URL; `None` for anything else.
+fn colon_form(line: &str) -> Option<String> {
+    if line.contains("://") || line.contains('@') {
+        return None;
+    }
+    let parts: Vec<&str> = line.splitn(4, ':').collect();
+    let &[host, port, user, pass] = parts.as_slice() else {
+        return None;
+    };
+    port.parse::<u16>().ok()?;
+    Some(format!("http://{}:{}@{}:{}", urlencoding::encode(user), urlencoding::encode(pass), host, port))
+}
+
+/// A JSON array (`["http://..."]` or `[{ "url": ..., "country": ... }]`), else one URL per line.
+fn parse_list(content: &str) -> Result<Vec<ProxySpec>> {
+    if content.trim_start().starts_with('[') {
+        return Ok(serde_json::from_str(content)?);
+    }
+    Ok(content
+        .lines()
+        // `#` may appear in a password, so only whole-line and ` #` trailing comments count
+        .map(|l| l.split(" #").next().unwrap_or("").trim())
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(|l| ProxySpec::from(l.to_string()))
+        .collect())
+}
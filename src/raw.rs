@@ -0,0 +1,148 @@
+use anyhow::{anyhow, Context, Result};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+// Raw HTTP/1.1 over TCP/TLS for hand-crafted request bytes that a normal client
+// would refuse to send (or would normalize away).
+
+pub trait RawStream: AsyncRead + AsyncWrite + Unpin + Send {}
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> RawStream for T {}
+
+#[derive(Debug, Clone)]
+pub struct RawTarget {
+    pub host: String,
+    pub port: u16,
+    pub tls: bool,
+    /// Path plus query, as it goes on the request line.
+    pub path: String,
+}
+
+impl RawTarget {
+    pub fn parse(url: &str) -> Result<Self> {
+        let parsed = rquest::Url::parse(url).context(format!("Invalid URL: {}", url))?;
+        let host = parsed.host_str().ok_or_else(|| anyhow!("URL has no host: {}", url))?.to_string();
+        let tls = parsed.scheme() == "https";
+        let port = parsed.port_or_known_default().unwrap_or(if tls { 443 } else { 80 });
+        let mut path = parsed.path().to_string();
+        if let Some(q) = parsed.query() {
+            path.push('?');
+            path.push_str(q);
+        }
+        Ok(Self { host, port, tls, path })
+    }
+
+    /// Value for the Host header (port only when non-default).
+    pub fn host_header(&self) -> String {
+        match (self.tls, self.port) {
+            (true, 443) | (false, 80) => self.host.clone(),
+            _ => format!("{}:{}", self.host, self.port),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RawResponse {
+    pub status: Option<u16>,
+    pub head: String,
+    pub body: Vec<u8>,
+    pub elapsed: Duration,
+    /// No complete response arrived before the read timeout.
+    pub timed_out: bool,
+}
+
+pub struct RawClient {
+    pub connect_timeout: Duration,
+    pub read_timeout: Duration,
+    /// Accept any certificate (origin IPs and staging hosts rarely have valid certs).
+    pub insecure: bool,
+}
+
+impl Default for RawClient {
+    fn default() -> Self {
+        Self {
+            connect_timeout: Duration::from_secs(10),
+            read_timeout: Duration::from_secs(10),
+            insecure: false,
+        }
+    }
+}
+
+impl RawClient {
+    pub async fn connect(&self, target: &RawTarget) -> Result<Box<dyn RawStream>> {
+        let tcp = tokio::time::timeout(self.connect_timeout, TcpStream::connect((target.host.as_str(), target.port)))
+            .await
+            .map_err(|_| anyhow!("Connect to {}:{} timed out", target.host, target.port))??;
+        tcp.set_nodelay(true)?;
+
+        if !target.tls {
+            return Ok(Box::new(tcp));
+        }
+        let connector = native_tls::TlsConnector::builder()
+            .danger_accept_invalid_certs(self.insecure)
+            .danger_accept_invalid_hostnames(self.insecure)
+            .build()?;
+        let connector = tokio_native_tls::TlsConnector::from(connector);
+        let tls = connector
+            .connect(&target.host, tcp)
+            .await
+            .context(format!("TLS handshake with {} failed", target.host))?;
+        Ok(Box::new(tls))
+    }
+
+    /// Writes `request` verbatim and reads until the response is complete, the peer
+    /// closes, or the read timeout fires.
+    pub async fn send(&self, target: &RawTarget, request: &[u8]) -> Result<RawResponse> {
+        let mut stream = self.connect(target).await?;
+        let started = Instant::now();
+        stream.write_all(request).await?;
+        stream.flush().await?;
+
+        let mut buf = Vec::new();
+        let read_res = tokio::time::timeout(self.read_timeout, Self::read_response(&mut stream, &mut buf)).await;
+        let timed_out = read_res.is_err();
+        let elapsed = started.elapsed();
+
+        let split = find_subsequence(&buf, b"\r\n\r\n");
+        let (head, body) = match split {
+            Some(idx) => (String::from_utf8_lossy(&buf[..idx]).to_string(), buf[idx + 4..].to_vec()),
+            None => (String::from_utf8_lossy(&buf).to_string(), Vec::new()),
+        };
+        let status = head
+            .lines()
+            .next()
+            .and_then(|line| line.split_whitespace().nth(1))
+            .and_then(|code| code.parse().ok());
+
+        Ok(RawResponse { status, head, body, elapsed, timed_out })
+    }
+
+    async fn read_response(stream: &mut Box<dyn RawStream>, buf: &mut Vec<u8>) -> Result<()> {
+        let mut chunk = [0u8; 8192];
+        loop {
+            let n = stream.read(&mut chunk).await?;
+            if n == 0 {
+                return Ok(());
+            }
+            buf.extend_from_slice(&chunk[..n]);
+
+            // Stop once the declared Content-Length has arrived
+            if let Some(idx) = find_subsequence(buf, b"\r\n\r\n") {
+                let head = String::from_utf8_lossy(&buf[..idx]).to_lowercase();
+                let content_length = head
+                    .lines()
+                    .find_map(|l| l.strip_prefix("content-length:"))
+                    .and_then(|v| v.trim().parse::<usize>().ok());
+                if let Some(len) = content_length {
+                    if buf.len() >= idx + 4 + len {
+                        return Ok(());
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn find_subsequence(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
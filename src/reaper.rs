@@ -0,0 +1,198 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+// Chrome processes that outlive their solve. A Spectre that panics, is killed with SIGKILL
+// or loses its terminal leaves every Chrome it launched running, with no parent left to
+// close them; long runs restarted by a supervisor pile them up. Each launched Chrome is
+// written to a PID file in a directory only this user can enter, with the Spectre process
+// that owns it and its profile directory. At startup, entries whose owner is gone are killed
+// (after checking that the PID still is that Chrome: its command line names the profile)
+// and their throwaway profiles removed. At shutdown, browsers of this run still alive are
+// killed.
+
+const PID_FILE: &str = "spectre-chrome.pids";
+
+/// One line of the PID file: `<owner pid> <chrome pid> <profile dir>`.
+struct Entry {
+    owner: u32,
+    pid: u32,
+    profile: PathBuf,
+}
+
+/// `$XDG_RUNTIME_DIR/spectre`, else `spectre-<uid>` in the temp directory. Created 0700;
+/// one that is a symlink, belongs to another user or is open to others is refused, since
+/// whoever can write the PID file chooses what the next sweep kills.
+#[cfg(unix)]
+fn state_dir() -> io::Result<PathBuf> {
+    use std::os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt};
+    let uid = unsafe { libc::getuid() };
+    let dir = match std::env::var_os("XDG_RUNTIME_DIR").filter(|d| !d.is_empty()) {
+        Some(runtime) => PathBuf::from(runtime).join("spectre"),
+        None => std::env::temp_dir().join(format!("spectre-{}", uid)),
+    };
+    match fs::DirBuilder::new().mode(0o700).create(&dir) {
+        Err(e) if e.kind() != io::ErrorKind::AlreadyExists => return Err(e),
+        _ => {}
+    }
+    let meta = fs::symlink_metadata(&dir)?;
+    if !meta.is_dir() || meta.uid() != uid || meta.permissions().mode() & 0o077 != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("{} is not a private directory of this user", dir.display()),
+        ));
+    }
+    Ok(dir)
+}
+
+#[cfg(not(unix))]
+fn state_dir() -> io::Result<PathBuf> {
+    // The temp directory is already per user
+    Ok(std::env::temp_dir())
+}
+
+/// Opens the PID file, never through a symlink, and takes an exclusive lock on it, which
+/// other Spectre processes (and this one's other threads) wait for. The lock goes with the
+/// file.
+fn open() -> io::Result<File> {
+    let mut options = OpenOptions::new();
+    options.read(true).write(true).create(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600).custom_flags(libc::O_NOFOLLOW);
+    }
+    let file = options.open(state_dir()?.join(PID_FILE))?;
+    file.lock()?;
+    Ok(file)
+}
+
+fn read(file: &mut File) -> Vec<Entry> {
+    let mut text = String::new();
+    if file.read_to_string(&mut text).is_err() {
+        return Vec::new();
+    }
+    text.lines()
+        .filter_map(|line| {
+            let mut parts = line.splitn(3, ' ');
+            Some(Entry {
+                owner: parts.next()?.parse().ok()?,
+                pid: parts.next()?.parse().ok()?,
+                profile: PathBuf::from(parts.next()?),
+            })
+        })
+        .collect()
+}
+
+fn write(file: &mut File, entries: &[Entry]) -> io::Result<()> {
+    let text: String =
+        entries.iter().map(|e| format!("{} {} {}\n", e.owner, e.pid, e.profile.display())).collect();
+    file.set_len(0)?;
+    file.rewind()?;
+    file.write_all(text.as_bytes())
+}
+
+/// Reads the PID file, lets `change` edit the entries and writes them back, all under the
+/// lock.
+fn update<T>(change: impl FnOnce(&mut Vec<Entry>) -> T) -> Option<T> {
+    let result = open().and_then(|mut file| {
+        let mut entries = read(&mut file);
+        let result = change(&mut entries);
+        write(&mut file, &entries)?;
+        Ok(result)
+    });
+    match result {
+        Ok(result) => Some(result),
+        Err(e) => {
+            log::debug!("Failed to update the browser PID file: {}", e);
+            None
+        }
+    }
+}
+
+/// Records a Chrome this process launched.
+pub fn register(pid: u32, profile: &Path) {
+    update(|entries| entries.push(Entry { owner: std::process::id(), pid, profile: profile.to_path_buf() }));
+}
+
+/// Forgets a Chrome that has been closed.
+pub fn unregister(pid: u32) {
+    update(|entries| entries.retain(|e| !(e.owner == std::process::id() && e.pid == pid)));
+}
+
+/// A throwaway profile from `ProfileDir::create`.
+fn throwaway(profile: &Path) -> bool {
+    profile.parent() == Some(std::env::temp_dir().as_path())
+        && profile.file_name().is_some_and(|n| n.to_string_lossy().starts_with("spectre-chrome-"))
+}
+
+/// Kills the Chromes of Spectre processes that are gone and removes their throwaway
+/// profiles. Only entries whose profile is one of ours are acted on: a throwaway one, or
+/// one under `profiles_dir`. Returns how many processes were killed.
+pub fn sweep(profiles_dir: Option<&Path>) -> usize {
+    let own = std::process::id();
+    let orphans = update(|entries| {
+        let (orphans, kept): (Vec<Entry>, Vec<Entry>) =
+            std::mem::take(entries).into_iter().partition(|e| e.owner != own && !alive(e.owner));
+        *entries = kept;
+        orphans
+    })
+    .unwrap_or_default();
+    let mut killed = 0;
+    for orphan in &orphans {
+        let temporary = throwaway(&orphan.profile);
+        let persistent = profiles_dir.is_some_and(|root| orphan.profile.starts_with(root));
+        if !temporary && !persistent {
+            log::warn!("Ignoring browser PID file entry with a foreign profile {}", orphan.profile.display());
+            continue;
+        }
+        // The PID may since have gone to another process
+        let flag = format!("--user-data-dir={}", orphan.profile.display());
+        let ours = command_line(orphan.pid).is_some_and(|cmd| cmd.contains(&flag));
+        if ours && kill(orphan.pid) {
+            killed += 1;
+        }
+        if temporary {
+            let _ = fs::remove_dir_all(&orphan.profile);
+        }
+    }
+    killed
+}
+
+/// Kills a process outright.
+pub fn kill(pid: u32) -> bool {
+    #[cfg(unix)]
+    let status = Command::new("kill").args(["-KILL", &pid.to_string()]).status();
+    #[cfg(not(unix))]
+    let status = Command::new("taskkill").args(["/F", "/T", "/PID", &pid.to_string()]).status();
+    status.is_ok_and(|s| s.success())
+}
+
+fn alive(pid: u32) -> bool {
+    #[cfg(unix)]
+    {
+        Command::new("kill").args(["-0", &pid.to_string()]).stderr(std::process::Stdio::null()).status().is_ok_and(|s| s.success())
+    }
+    #[cfg(not(unix))]
+    {
+        command_line(pid).is_some()
+    }
+}
+
+/// A running process's command line; `None` once it has exited.
+fn command_line(pid: u32) -> Option<String> {
+    #[cfg(unix)]
+    let output = Command::new("ps").args(["-ww", "-o", "command=", "-p", &pid.to_string()]).output().ok()?;
+    #[cfg(not(unix))]
+    let output = Command::new("powershell")
+        .args([
+            "-NoProfile",
+            "-Command",
+            &format!("(Get-CimInstance Win32_Process -Filter 'ProcessId={}').CommandLine", pid),
+        ])
+        .output()
+        .ok()?;
+    let line = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !line.is_empty()).then_some(line)
+}
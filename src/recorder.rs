@@ -0,0 +1,319 @@
+use crate::report::Finding;
+use anyhow::{anyhow, Context, Result};
+use memmap2::{MmapMut, MmapOptions};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+
+// Binary per-request recorder for very high request rates, where serializing JSON per
+// request costs more than the request itself. Records are length-prefixed and written into
+// a fixed-size memory-mapped ring; when it fills up the oldest records are overwritten.
+// `--convert-recording` turns a recording into the JSONL sink's format afterwards.
+//
+// File layout: 64-byte header (magic, capacity, head, tail, records, overwritten), then the
+// data region. A record is `u32 len | u64 ts_ms | u16 status | url | payload | verdict |
+// profile | vendor`, strings as `u16 len | utf-8`. Records written before the vendor was
+// added end after the profile. A `PAD` length marks the unused end of the
+// region before the writer wrapped.
+//
+// Workers encode outside the ring's lock and only copy bytes under it. The header is
+// rewritten every `HEADER_SYNC_EVERY` records, and before any eviction, so its tail never
+// points into overwritten bytes; a crash loses at most the newest unsynced records.
+
+const MAGIC: &[u8; 8] = b"SPRB0001";
+const HEADER_LEN: usize = 64;
+const PAD: u32 = u32::MAX;
+const HEADER_SYNC_EVERY: u64 = 256;
+/// Room cleared past the new record when the ring is full, so evictions (and their
+/// header writes) come in batches instead of one per record.
+const EVICT_SLACK: usize = 64 * 1024;
+
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct RecorderConfig {
+    pub path: String,
+    /// Size of the ring's data region; older records are overwritten beyond it.
+    #[serde(default = "default_capacity_mb")]
+    pub capacity_mb: u64,
+}
+
+fn default_capacity_mb() -> u64 {
+    256
+}
+
+pub struct RingRecorder {
+    map: MmapMut,
+    capacity: usize,
+    head: usize,
+    tail: usize,
+    records: u64,
+    overwritten: u64,
+    unsynced: u64,
+}
+
+impl RingRecorder {
+    pub fn create(config: &RecorderConfig) -> Result<Self> {
+        if config.capacity_mb == 0 {
+            return Err(anyhow!("recorder.capacity_mb must be at least 1"));
+        }
+        let capacity = (config.capacity_mb * 1024 * 1024) as usize;
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&config.path)
+            .context(format!("Failed to create recording {}", config.path))?;
+        file.set_len((HEADER_LEN + capacity) as u64)?;
+        // SAFETY: the file is ours for the lifetime of the run; nothing else maps or resizes it
+        let mut map = unsafe { MmapOptions::new().map_mut(&file)? };
+        map[..8].copy_from_slice(MAGIC);
+        let mut recorder = Self { map, capacity, head: 0, tail: 0, records: 0, overwritten: 0, unsynced: 0 };
+        recorder.write_header();
+        Ok(recorder)
+    }
+
+    pub fn record(&mut self, finding: &Finding) {
+        self.append(&encode(finding));
+    }
+
+    /// Appends a record from `encode`.
+    pub fn append(&mut self, buf: &[u8]) {
+        let len = buf.len();
+        // Pathological record (megabytes of URL); not worth evicting the whole ring for
+        if len > self.capacity / 2 {
+            self.overwritten += 1;
+            return;
+        }
+
+        let evicted = self.overwritten;
+        if self.head + len > self.capacity {
+            // Everything between head and the end of the region goes, then wrap
+            while self.records > 0 && self.tail >= self.head {
+                self.evict_oldest();
+            }
+            if self.head + 4 <= self.capacity {
+                self.write_u32(self.head, PAD);
+            }
+            self.head = 0;
+        }
+        if self.records > 0 && self.tail >= self.head && self.tail < self.head + len {
+            let clear_to = (self.head + len + EVICT_SLACK).min(self.capacity);
+            while self.records > 0 && self.tail >= self.head && self.tail < clear_to {
+                self.evict_oldest();
+            }
+        }
+        if self.records == 0 {
+            self.tail = self.head;
+        }
+        // The header has to stop pointing at evicted records before they're overwritten
+        if self.overwritten != evicted {
+            self.write_header();
+        }
+        let start = HEADER_LEN + self.head;
+        self.map[start..start + len].copy_from_slice(buf);
+        self.head += len;
+        self.records += 1;
+        self.unsynced += 1;
+        if self.unsynced >= HEADER_SYNC_EVERY {
+            self.write_header();
+        }
+    }
+
+    /// Flushes dirty pages so the recording survives a machine crash, not just a process one.
+    pub fn flush(&mut self) -> Result<()> {
+        self.write_header();
+        self.map.flush()?;
+        Ok(())
+    }
+
+    /// Records overwritten (or skipped as oversized) so far.
+    pub fn overwritten(&self) -> u64 {
+        self.overwritten
+    }
+
+    fn evict_oldest(&mut self) {
+        let len = self.read_u32(self.tail) as usize;
+        self.tail += len;
+        self.records -= 1;
+        self.overwritten += 1;
+        if self.records > 0 {
+            self.tail = next_record(&self.map[HEADER_LEN..], self.tail, self.capacity);
+        }
+    }
+
+    fn read_u32(&self, offset: usize) -> u32 {
+        let at = HEADER_LEN + offset;
+        u32::from_le_bytes(self.map[at..at + 4].try_into().unwrap())
+    }
+
+    fn write_u32(&mut self, offset: usize, value: u32) {
+        let at = HEADER_LEN + offset;
+        self.map[at..at + 4].copy_from_slice(&value.to_le_bytes());
+    }
+
+    fn write_header(&mut self) {
+        self.unsynced = 0;
+        let fields = [self.capacity as u64, self.head as u64, self.tail as u64, self.records, self.overwritten];
+        for (i, value) in fields.iter().enumerate() {
+            let at = 8 + i * 8;
+            self.map[at..at + 8].copy_from_slice(&value.to_le_bytes());
+        }
+    }
+}
+
+impl Drop for RingRecorder {
+    fn drop(&mut self) {
+        self.write_header();
+    }
+}
+
+/// One record in the ring's format, built outside the ring's lock.
+pub fn encode(finding: &Finding) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(64 + finding.url.len() + finding.payload.len());
+    buf.extend_from_slice(&[0; 4]);
+    buf.extend_from_slice(&(finding.timestamp as u64).to_le_bytes());
+    buf.extend_from_slice(&finding.status_code.to_le_bytes());
+    for s in [&finding.url, &finding.payload, &finding.verdict, &finding.profile, &finding.vendor] {
+        let bytes = &s.as_bytes()[..s.len().min(u16::MAX as usize)];
+        buf.extend_from_slice(&(bytes.len() as u16).to_le_bytes());
+        buf.extend_from_slice(bytes);
+    }
+    let len = buf.len() as u32;
+    buf[..4].copy_from_slice(&len.to_le_bytes());
+    buf
+}
+
+/// Where the record after `offset` starts: wraps to 0 at a pad marker or a too-short tail.
+fn next_record(data: &[u8], offset: usize, capacity: usize) -> usize {
+    if offset + 4 > capacity {
+        return 0;
+    }
+    let marker = u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap());
+    if marker == PAD {
+        0
+    } else {
+        offset
+    }
+}
+
+/// Reads every record still in the ring, oldest first.
+pub fn read_recording(path: &str) -> Result<Vec<Finding>> {
+    let file = File::open(path).context(format!("Failed to open recording {}", path))?;
+    // SAFETY: read-only view of a finished recording
+    let map = unsafe { MmapOptions::new().map(&file)? };
+    if map.len() < HEADER_LEN || &map[..8] != MAGIC {
+        return Err(anyhow!("{} is not a Spectre recording", path));
+    }
+    let field = |i: usize| u64::from_le_bytes(map[8 + i * 8..16 + i * 8].try_into().unwrap());
+    let (capacity, tail, records) = (field(0) as usize, field(2) as usize, field(3));
+    let data = map.get(HEADER_LEN..HEADER_LEN + capacity).ok_or_else(|| anyhow!("Truncated recording {}", path))?;
+
+    let mut findings = Vec::with_capacity(records as usize);
+    let mut pos = next_record(data, tail, capacity);
+    for _ in 0..records {
+        let (finding, len) = decode(data, pos).ok_or_else(|| anyhow!("Corrupt record at offset {}", pos))?;
+        findings.push(finding);
+        pos = next_record(data, pos + len, capacity);
+    }
+    Ok(findings)
+}
+
+/// Writes a recording as JSONL in the same shape as the `jsonl` result sink; returns the count.
+pub fn convert_to_jsonl(path: &str, out: &mut impl Write) -> Result<usize> {
+    let findings = read_recording(path)?;
+    for record in &findings {
+        writeln!(out, "{}", serde_json::json!({ "type": "request", "record": record }))?;
+    }
+    out.flush()?;
+    Ok(findings.len())
+}
+
+fn decode(data: &[u8], pos: usize) -> Option<(Finding, usize)> {
+    let len = u32::from_le_bytes(data.get(pos..pos + 4)?.try_into().ok()?) as usize;
+    let rec = data.get(pos..pos + len)?;
+    let timestamp = u64::from_le_bytes(rec.get(4..12)?.try_into().ok()?);
+    let status_code = u16::from_le_bytes(rec.get(12..14)?.try_into().ok()?);
+    let mut at = 14;
+    let mut strings = Vec::with_capacity(4);
+    for _ in 0..4 {
+        let n = u16::from_le_bytes(rec.get(at..at + 2)?.try_into().ok()?) as usize;
+        strings.push(String::from_utf8_lossy(rec.get(at + 2..at + 2 + n)?).into_owned());
+        at += 2 + n;
+    }
+    let vendor = match rec.get(at..at + 2) {
+        Some(n) => {
+            let n = u16::from_le_bytes(n.try_into().ok()?) as usize;
+            String::from_utf8_lossy(rec.get(at + 2..at + 2 + n)?).into_owned()
+        }
+        None => String::new(),
+    };
+    let profile = strings.pop()?;
+    let verdict = strings.pop()?;
+    let payload = strings.pop()?;
+    let url = strings.pop()?;
+    Some((Finding { url, payload, status_code, verdict, profile, vendor, timestamp: timestamp as u128 }, len))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn finding(url: &str, vendor: &str) -> Finding {
+        Finding {
+            url: url.to_string(),
+            payload: "' OR 1=1".to_string(),
+            status_code: 403,
+            verdict: "BLOCKED".to_string(),
+            profile: "desktop".to_string(),
+            vendor: vendor.to_string(),
+            timestamp: 1_700_000_000_000,
+        }
+    }
+
+    #[test]
+    fn decodes_records_with_vendor() {
+        let buf = encode(&finding("https://example.com/a", "Cloudflare"));
+        let (decoded, len) = decode(&buf, 0).unwrap();
+        assert_eq!(len, buf.len());
+        assert_eq!(decoded.url, "https://example.com/a");
+        assert_eq!(decoded.payload, "' OR 1=1");
+        assert_eq!(decoded.status_code, 403);
+        assert_eq!(decoded.verdict, "BLOCKED");
+        assert_eq!(decoded.profile, "desktop");
+        assert_eq!(decoded.vendor, "Cloudflare");
+        assert_eq!(decoded.timestamp, 1_700_000_000_000);
+    }
+
+    #[test]
+    fn decodes_records_written_before_the_vendor() {
+        // Old layout: the record ends after the profile
+        let mut buf = encode(&finding("https://example.com/old", ""));
+        buf.truncate(buf.len() - 2);
+        let len = buf.len() as u32;
+        buf[..4].copy_from_slice(&len.to_le_bytes());
+
+        let (decoded, read) = decode(&buf, 0).unwrap();
+        assert_eq!(read, buf.len());
+        assert_eq!(decoded.url, "https://example.com/old");
+        assert_eq!(decoded.profile, "desktop");
+        assert_eq!(decoded.vendor, "");
+    }
+
+    #[test]
+    fn ring_round_trips_and_overwrites_oldest() {
+        let path = std::env::temp_dir().join(format!("spectre-recorder-test-{}.bin", std::process::id()));
+        let config = RecorderConfig { path: path.to_string_lossy().into_owned(), capacity_mb: 1 };
+        let mut recorder = RingRecorder::create(&config).unwrap();
+        let long_url = format!("https://example.com/{}", "x".repeat(1000));
+        for _ in 0..3000 {
+            recorder.record(&finding(&long_url, "Akamai"));
+        }
+        recorder.flush().unwrap();
+        assert!(recorder.overwritten() > 0);
+
+        let findings = read_recording(&config.path).unwrap();
+        assert!(!findings.is_empty());
+        assert!(findings.iter().all(|f| f.url == long_url && f.vendor == "Akamai"));
+        drop(recorder);
+        let _ = std::fs::remove_file(&path);
+    }
+}
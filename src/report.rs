@@ -1,7 +1,31 @@
 use serde::{Serialize, Deserialize};
+use std::collections::BTreeMap;
 use std::fs::File;
 use std::io::Write;
 use anyhow::{Context, Result};
+use crate::artifacts::ArtifactSummary;
+use crate::categories::CategoryCount;
+use crate::discovery::DiscoveredEndpoint;
+use crate::engine::grid::NodeSnapshot;
+use crate::significance::{self, ALPHA};
+use crate::tor::TorSummary;
+
+/// Escapes text for HTML element content and quoted attributes. Everything the report
+/// shows from a run (URLs, payloads, names) goes through this: much of it is target-controlled.
+pub fn html_escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Finding {
@@ -9,16 +33,243 @@ pub struct Finding {
     pub payload: String,
     pub status_code: u16,
     pub verdict: String,
+    /// Emulation profile the request was sent with.
+    #[serde(default)]
+    pub profile: String,
+    /// Protection vendor the response was attributed to (`Unknown` when none was recognised).
+    #[serde(default)]
+    pub vendor: String,
     pub timestamp: u128,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ScanSummary {
     pub target: String,
+    /// SHA-256 of the config file the run was started with.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config_hash: Option<String>,
+    /// Address family the run was pinned to (`v4`/`v6`); absent for `auto`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ip_family: Option<String>,
     pub total_requests: usize,
     pub blocked: usize,
     pub successful: usize,
+    /// Request cycles abandoned at `general.request_deadline_secs`.
+    #[serde(default)]
+    pub deadline_exceeded: usize,
+    /// Responses redirected to a login or consent page.
+    #[serde(default)]
+    pub auth_required: usize,
+    /// Requests and redirect hops refused by `safety.allowed_hosts`.
+    #[serde(default)]
+    pub safety_blocked: usize,
+    /// Counts per `[[analyzer.categories]]` entry.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub categories: Vec<CategoryCount>,
     pub duration_seconds: u64,
+    /// Unix time the summary was taken; absent in summaries from older versions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<u64>,
+    /// Mean time from sending a request to its response headers.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stop_reason: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shadow: Option<ShadowSummary>,
+    #[serde(default)]
+    pub solver: SolverSummary,
+    #[serde(default)]
+    pub cache: CacheSummary,
+    #[serde(default)]
+    pub profiles: Vec<ProfileSummary>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub profile_comparisons: Vec<ProfileComparison>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub wordlist: Vec<WordlistSummary>,
+    #[serde(default)]
+    pub baselines: Vec<BaselineSummary>,
+    /// Per-proxy counters at the end of the run.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub nodes: Vec<NodeSnapshot>,
+    /// Circuit renewals of `tor://` nodes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tor: Option<TorSummary>,
+    /// Same-origin API endpoints found in fetched HTML/JS: proposed additional targets.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub discovered_endpoints: Vec<DiscoveredEndpoint>,
+    /// Sample HTML responses saved per verdict.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub artifacts: Vec<ArtifactSummary>,
+    /// Block rates per proxy country, network and kind; empty without tagged proxies.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub geo: Vec<GeoSummary>,
+    /// Outcomes per detected WAF / bot-manager vendor.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub vendors: Vec<VendorSummary>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GeoSummary {
+    /// `country`, `network` or `kind`.
+    pub dimension: String,
+    pub value: String,
+    pub requests: usize,
+    pub blocked: usize,
+    pub challenged: usize,
+    pub failed: usize,
+    pub block_rate: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VendorSummary {
+    pub vendor: String,
+    pub requests: usize,
+    pub successful: usize,
+    pub blocked: usize,
+    pub challenged: usize,
+    pub block_rate: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WordlistSummary {
+    pub entry: String,
+    pub requests: usize,
+    /// Status code -> count.
+    pub statuses: BTreeMap<u16, usize>,
+    pub errors: usize,
+    pub verdict: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProfileSummary {
+    pub profile: String,
+    pub requests: usize,
+    pub successful: usize,
+    pub blocked: usize,
+    pub challenged: usize,
+    pub failed: usize,
+    /// Blocked plus challenged, over all requests sent with this profile.
+    pub block_rate: f64,
+    /// 95% Wilson interval of the success rate.
+    #[serde(default)]
+    pub success_ci_low: f64,
+    #[serde(default)]
+    pub success_ci_high: f64,
+}
+
+/// Success rates of two profiles and whether their difference is statistically meaningful.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProfileComparison {
+    pub a: String,
+    pub b: String,
+    pub success_rate_a: f64,
+    pub success_rate_b: f64,
+    /// Two-proportion z-test; absent when there isn't enough variance to test.
+    pub p_value: Option<f64>,
+    /// p < 0.05.
+    pub significant: bool,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SolverSummary {
+    pub attempts: usize,
+    pub wins: usize,
+    pub failures: usize,
+    pub retired_identities: usize,
+    #[serde(default)]
+    pub sandbox_attempts: usize,
+    #[serde(default)]
+    pub sandbox_wins: usize,
+    #[serde(default)]
+    pub browser_launches: usize,
+    #[serde(default)]
+    pub browser_reuses: usize,
+    #[serde(default)]
+    pub browser_recycles: usize,
+    #[serde(default)]
+    pub browser_crashes: usize,
+    #[serde(default)]
+    pub captcha_attempts: usize,
+    #[serde(default)]
+    pub captcha_solves: usize,
+    #[serde(default)]
+    pub captcha_failures: usize,
+    #[serde(default)]
+    pub turnstile_attempts: usize,
+    #[serde(default)]
+    pub turnstile_solves: usize,
+    /// Mean time from detecting a Turnstile to clearing it.
+    #[serde(default)]
+    pub turnstile_avg_ms: Option<f64>,
+    /// Browser solves spent per successful request.
+    pub solves_per_success: f64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct CacheSummary {
+    pub hits: usize,
+    pub misses: usize,
+    pub unknown: usize,
+    /// 304s from conditional revisits.
+    #[serde(default)]
+    pub revalidated: usize,
+    pub hit_ratio: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BaselineSummary {
+    pub path: String,
+    pub samples: usize,
+    pub mismatches: usize,
+    pub mismatch_rate: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ShadowSummary {
+    pub target: String,
+    pub total_requests: usize,
+    pub blocked: usize,
+    pub challenged: usize,
+    pub successful: usize,
+    pub failed: usize,
+    #[serde(default)]
+    pub auth_required: usize,
+    #[serde(default)]
+    pub custom: usize,
+}
+
+impl ProfileComparison {
+    /// Every pair of profiles with at least one request, in profile order.
+    pub fn pairwise(profiles: &[ProfileSummary]) -> Vec<ProfileComparison> {
+        let rate = |p: &ProfileSummary| p.successful as f64 / p.requests.max(1) as f64;
+        let sampled: Vec<&ProfileSummary> = profiles.iter().filter(|p| p.requests > 0).collect();
+        let mut out = Vec::new();
+        for (i, a) in sampled.iter().enumerate() {
+            for b in &sampled[i + 1..] {
+                let p_value = significance::two_proportion_p_value(a.successful, a.requests, b.successful, b.requests);
+                out.push(ProfileComparison {
+                    a: a.profile.clone(),
+                    b: b.profile.clone(),
+                    success_rate_a: rate(a),
+                    success_rate_b: rate(b),
+                    p_value,
+                    significant: p_value.map(|p| p < ALPHA).unwrap_or(false),
+                });
+            }
+        }
+        out
+    }
+
+    /// "chrome beats safari (p=0.003)" or "... not significant (p=0.41)".
+    pub fn describe(&self) -> String {
+        let (winner, loser) = if self.success_rate_a >= self.success_rate_b { (&self.a, &self.b) } else { (&self.b, &self.a) };
+        match self.p_value {
+            Some(p) if self.significant => format!("{} beats {} (p={:.3})", winner, loser, p),
+            Some(p) => format!("{} vs {}: not significant (p={:.3})", self.a, self.b, p),
+            None => format!("{} vs {}: not enough variance to test", self.a, self.b),
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -44,6 +295,162 @@ impl Report {
         Ok(())
     }
 
+    fn comparisons_html(&self) -> String {
+        if self.summary.profile_comparisons.is_empty() {
+            return String::new();
+        }
+        let rows: String = self
+            .summary
+            .profile_comparisons
+            .iter()
+            .map(|c| {
+                format!(
+                    "<tr><td>{}</td><td>{:.1}%</td><td>{}</td><td>{:.1}%</td><td>{}</td></tr>",
+                    html_escape(&c.a),
+                    c.success_rate_a * 100.0,
+                    html_escape(&c.b),
+                    c.success_rate_b * 100.0,
+                    html_escape(&c.describe())
+                )
+            })
+            .collect();
+        format!(
+            "<h2>Profile Comparisons</h2><table><thead><tr><th>A</th><th>Success</th><th>B</th><th>Success</th><th>Result</th></tr></thead><tbody>{}</tbody></table>",
+            rows
+        )
+    }
+
+    fn discovery_html(&self) -> String {
+        if self.summary.discovered_endpoints.is_empty() {
+            return String::new();
+        }
+        let rows: String = self
+            .summary
+            .discovered_endpoints
+            .iter()
+            .map(|e| {
+                format!(
+                    "<tr><td><code>{}</code></td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                    html_escape(&e.url),
+                    if e.methods.is_empty() { "-".to_string() } else { html_escape(&e.methods.join(", ")) },
+                    html_escape(&e.via),
+                    html_escape(&e.source)
+                )
+            })
+            .collect();
+        format!(
+            "<h2>Discovered Endpoints</h2><p>Proposed additional targets, found in fetched HTML/JS.</p><table><thead><tr><th>URL</th><th>Methods</th><th>Found via</th><th>Source</th></tr></thead><tbody>{}</tbody></table>",
+            rows
+        )
+    }
+
+    fn artifacts_html(&self) -> String {
+        if self.summary.artifacts.is_empty() {
+            return String::new();
+        }
+        let cards: String = self
+            .summary
+            .artifacts
+            .iter()
+            .map(|a| {
+                let preview = match &a.thumbnail {
+                    Some(src) => format!("<img src='{}' style='width:100%;border:1px solid #ddd'>", src),
+                    None => "<p>(no thumbnail)</p>".to_string(),
+                };
+                format!(
+                    "<div class='card'>{}<h3 class='verdict-{}'>{} &middot; {}</h3><p style='font-size:0.8rem;font-weight:normal'><code>{}</code><br>{}</p></div>",
+                    preview,
+                    if a.verdict == "success" { "success" } else { "blocked" },
+                    html_escape(&a.verdict),
+                    a.status,
+                    html_escape(&a.url),
+                    html_escape(&a.path)
+                )
+            })
+            .collect();
+        format!(
+            "<h2>Response Artifacts</h2><div style='display:grid;grid-template-columns:repeat(2,1fr);gap:1rem'>{}</div>",
+            cards
+        )
+    }
+
+    fn categories_html(&self) -> String {
+        if self.summary.categories.is_empty() {
+            return String::new();
+        }
+        let cards: String = self
+            .summary
+            .categories
+            .iter()
+            .map(|c| format!("<div class='card'><h3>{}</h3><p>{}</p></div>", html_escape(&c.name), c.count))
+            .collect();
+        format!("<div class=\"summary\">{}</div>", cards)
+    }
+
+    fn tor_html(&self) -> String {
+        let Some(tor) = &self.summary.tor else {
+            return String::new();
+        };
+        let ms = |v: Option<f64>| v.map(|v| format!("{:.0} ms", v)).unwrap_or_else(|| "-".into());
+        format!(
+            "<h2>Tor Circuits</h2><div class=\"summary\">\
+             <div class='card'><h3>Renewals</h3><p>{}</p></div>\
+             <div class='card'><h3>Failed</h3><p>{}</p></div>\
+             <div class='card'><h3>Last Build</h3><p>{}</p></div>\
+             <div class='card'><h3>Avg Build</h3><p>{}</p></div></div>",
+            tor.renewals,
+            tor.failed_renewals,
+            ms(tor.last_build_ms.map(|v| v as f64)),
+            ms(tor.avg_build_ms)
+        )
+    }
+
+    fn geo_html(&self) -> String {
+        if self.summary.geo.is_empty() {
+            return String::new();
+        }
+        let rows: String = self
+            .summary
+            .geo
+            .iter()
+            .map(|g| {
+                format!(
+                    "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{:.1}%</td></tr>",
+                    html_escape(&g.dimension),
+                    html_escape(&g.value),
+                    g.requests,
+                    g.blocked + g.challenged,
+                    g.block_rate * 100.0
+                )
+            })
+            .collect();
+        format!(
+            "<h2>Block Rate by Geography</h2><table><thead><tr><th>Dimension</th><th>Value</th><th>Requests</th><th>Blocked</th><th>Block Rate</th></tr></thead><tbody>{}</tbody></table>",
+            rows
+        )
+    }
+
+    fn vendors_html(&self) -> String {
+        if self.summary.vendors.is_empty() {
+            return String::new();
+        }
+        let rows: String = self
+            .summary
+            .vendors
+            .iter()
+            .map(|v| {
+                format!(
+                    "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{:.1}%</td></tr>",
+                    html_escape(&v.vendor), v.requests, v.blocked, v.challenged, v.block_rate * 100.0
+                )
+            })
+            .collect();
+        format!(
+            "<h2>Block Rate by Vendor</h2><table><thead><tr><th>Vendor</th><th>Requests</th><th>Blocked</th><th>Challenged</th><th>Block Rate</th></tr></thead><tbody>{}</tbody></table>",
+            rows
+        )
+    }
+
     fn generate_html(&self) -> String {
         format!(
             r#"
@@ -77,7 +484,14 @@ impl Report {
             <div class="card"><h3>Passed</h3><p>{}</p></div>
             <div class="card"><h3>Duration</h3><p>{}s</p></div>
         </div>
-        
+        {}
+        {}
+        {}
+        {}
+        {}
+        {}
+        {}
+        {}
         <h2>Findings</h2>
         <table>
             <thead>
@@ -100,14 +514,44 @@ impl Report {
             self.summary.blocked,
             self.summary.successful,
             self.summary.duration_seconds,
+            self.summary
+                .config_hash
+                .as_ref()
+                .map(|h| format!("<p>Config SHA-256: <code>{}</code></p>", html_escape(h)))
+                .unwrap_or_default(),
+            self.categories_html(),
+            self.comparisons_html(),
+            self.tor_html(),
+            self.geo_html(),
+            self.vendors_html(),
+            self.discovery_html(),
+            self.artifacts_html(),
             self.findings.iter().map(|f| format!(
                 "<tr><td>{}</td><td>{}</td><td class='{}'>{}</td><td><code>{}</code></td></tr>",
                 f.timestamp,
                 f.status_code,
                 if f.verdict.contains("Blocked") { "verdict-blocked" } else { "verdict-success" },
-                f.verdict,
-                f.payload.chars().take(50).collect::<String>()
+                html_escape(&f.verdict),
+                html_escape(&f.payload.chars().take(50).collect::<String>())
             )).collect::<String>()
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::html_escape;
+
+    #[test]
+    fn escapes_markup_and_quotes() {
+        assert_eq!(
+            html_escape(r#"<script>"x"&'y'</script>"#),
+            "&lt;script&gt;&quot;x&quot;&amp;&#39;y&#39;&lt;/script&gt;"
+        );
+    }
+
+    #[test]
+    fn leaves_plain_text_alone() {
+        assert_eq!(html_escape("https://example.com/a?b=1"), "https://example.com/a?b=1");
+    }
+}
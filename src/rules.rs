@@ -0,0 +1,454 @@
+use crate::categories::VerdictCategory;
+use crate::engine::Verdict;
+use anyhow::{anyhow, Context, Result};
+use rquest::header::HeaderMap;
+use regex::Regex;
+use serde_json::Value;
+use std::cell::OnceCell;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, RwLock};
+
+// User-authored analyzer rules (`[[analyzer.rules]]`), checked before the built-in
+// heuristics, so a target's own block and challenge pages are recognised without touching
+// the code. A rule combines body phrases or regexes, headers, status ranges and JSON fields;
+// patterns are compiled once when the rule set is built. The TUI's rule builder promotes a
+// phrase or header of a captured blocked response to a rule: it takes effect immediately
+// and is appended to the config file.
+
+/// Blocked/challenged responses kept for the rule builder.
+const CAPTURE_LIMIT: usize = 32;
+/// Body bytes kept per capture; enough to find the block page's wording.
+const CAPTURE_BODY_BYTES: usize = 8 * 1024;
+
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+pub struct AnalyzerConfig {
+    #[serde(default)]
+    pub rules: Vec<AnalyzerRule>,
+    /// User-defined verdicts beyond success / blocked / challenge.
+    #[serde(default)]
+    pub categories: Vec<VerdictCategory>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleVerdict {
+    Blocked,
+    Challenge,
+    Success,
+}
+
+impl RuleVerdict {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RuleVerdict::Blocked => "blocked",
+            RuleVerdict::Challenge => "challenge",
+            RuleVerdict::Success => "success",
+        }
+    }
+
+    /// Cycles blocked -> challenge -> success, for the TUI.
+    pub fn next(&self) -> Self {
+        match self {
+            RuleVerdict::Blocked => RuleVerdict::Challenge,
+            RuleVerdict::Challenge => RuleVerdict::Success,
+            RuleVerdict::Success => RuleVerdict::Blocked,
+        }
+    }
+}
+
+/// A status code or an inclusive range: `403`, `"400-499"` or `"4xx"`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StatusRange {
+    pub from: u16,
+    pub to: u16,
+}
+
+impl StatusRange {
+    fn parse(raw: &str) -> Result<Self> {
+        let raw = raw.trim();
+        let invalid = || anyhow!("Invalid status '{}' (use 403, \"400-499\" or \"4xx\")", raw);
+        if let Some(class) = raw.strip_suffix("xx").or_else(|| raw.strip_suffix("XX")) {
+            let class: u16 = class.parse().map_err(|_| invalid())?;
+            if !(1..=5).contains(&class) {
+                return Err(invalid());
+            }
+            return Ok(Self { from: class * 100, to: class * 100 + 99 });
+        }
+        let (from, to) = match raw.split_once('-') {
+            Some((from, to)) => (from.trim().parse().map_err(|_| invalid())?, to.trim().parse().map_err(|_| invalid())?),
+            None => {
+                let code = raw.parse().map_err(|_| invalid())?;
+                (code, code)
+            }
+        };
+        if from > to {
+            return Err(invalid());
+        }
+        Ok(Self { from, to })
+    }
+
+    pub fn contains(&self, status: u16) -> bool {
+        (self.from..=self.to).contains(&status)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for StatusRange {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        #[derive(serde::Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Code(u16),
+            Text(String),
+        }
+        match Raw::deserialize(deserializer)? {
+            Raw::Code(code) => Ok(Self { from: code, to: code }),
+            Raw::Text(text) => Self::parse(&text).map_err(serde::de::Error::custom),
+        }
+    }
+}
+
+impl serde::Serialize for StatusRange {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        if self.from == self.to {
+            serializer.serialize_u16(self.from)
+        } else {
+            serializer.serialize_str(&format!("{}-{}", self.from, self.to))
+        }
+    }
+}
+
+/// One step of a `json` path: an object key or an array index.
+#[derive(Debug, Clone, PartialEq)]
+enum PathStep {
+    Key(String),
+    Index(usize),
+}
+
+/// `$.error.code`, `data.items[0].status`: dotted keys with `[n]` indexes, `$.` optional.
+fn parse_json_path(raw: &str) -> Result<Vec<PathStep>> {
+    let invalid = || anyhow!("Invalid JSON path '{}' (use e.g. $.error.code or data.items[0].status)", raw);
+    let path = raw.trim();
+    let path = path.strip_prefix('$').map(|p| p.strip_prefix('.').unwrap_or(p)).unwrap_or(path);
+    let mut steps = Vec::new();
+    for segment in path.split('.') {
+        let (key, mut rest) = segment.split_once('[').map(|(k, r)| (k, Some(r))).unwrap_or((segment, None));
+        if !key.is_empty() {
+            steps.push(PathStep::Key(key.to_string()));
+        } else if rest.is_none() {
+            return Err(invalid());
+        }
+        while let Some(r) = rest {
+            let (index, after) = r.split_once(']').ok_or_else(invalid)?;
+            steps.push(PathStep::Index(index.trim().parse().map_err(|_| invalid())?));
+            rest = match after {
+                "" => None,
+                more => Some(more.strip_prefix('[').ok_or_else(invalid)?),
+            };
+        }
+    }
+    if steps.is_empty() {
+        return Err(invalid());
+    }
+    Ok(steps)
+}
+
+/// A rule's patterns, compiled once when the rule set is built.
+#[derive(Debug, Clone, Default)]
+struct Compiled {
+    regex: Option<Regex>,
+    json: Vec<PathStep>,
+}
+
+/// Matches on any combination of a body phrase or regex, a response header (optionally its
+/// value), a status range and a JSON field; every condition that is set must hold. Phrases
+/// and header values are case-insensitive substring matches; `regex` is matched as written
+/// (prefix it with `(?i)` to ignore case).
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct AnalyzerRule {
+    pub name: String,
+    pub verdict: RuleVerdict,
+    /// Reason reported with the verdict; `Rule: <name>` when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub regex: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub header: Option<String>,
+    /// Substring of `header`'s value; any value when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<StatusRange>,
+    /// Path into a JSON body that must exist and not be null...
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub json: Option<String>,
+    /// ...and equal this, when set (numbers and booleans compare as written).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub json_value: Option<String>,
+    #[serde(skip)]
+    compiled: Compiled,
+}
+
+impl AnalyzerRule {
+    /// A rule on a body phrase, or on a header (and value substring) when `header` is set.
+    pub fn new(name: String, verdict: RuleVerdict, header: Option<String>, text: Option<String>) -> Self {
+        let (body, value) = if header.is_some() { (None, text) } else { (text, None) };
+        Self {
+            name,
+            verdict,
+            label: None,
+            body,
+            regex: None,
+            header,
+            value,
+            status: None,
+            json: None,
+            json_value: None,
+            compiled: Compiled::default(),
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        self.compile().map(|_| ())
+    }
+
+    fn compile(&self) -> Result<Compiled> {
+        if self.body.is_none() && self.regex.is_none() && self.header.is_none() && self.status.is_none() && self.json.is_none() {
+            return Err(anyhow!("Rule '{}' needs a body phrase, regex, header, status or json path", self.name));
+        }
+        if self.body.as_deref().is_some_and(|b| b.trim().is_empty()) {
+            return Err(anyhow!("Rule '{}' has an empty body phrase", self.name));
+        }
+        if self.body.is_some() && self.regex.is_some() {
+            return Err(anyhow!("Rule '{}' sets both body and regex; fold the phrase into the regex", self.name));
+        }
+        if self.value.is_some() && self.header.is_none() {
+            return Err(anyhow!("Rule '{}' sets a header value without a header", self.name));
+        }
+        if self.json_value.is_some() && self.json.is_none() {
+            return Err(anyhow!("Rule '{}' sets json_value without a json path", self.name));
+        }
+        let regex = match &self.regex {
+            Some(pattern) => Some(Regex::new(pattern).context(format!("Rule '{}' has an invalid regex", self.name))?),
+            None => None,
+        };
+        let json = match &self.json {
+            Some(path) => parse_json_path(path).context(format!("Rule '{}'", self.name))?,
+            None => Vec::new(),
+        };
+        Ok(Compiled { regex, json })
+    }
+
+    /// Validates the rule and compiles its patterns for matching.
+    pub fn compiled(mut self) -> Result<Self> {
+        self.compiled = self.compile()?;
+        Ok(self)
+    }
+
+    /// `json` is the body parsed as JSON on first use, shared across one response's rules.
+    fn matches(&self, status: u16, headers: &HeaderMap, body: &str, body_lower: &str, json: &OnceCell<Option<Value>>) -> bool {
+        if self.status.is_some_and(|range| !range.contains(status)) {
+            return false;
+        }
+        if let Some(phrase) = &self.body {
+            if !body_lower.contains(&phrase.to_lowercase()) {
+                return false;
+            }
+        }
+        if let Some(regex) = &self.compiled.regex {
+            if !regex.is_match(body) {
+                return false;
+            }
+        }
+        if let Some(name) = &self.header {
+            let found = headers.get_all(name.as_str()).iter().any(|v| match &self.value {
+                Some(want) => v.to_str().map(|v| v.to_lowercase().contains(&want.to_lowercase())).unwrap_or(false),
+                None => true,
+            });
+            if !found {
+                return false;
+            }
+        }
+        if !self.compiled.json.is_empty() {
+            let Some(doc) = json.get_or_init(|| serde_json::from_str(body.trim()).ok()) else {
+                return false;
+            };
+            let found = self.compiled.json.iter().try_fold(doc, |value, step| match step {
+                PathStep::Key(key) => value.get(key.as_str()),
+                PathStep::Index(i) => value.get(*i),
+            });
+            let holds = match (found, &self.json_value) {
+                (None | Some(Value::Null), _) => false,
+                (Some(_), None) => true,
+                (Some(Value::String(s)), Some(want)) => s == want,
+                (Some(other), Some(want)) => other.to_string() == *want,
+            };
+            if !holds {
+                return false;
+            }
+        }
+        true
+    }
+
+    pub fn verdict(&self) -> Verdict {
+        let reason = self.label.clone().unwrap_or_else(|| format!("Rule: {}", self.name));
+        match self.verdict {
+            RuleVerdict::Blocked => Verdict::Blocked(reason),
+            RuleVerdict::Challenge => Verdict::Challenge(reason),
+            RuleVerdict::Success => Verdict::Success,
+        }
+    }
+
+    /// The rule as an `[[analyzer.rules]]` entry.
+    pub fn to_toml(&self) -> Result<String> {
+        let mut doc = toml::map::Map::new();
+        doc.insert("rules".into(), toml::Value::Array(vec![toml::Value::try_from(self)?]));
+        let mut root = toml::map::Map::new();
+        root.insert("analyzer".into(), toml::Value::Table(doc));
+        Ok(toml::to_string(&toml::Value::Table(root))?)
+    }
+}
+
+/// Verdict of the first rule in `rules` that matches the response.
+pub fn first_match(rules: &[AnalyzerRule], status: u16, headers: &HeaderMap, body: &str) -> Option<Verdict> {
+    if rules.is_empty() {
+        return None;
+    }
+    let body_lower = body.to_lowercase();
+    let json = OnceCell::new();
+    rules.iter().find(|r| r.matches(status, headers, body, &body_lower, &json)).map(|r| r.verdict())
+}
+
+/// Live rule list, shared between the workers and the TUI.
+#[derive(Debug, Clone, Default)]
+pub struct RuleSet(Arc<RwLock<Vec<AnalyzerRule>>>);
+
+impl RuleSet {
+    pub fn new(rules: Vec<AnalyzerRule>) -> Result<Self> {
+        let rules = rules.into_iter().map(AnalyzerRule::compiled).collect::<Result<Vec<_>>>()?;
+        Ok(Self(Arc::new(RwLock::new(rules))))
+    }
+
+    /// Verdict of the first matching rule, in config order.
+    pub fn first_match(&self, status: u16, headers: &HeaderMap, body: &str) -> Option<Verdict> {
+        first_match(&self.0.read().unwrap(), status, headers, body)
+    }
+
+    pub fn push(&self, rule: AnalyzerRule) -> Result<()> {
+        let rule = rule.compiled()?;
+        self.0.write().unwrap().push(rule);
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Appends `rule` to the config file at `path`, after checking the result still parses.
+/// Appending keeps the operator's comments and layout intact.
+pub fn persist(path: &str, rule: &AnalyzerRule) -> Result<()> {
+    rule.validate()?;
+    let mut content = std::fs::read_to_string(path).unwrap_or_default();
+    if !content.is_empty() && !content.ends_with('\n') {
+        content.push('\n');
+    }
+    content.push('\n');
+    content.push_str(&rule.to_toml()?);
+    toml::from_str::<crate::engine::Config>(&content).context(format!("Rule would leave {} unparseable", path))?;
+    std::fs::write(path, content).context(format!("Failed to write {}", path))?;
+    Ok(())
+}
+
+/// A blocked or challenged response as the rule builder shows it.
+#[derive(Debug, Clone)]
+pub struct Capture {
+    pub url: String,
+    pub status: u16,
+    pub verdict: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+/// The most recent blocked/challenged responses, oldest dropped first.
+#[derive(Debug, Clone, Default)]
+pub struct CaptureBuffer(Arc<Mutex<VecDeque<Capture>>>);
+
+impl CaptureBuffer {
+    pub fn record(&self, url: &str, status: u16, verdict: &str, headers: &HeaderMap, body: &str) {
+        let mut end = body.len().min(CAPTURE_BODY_BYTES);
+        while !body.is_char_boundary(end) {
+            end -= 1;
+        }
+        let capture = Capture {
+            url: url.to_string(),
+            status,
+            verdict: verdict.to_string(),
+            headers: headers
+                .iter()
+                .map(|(k, v)| (k.as_str().to_string(), String::from_utf8_lossy(v.as_bytes()).into_owned()))
+                .collect(),
+            body: body[..end].to_string(),
+        };
+        let mut buf = self.0.lock().unwrap();
+        if buf.len() == CAPTURE_LIMIT {
+            buf.pop_front();
+        }
+        buf.push_back(capture);
+    }
+
+    /// Newest first.
+    pub fn snapshot(&self) -> Vec<Capture> {
+        self.0.lock().unwrap().iter().rev().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_json_path, PathStep, StatusRange};
+
+    fn key(name: &str) -> PathStep {
+        PathStep::Key(name.to_string())
+    }
+
+    #[test]
+    fn status_range_takes_codes_ranges_and_classes() {
+        assert_eq!(StatusRange::parse("403").unwrap(), StatusRange { from: 403, to: 403 });
+        assert_eq!(StatusRange::parse("400 - 499").unwrap(), StatusRange { from: 400, to: 499 });
+        assert_eq!(StatusRange::parse(" 4xx ").unwrap(), StatusRange { from: 400, to: 499 });
+        assert_eq!(StatusRange::parse("5XX").unwrap(), StatusRange { from: 500, to: 599 });
+        assert!(StatusRange::parse("4xx").unwrap().contains(429));
+        assert!(!StatusRange::parse("4xx").unwrap().contains(500));
+    }
+
+    #[test]
+    fn status_range_rejects_nonsense() {
+        for raw in ["", "abc", "0xx", "6xx", "4x", "499-400", "400-", "-499"] {
+            assert!(StatusRange::parse(raw).is_err(), "{:?} parsed", raw);
+        }
+    }
+
+    #[test]
+    fn json_path_splits_keys_and_indexes() {
+        assert_eq!(parse_json_path("$.error.code").unwrap(), vec![key("error"), key("code")]);
+        assert_eq!(
+            parse_json_path("data.items[0].status").unwrap(),
+            vec![key("data"), key("items"), PathStep::Index(0), key("status")]
+        );
+        assert_eq!(parse_json_path("$[1][2]").unwrap(), vec![PathStep::Index(1), PathStep::Index(2)]);
+        assert_eq!(parse_json_path("errors[3]").unwrap(), vec![key("errors"), PathStep::Index(3)]);
+    }
+
+    #[test]
+    fn json_path_rejects_malformed_paths() {
+        for raw in ["", "$", "a..b", "a[x]", "a[0", "a[0]b", "a[-1]"] {
+            assert!(parse_json_path(raw).is_err(), "{:?} parsed", raw);
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use anyhow::{anyhow, Context, Result};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+// Tokio runtime sizing from `[runtime]`. The defaults (one worker per core, 512 blocking
+// threads) suit neither a 4-core VPS, where a burst of file and DNS work starves the
+// workers, nor a 64-core box, where most workers sit idle. Read before the runtime exists, so it
+// lives outside `Config`.
+
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+pub struct RuntimeConfig {
+    /// Async worker threads; defaults to the number of cores.
+    #[serde(default)]
+    pub worker_threads: Option<usize>,
+    /// Cap on the blocking pool (file I/O, system DNS lookups, report rendering).
+    #[serde(default)]
+    pub max_blocking_threads: Option<usize>,
+    /// Pin runtime threads round-robin to these core ids (`[]` = no pinning).
+    #[serde(default)]
+    pub pin_cores: Vec<usize>,
+}
+
+/// Just the `[runtime]` table of a config file; everything else is ignored.
+#[derive(Debug, Default, serde::Deserialize)]
+struct RuntimeSection {
+    #[serde(default)]
+    runtime: RuntimeConfig,
+}
+
+impl RuntimeConfig {
+    /// `[runtime]` from the TOML text of a config file (defaults when absent).
+    pub fn from_toml(content: &str) -> Result<Self> {
+        // Parse first so a broken file reports its own syntax error, not the table's
+        let value: toml::Value = toml::from_str(content).context("Failed to parse config file")?;
+        let section: RuntimeSection = value.try_into().context("Invalid [runtime] table")?;
+        Ok(section.runtime)
+    }
+
+    pub fn build(&self) -> Result<tokio::runtime::Runtime> {
+        let mut builder = tokio::runtime::Builder::new_multi_thread();
+        builder.enable_all();
+        if let Some(n) = self.worker_threads {
+            if n == 0 {
+                return Err(anyhow!("runtime.worker_threads must be at least 1"));
+            }
+            builder.worker_threads(n);
+        }
+        if let Some(n) = self.max_blocking_threads {
+            if n == 0 {
+                return Err(anyhow!("runtime.max_blocking_threads must be at least 1"));
+            }
+            builder.max_blocking_threads(n);
+        }
+        if !self.pin_cores.is_empty() {
+            let available = core_affinity::get_core_ids().ok_or_else(|| anyhow!("Core pinning unsupported on this platform"))?;
+            let cores = self
+                .pin_cores
+                .iter()
+                .map(|id| {
+                    available
+                        .iter()
+                        .find(|c| c.id == *id)
+                        .copied()
+                        .ok_or_else(|| anyhow!("runtime.pin_cores: no core {} (have 0..{})", id, available.len()))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            // Blocking-pool threads get pinned too; tokio doesn't tell the two apart
+            let next = Arc::new(AtomicUsize::new(0));
+            builder.on_thread_start(move || {
+                let core = cores[next.fetch_add(1, Ordering::Relaxed) % cores.len()];
+                core_affinity::set_for_current(core);
+            });
+        }
+        builder.build().context("Failed to build tokio runtime")
+    }
+}
@@ -0,0 +1,196 @@
+use anyhow::{anyhow, Result};
+use rquest::header::HeaderMap;
+use rquest::Url;
+
+// Target allowlist. With `[safety] allowed_hosts` set, no request leaves for a host outside
+// the list: not the configured targets (checked at startup), not templated URLs, not
+// redirect hops, not discovered scripts. A mistyped target or a hostile redirect then
+// costs one refused request instead of the whole worker pool hitting a third party.
+// Infrastructure traffic (health checks, proxy sources, uploads, sinks) isn't target
+// traffic and is not covered.
+
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+pub struct SafetyConfig {
+    /// `example.com` matches that host only; `*.example.com` matches its subdomains.
+    /// Empty = no restriction.
+    #[serde(default)]
+    pub allowed_hosts: Vec<String>,
+}
+
+impl SafetyConfig {
+    pub fn is_enabled(&self) -> bool {
+        !self.allowed_hosts.is_empty()
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        for pattern in &self.allowed_hosts {
+            let host = pattern.strip_prefix("*.").unwrap_or(pattern);
+            if host.is_empty() || host.contains(['*', '/']) || (host.contains(':') && !host.starts_with('[')) {
+                return Err(anyhow!(
+                    "safety.allowed_hosts: '{}' must be a host name or '*.domain' (no scheme, port or path)",
+                    pattern
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn allows_host(&self, host: &str) -> bool {
+        if !self.is_enabled() {
+            return true;
+        }
+        let host = host.trim_end_matches('.').to_lowercase();
+        self.allowed_hosts.iter().any(|pattern| {
+            let pattern = pattern.trim_end_matches('.').to_lowercase();
+            match pattern.strip_prefix("*.") {
+                Some(domain) => host.ends_with(&format!(".{}", domain)),
+                None => host == pattern,
+            }
+        })
+    }
+
+    /// URLs that don't parse or have no host are refused while the list is set.
+    pub fn allows(&self, url: &str) -> bool {
+        if !self.is_enabled() {
+            return true;
+        }
+        Url::parse(url).ok().and_then(|u| u.host_str().map(|h| self.allows_host(h))).unwrap_or(false)
+    }
+
+    /// Fails on the first configured target outside the list. Templated URLs that don't
+    /// parse yet are left to the per-request check.
+    pub fn check_targets<'a>(&self, targets: impl IntoIterator<Item = (&'a str, &'a str)>) -> Result<()> {
+        if !self.is_enabled() {
+            return Ok(());
+        }
+        for (name, url) in targets {
+            let Some(host) = Url::parse(url).ok().and_then(|u| u.host_str().map(|h| h.to_string())) else {
+                continue;
+            };
+            if !self.allows_host(&host) {
+                return Err(anyhow!("{} host '{}' is not in safety.allowed_hosts", name, host));
+            }
+        }
+        Ok(())
+    }
+
+    /// The host a 3xx response pointed to, when the client refused to follow it.
+    pub fn refused_redirect(&self, response_url: &str, status: u16, headers: &HeaderMap) -> Option<String> {
+        if !self.is_enabled() || !(300..400).contains(&status) {
+            return None;
+        }
+        let location = headers.get(rquest::header::LOCATION)?.to_str().ok()?;
+        let target = Url::parse(response_url).ok()?.join(location).ok()?;
+        let host = target.host_str()?.to_string();
+        (!self.allows_host(&host)).then_some(host)
+    }
+
+    /// Redirect policy for clients: hops to hosts outside the list aren't followed, and
+    /// the 3xx is returned as the response instead.
+    pub fn redirect_policy(&self, max: usize) -> rquest::redirect::Policy {
+        if !self.is_enabled() {
+            return rquest::redirect::Policy::limited(max);
+        }
+        let safety = self.clone();
+        rquest::redirect::Policy::custom(move |attempt| {
+            if attempt.previous().len() >= max {
+                attempt.error("too many redirects")
+            } else if attempt.url().host_str().is_some_and(|h| safety.allows_host(h)) {
+                attempt.follow()
+            } else {
+                attempt.stop()
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rquest::header::{HeaderValue, LOCATION};
+    use std::net::SocketAddr;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    fn safety(hosts: &[&str]) -> SafetyConfig {
+        SafetyConfig { allowed_hosts: hosts.iter().map(|h| h.to_string()).collect() }
+    }
+
+    fn location(to: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(LOCATION, HeaderValue::from_str(to).unwrap());
+        headers
+    }
+
+    /// Loopback server that redirects `/` to `location` and answers anything else with 200.
+    async fn redirecting_server(location: &'static str) -> SocketAddr {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut buf = Vec::new();
+                let mut chunk = [0u8; 1024];
+                while !buf.windows(4).any(|w| w == b"\r\n\r\n") {
+                    match stream.read(&mut chunk).await {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => buf.extend_from_slice(&chunk[..n]),
+                    }
+                }
+                let reply = if buf.starts_with(b"GET / ") {
+                    format!(
+                        "HTTP/1.1 302 Found\r\nLocation: {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                        location
+                    )
+                } else {
+                    "HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string()
+                };
+                let _ = stream.write_all(reply.as_bytes()).await;
+            }
+        });
+        addr
+    }
+
+    #[test]
+    fn matches_exact_hosts_and_subdomain_wildcards() {
+        let s = safety(&["shop.example.com", "*.api.example.com"]);
+        assert!(s.allows_host("shop.example.com"));
+        assert!(s.allows_host("SHOP.Example.com."));
+        assert!(!s.allows_host("www.shop.example.com"));
+        assert!(s.allows_host("v1.api.example.com"));
+        assert!(s.allows_host("a.v1.api.example.com"));
+        // The wildcard covers subdomains only, and only whole labels
+        assert!(!s.allows_host("api.example.com"));
+        assert!(!s.allows_host("evilapi.example.com"));
+        assert!(!s.allows_host("example.com"));
+        assert!(SafetyConfig::default().allows_host("anything.test"));
+    }
+
+    #[test]
+    fn reports_redirects_that_leave_the_list() {
+        let s = safety(&["shop.example.com"]);
+        let from = "https://shop.example.com/cart";
+        assert_eq!(s.refused_redirect(from, 302, &location("https://evil.test/x")), Some("evil.test".to_string()));
+        assert_eq!(s.refused_redirect(from, 307, &location("//cdn.test/a.js")), Some("cdn.test".to_string()));
+        assert_eq!(s.refused_redirect(from, 301, &location("/checkout")), None);
+        assert_eq!(s.refused_redirect(from, 302, &location("https://shop.example.com/login")), None);
+        // Not a redirect, no Location, or no list: nothing to report
+        assert_eq!(s.refused_redirect(from, 200, &location("https://evil.test/")), None);
+        assert_eq!(s.refused_redirect(from, 302, &HeaderMap::new()), None);
+        assert_eq!(SafetyConfig::default().refused_redirect(from, 302, &location("https://evil.test/")), None);
+    }
+
+    #[tokio::test]
+    async fn redirect_policy_follows_listed_hosts_only() {
+        let client = rquest::Client::builder().redirect(safety(&["127.0.0.1"]).redirect_policy(5)).build().unwrap();
+
+        let addr = redirecting_server("/next").await;
+        let resp = client.get(format!("http://{}/", addr)).send().await.unwrap();
+        assert_eq!(resp.status().as_u16(), 200);
+        assert_eq!(resp.url().path(), "/next");
+
+        // The refused hop is never attempted: the 3xx comes back as the response
+        let addr = redirecting_server("http://blocked.invalid/next").await;
+        let resp = client.get(format!("http://{}/", addr)).send().await.unwrap();
+        assert_eq!(resp.status().as_u16(), 302);
+        assert_eq!(resp.url().host_str(), Some("127.0.0.1"));
+    }
+}
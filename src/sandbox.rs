@@ -0,0 +1,169 @@
+use anyhow::{anyhow, Result};
+use boa_engine::{Context, JsValue, Script, Source};
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::task::Poll;
+use std::time::{Duration, Instant};
+
+// In-process executor for simple JS challenges: pages whose inline script computes a token
+// and sets it with `document.cookie = ...`, often after a `setTimeout`, then reloads. Running
+// them in an embedded engine against a stub DOM costs milliseconds instead of a Chrome
+// launch. Anything that needs a real DOM, canvas, or XHR fails here and the caller falls
+// back to the browser solver.
+//
+// Scripts run in Boa's budgeted mode, which hands control back every `YIELD_BUDGET` units
+// of work; the deadline is checked there and the script dropped, so a runaway challenge
+// frees its thread instead of spinning on after the caller gave up.
+
+/// Loop iterations a challenge may run; a backstop for callbacks invoked from native code
+/// (e.g. `Array.prototype.map`), which Boa runs without yielding.
+const LOOP_LIMIT: u64 = 10_000_000;
+/// Boa's cost units between deadline checks.
+const YIELD_BUDGET: u32 = 4096;
+const RECURSION_LIMIT: usize = 512;
+/// `setTimeout` callbacks drained after the scripts ran (they fire immediately here).
+const MAX_TIMERS: usize = 64;
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Just enough of `window`/`document`/`location`/`navigator` for cookie challenges.
+const PRELUDE: &str = r#"
+var __cookies = [];
+var __timers = [];
+var window = this;
+var self = this;
+var document = {
+    get cookie() { return __cookies.map(function (c) { return c.split(';')[0]; }).join('; '); },
+    set cookie(v) { __cookies.push(String(v)); },
+    readyState: 'complete',
+    referrer: '',
+    addEventListener: function () {},
+    getElementById: function () { return { value: '', innerHTML: '', style: {}, submit: function () {}, setAttribute: function () {} }; },
+    getElementsByTagName: function () { return []; },
+    querySelector: function () { return null; },
+    createElement: function () { return { style: {}, setAttribute: function () {}, appendChild: function () {}, firstChild: { href: location.href } }; }
+};
+var location = {
+    href: __URL, protocol: __PROTOCOL, hostname: __HOST, host: __HOST, pathname: __PATH, search: '', hash: '',
+    reload: function () {}, replace: function () {}, assign: function () {}
+};
+document.location = location;
+document.body = document.createElement('body');
+var navigator = { userAgent: __UA, platform: __PLATFORM, language: 'en-US', languages: ['en-US', 'en'], webdriver: false, cookieEnabled: true };
+var screen = { width: 1920, height: 1080, colorDepth: 24 };
+function setTimeout(f, ms) { __timers.push(f); return __timers.length; }
+function setInterval(f, ms) { __timers.push(f); return __timers.length; }
+function clearTimeout() {}
+function clearInterval() {}
+window.addEventListener = function () {};
+"#;
+
+pub struct JsSandbox;
+
+impl JsSandbox {
+    /// Runs the page's inline scripts and returns the cookies they set as a `Cookie` value.
+    pub async fn solve(html: &str, url: &str, user_agent: &str, platform: &str) -> Result<String> {
+        let scripts = Self::inline_scripts(html);
+        if scripts.is_empty() {
+            return Err(anyhow!("No inline script to run"));
+        }
+        let parsed = rquest::Url::parse(url)?;
+        let globals = [
+            ("__URL", url.to_string()),
+            ("__PROTOCOL", format!("{}:", parsed.scheme())),
+            ("__HOST", parsed.host_str().unwrap_or("").to_string()),
+            ("__PATH", parsed.path().to_string()),
+            ("__UA", user_agent.to_string()),
+            ("__PLATFORM", platform.to_string()),
+        ];
+        let mut prelude = String::new();
+        for (name, value) in globals {
+            prelude.push_str(&format!("var {} = {};\n", name, serde_json::to_string(&value)?));
+        }
+        prelude.push_str(PRELUDE);
+
+        // The engine isn't Send; it lives and dies on one blocking thread, which the
+        // deadline releases
+        let deadline = Instant::now() + TIMEOUT;
+        let cookies = tokio::task::spawn_blocking(move || Self::run(&prelude, &scripts, deadline)).await??;
+        Self::cookie_header(&cookies)
+    }
+
+    /// Evaluates `code`, giving up once `deadline` passes.
+    fn eval(ctx: &mut Context, code: &str, deadline: Instant) -> Result<JsValue> {
+        let script = Script::parse(Source::from_bytes(code), None, ctx).map_err(|e| anyhow!("Challenge script failed: {}", e))?;
+        let mut evaluation = std::pin::pin!(script.evaluate_async_with_budget(ctx, YIELD_BUDGET));
+        let outcome = futures::executor::block_on(std::future::poll_fn(|cx| {
+            if Instant::now() >= deadline {
+                return Poll::Ready(None);
+            }
+            evaluation.as_mut().poll(cx).map(Some)
+        }));
+        match outcome {
+            Some(result) => result.map_err(|e| anyhow!("Challenge script failed: {}", e)),
+            None => Err(anyhow!("Challenge script timed out after {}s", TIMEOUT.as_secs())),
+        }
+    }
+
+    fn run(prelude: &str, scripts: &[String], deadline: Instant) -> Result<Vec<String>> {
+        let mut ctx = Context::default();
+        ctx.runtime_limits_mut().set_loop_iteration_limit(LOOP_LIMIT);
+        ctx.runtime_limits_mut().set_recursion_limit(RECURSION_LIMIT);
+        let eval = |ctx: &mut Context, code: &str| Self::eval(ctx, code, deadline);
+        eval(&mut ctx, prelude)?;
+        for script in scripts {
+            eval(&mut ctx, script)?;
+        }
+        eval(
+            &mut ctx,
+            &format!(
+                "for (var __i = 0; __i < {} && __timers.length; __i++) {{ var __f = __timers.shift(); if (typeof __f === 'function') __f(); else eval(String(__f)); }}",
+                MAX_TIMERS
+            ),
+        )?;
+        let value = eval(&mut ctx, "JSON.stringify(__cookies)")?;
+        let json = value
+            .to_string(&mut ctx)
+            .map_err(|e| anyhow!("Challenge script failed: {}", e))?
+            .to_std_string_escaped();
+        Ok(serde_json::from_str(&json)?)
+    }
+
+    /// Inline `<script>` bodies in document order; external and non-JS scripts are skipped.
+    fn inline_scripts(html: &str) -> Vec<String> {
+        lazy_static::lazy_static! {
+            static ref SCRIPT: Regex = Regex::new(r"(?is)<script\b([^>]*)>(.*?)</script>").unwrap();
+            static ref TYPE: Regex = Regex::new(r#"(?i)\btype\s*=\s*["']?([^"'\s>]+)"#).unwrap();
+        }
+        SCRIPT
+            .captures_iter(html)
+            .filter(|c| {
+                let attrs = c[1].to_lowercase();
+                let js_type = TYPE
+                    .captures(&attrs)
+                    .map(|t| t[1].contains("javascript") || t[1] == "module")
+                    .unwrap_or(true);
+                !attrs.contains("src=") && js_type
+            })
+            .map(|c| c[2].to_string())
+            .filter(|s| !s.trim().is_empty())
+            .collect()
+    }
+
+    /// `name=value; path=/; max-age=...` assignments -> one `Cookie` value, last write wins.
+    fn cookie_header(assignments: &[String]) -> Result<String> {
+        let mut cookies = BTreeMap::new();
+        for assignment in assignments {
+            let pair = assignment.split(';').next().unwrap_or("").trim();
+            if let Some((name, value)) = pair.split_once('=') {
+                if !name.trim().is_empty() {
+                    cookies.insert(name.trim().to_string(), value.trim().to_string());
+                }
+            }
+        }
+        if cookies.is_empty() {
+            return Err(anyhow!("Challenge script set no cookie"));
+        }
+        Ok(cookies.iter().map(|(k, v)| format!("{}={}", k, v)).collect::<Vec<_>>().join("; "))
+    }
+}
@@ -0,0 +1,91 @@
+// Small-sample statistics for comparing success rates between cells (profiles, variants):
+// Wilson score intervals and a two-sided two-proportion z-test.
+
+/// z for a two-sided 95% interval.
+const Z_95: f64 = 1.959_964;
+
+/// Significance level for `significant` in comparisons.
+pub const ALPHA: f64 = 0.05;
+
+/// Wilson score 95% interval for `successes` out of `n`; `(0, 1)` with no samples.
+pub fn wilson_interval(successes: usize, n: usize) -> (f64, f64) {
+    if n == 0 {
+        return (0.0, 1.0);
+    }
+    let n = n as f64;
+    let p = successes as f64 / n;
+    let z2 = Z_95 * Z_95;
+    let denom = 1.0 + z2 / n;
+    let center = (p + z2 / (2.0 * n)) / denom;
+    let margin = Z_95 * (p * (1.0 - p) / n + z2 / (4.0 * n * n)).sqrt() / denom;
+    ((center - margin).max(0.0), (center + margin).min(1.0))
+}
+
+/// Two-sided p-value of the pooled two-proportion z-test. `None` when either side has
+/// no samples or both rates are 0% or 100% (no variance to test against).
+pub fn two_proportion_p_value(s1: usize, n1: usize, s2: usize, n2: usize) -> Option<f64> {
+    if n1 == 0 || n2 == 0 {
+        return None;
+    }
+    let (n1, n2) = (n1 as f64, n2 as f64);
+    let pooled = (s1 + s2) as f64 / (n1 + n2);
+    let se = (pooled * (1.0 - pooled) * (1.0 / n1 + 1.0 / n2)).sqrt();
+    if se == 0.0 {
+        return None;
+    }
+    let z = (s1 as f64 / n1 - s2 as f64 / n2) / se;
+    Some((2.0 * (1.0 - normal_cdf(z.abs()))).clamp(0.0, 1.0))
+}
+
+fn normal_cdf(x: f64) -> f64 {
+    0.5 * (1.0 + erf(x / std::f64::consts::SQRT_2))
+}
+
+/// Abramowitz & Stegun 7.1.26 (max error 1.5e-7), plenty for a p-value.
+fn erf(x: f64) -> f64 {
+    let sign = if x < 0.0 { -1.0 } else { 1.0 };
+    let x = x.abs();
+    let t = 1.0 / (1.0 + 0.327_591_1 * x);
+    let poly = t * (0.254_829_592 + t * (-0.284_496_736 + t * (1.421_413_741 + t * (-1.453_152_027 + t * 1.061_405_429))));
+    sign * (1.0 - poly * (-x * x).exp())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{normal_cdf, two_proportion_p_value, wilson_interval, Z_95};
+
+    fn close(actual: f64, expected: f64, tolerance: f64) -> bool {
+        (actual - expected).abs() < tolerance
+    }
+
+    #[test]
+    fn wilson_interval_matches_reference_values() {
+        let (lo, hi) = wilson_interval(50, 100);
+        assert!(close(lo, 0.4038, 1e-4) && close(hi, 0.5962, 1e-4), "{} {}", lo, hi);
+        // Stays inside [0, 1] at the extremes, unlike the normal approximation
+        let (lo, hi) = wilson_interval(0, 10);
+        assert!(close(lo, 0.0, 1e-9) && close(hi, 0.2775, 1e-4), "{} {}", lo, hi);
+        let (lo, hi) = wilson_interval(10, 10);
+        assert!(close(lo, 0.7225, 1e-4) && close(hi, 1.0, 1e-9), "{} {}", lo, hi);
+        assert_eq!(wilson_interval(0, 0), (0.0, 1.0));
+    }
+
+    #[test]
+    fn p_value_matches_the_z_test() {
+        // 60/100 vs 40/100: z = 2.828
+        let p = two_proportion_p_value(60, 100, 40, 100).unwrap();
+        assert!(close(p, 0.00468, 1e-5), "{}", p);
+        assert_eq!(two_proportion_p_value(40, 100, 60, 100), Some(p));
+        let p = two_proportion_p_value(50, 100, 50, 100).unwrap();
+        assert!(close(p, 1.0, 1e-6), "{}", p);
+        assert!(close(normal_cdf(Z_95), 0.975, 1e-6));
+    }
+
+    #[test]
+    fn p_value_needs_samples_and_variance() {
+        assert_eq!(two_proportion_p_value(0, 0, 5, 10), None);
+        assert_eq!(two_proportion_p_value(5, 10, 0, 0), None);
+        assert_eq!(two_proportion_p_value(0, 10, 0, 20), None);
+        assert_eq!(two_proportion_p_value(10, 10, 20, 20), None);
+    }
+}
@@ -0,0 +1,106 @@
+use anyhow::{anyhow, Context, Result};
+use ed25519_dalek::pkcs8::DecodePrivateKey;
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use sha2::{Digest, Sha256};
+
+// Detached ed25519 signatures for exported reports. The report's summary carries the
+// SHA-256 of the config file the run was started with; `<report>.sig` signs the report's
+// bytes together with that hash, so a client can check that a deliverable is untouched
+// and came from a specific configuration.
+
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct SigningConfig {
+    /// PKCS#8 PEM ed25519 private key (`openssl genpkey -algorithm ed25519`).
+    pub key: String,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct ReportSignature {
+    pub algorithm: String,
+    /// Hex-encoded ed25519 public key.
+    pub public_key: String,
+    pub signature: String,
+    pub report_sha256: String,
+    #[serde(default)]
+    pub config_hash: Option<String>,
+}
+
+/// SHA-256 of a config file's text, as embedded in the report summary.
+pub fn config_hash(content: &str) -> String {
+    hex::encode(Sha256::digest(content.as_bytes()))
+}
+
+pub fn signature_path(report_path: &str) -> String {
+    format!("{}.sig", report_path)
+}
+
+/// What gets signed: the report digest and config hash, versioned so the scheme can change.
+fn message(report_sha256: &str, config_hash: Option<&str>) -> Vec<u8> {
+    format!("spectre-report-v1\n{}\n{}\n", report_sha256, config_hash.unwrap_or("-")).into_bytes()
+}
+
+pub struct ReportSigner {
+    key: SigningKey,
+}
+
+impl ReportSigner {
+    pub fn load(config: &SigningConfig) -> Result<Self> {
+        let pem = std::fs::read_to_string(&config.key).context(format!("Failed to read signing key {}", config.key))?;
+        let key = SigningKey::from_pkcs8_pem(&pem).map_err(|e| anyhow!("Invalid ed25519 key in {}: {}", config.key, e))?;
+        Ok(Self { key })
+    }
+
+    pub fn public_key(&self) -> String {
+        hex::encode(self.key.verifying_key().as_bytes())
+    }
+
+    /// Writes `<report>.sig` next to the report and returns its path.
+    pub fn sign_file(&self, report_path: &str, config_hash: Option<&str>) -> Result<String> {
+        let bytes = std::fs::read(report_path).context(format!("Failed to read report {}", report_path))?;
+        let report_sha256 = hex::encode(Sha256::digest(&bytes));
+        let signature = self.key.sign(&message(&report_sha256, config_hash));
+        let sig = ReportSignature {
+            algorithm: "ed25519".to_string(),
+            public_key: self.public_key(),
+            signature: hex::encode(signature.to_bytes()),
+            report_sha256,
+            config_hash: config_hash.map(|h| h.to_string()),
+        };
+        let path = signature_path(report_path);
+        std::fs::write(&path, serde_json::to_string_pretty(&sig)?).context(format!("Failed to write {}", path))?;
+        Ok(path)
+    }
+}
+
+/// Checks `report_path` against its `.sig`. With `expected_key` (hex) the signer must be
+/// that key; otherwise the caller has to compare the returned public key out of band.
+pub fn verify_file(report_path: &str, expected_key: Option<&str>) -> Result<ReportSignature> {
+    let sig_path = signature_path(report_path);
+    let sig: ReportSignature = serde_json::from_str(
+        &std::fs::read_to_string(&sig_path).context(format!("Failed to read signature {}", sig_path))?,
+    )
+    .context(format!("Invalid signature file {}", sig_path))?;
+    if sig.algorithm != "ed25519" {
+        return Err(anyhow!("Unsupported signature algorithm '{}'", sig.algorithm));
+    }
+    if let Some(expected) = expected_key {
+        if !expected.trim().eq_ignore_ascii_case(&sig.public_key) {
+            return Err(anyhow!("Signed by {}, not the expected key", sig.public_key));
+        }
+    }
+    let bytes = std::fs::read(report_path).context(format!("Failed to read report {}", report_path))?;
+    let actual = hex::encode(Sha256::digest(&bytes));
+    if actual != sig.report_sha256 {
+        return Err(anyhow!("Report {} was modified after signing", report_path));
+    }
+    let key_bytes: [u8; 32] = hex::decode(&sig.public_key)?
+        .try_into()
+        .map_err(|_| anyhow!("Public key must be 32 bytes"))?;
+    let sig_bytes: [u8; 64] = hex::decode(&sig.signature)?
+        .try_into()
+        .map_err(|_| anyhow!("Signature must be 64 bytes"))?;
+    VerifyingKey::from_bytes(&key_bytes)?
+        .verify_strict(&message(&actual, sig.config_hash.as_deref()), &Signature::from_bytes(&sig_bytes))
+        .map_err(|_| anyhow!("Signature does not match report {}", report_path))?;
+    Ok(sig)
+}
@@ -0,0 +1,279 @@
+use crate::report::{Finding, ScanSummary};
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use std::fs::File;
+use log::{error, warn};
+use std::io::{BufWriter, Write};
+use std::time::Duration;
+
+// Result backends. The engine streams one record per request to every configured sink and
+// hands them the run summary at the end; new backends only need a `ResultSink` impl and a
+// `SinkConfig` variant.
+
+/// One `[[sinks]]` entry.
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SinkConfig {
+    Jsonl { path: String },
+    /// Request records as CSV rows; the summary goes to `<path>.summary.json`.
+    Csv { path: String },
+    Sqlite { path: String },
+    /// POSTs records as JSON arrays of `batch_size`, then the summary.
+    Http {
+        url: String,
+        #[serde(default = "default_batch_size")]
+        batch_size: usize,
+    },
+}
+
+fn default_batch_size() -> usize {
+    100
+}
+
+#[async_trait]
+pub trait ResultSink: Send {
+    async fn write_request_record(&mut self, record: &Finding) -> Result<()>;
+    async fn write_summary(&mut self, summary: &ScanSummary) -> Result<()>;
+    async fn flush(&mut self) -> Result<()>;
+}
+
+impl SinkConfig {
+    /// Local files this sink writes (for artifact upload).
+    pub fn artifacts(&self) -> Vec<String> {
+        match self {
+            SinkConfig::Jsonl { path } | SinkConfig::Sqlite { path } => vec![path.clone()],
+            SinkConfig::Csv { path } => vec![path.clone(), format!("{}.summary.json", path)],
+            SinkConfig::Http { .. } => vec![],
+        }
+    }
+
+    pub fn open(&self) -> Result<Box<dyn ResultSink>> {
+        Ok(match self {
+            SinkConfig::Jsonl { path } => Box::new(JsonlSink::create(path)?),
+            SinkConfig::Csv { path } => Box::new(CsvSink::create(path)?),
+            SinkConfig::Sqlite { path } => Box::new(SqliteSink::open(path)?),
+            SinkConfig::Http { url, batch_size } => Box::new(HttpSink::new(url, *batch_size)?),
+        })
+    }
+}
+
+pub struct JsonlSink {
+    out: BufWriter<File>,
+}
+
+impl JsonlSink {
+    pub fn create(path: &str) -> Result<Self> {
+        let file = File::create(path).context(format!("Failed to create JSONL sink {}", path))?;
+        Ok(Self { out: BufWriter::new(file) })
+    }
+}
+
+#[async_trait]
+impl ResultSink for JsonlSink {
+    async fn write_request_record(&mut self, record: &Finding) -> Result<()> {
+        let line = serde_json::json!({ "type": "request", "record": record });
+        writeln!(self.out, "{}", line)?;
+        Ok(())
+    }
+
+    async fn write_summary(&mut self, summary: &ScanSummary) -> Result<()> {
+        let line = serde_json::json!({ "type": "summary", "summary": summary });
+        writeln!(self.out, "{}", line)?;
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+pub struct CsvSink {
+    path: String,
+    writer: csv::Writer<File>,
+}
+
+impl CsvSink {
+    pub fn create(path: &str) -> Result<Self> {
+        let writer = csv::Writer::from_path(path).context(format!("Failed to create CSV sink {}", path))?;
+        Ok(Self { path: path.to_string(), writer })
+    }
+}
+
+#[async_trait]
+impl ResultSink for CsvSink {
+    async fn write_request_record(&mut self, record: &Finding) -> Result<()> {
+        self.writer.serialize(record)?;
+        Ok(())
+    }
+
+    async fn write_summary(&mut self, summary: &ScanSummary) -> Result<()> {
+        // The summary is nested; CSV can't hold it
+        let path = format!("{}.summary.json", self.path);
+        let file = File::create(&path).context(format!("Failed to create {}", path))?;
+        serde_json::to_writer_pretty(file, summary)?;
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Local file writes; fast enough to run inline on the sink task.
+pub struct SqliteSink {
+    conn: rusqlite::Connection,
+}
+
+impl SqliteSink {
+    pub fn open(path: &str) -> Result<Self> {
+        let conn = rusqlite::Connection::open(path).context(format!("Failed to open SQLite sink {}", path))?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS requests (
+                ts INTEGER NOT NULL,
+                url TEXT NOT NULL,
+                payload TEXT NOT NULL,
+                status INTEGER NOT NULL,
+                verdict TEXT NOT NULL,
+                profile TEXT NOT NULL,
+                vendor TEXT NOT NULL DEFAULT ''
+            );
+            CREATE TABLE IF NOT EXISTS summaries (
+                ts INTEGER NOT NULL,
+                target TEXT NOT NULL,
+                summary TEXT NOT NULL
+            );",
+        )?;
+        // Databases created before the vendor column
+        let has_vendor = conn
+            .prepare("SELECT 1 FROM pragma_table_info('requests') WHERE name = 'vendor'")?
+            .exists([])?;
+        if !has_vendor {
+            conn.execute("ALTER TABLE requests ADD COLUMN vendor TEXT NOT NULL DEFAULT ''", [])
+                .context(format!("Failed to add the vendor column to {}", path))?;
+        }
+        Ok(Self { conn })
+    }
+}
+
+#[async_trait]
+impl ResultSink for SqliteSink {
+    async fn write_request_record(&mut self, record: &Finding) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO requests (ts, url, payload, status, verdict, profile, vendor) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            rusqlite::params![
+                record.timestamp as i64,
+                record.url,
+                record.payload,
+                record.status_code,
+                record.verdict,
+                record.profile,
+                record.vendor
+            ],
+        )?;
+        Ok(())
+    }
+
+    async fn write_summary(&mut self, summary: &ScanSummary) -> Result<()> {
+        let ts = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as i64;
+        self.conn.execute(
+            "INSERT INTO summaries (ts, target, summary) VALUES (?1, ?2, ?3)",
+            rusqlite::params![ts, summary.target, serde_json::to_string(summary)?],
+        )?;
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+const HTTP_SINK_ATTEMPTS: u32 = 3;
+const HTTP_SINK_BACKOFF: Duration = Duration::from_secs(1);
+/// Failed batches kept for retry before the oldest records are dropped.
+const HTTP_SINK_MAX_PENDING_BATCHES: usize = 10;
+
+pub struct HttpSink {
+    client: rquest::Client,
+    url: String,
+    batch_size: usize,
+    pending: Vec<Finding>,
+}
+
+impl HttpSink {
+    pub fn new(url: &str, batch_size: usize) -> Result<Self> {
+        if batch_size == 0 {
+            return Err(anyhow!("HTTP sink batch_size must be at least 1"));
+        }
+        Ok(Self {
+            client: rquest::Client::builder().build()?,
+            url: url.to_string(),
+            batch_size,
+            pending: Vec::new(),
+        })
+    }
+
+    async fn post_once(&self, body: &serde_json::Value) -> Result<()> {
+        let resp = self.client.post(&self.url).json(body).send().await?;
+        if !resp.status().is_success() {
+            return Err(anyhow!("HTTP sink {} answered {}", self.url, resp.status()));
+        }
+        Ok(())
+    }
+
+    /// Retries with exponential backoff before giving up on a body.
+    async fn post(&self, body: &serde_json::Value) -> Result<()> {
+        let mut delay = HTTP_SINK_BACKOFF;
+        for _ in 1..HTTP_SINK_ATTEMPTS {
+            match self.post_once(body).await {
+                Ok(()) => return Ok(()),
+                Err(e) => warn!("HTTP sink post failed, retrying in {:?}: {:#}", delay, e),
+            }
+            tokio::time::sleep(delay).await;
+            delay *= 2;
+        }
+        self.post_once(body).await
+    }
+}
+
+#[async_trait]
+impl ResultSink for HttpSink {
+    async fn write_request_record(&mut self, record: &Finding) -> Result<()> {
+        self.pending.push(record.clone());
+        if self.pending.len() >= self.batch_size {
+            self.flush().await?;
+        }
+        Ok(())
+    }
+
+    async fn write_summary(&mut self, summary: &ScanSummary) -> Result<()> {
+        self.flush().await?;
+        self.post(&serde_json::json!({ "summary": summary })).await
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        let batch = std::mem::take(&mut self.pending);
+        if let Err(e) = self.post(&serde_json::json!({ "records": batch })).await {
+            // Keep the records for the next flush, up to a bound so a dead endpoint can't
+            // grow the queue without limit
+            let mut requeued = batch;
+            requeued.append(&mut self.pending);
+            let limit = self.batch_size * HTTP_SINK_MAX_PENDING_BATCHES;
+            if requeued.len() > limit {
+                let dropped = requeued.len() - limit;
+                requeued.drain(..dropped);
+                error!("HTTP sink {} unreachable, dropped {} oldest records", self.url, dropped);
+            }
+            self.pending = requeued;
+            return Err(e);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,116 @@
+use crate::raw::{RawClient, RawTarget};
+use anyhow::Result;
+use std::time::Duration;
+
+// Timing-based HTTP desync detection (CL.TE / TE.CL). Probes are built so that a
+// vulnerable back-end waits for bytes that never come, instead of smuggling a second
+// request: a susceptible path shows up as a timeout, nothing is poisoned.
+// Only runs with --enable-intrusive.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DesyncKind {
+    ClTe,
+    TeCl,
+}
+
+impl std::fmt::Display for DesyncKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DesyncKind::ClTe => write!(f, "CL.TE"),
+            DesyncKind::TeCl => write!(f, "TE.CL"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ProbeResult {
+    pub kind: DesyncKind,
+    pub attempts: usize,
+    pub timeouts: usize,
+    pub baseline_ms: u128,
+    pub skipped: bool,
+}
+
+impl ProbeResult {
+    /// Majority of attempts stalled while a normal request answers promptly.
+    pub fn likely_susceptible(&self) -> bool {
+        !self.skipped && self.timeouts * 2 > self.attempts
+    }
+}
+
+pub struct SmuggleProbe {
+    target: RawTarget,
+    client: RawClient,
+    attempts: usize,
+}
+
+impl SmuggleProbe {
+    pub fn new(url: &str, timeout: Duration, attempts: usize) -> Result<Self> {
+        Ok(Self {
+            target: RawTarget::parse(url)?,
+            client: RawClient {
+                read_timeout: timeout,
+                ..RawClient::default()
+            },
+            attempts: attempts.max(1),
+        })
+    }
+
+    fn request(&self, extra_headers: &str, body: &str) -> Vec<u8> {
+        format!(
+            "POST {} HTTP/1.1\r\nHost: {}\r\nUser-Agent: Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/130.0.0.0 Safari/537.36\r\nContent-Type: application/x-www-form-urlencoded\r\nConnection: close\r\n{}\r\n{}",
+            self.target.path,
+            self.target.host_header(),
+            extra_headers,
+            body
+        )
+        .into_bytes()
+    }
+
+    fn baseline_request(&self) -> Vec<u8> {
+        self.request("Content-Length: 3\r\n", "a=1")
+    }
+
+    /// Front-end honours Content-Length (4 bytes), back-end waits for the next chunk.
+    fn cl_te_request(&self) -> Vec<u8> {
+        self.request("Transfer-Encoding: chunked\r\nContent-Length: 4\r\n", "1\r\nA\r\nX")
+    }
+
+    /// Front-end honours chunked (stops at 0-chunk), back-end waits for Content-Length bytes.
+    fn te_cl_request(&self) -> Vec<u8> {
+        self.request("Transfer-Encoding: chunked\r\nContent-Length: 6\r\n", "0\r\n\r\nX")
+    }
+
+    async fn run_probe(&self, kind: DesyncKind, baseline_ms: u128) -> Result<ProbeResult> {
+        let payload = match kind {
+            DesyncKind::ClTe => self.cl_te_request(),
+            DesyncKind::TeCl => self.te_cl_request(),
+        };
+        let mut timeouts = 0;
+        for _ in 0..self.attempts {
+            if self.client.send(&self.target, &payload).await?.timed_out {
+                timeouts += 1;
+            }
+        }
+        Ok(ProbeResult { kind, attempts: self.attempts, timeouts, baseline_ms, skipped: false })
+    }
+
+    pub async fn run(&self) -> Result<Vec<ProbeResult>> {
+        let baseline = self.client.send(&self.target, &self.baseline_request()).await?;
+        let baseline_ms = baseline.elapsed.as_millis();
+        if baseline.timed_out {
+            return Err(anyhow::anyhow!(
+                "Baseline request timed out; timing probes would be meaningless"
+            ));
+        }
+
+        let cl_te = self.run_probe(DesyncKind::ClTe, baseline_ms).await?;
+        // The TE.CL probe can desync a CL.TE pipeline, so only send it when CL.TE came back clean
+        let te_cl = if cl_te.likely_susceptible() {
+            ProbeResult { kind: DesyncKind::TeCl, attempts: 0, timeouts: 0, baseline_ms, skipped: true }
+        } else {
+            self.run_probe(DesyncKind::TeCl, baseline_ms).await?
+        };
+        Ok(vec![cl_te, te_cl])
+    }
+}
@@ -0,0 +1,468 @@
+use crate::engine::ProxyEndpoint;
+use anyhow::{anyhow, Context, Result};
+use base64::Engine as _;
+use rand::Rng;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+
+// Authenticated SOCKS proxies for the browser solver. Chrome and Firefox speak SOCKS5
+// without authentication only and never ask for credentials, so CDP's Fetch.authRequired
+// can't help. Spectre runs a relay per SOCKS proxy with credentials, replays each CONNECT
+// upstream with username/password authentication (RFC 1929), then relays the bytes.
+// Hostnames pass through untouched, so socks5h keeps resolving on the proxy.
+//
+// The relay must not hand the paid upstream to whoever else runs on the host. Chrome gets
+// an HTTP proxy on loopback that wants per-relay random credentials, which it does answer
+// over CDP. Firefox answers neither, so it gets SOCKS5 on a Unix socket in a fresh directory
+// only this user can enter. The relays belong to one run and close with it.
+
+/// A relay's front, as the browser is pointed at it.
+#[derive(Debug, Clone)]
+enum Front {
+    Http(ProxyEndpoint),
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+#[derive(Debug)]
+struct Relay {
+    front: Front,
+    listener: JoinHandle<()>,
+}
+
+impl Drop for Relay {
+    fn drop(&mut self) {
+        self.listener.abort();
+        #[cfg(unix)]
+        if let Front::Unix(path) = &self.front {
+            if let Some(dir) = path.parent() {
+                let _ = std::fs::remove_dir_all(dir);
+            }
+        }
+    }
+}
+
+/// The relays of one run, by upstream; dropping this closes their listeners.
+#[derive(Debug, Default)]
+pub struct SocksRelays {
+    relays: Mutex<HashMap<String, Relay>>,
+}
+
+impl SocksRelays {
+    /// An HTTP proxy on loopback that relays to `proxy`, with credentials of its own for
+    /// Chrome to answer; started on first use.
+    pub async fn http(&self, proxy: &ProxyEndpoint) -> Result<ProxyEndpoint> {
+        let key = Self::key("http", proxy)?;
+        let mut relays = self.relays.lock().await;
+        if let Some(Relay { front: Front::Http(endpoint), .. }) = relays.get(&key) {
+            return Ok(endpoint.clone());
+        }
+        let listener = TcpListener::bind("127.0.0.1:0").await.context("Failed to bind SOCKS relay listener")?;
+        let addr = listener.local_addr()?;
+        let endpoint = ProxyEndpoint {
+            scheme: "http".to_string(),
+            host: addr.ip().to_string(),
+            port: addr.port(),
+            username: Some(token()),
+            password: Some(token()),
+        };
+        let expected = basic_auth(&endpoint);
+        let upstream = proxy.clone();
+        let listener = tokio::spawn(async move {
+            while let Ok((inbound, _)) = listener.accept().await {
+                let (upstream, expected) = (upstream.clone(), expected.clone());
+                tokio::spawn(async move {
+                    if let Err(e) = serve_http(inbound, &expected, &upstream).await {
+                        log::debug!("SOCKS relay to {} failed: {:#}", upstream.server(), e);
+                    }
+                });
+            }
+        });
+        relays.insert(key, Relay { front: Front::Http(endpoint.clone()), listener });
+        Ok(endpoint)
+    }
+
+    /// A SOCKS5 Unix socket that relays to `proxy`, in a directory only this user can
+    /// enter; started on first use.
+    #[cfg(unix)]
+    pub async fn unix(&self, proxy: &ProxyEndpoint) -> Result<PathBuf> {
+        use std::os::unix::fs::DirBuilderExt;
+        let key = Self::key("unix", proxy)?;
+        let mut relays = self.relays.lock().await;
+        if let Some(Relay { front: Front::Unix(path), .. }) = relays.get(&key) {
+            return Ok(path.clone());
+        }
+        // A fresh, unguessable name: creating it fails rather than reuses what someone planted
+        let dir = std::env::temp_dir().join(format!("spectre-relay-{}-{}", std::process::id(), token()));
+        std::fs::DirBuilder::new()
+            .mode(0o700)
+            .create(&dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+        let path = dir.join("socks.sock");
+        let listener = tokio::net::UnixListener::bind(&path).context("Failed to bind SOCKS relay socket")?;
+        let upstream = proxy.clone();
+        let listener = tokio::spawn(async move {
+            while let Ok((inbound, _)) = listener.accept().await {
+                let upstream = upstream.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = serve_socks(inbound, &upstream).await {
+                        log::debug!("SOCKS relay to {} failed: {:#}", upstream.server(), e);
+                    }
+                });
+            }
+        });
+        relays.insert(key, Relay { front: Front::Unix(path.clone()), listener });
+        Ok(path)
+    }
+
+    fn key(front: &str, proxy: &ProxyEndpoint) -> Result<String> {
+        if !proxy.is_socks() || !proxy.has_credentials() {
+            return Err(anyhow!("{} needs no relay", proxy.server()));
+        }
+        Ok(format!("{} {}@{}", front, proxy.username.as_deref().unwrap_or(""), proxy.server()))
+    }
+}
+
+/// 128 random bits, hex.
+fn token() -> String {
+    hex::encode(rand::thread_rng().gen::<[u8; 16]>())
+}
+
+/// The `Proxy-Authorization` value `endpoint`'s credentials make.
+fn basic_auth(endpoint: &ProxyEndpoint) -> String {
+    let pair = format!("{}:{}", endpoint.username.as_deref().unwrap_or(""), endpoint.password.as_deref().unwrap_or(""));
+    format!("Basic {}", base64::engine::general_purpose::STANDARD.encode(pair))
+}
+
+/// Longest request head the HTTP front reads.
+const MAX_HEAD: usize = 16 * 1024;
+
+/// One connection to the HTTP front: a CONNECT, or a plain request in absolute form,
+/// carrying the relay's credentials. Anything else is turned away with a 407, which is
+/// what makes Chrome send them.
+async fn serve_http(mut inbound: TcpStream, expected: &str, proxy: &ProxyEndpoint) -> Result<()> {
+    let head = read_head(&mut inbound).await?;
+    let text = String::from_utf8_lossy(&head).to_string();
+    let mut lines = text.split("\r\n");
+    let request_line = lines.next().unwrap_or_default().to_string();
+    let headers: Vec<&str> = lines.take_while(|l| !l.is_empty()).collect();
+    let authorized = headers.iter().any(|h| {
+        h.split_once(':')
+            .is_some_and(|(name, value)| name.trim().eq_ignore_ascii_case("proxy-authorization") && value.trim() == expected)
+    });
+    if !authorized {
+        inbound
+            .write_all(b"HTTP/1.1 407 Proxy Authentication Required\r\nProxy-Authenticate: Basic realm=\"spectre\"\r\nContent-Length: 0\r\n\r\n")
+            .await?;
+        return Ok(());
+    }
+
+    let mut parts = request_line.split(' ');
+    let (method, target) = (parts.next().unwrap_or_default(), parts.next().unwrap_or_default());
+    let (host, port, forward) = if method.eq_ignore_ascii_case("CONNECT") {
+        let (host, port) = split_host_port(target, 443)?;
+        (host, port, None)
+    } else {
+        let url = rquest::Url::parse(target).map_err(|_| anyhow!("not a proxy request: {}", request_line))?;
+        let host = url.host_str().ok_or_else(|| anyhow!("no host in {}", target))?.trim_matches(&['[', ']'][..]).to_string();
+        let port = url.port_or_known_default().unwrap_or(80);
+        let path = match url.query() {
+            Some(query) => format!("{}?{}", url.path(), query),
+            None => url.path().to_string(),
+        };
+        // Origin form, one request per connection: the next may be for another host
+        let mut forward = format!("{} {} HTTP/1.1\r\n", method, path);
+        for header in &headers {
+            let name = header.split(':').next().unwrap_or_default().trim().to_ascii_lowercase();
+            if !matches!(name.as_str(), "proxy-authorization" | "proxy-connection" | "connection" | "keep-alive") {
+                forward.push_str(header);
+                forward.push_str("\r\n");
+            }
+        }
+        forward.push_str("Connection: close\r\n\r\n");
+        (host, port, Some(forward))
+    };
+
+    let mut request = vec![5, 1, 0];
+    request.extend(encode_address(&host, port)?);
+    let mut upstream = match open(proxy, &request).await {
+        Ok(upstream) => upstream,
+        Err(e) => {
+            let _ = inbound.write_all(b"HTTP/1.1 502 Bad Gateway\r\nContent-Length: 0\r\n\r\n").await;
+            return Err(e);
+        }
+    };
+    match forward {
+        None => inbound.write_all(b"HTTP/1.1 200 Connection Established\r\n\r\n").await?,
+        Some(forward) => upstream.write_all(forward.as_bytes()).await?,
+    }
+    // Bytes Chrome sent after the head (a request body) go on as they came
+    let body_start = text.find("\r\n\r\n").map(|i| i + 4).unwrap_or(head.len());
+    upstream.write_all(&head[body_start..]).await?;
+    tokio::io::copy_bidirectional(&mut inbound, &mut upstream).await?;
+    Ok(())
+}
+
+/// Reads until the end of the request head; returns everything read, which may run on
+/// into the body.
+async fn read_head(inbound: &mut TcpStream) -> Result<Vec<u8>> {
+    let mut head = Vec::new();
+    let mut buf = [0u8; 4096];
+    while !head.windows(4).any(|w| w == b"\r\n\r\n") {
+        if head.len() > MAX_HEAD {
+            return Err(anyhow!("request head over {} bytes", MAX_HEAD));
+        }
+        let n = inbound.read(&mut buf).await?;
+        if n == 0 {
+            return Err(anyhow!("connection closed before the request head ended"));
+        }
+        head.extend_from_slice(&buf[..n]);
+    }
+    Ok(head)
+}
+
+/// `host:port` or `[v6]:port`.
+fn split_host_port(target: &str, default_port: u16) -> Result<(String, u16)> {
+    if let Some(rest) = target.strip_prefix('[') {
+        let (host, port) = rest.split_once(']').ok_or_else(|| anyhow!("bad address {}", target))?;
+        let port = port.strip_prefix(':').map(str::parse).transpose()?.unwrap_or(default_port);
+        return Ok((host.to_string(), port));
+    }
+    match target.rsplit_once(':') {
+        Some((host, port)) => Ok((host.to_string(), port.parse().with_context(|| format!("bad port in {}", target))?)),
+        None => Ok((target.to_string(), default_port)),
+    }
+}
+
+/// A SOCKS5 address: IPv4, IPv6, or the name as given, then the port.
+fn encode_address(host: &str, port: u16) -> Result<Vec<u8>> {
+    let mut out = match host.parse::<std::net::IpAddr>() {
+        Ok(std::net::IpAddr::V4(ip)) => [&[1u8][..], &ip.octets()].concat(),
+        Ok(std::net::IpAddr::V6(ip)) => [&[4u8][..], &ip.octets()].concat(),
+        Err(_) => {
+            if host.len() > 255 {
+                return Err(anyhow!("host name over 255 bytes"));
+            }
+            [&[3u8, host.len() as u8][..], host.as_bytes()].concat()
+        }
+    };
+    out.extend(port.to_be_bytes());
+    Ok(out)
+}
+
+/// One connection to the Unix-socket front, from Firefox: unauthenticated SOCKS5, since
+/// only this user can reach the socket.
+async fn serve_socks<S: AsyncRead + AsyncWrite + Unpin>(mut inbound: S, proxy: &ProxyEndpoint) -> Result<()> {
+    // The greeting: version, method count, methods. We offer "no authentication".
+    let mut greeting = [0u8; 2];
+    inbound.read_exact(&mut greeting).await?;
+    if greeting[0] != 5 {
+        return Err(anyhow!("not a SOCKS5 client"));
+    }
+    let mut methods = vec![0u8; greeting[1] as usize];
+    inbound.read_exact(&mut methods).await?;
+    inbound.write_all(&[5, 0]).await?;
+
+    let request = read_request(&mut inbound).await?;
+    let mut upstream = match open(proxy, &request).await {
+        Ok(upstream) => upstream,
+        Err(e) => {
+            // General failure, so the browser reports a proxy error rather than hanging
+            let _ = inbound.write_all(&[5, 1, 0, 1, 0, 0, 0, 0, 0, 0]).await;
+            return Err(e);
+        }
+    };
+    inbound.write_all(&[5, 0, 0, 1, 0, 0, 0, 0, 0, 0]).await?;
+    tokio::io::copy_bidirectional(&mut inbound, &mut upstream).await?;
+    Ok(())
+}
+
+/// Logs in to the upstream proxy and sends `request`, a SOCKS5 CONNECT; returns the
+/// connection once the proxy has accepted it.
+async fn open(proxy: &ProxyEndpoint, request: &[u8]) -> Result<TcpStream> {
+    let mut upstream = TcpStream::connect((proxy.host.as_str(), proxy.port))
+        .await
+        .with_context(|| format!("{} unreachable", proxy.server()))?;
+    login(&mut upstream, proxy).await?;
+    upstream.write_all(request).await?;
+    let reply = read_reply(&mut upstream).await?;
+    if reply[1] != 0 {
+        return Err(anyhow!("{} refused the CONNECT (reply {})", proxy.server(), reply[1]));
+    }
+    Ok(upstream)
+}
+
+/// Username/password login on the upstream proxy. One that needs no authentication
+/// (it answers `[5, 0]`) is taken as it is.
+async fn login(upstream: &mut TcpStream, proxy: &ProxyEndpoint) -> Result<()> {
+    upstream.write_all(&[5, 2, 0, 2]).await?;
+    let mut choice = [0u8; 2];
+    upstream.read_exact(&mut choice).await?;
+    match choice {
+        [5, 0] => return Ok(()),
+        [5, 2] => {}
+        _ => return Err(anyhow!("{} doesn't offer username/password authentication", proxy.server())),
+    }
+    let user = proxy.username.as_deref().unwrap_or("").as_bytes();
+    let pass = proxy.password.as_deref().unwrap_or("").as_bytes();
+    if user.len() > 255 || pass.len() > 255 {
+        return Err(anyhow!("SOCKS credentials are limited to 255 bytes each"));
+    }
+    let mut auth = vec![1, user.len() as u8];
+    auth.extend_from_slice(user);
+    auth.push(pass.len() as u8);
+    auth.extend_from_slice(pass);
+    upstream.write_all(&auth).await?;
+    let mut status = [0u8; 2];
+    upstream.read_exact(&mut status).await?;
+    if status[1] != 0 {
+        return Err(anyhow!("{} rejected the credentials", proxy.server()));
+    }
+    Ok(())
+}
+
+/// A CONNECT request, byte for byte, for replaying upstream.
+async fn read_request<S: AsyncRead + AsyncWrite + Unpin>(inbound: &mut S) -> Result<Vec<u8>> {
+    let mut head = [0u8; 4];
+    inbound.read_exact(&mut head).await?;
+    if head[1] != 1 {
+        inbound.write_all(&[5, 7, 0, 1, 0, 0, 0, 0, 0, 0]).await?;
+        return Err(anyhow!("only CONNECT is relayed (command {})", head[1]));
+    }
+    let mut request = head.to_vec();
+    request.extend(read_address(inbound, head[3]).await?);
+    Ok(request)
+}
+
+/// The upstream's reply to CONNECT, byte for byte.
+async fn read_reply(upstream: &mut TcpStream) -> Result<Vec<u8>> {
+    let mut head = [0u8; 4];
+    upstream.read_exact(&mut head).await?;
+    let mut reply = head.to_vec();
+    reply.extend(read_address(upstream, head[3]).await?);
+    Ok(reply)
+}
+
+/// Address and port of the given type: IPv4, domain name or IPv6.
+async fn read_address<S: AsyncRead + Unpin>(stream: &mut S, kind: u8) -> Result<Vec<u8>> {
+    let len = match kind {
+        1 => 4,
+        3 => {
+            let mut n = [0u8; 1];
+            stream.read_exact(&mut n).await?;
+            let mut name = vec![0u8; n[0] as usize + 2];
+            stream.read_exact(&mut name).await?;
+            let mut out = n.to_vec();
+            out.extend(name);
+            return Ok(out);
+        }
+        4 => 16,
+        _ => return Err(anyhow!("unknown SOCKS address type {}", kind)),
+    };
+    let mut address = vec![0u8; len + 2];
+    stream.read_exact(&mut address).await?;
+    Ok(address)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A SOCKS5 proxy that wants `user`/`pass` when `auth`, else no authentication, and
+    /// echoes whatever comes through the tunnel. Returns its address and the CONNECT it got.
+    async fn upstream(auth: bool) -> (SocketAddr, JoinHandle<Vec<u8>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let task = tokio::spawn(async move {
+            let (mut conn, _) = listener.accept().await.unwrap();
+            let mut greeting = [0u8; 4];
+            conn.read_exact(&mut greeting).await.unwrap();
+            assert_eq!(greeting, [5, 2, 0, 2]);
+            if auth {
+                conn.write_all(&[5, 2]).await.unwrap();
+                let mut login = [0u8; 11];
+                conn.read_exact(&mut login).await.unwrap();
+                assert_eq!(&login, b"\x01\x04user\x04pass");
+                conn.write_all(&[1, 0]).await.unwrap();
+            } else {
+                conn.write_all(&[5, 0]).await.unwrap();
+            }
+            let request = read_request(&mut conn).await.unwrap();
+            conn.write_all(&[5, 0, 0, 1, 0, 0, 0, 0, 0, 0]).await.unwrap();
+            let mut ping = [0u8; 4];
+            conn.read_exact(&mut ping).await.unwrap();
+            conn.write_all(&ping).await.unwrap();
+            request
+        });
+        (addr, task)
+    }
+
+    fn endpoint(addr: SocketAddr) -> ProxyEndpoint {
+        ProxyEndpoint {
+            scheme: "socks5h".to_string(),
+            host: addr.ip().to_string(),
+            port: addr.port(),
+            username: Some("user".to_string()),
+            password: Some("pass".to_string()),
+        }
+    }
+
+    async fn connect(front: &ProxyEndpoint, authorization: Option<&str>) -> (TcpStream, String) {
+        let mut conn = TcpStream::connect((front.host.as_str(), front.port)).await.unwrap();
+        let mut head = "CONNECT example.com:443 HTTP/1.1\r\nHost: example.com:443\r\n".to_string();
+        if let Some(value) = authorization {
+            head.push_str(&format!("Proxy-Authorization: {}\r\n", value));
+        }
+        head.push_str("\r\n");
+        conn.write_all(head.as_bytes()).await.unwrap();
+        let mut buf = vec![0u8; 256];
+        let n = conn.read(&mut buf).await.unwrap();
+        (conn, String::from_utf8_lossy(&buf[..n]).to_string())
+    }
+
+    #[tokio::test]
+    async fn tunnels_through_an_authenticating_upstream() {
+        let (addr, proxy) = upstream(true).await;
+        let relays = SocksRelays::default();
+        let front = relays.http(&endpoint(addr)).await.unwrap();
+        let (mut conn, reply) = connect(&front, Some(&basic_auth(&front))).await;
+        assert!(reply.starts_with("HTTP/1.1 200"), "{}", reply);
+        conn.write_all(b"ping").await.unwrap();
+        let mut echo = [0u8; 4];
+        conn.read_exact(&mut echo).await.unwrap();
+        assert_eq!(&echo, b"ping");
+        assert_eq!(proxy.await.unwrap(), [&[5u8, 1, 0, 3, 11][..], b"example.com", &443u16.to_be_bytes()].concat());
+    }
+
+    #[tokio::test]
+    async fn accepts_an_upstream_that_needs_no_login() {
+        let (addr, _proxy) = upstream(false).await;
+        let relays = SocksRelays::default();
+        let front = relays.http(&endpoint(addr)).await.unwrap();
+        let (_conn, reply) = connect(&front, Some(&basic_auth(&front))).await;
+        assert!(reply.starts_with("HTTP/1.1 200"), "{}", reply);
+    }
+
+    #[tokio::test]
+    async fn turns_away_clients_without_the_relay_credentials() {
+        let (addr, _proxy) = upstream(true).await;
+        let relays = SocksRelays::default();
+        let front = relays.http(&endpoint(addr)).await.unwrap();
+        let (_conn, reply) = connect(&front, None).await;
+        assert!(reply.starts_with("HTTP/1.1 407"), "{}", reply);
+        let (_conn, reply) = connect(&front, Some("Basic dXNlcjpwYXNz")).await;
+        assert!(reply.starts_with("HTTP/1.1 407"), "{}", reply);
+    }
+
+    #[test]
+    fn encodes_names_and_addresses() {
+        assert_eq!(encode_address("10.0.0.1", 80).unwrap(), [1, 10, 0, 0, 1, 0, 80]);
+        assert_eq!(encode_address("a.b", 443).unwrap(), [3, 3, b'a', b'.', b'b', 1, 187]);
+        assert_eq!(split_host_port("[::1]:8443", 443).unwrap(), ("::1".to_string(), 8443));
+    }
+}
@@ -0,0 +1,217 @@
+use crate::identity::IdentityTraits;
+use anyhow::{anyhow, Result};
+
+// Fingerprint patches for the solver's Chrome. Headless Chrome gives itself away to any
+// fingerprint script: `navigator.webdriver`, an empty plugin list, no `chrome.runtime`, a
+// notifications permission that contradicts `Notification.permission`, a SwiftShader WebGL
+// renderer, the host's core count. The patches are registered with
+// `Page.addScriptToEvaluateOnNewDocument` before navigation, so they are in place before
+// the first page script runs. `[profiles.<name>.stealth]` turns them on or off per profile.
+
+/// `[profiles.<name>.stealth]`; every patch is on unless switched off.
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct StealthConfig {
+    /// `navigator.webdriver` reads `false`, as in a browser nobody automates.
+    #[serde(default = "on")]
+    pub webdriver: bool,
+    /// Chrome's built-in PDF plugins in `navigator.plugins` and `navigator.mimeTypes`.
+    /// Desktop identities only: mobile Chrome has none.
+    #[serde(default = "on")]
+    pub plugins: bool,
+    /// `window.chrome.runtime`, as on desktop Chrome.
+    #[serde(default = "on")]
+    pub chrome_runtime: bool,
+    /// `permissions.query({name: 'notifications'})` agrees with `Notification.permission`.
+    #[serde(default = "on")]
+    pub permissions: bool,
+    /// WebGL unmasked vendor and renderer from the identity's GPU.
+    #[serde(default = "on")]
+    pub webgl: bool,
+    /// `navigator.hardwareConcurrency` from the identity.
+    #[serde(default = "on")]
+    pub hardware_concurrency: bool,
+    /// Screen size and `devicePixelRatio` from the identity.
+    #[serde(default = "on")]
+    pub screen: bool,
+    /// `navigator.languages` and the solver's `Accept-Language`, unless the profile sends
+    /// its own `Accept-Language`.
+    #[serde(default = "default_languages")]
+    pub languages: Vec<String>,
+}
+
+/// Serde default for the switches that are on unless turned off.
+pub(crate) fn on() -> bool {
+    true
+}
+
+fn default_languages() -> Vec<String> {
+    vec!["en-US".into(), "en".into()]
+}
+
+impl Default for StealthConfig {
+    fn default() -> Self {
+        Self {
+            webdriver: true,
+            plugins: true,
+            chrome_runtime: true,
+            permissions: true,
+            webgl: true,
+            hardware_concurrency: true,
+            screen: true,
+            languages: default_languages(),
+        }
+    }
+}
+
+/// Chrome's PDF viewer entries, identical in every desktop build since Chrome 94.
+const PLUGINS: &str = r#"
+    (() => {
+        const names = ['PDF Viewer', 'Chrome PDF Viewer', 'Chromium PDF Viewer', 'Microsoft Edge PDF Viewer', 'WebKit built-in PDF'];
+        const types = [['application/pdf', 'pdf'], ['text/pdf', 'pdf']];
+        const plugins = names.map((name) => {
+            const plugin = Object.create(Plugin.prototype);
+            const mimes = types.map(([type, suffixes]) => {
+                const mime = Object.create(MimeType.prototype);
+                Object.defineProperties(mime, {
+                    type: { get: () => type },
+                    suffixes: { get: () => suffixes },
+                    description: { get: () => 'Portable Document Format' },
+                    enabledPlugin: { get: () => plugin },
+                });
+                return mime;
+            });
+            Object.defineProperties(plugin, {
+                name: { get: () => name },
+                filename: { get: () => 'internal-pdf-viewer' },
+                description: { get: () => 'Portable Document Format' },
+                length: { get: () => mimes.length },
+            });
+            mimes.forEach((mime, i) => Object.defineProperty(plugin, i, { get: () => mime }));
+            plugin.item = (i) => mimes[i] || null;
+            plugin.namedItem = (type) => mimes.find((m) => m.type === type) || null;
+            return plugin;
+        });
+        const list = (proto, items, key) => {
+            const arr = Object.create(proto);
+            items.forEach((item, i) => Object.defineProperty(arr, i, { get: () => item, enumerable: true }));
+            Object.defineProperty(arr, 'length', { get: () => items.length });
+            arr.item = (i) => items[i] || null;
+            arr.namedItem = (name) => items.find((item) => item[key] === name) || null;
+            arr[Symbol.iterator] = function* () { yield* items; };
+            return arr;
+        };
+        const pluginArray = list(PluginArray.prototype, plugins, 'name');
+        pluginArray.refresh = () => {};
+        const mimeArray = list(MimeTypeArray.prototype, plugins[0] ? [plugins[0][0], plugins[0][1]] : [], 'type');
+        Object.defineProperty(Navigator.prototype, 'plugins', { get: () => pluginArray, configurable: true });
+        Object.defineProperty(Navigator.prototype, 'mimeTypes', { get: () => mimeArray, configurable: true });
+    })();
+"#;
+
+const CHROME_RUNTIME: &str = r#"
+    window.chrome = window.chrome || {};
+    if (!window.chrome.runtime) {
+        window.chrome.runtime = {
+            OnInstalledReason: { CHROME_UPDATE: 'chrome_update', INSTALL: 'install', SHARED_MODULE_UPDATE: 'shared_module_update', UPDATE: 'update' },
+            PlatformOs: { ANDROID: 'android', CROS: 'cros', LINUX: 'linux', MAC: 'mac', OPENBSD: 'openbsd', WIN: 'win' },
+            connect: () => { throw new TypeError('Error in invocation of runtime.connect'); },
+            sendMessage: () => { throw new TypeError('Error in invocation of runtime.sendMessage'); },
+        };
+    }
+"#;
+
+const PERMISSIONS: &str = r#"
+    if (navigator.permissions) {
+        const query = navigator.permissions.query.bind(navigator.permissions);
+        navigator.permissions.query = (descriptor) =>
+            descriptor && descriptor.name === 'notifications'
+                ? Promise.resolve(Object.setPrototypeOf({ state: Notification.permission === 'default' ? 'prompt' : Notification.permission, onchange: null }, PermissionStatus.prototype))
+                : query(descriptor);
+    }
+"#;
+
+impl StealthConfig {
+    /// `profile` names the profile in errors.
+    pub fn validate(&self, profile: &str) -> Result<()> {
+        if self.languages.is_empty() || self.languages.iter().any(|l| l.trim().is_empty()) {
+            return Err(anyhow!("profiles.{}.stealth.languages needs at least one language tag", profile));
+        }
+        Ok(())
+    }
+
+    /// `Accept-Language` matching `languages`: "en-US,en;q=0.9".
+    pub fn accept_language(&self) -> String {
+        self.languages
+            .iter()
+            .enumerate()
+            .map(|(i, lang)| match i {
+                0 => lang.clone(),
+                // 0.9, 0.8, ... never below 0.1
+                _ => format!("{};q={:.1}", lang, (1.0 - i as f32 * 0.1).max(0.1)),
+            })
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    /// The patches for one identity, as a single script. `languages` comes from the
+    /// browser persona, which prefers the profile's `Accept-Language` to `self.languages`.
+    pub fn script(&self, traits: &IdentityTraits, languages: &[String]) -> String {
+        let desktop = !traits.family.is_mobile();
+        let mut script = String::new();
+        if self.webdriver {
+            script.push_str("Object.defineProperty(Navigator.prototype, 'webdriver', { get: () => false, configurable: true });\n");
+        }
+        script.push_str(&format!(
+            "Object.defineProperty(Navigator.prototype, 'languages', {{ get: () => {:?}, configurable: true }});\n",
+            languages
+        ));
+        if self.plugins && desktop {
+            script.push_str(PLUGINS);
+        }
+        if self.chrome_runtime && desktop {
+            script.push_str(CHROME_RUNTIME);
+        }
+        if self.permissions {
+            script.push_str(PERMISSIONS);
+        }
+        if self.hardware_concurrency {
+            script.push_str(&format!(
+                "Object.defineProperty(Navigator.prototype, 'hardwareConcurrency', {{ get: () => {}, configurable: true }});\n",
+                traits.hardware_concurrency
+            ));
+        }
+        if self.screen {
+            script.push_str(&format!(
+                r#"
+                Object.defineProperty(screen, 'width', {{ get: () => {w} }});
+                Object.defineProperty(screen, 'height', {{ get: () => {h} }});
+                Object.defineProperty(screen, 'availWidth', {{ get: () => {w} }});
+                Object.defineProperty(screen, 'availHeight', {{ get: () => {avail_h} }});
+                Object.defineProperty(window, 'devicePixelRatio', {{ get: () => {dpr} }});
+            "#,
+                w = traits.screen_width,
+                h = traits.screen_height,
+                // Taskbar/dock height
+                avail_h = traits.screen_height.saturating_sub(40),
+                dpr = traits.pixel_ratio,
+            ));
+        }
+        if self.webgl {
+            script.push_str(&format!(
+                r#"
+                for (const proto of [WebGLRenderingContext.prototype, WebGL2RenderingContext.prototype]) {{
+                    const getParameter = proto.getParameter;
+                    proto.getParameter = function (p) {{
+                        if (p === 37445) return {vendor:?};
+                        if (p === 37446) return {renderer:?};
+                        return getParameter.call(this, p);
+                    }};
+                }}
+            "#,
+                vendor = traits.gpu_vendor,
+                renderer = traits.gpu_renderer,
+            ));
+        }
+        script
+    }
+}
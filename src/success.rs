@@ -0,0 +1,129 @@
+use anyhow::{anyhow, Result};
+use regex::Regex;
+use rquest::header::HeaderMap;
+use std::sync::OnceLock;
+
+// What a passed challenge looks like. `[success]` (or `[domains."host".success]` for one
+// host) replaces guessing from page text: an element the real app renders, a body pattern,
+// the clearance cookie, or the URL the challenge lands on. The browser solver polls for it
+// after navigating; HTTP responses that meet it are counted as successes before the
+// built-in heuristics run.
+
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+pub struct SuccessCriteria {
+    /// CSS selector of an element the page must contain, e.g. `app-root`. Checked in the
+    /// browser only.
+    #[serde(default)]
+    pub selector: Option<String>,
+    /// Regex the body must match.
+    #[serde(default)]
+    pub body: Option<String>,
+    /// Cookie that must be set, e.g. `cf_clearance`.
+    #[serde(default)]
+    pub cookie: Option<String>,
+    /// URL the page must have reached: a prefix of the full URL, or of its path when it
+    /// starts with `/`.
+    #[serde(default)]
+    pub url: Option<String>,
+    #[serde(skip)]
+    body_regex: OnceLock<Option<Regex>>,
+}
+
+impl SuccessCriteria {
+    pub fn is_empty(&self) -> bool {
+        self.selector.is_none() && self.body.is_none() && self.cookie.is_none() && self.url.is_none()
+    }
+
+    /// `what` names the table in errors.
+    pub fn validate(&self, what: &str) -> Result<()> {
+        if self.selector.as_deref().is_some_and(|s| s.trim().is_empty()) {
+            return Err(anyhow!("{}.selector is empty", what));
+        }
+        if self.cookie.as_deref().is_some_and(|c| c.trim().is_empty()) {
+            return Err(anyhow!("{}.cookie is empty", what));
+        }
+        if let Some(url) = self.url.as_deref() {
+            if !url.starts_with('/') && rquest::Url::parse(url).is_err() {
+                return Err(anyhow!("{}.url: '{}' is neither a URL nor a path", what, url));
+            }
+        }
+        if let Some(pattern) = &self.body {
+            Regex::new(pattern).map_err(|e| anyhow!("{}.body: invalid regex: {}", what, e))?;
+        }
+        Ok(())
+    }
+
+    fn body_matches(&self, body: &str) -> bool {
+        let Some(pattern) = &self.body else {
+            return true;
+        };
+        self.body_regex
+            .get_or_init(|| Regex::new(pattern).ok())
+            .as_ref()
+            .is_some_and(|re| re.is_match(body))
+    }
+
+    fn url_matches(&self, url: &str) -> bool {
+        let Some(want) = &self.url else {
+            return true;
+        };
+        if want.starts_with('/') {
+            rquest::Url::parse(url).is_ok_and(|u| u.path().starts_with(want.as_str()))
+        } else {
+            url.starts_with(want.as_str())
+        }
+    }
+
+    fn cookie_matches<'a>(&self, mut names: impl Iterator<Item = &'a str>) -> bool {
+        match &self.cookie {
+            Some(want) => names.any(|name| name == want),
+            None => true,
+        }
+    }
+
+    /// The browser's view: every condition except the selector, which the caller checks.
+    pub fn matches_page<'a>(&self, url: &str, cookies: impl Iterator<Item = &'a str>, body: &str) -> bool {
+        self.url_matches(url) && self.cookie_matches(cookies) && self.body_matches(body)
+    }
+
+    /// An HTTP response: every condition other than the selector. The cookie must be set by
+    /// this response; one the request merely carried proves nothing about the answer.
+    /// Criteria with nothing but a selector never match here.
+    pub fn matches_response(&self, url: &str, headers: &HeaderMap, body: &str) -> bool {
+        if self.body.is_none() && self.cookie.is_none() && self.url.is_none() {
+            return false;
+        }
+        let names = headers
+            .get_all(rquest::header::SET_COOKIE)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .filter_map(|v| v.split(';').next())
+            .filter_map(|pair| pair.split('=').next())
+            .map(str::trim);
+        self.url_matches(url) && self.cookie_matches(names) && self.body_matches(body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SuccessCriteria;
+    use rquest::header::{HeaderMap, HeaderValue, SET_COOKIE};
+
+    fn cookie(name: &str) -> SuccessCriteria {
+        SuccessCriteria { cookie: Some(name.to_string()), ..Default::default() }
+    }
+
+    #[test]
+    fn cookie_must_be_set_by_the_response() {
+        let mut headers = HeaderMap::new();
+        headers.insert(SET_COOKIE, HeaderValue::from_static("cf_clearance=abc; Path=/; HttpOnly"));
+        assert!(cookie("cf_clearance").matches_response("https://example.com/", &headers, ""));
+        assert!(!cookie("cf_clearance").matches_response("https://example.com/", &HeaderMap::new(), ""));
+    }
+
+    #[test]
+    fn selector_alone_never_matches_a_response() {
+        let criteria = SuccessCriteria { selector: Some("app-root".to_string()), ..Default::default() };
+        assert!(!criteria.matches_response("https://example.com/", &HeaderMap::new(), "<app-root></app-root>"));
+    }
+}
@@ -0,0 +1,200 @@
+use anyhow::{anyhow, Context, Result};
+use rquest::{EmulationProvider, ExtensionType, TlsConfig};
+
+// Custom TLS ClientHello per profile: either a JA3 string or the structured fields
+// below. JA4 is a hash, so a JA4 target has to be written out in structured form.
+
+/// IANA cipher suite id -> BoringSSL name. TLS 1.3 suites are fixed by BoringSSL and
+/// only accepted so JA3 strings from real browsers parse.
+static CIPHERS: &[(u16, &str)] = &[
+    (0x1301, "TLS_AES_128_GCM_SHA256"),
+    (0x1302, "TLS_AES_256_GCM_SHA384"),
+    (0x1303, "TLS_CHACHA20_POLY1305_SHA256"),
+    (0xc02b, "ECDHE-ECDSA-AES128-GCM-SHA256"),
+    (0xc02f, "ECDHE-RSA-AES128-GCM-SHA256"),
+    (0xc02c, "ECDHE-ECDSA-AES256-GCM-SHA384"),
+    (0xc030, "ECDHE-RSA-AES256-GCM-SHA384"),
+    (0xcca9, "ECDHE-ECDSA-CHACHA20-POLY1305"),
+    (0xcca8, "ECDHE-RSA-CHACHA20-POLY1305"),
+    (0xc009, "ECDHE-ECDSA-AES128-SHA"),
+    (0xc00a, "ECDHE-ECDSA-AES256-SHA"),
+    (0xc013, "ECDHE-RSA-AES128-SHA"),
+    (0xc014, "ECDHE-RSA-AES256-SHA"),
+    (0x009c, "AES128-GCM-SHA256"),
+    (0x009d, "AES256-GCM-SHA384"),
+    (0x002f, "AES128-SHA"),
+    (0x0035, "AES256-SHA"),
+    (0x000a, "DES-CBC3-SHA"),
+];
+
+static CURVES: &[(u16, &str)] = &[
+    (29, "X25519"),
+    (23, "P-256"),
+    (24, "P-384"),
+    (25, "P-521"),
+    (4588, "X25519MLKEM768"),
+    (25497, "X25519Kyber768Draft00"),
+];
+
+/// ClientHello `legacy_version` for TLS 1.2 and 1.3.
+const TLS12_VERSION: u16 = 771;
+const EC_POINT_FORMATS: u16 = 11;
+
+fn is_grease(id: u16) -> bool {
+    id & 0x0f0f == 0x0a0a && (id >> 8) == (id & 0xff)
+}
+
+fn parse_id_list(field: &str) -> Result<Vec<u16>> {
+    if field.is_empty() {
+        return Ok(Vec::new());
+    }
+    field
+        .split('-')
+        .map(|v| v.trim().parse::<u16>().context(format!("Invalid JA3 value: {}", v)))
+        .filter(|id| !matches!(id, Ok(id) if is_grease(*id)))
+        .collect()
+}
+
+/// Accepts a name (`ECDHE-RSA-AES128-GCM-SHA256`, `X25519`), a decimal id or a `0x` hex id.
+fn lookup(value: &str, table: &[(u16, &str)], kind: &str) -> Result<(u16, &'static str)> {
+    let trimmed = value.trim();
+    let id = if let Some(hex) = trimmed.strip_prefix("0x") {
+        u16::from_str_radix(hex, 16).ok()
+    } else {
+        trimmed.parse::<u16>().ok()
+    };
+    table
+        .iter()
+        .find(|(tid, name)| Some(*tid) == id || name.eq_ignore_ascii_case(trimmed))
+        .map(|(tid, name)| (*tid, *name))
+        .ok_or_else(|| anyhow!("Unsupported {}: {}", kind, value))
+}
+
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+pub struct TlsSpec {
+    /// `version,ciphers,extensions,curves,point_formats` as in JA3; overrides the lists below.
+    #[serde(default)]
+    pub ja3: Option<String>,
+    #[serde(default)]
+    pub ciphers: Vec<String>,
+    #[serde(default)]
+    pub curves: Vec<String>,
+    /// Signature algorithms in OpenSSL notation, e.g. `ecdsa_secp256r1_sha256`.
+    #[serde(default)]
+    pub sigalgs: Vec<String>,
+    /// Extension ids in ClientHello order.
+    #[serde(default)]
+    pub extensions: Vec<u16>,
+    #[serde(default)]
+    pub grease: Option<bool>,
+}
+
+/// A `TlsSpec` with every name checked and mapped.
+#[derive(Debug, Clone)]
+pub struct ResolvedTls {
+    /// Legacy ClientHello version, the first JA3 field.
+    pub version: u16,
+    pub ciphers: Vec<(u16, &'static str)>,
+    pub curves: Vec<(u16, &'static str)>,
+    pub sigalgs: Vec<String>,
+    pub extensions: Vec<u16>,
+    /// EC point formats; empty when the hello has no `ec_point_formats` extension.
+    pub point_formats: Vec<u16>,
+    pub grease: bool,
+}
+
+impl TlsSpec {
+    pub fn resolve(&self) -> Result<ResolvedTls> {
+        let (version, cipher_values, curve_values, extensions, point_formats) = match &self.ja3 {
+            Some(ja3) => {
+                let fields: Vec<&str> = ja3.trim().split(',').collect();
+                if fields.len() != 5 {
+                    return Err(anyhow!("JA3 needs 5 comma-separated fields, got {}", fields.len()));
+                }
+                let version = fields[0]
+                    .trim()
+                    .parse::<u16>()
+                    .context(format!("Invalid JA3 version: {}", fields[0]))?;
+                let ciphers = parse_id_list(fields[1])?.iter().map(|id| id.to_string()).collect();
+                let curves = parse_id_list(fields[3])?.iter().map(|id| id.to_string()).collect();
+                (version, ciphers, curves, parse_id_list(fields[2])?, parse_id_list(fields[4])?)
+            }
+            None => {
+                // BoringSSL sends uncompressed points whenever the extension is present
+                let point_formats = if self.extensions.contains(&EC_POINT_FORMATS) { vec![0] } else { Vec::new() };
+                (TLS12_VERSION, self.ciphers.clone(), self.curves.clone(), self.extensions.clone(), point_formats)
+            }
+        };
+        // TLS 1.3 hellos also carry 1.2 as their legacy version; older ones can't be built
+        if version != TLS12_VERSION {
+            return Err(anyhow!("Unsupported JA3 version {} (only 771 is supported)", version));
+        }
+        if point_formats.iter().any(|f| *f != 0) {
+            return Err(anyhow!("Unsupported EC point formats: {:?} (only 0 is supported)", point_formats));
+        }
+
+        let ciphers = cipher_values
+            .iter()
+            .map(|c| lookup(c, CIPHERS, "cipher suite"))
+            .collect::<Result<Vec<_>>>()?;
+        let curves = curve_values
+            .iter()
+            .map(|c| lookup(c, CURVES, "curve"))
+            .collect::<Result<Vec<_>>>()?;
+        if ciphers.is_empty() {
+            return Err(anyhow!("TLS spec has no cipher suites"));
+        }
+
+        Ok(ResolvedTls {
+            version,
+            ciphers,
+            curves,
+            sigalgs: self.sigalgs.clone(),
+            extensions,
+            point_formats,
+            // Real JA3 captures from browsers imply GREASE; structured specs opt in
+            grease: self.grease.unwrap_or(self.ja3.is_some()),
+        })
+    }
+}
+
+impl ResolvedTls {
+    /// JA3 string for the hello this spec produces (GREASE excluded, as JA3 does).
+    pub fn ja3(&self) -> String {
+        let join = |ids: Vec<u16>| ids.iter().map(|i| i.to_string()).collect::<Vec<_>>().join("-");
+        format!(
+            "{},{},{},{},{}",
+            self.version,
+            join(self.ciphers.iter().map(|(id, _)| *id).collect()),
+            join(self.extensions.clone()),
+            join(self.curves.iter().map(|(id, _)| *id).collect()),
+            join(self.point_formats.clone()),
+        )
+    }
+
+    pub fn ja3_hash(&self) -> String {
+        format!("{:x}", md5::compute(self.ja3()))
+    }
+
+    /// Emulation carrying only this TLS layer (no preset HTTP/2 settings or headers).
+    pub fn emulation(&self) -> EmulationProvider {
+        let cipher_list = self.ciphers.iter().map(|(_, name)| *name).collect::<Vec<_>>().join(":");
+        let curves = self.curves.iter().map(|(_, name)| *name).collect::<Vec<_>>().join(":");
+
+        let mut tls = TlsConfig::builder()
+            .cipher_list(cipher_list)
+            .grease_enabled(self.grease);
+        if !curves.is_empty() {
+            tls = tls.curves_list(curves);
+        }
+        if !self.sigalgs.is_empty() {
+            tls = tls.sigalgs_list(self.sigalgs.join(":"));
+        }
+        if !self.extensions.is_empty() {
+            let order: Vec<ExtensionType> = self.extensions.iter().map(|id| ExtensionType::from(*id)).collect();
+            tls = tls.extension_permutation(order);
+        }
+
+        EmulationProvider::builder().tls_config(tls.build()).build()
+    }
+}
@@ -0,0 +1,211 @@
+use crate::engine::ProxyEndpoint;
+use anyhow::{anyhow, Context, Result};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+
+// Tor as a proxy node. A `tor://host:port` entry in `network.proxies` is the SOCKS port of
+// a local Tor daemon; clients reach it as `socks5h` so target names resolve over Tor.
+// When the node is reported as failing, Spectre asks the daemon for a new identity
+// (SIGNAL NEWNYM) over its control port and times how long a fresh circuit takes to build.
+// One daemon is one node: a renewal moves every session using it to new circuits.
+
+pub const TOR_SCHEME: &str = "tor://";
+
+/// Tor's own SOCKS port default.
+pub const DEFAULT_TOR_PORT: u16 = 9050;
+
+/// `[network.tor]`.
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct TorConfig {
+    /// Control port on the same host as the SOCKS port.
+    #[serde(default = "default_control_port")]
+    pub control_port: u16,
+    /// For `HashedControlPassword`.
+    #[serde(default)]
+    pub password: Option<String>,
+    /// For `CookieAuthentication`, e.g. `/run/tor/control.authcookie`.
+    #[serde(default)]
+    pub cookie_file: Option<String>,
+    /// Failures within this long of the last renewal don't trigger another. Tor itself
+    /// ignores NEWNYM more often than every 10 seconds.
+    #[serde(default = "default_min_interval")]
+    pub min_interval_secs: u64,
+    /// How long to wait for the fresh circuit before counting the renewal as failed.
+    #[serde(default = "default_build_timeout")]
+    pub build_timeout_secs: u64,
+}
+
+fn default_control_port() -> u16 {
+    9051
+}
+
+fn default_min_interval() -> u64 {
+    10
+}
+
+fn default_build_timeout() -> u64 {
+    60
+}
+
+impl Default for TorConfig {
+    fn default() -> Self {
+        Self {
+            control_port: default_control_port(),
+            password: None,
+            cookie_file: None,
+            min_interval_secs: default_min_interval(),
+            build_timeout_secs: default_build_timeout(),
+        }
+    }
+}
+
+/// Renewal counters across all Tor nodes, for the stats and the report.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct TorSummary {
+    pub renewals: usize,
+    pub failed_renewals: usize,
+    /// Circuit-build time of the latest successful renewal.
+    pub last_build_ms: Option<u64>,
+    pub avg_build_ms: Option<f64>,
+}
+
+#[derive(Default)]
+struct NodeState {
+    renewing: bool,
+    last_renewal: Option<Instant>,
+}
+
+#[derive(Default)]
+struct Registry {
+    nodes: HashMap<String, NodeState>,
+    summary: TorSummary,
+    build_total_ms: u64,
+}
+
+lazy_static::lazy_static! {
+    static ref REGISTRY: Mutex<Registry> = Mutex::new(Registry::default());
+}
+
+pub fn is_tor(node: &str) -> bool {
+    node.starts_with(TOR_SCHEME)
+}
+
+/// The `socks5h://` URL clients use for a Tor node; other nodes are returned unchanged.
+pub fn socks_url(node: &str) -> String {
+    match node.strip_prefix(TOR_SCHEME) {
+        Some(rest) => format!("socks5h://{}", rest),
+        None => node.to_string(),
+    }
+}
+
+/// `None` until a Tor node has been renewed (or failed to).
+pub fn summary() -> Option<TorSummary> {
+    let registry = REGISTRY.lock().unwrap();
+    (registry.summary.renewals + registry.summary.failed_renewals > 0).then(|| registry.summary.clone())
+}
+
+/// Starts a renewal of `node` in the background, unless one is running or the last one
+/// was less than `min_interval_secs` ago.
+pub fn renew(node: &str, config: &TorConfig) {
+    let Ok(handle) = tokio::runtime::Handle::try_current() else {
+        return;
+    };
+    {
+        let mut registry = REGISTRY.lock().unwrap();
+        let state = registry.nodes.entry(node.to_string()).or_default();
+        let min_interval = Duration::from_secs(config.min_interval_secs);
+        if state.renewing || state.last_renewal.is_some_and(|t| t.elapsed() < min_interval) {
+            return;
+        }
+        state.renewing = true;
+    }
+    let (node, config) = (node.to_string(), config.clone());
+    handle.spawn(async move {
+        let label = ProxyEndpoint::redact(&node);
+        let result = tokio::time::timeout(Duration::from_secs(config.build_timeout_secs), newnym(&node, &config))
+            .await
+            .unwrap_or_else(|_| Err(anyhow!("no circuit built within {}s", config.build_timeout_secs)));
+        let mut registry = REGISTRY.lock().unwrap();
+        let state = registry.nodes.entry(node.clone()).or_default();
+        state.renewing = false;
+        state.last_renewal = Some(Instant::now());
+        match result {
+            Ok(build) => {
+                let ms = build.as_millis() as u64;
+                registry.build_total_ms += ms;
+                registry.summary.renewals += 1;
+                registry.summary.last_build_ms = Some(ms);
+                registry.summary.avg_build_ms = Some(registry.build_total_ms as f64 / registry.summary.renewals as f64);
+                log::info!("Tor {}: new identity, circuit built in {} ms", label, ms);
+            }
+            Err(e) => {
+                registry.summary.failed_renewals += 1;
+                log::warn!("Tor {}: renewal failed: {:#}", label, e);
+            }
+        }
+    });
+}
+
+/// NEWNYM, then a fresh circuit; returns how long the circuit took to build.
+async fn newnym(node: &str, config: &TorConfig) -> Result<Duration> {
+    let endpoint = ProxyEndpoint::parse(node)?;
+    let stream = TcpStream::connect((endpoint.host.as_str(), config.control_port))
+        .await
+        .with_context(|| format!("Tor control port {}:{} unreachable", endpoint.host, config.control_port))?;
+    let (read, mut write) = stream.into_split();
+    let mut lines = BufReader::new(read).lines();
+
+    let auth = match (&config.password, &config.cookie_file) {
+        (Some(password), _) => format!("AUTHENTICATE \"{}\"", password.replace('\\', "\\\\").replace('"', "\\\"")),
+        (None, Some(path)) => {
+            let cookie = std::fs::read(path).with_context(|| format!("Failed to read Tor cookie {}", path))?;
+            format!("AUTHENTICATE {}", cookie.iter().map(|b| format!("{:02X}", b)).collect::<String>())
+        }
+        (None, None) => "AUTHENTICATE".to_string(),
+    };
+    for command in [auth.as_str(), "SETEVENTS CIRC", "SIGNAL NEWNYM"] {
+        write.write_all(format!("{}\r\n", command).as_bytes()).await?;
+        let reply = reply_line(&mut lines).await?;
+        if !reply.starts_with("250") {
+            // Don't echo the password back into the log
+            let sent = command.split_whitespace().next().unwrap_or(command);
+            return Err(anyhow!("{} rejected: {}", sent, reply));
+        }
+    }
+
+    let started = Instant::now();
+    write.write_all(b"EXTENDCIRCUIT 0\r\n").await?;
+    let reply = reply_line(&mut lines).await?;
+    let id = reply
+        .strip_prefix("250 EXTENDED ")
+        .map(|id| id.trim().to_string())
+        .ok_or_else(|| anyhow!("EXTENDCIRCUIT rejected: {}", reply))?;
+    loop {
+        let line = lines.next_line().await?.ok_or_else(|| anyhow!("Tor closed the control connection"))?;
+        let mut fields = line.split_whitespace();
+        if fields.next() != Some("650") || fields.next() != Some("CIRC") || fields.next() != Some(id.as_str()) {
+            continue;
+        }
+        match fields.next() {
+            Some("BUILT") => break,
+            Some(status @ ("FAILED" | "CLOSED")) => return Err(anyhow!("circuit {} {}", id, status.to_lowercase())),
+            _ => {}
+        }
+    }
+    let build = started.elapsed();
+    let _ = write.write_all(b"QUIT\r\n").await;
+    Ok(build)
+}
+
+/// The next synchronous reply, skipping asynchronous (650) events.
+async fn reply_line<R: tokio::io::AsyncBufRead + Unpin>(lines: &mut tokio::io::Lines<R>) -> Result<String> {
+    loop {
+        let line = lines.next_line().await?.ok_or_else(|| anyhow!("Tor closed the control connection"))?;
+        if !line.starts_with("650") {
+            return Ok(line);
+        }
+    }
+}
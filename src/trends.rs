@@ -0,0 +1,327 @@
+use crate::dates::UtcDateTime;
+use crate::report::{html_escape, ScanSummary};
+use anyhow::{anyhow, Context, Result};
+use std::collections::{BTreeMap, HashSet};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+// `spectre trends`: WAF posture drift across sessions. Every run summary that can be found
+// (JSON reports, journals, JSONL/CSV sink summaries, a SQLite sink's `summaries` table) is
+// bucketed per target and week, and success rate, challenge rate and latency are charted
+// over time. Nothing is sent anywhere; this only reads what earlier runs left behind.
+
+/// Buckets start on a Monday (1970-01-05) so weekly buckets line up with calendar weeks.
+const BUCKET_ORIGIN: u64 = 4 * 86_400;
+
+/// One run, reduced to the numbers the trends are built from.
+#[derive(Debug, Clone)]
+struct RunPoint {
+    target: String,
+    finished_at: u64,
+    requests: usize,
+    successful: usize,
+    challenged: usize,
+    latency_ms: Option<f64>,
+    /// Identifies the run across files: the same run shows up in its report, journal and sinks.
+    key: (String, usize, usize, usize, u64, Option<u64>),
+}
+
+impl RunPoint {
+    fn from_summary(summary: ScanSummary, fallback_ts: u64) -> Self {
+        let challenged = summary.profiles.iter().map(|p| p.challenged).sum();
+        Self {
+            key: (
+                summary.target.clone(),
+                summary.total_requests,
+                summary.successful,
+                summary.blocked,
+                summary.duration_seconds,
+                summary.finished_at,
+            ),
+            target: summary.target,
+            finished_at: summary.finished_at.unwrap_or(fallback_ts),
+            requests: summary.total_requests,
+            successful: summary.successful,
+            challenged,
+            latency_ms: summary.latency_ms,
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct TrendBucket {
+    /// First day of the bucket, `YYYY-MM-DD` (UTC).
+    pub start: String,
+    pub runs: usize,
+    pub requests: usize,
+    pub success_rate: f64,
+    pub challenge_rate: f64,
+    /// Request-weighted mean over the runs that recorded latency.
+    pub latency_ms: Option<f64>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct TargetTrend {
+    pub target: String,
+    pub buckets: Vec<TrendBucket>,
+}
+
+#[derive(Debug, Default, serde::Serialize)]
+pub struct TrendReport {
+    pub runs: usize,
+    pub bucket_days: u64,
+    pub targets: Vec<TargetTrend>,
+}
+
+impl TrendReport {
+    /// Reads every summary under `dir` (recursively) and in `db`, optionally for one target.
+    pub fn build(dir: &str, db: Option<&str>, target: Option<&str>, bucket_days: u64) -> Result<Self> {
+        if bucket_days == 0 {
+            return Err(anyhow!("--bucket-days must be at least 1"));
+        }
+        let mut points = Vec::new();
+        if Path::new(dir).is_dir() {
+            collect_dir(Path::new(dir), &mut points)?;
+        } else if db.is_none() {
+            return Err(anyhow!("Runs directory {} does not exist", dir));
+        }
+        if let Some(db) = db {
+            collect_sqlite(db, &mut points)?;
+        }
+        let mut seen = HashSet::new();
+        points.retain(|p| seen.insert(p.key.clone()));
+        if let Some(t) = target {
+            points.retain(|p| p.target == t);
+        }
+        if points.is_empty() {
+            return Err(anyhow!("No run summaries found"));
+        }
+
+        let span = bucket_days * 86_400;
+        let mut grouped: BTreeMap<String, BTreeMap<u64, Vec<&RunPoint>>> = BTreeMap::new();
+        for p in &points {
+            let bucket = p.finished_at.saturating_sub(BUCKET_ORIGIN) / span * span + BUCKET_ORIGIN;
+            grouped.entry(p.target.clone()).or_default().entry(bucket).or_default().push(p);
+        }
+        let targets = grouped
+            .into_iter()
+            .map(|(target, buckets)| TargetTrend {
+                target,
+                buckets: buckets.into_iter().map(|(start, runs)| Self::bucket(start, &runs)).collect(),
+            })
+            .collect();
+        Ok(Self { runs: points.len(), bucket_days, targets })
+    }
+
+    fn bucket(start: u64, runs: &[&RunPoint]) -> TrendBucket {
+        let requests: usize = runs.iter().map(|r| r.requests).sum();
+        let rate = |n: usize| if requests == 0 { 0.0 } else { n as f64 / requests as f64 };
+        let timed: Vec<(f64, usize)> = runs.iter().filter_map(|r| r.latency_ms.map(|l| (l, r.requests.max(1)))).collect();
+        let weight: usize = timed.iter().map(|(_, w)| w).sum();
+        TrendBucket {
+            start: UtcDateTime::from_unix(start).date(),
+            runs: runs.len(),
+            requests,
+            success_rate: rate(runs.iter().map(|r| r.successful).sum()),
+            challenge_rate: rate(runs.iter().map(|r| r.challenged).sum()),
+            latency_ms: (weight > 0).then(|| timed.iter().map(|(l, w)| l * *w as f64).sum::<f64>() / weight as f64),
+        }
+    }
+
+    pub fn print(&self) {
+        println!("{} runs, {}-day buckets", self.runs, self.bucket_days);
+        for t in &self.targets {
+            println!("\n{}", t.target);
+            println!("  {:<10} {:>5} {:>9} {:>8} {:>10} {:>9}", "start", "runs", "requests", "success", "challenge", "latency");
+            for b in &t.buckets {
+                println!(
+                    "  {:<10} {:>5} {:>9} {:>7.1}% {:>9.1}% {:>9}",
+                    b.start,
+                    b.runs,
+                    b.requests,
+                    b.success_rate * 100.0,
+                    b.challenge_rate * 100.0,
+                    b.latency_ms.map(|l| format!("{:.0} ms", l)).unwrap_or_else(|| "-".into())
+                );
+            }
+        }
+    }
+
+    pub fn save(&self, path: &str) -> Result<()> {
+        if path.ends_with(".json") {
+            let file = File::create(path).context("Failed to create JSON trend report")?;
+            serde_json::to_writer_pretty(file, self)?;
+        } else if path.ends_with(".html") {
+            let mut file = File::create(path).context("Failed to create HTML trend report")?;
+            file.write_all(self.generate_html().as_bytes())?;
+        } else {
+            return Err(anyhow!("Trend report must end in .json or .html: {}", path));
+        }
+        Ok(())
+    }
+
+    fn generate_html(&self) -> String {
+        let sections: String = self
+            .targets
+            .iter()
+            .map(|t| {
+                let labels: Vec<&str> = t.buckets.iter().map(|b| b.start.as_str()).collect();
+                let pct = |f: fn(&TrendBucket) -> f64| t.buckets.iter().map(|b| Some(f(b) * 100.0)).collect::<Vec<_>>();
+                let latency: Vec<Option<f64>> = t.buckets.iter().map(|b| b.latency_ms).collect();
+                format!(
+                    "<h2>{}</h2>{}{}{}",
+                    html_escape(&t.target),
+                    svg_chart("Success rate (%)", &labels, &pct(|b| b.success_rate), Some(100.0), "#5cb85c"),
+                    svg_chart("Challenge rate (%)", &labels, &pct(|b| b.challenge_rate), Some(100.0), "#d9534f"),
+                    svg_chart("Latency (ms)", &labels, &latency, None, "#337ab7")
+                )
+            })
+            .collect();
+        format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <title>Spectre Trends</title>
+    <style>
+        body {{ font-family: sans-serif; margin: 2rem; background: #f4f4f4; }}
+        .container {{ max-width: 900px; margin: 0 auto; background: white; padding: 2rem; border-radius: 8px; box-shadow: 0 2px 5px rgba(0,0,0,0.1); }}
+        h1 {{ color: #333; border-bottom: 2px solid #ddd; padding-bottom: 0.5rem; }}
+        h3 {{ color: #666; font-size: 0.9rem; margin-bottom: 0.25rem; }}
+    </style>
+</head>
+<body>
+    <div class="container">
+        <h1>Spectre Trends</h1>
+        <p>{} runs, {}-day buckets.</p>
+        {}
+    </div>
+</body>
+</html>
+"#,
+            self.runs, self.bucket_days, sections
+        )
+    }
+}
+
+/// A line chart of `values` over `labels`; gaps where a value is missing.
+fn svg_chart(title: &str, labels: &[&str], values: &[Option<f64>], max: Option<f64>, color: &str) -> String {
+    const W: f64 = 820.0;
+    const H: f64 = 160.0;
+    const PAD: f64 = 30.0;
+    let max = max.unwrap_or_else(|| values.iter().flatten().fold(0.0, |a: f64, b| a.max(*b)) * 1.1).max(1.0);
+    let x = |i: usize| PAD + if labels.len() > 1 { i as f64 * (W - 2.0 * PAD) / (labels.len() - 1) as f64 } else { (W - 2.0 * PAD) / 2.0 };
+    let y = |v: f64| H - PAD - v / max * (H - 2.0 * PAD);
+    let mut lines = Vec::new();
+    let mut current = Vec::new();
+    for (i, v) in values.iter().enumerate() {
+        match v {
+            Some(v) => current.push(format!("{:.1},{:.1}", x(i), y(*v))),
+            None if !current.is_empty() => lines.push(std::mem::take(&mut current)),
+            None => {}
+        }
+    }
+    if !current.is_empty() {
+        lines.push(current);
+    }
+    let polylines: String = lines
+        .iter()
+        .map(|pts| format!("<polyline fill='none' stroke='{}' stroke-width='2' points='{}'/>", color, pts.join(" ")))
+        .collect();
+    let dots: String = values
+        .iter()
+        .enumerate()
+        .filter_map(|(i, v)| v.map(|v| format!("<circle cx='{:.1}' cy='{:.1}' r='3' fill='{}'><title>{}: {:.1}</title></circle>", x(i), y(v), color, labels[i], v)))
+        .collect();
+    format!(
+        "<h3>{}</h3><svg width='100%' viewBox='0 0 {} {}'><line x1='{p}' y1='{b}' x2='{r}' y2='{b}' stroke='#ccc'/><line x1='{p}' y1='{p}' x2='{p}' y2='{b}' stroke='#ccc'/><text x='2' y='{p}' font-size='10'>{:.0}</text><text x='{p}' y='{}' font-size='10'>{}</text><text x='{r}' y='{}' font-size='10' text-anchor='end'>{}</text>{}{}</svg>",
+        title,
+        W,
+        H,
+        max,
+        H - 8.0,
+        labels.first().copied().unwrap_or(""),
+        H - 8.0,
+        labels.last().copied().unwrap_or(""),
+        polylines,
+        dots,
+        p = PAD,
+        b = H - PAD,
+        r = W - PAD,
+    )
+}
+
+fn collect_dir(dir: &Path, out: &mut Vec<RunPoint>) -> Result<()> {
+    for entry in std::fs::read_dir(dir).context(format!("Failed to read {}", dir.display()))? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_dir(&path, out)?;
+            continue;
+        }
+        let mtime = std::fs::metadata(&path)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        let name = path.to_string_lossy();
+        // Files that aren't summaries (configs, recordings, findings) are skipped quietly
+        let summary = if name.ends_with(".jsonl") {
+            last_jsonl_summary(&path)
+        } else if name.ends_with(".json") {
+            json_summary(&path)
+        } else {
+            None
+        };
+        out.extend(summary.map(|s| RunPoint::from_summary(s, mtime)));
+    }
+    Ok(())
+}
+
+/// A JSON report (`{ "summary": ..., "findings": ... }`) or a bare summary (CSV sink).
+fn json_summary(path: &Path) -> Option<ScanSummary> {
+    let value: serde_json::Value = serde_json::from_reader(BufReader::new(File::open(path).ok()?)).ok()?;
+    let summary = match value.get("summary") {
+        Some(s) => s.clone(),
+        None => value,
+    };
+    serde_json::from_value(summary).ok()
+}
+
+/// The last summary in a journal (`checkpoint` lines) or a JSONL sink (`summary` line).
+fn last_jsonl_summary(path: &Path) -> Option<ScanSummary> {
+    let reader = BufReader::new(File::open(path).ok()?);
+    let mut last = None;
+    for line in reader.lines() {
+        let line = line.ok()?;
+        // Record lines dominate these files; only parse the summary ones
+        if !line.contains("\"type\":\"summary\"") && !line.contains("\"type\":\"checkpoint\"") {
+            continue;
+        }
+        if let Some(summary) = serde_json::from_str::<serde_json::Value>(&line)
+            .ok()
+            .and_then(|v| v.get("summary").cloned())
+            .and_then(|s| serde_json::from_value(s).ok())
+        {
+            last = Some(summary);
+        }
+    }
+    last
+}
+
+/// Every row of a SQLite sink's `summaries` table.
+fn collect_sqlite(path: &str, out: &mut Vec<RunPoint>) -> Result<()> {
+    let conn = rusqlite::Connection::open_with_flags(path, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .context(format!("Failed to open SQLite database {}", path))?;
+    let mut stmt = conn.prepare("SELECT ts, summary FROM summaries").context("No summaries table")?;
+    let rows = stmt.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?;
+    for row in rows {
+        let (ts, json) = row?;
+        if let Ok(summary) = serde_json::from_str::<ScanSummary>(&json) {
+            out.push(RunPoint::from_summary(summary, (ts / 1000).max(0) as u64));
+        }
+    }
+    Ok(())
+}
@@ -1,22 +1,154 @@
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
+    cursor::Show,
+    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use ratatui::{
     backend::{Backend, CrosstermBackend},
-    layout::{Constraint, Direction, Layout},
+    layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Span, Line},
-    widgets::{Block, Borders, Gauge, Paragraph, Sparkline},
-    Terminal,
+    widgets::{Block, Borders, Cell, Gauge, Paragraph, Row, Sparkline, Table, Wrap},
+    Frame, Terminal, TerminalOptions, Viewport,
 };
-use std::{io, sync::atomic::Ordering, time::{Duration, Instant}};
-use crate::engine::EngineStats;
+use std::{io, sync::atomic::{AtomicBool, Ordering}, sync::Once, time::{Duration, Instant}};
+use crate::engine::solver::BrowserSolver;
+use crate::engine::{EngineStats, ShutdownPhase};
+use crate::rules::{self, AnalyzerRule, Capture, RuleVerdict};
+
+/// Rows the full dashboard needs (panels plus margins); smaller consoles get a notice.
+const MIN_HEIGHT: u16 = 20;
+const MIN_WIDTH: u16 = 40;
+/// Legacy conhost default, used when the console reports no usable size.
+const FALLBACK_SIZE: (u16, u16) = (80, 25);
+
+/// Set while raw mode / the alternate screen are active, so restoring is idempotent.
+static TERMINAL_ACTIVE: AtomicBool = AtomicBool::new(false);
+static MOUSE_CAPTURED: AtomicBool = AtomicBool::new(false);
+static PANIC_HOOK: Once = Once::new();
+
+/// Puts the console back: raw mode off, main screen, mouse released, cursor shown. Safe to
+/// call more than once and from a panic hook; errors are ignored since there's nothing
+/// left to report them to.
+pub fn restore_terminal() {
+    if !TERMINAL_ACTIVE.swap(false, Ordering::SeqCst) {
+        return;
+    }
+    let _ = disable_raw_mode();
+    let mut stdout = io::stdout();
+    if MOUSE_CAPTURED.swap(false, Ordering::SeqCst) {
+        let _ = execute!(stdout, DisableMouseCapture);
+    }
+    let _ = execute!(stdout, LeaveAlternateScreen, Show);
+}
+
+/// Restores the terminal before the default hook prints the panic, so the message lands
+/// on the main screen instead of a mangled raw-mode console.
+fn install_panic_hook() {
+    PANIC_HOOK.call_once(|| {
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            restore_terminal();
+            previous(info);
+        }));
+    });
+}
+
+/// Restores the terminal when the TUI returns, errors out or unwinds.
+struct TerminalGuard;
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        restore_terminal();
+    }
+}
+
+/// How the TUI loop ended.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TuiExit {
+    Graceful,
+    Forced,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum View {
+    Dashboard,
+    Rules,
+}
+
+/// One selectable line of a capture in the rule builder.
+enum DetailLine {
+    Header(String, String),
+    Body(String),
+}
+
+impl DetailLine {
+    fn of(capture: &Capture) -> Vec<DetailLine> {
+        let headers = capture.headers.iter().map(|(k, v)| DetailLine::Header(k.clone(), v.clone()));
+        let body = capture
+            .body
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty())
+            .map(|l| DetailLine::Body(l.chars().take(200).collect()));
+        headers.chain(body).collect()
+    }
+
+    fn text(&self) -> String {
+        match self {
+            DetailLine::Header(k, v) => format!("{}: {}", k, v),
+            DetailLine::Body(b) => b.clone(),
+        }
+    }
+}
+
+/// A rule being edited: the phrase (or header value) trimmed down from the selected line.
+struct Draft {
+    header: Option<String>,
+    text: Vec<char>,
+    cursor: usize,
+    verdict: RuleVerdict,
+}
+
+impl Draft {
+    fn rule(&self) -> AnalyzerRule {
+        let text: String = self.text.iter().collect::<String>().trim().to_string();
+        match &self.header {
+            Some(name) => AnalyzerRule::new(
+                if text.is_empty() { format!("header {}", name) } else { format!("header {}: {}", name, text) },
+                self.verdict,
+                Some(name.clone()),
+                (!text.is_empty()).then_some(text),
+            ),
+            None => AnalyzerRule::new(
+                format!("body: {}", text.chars().take(40).collect::<String>()),
+                self.verdict,
+                None,
+                Some(text),
+            ),
+        }
+    }
+}
+
+/// Rule builder state: captures are a snapshot taken when the view opens (`u` refreshes).
+#[derive(Default)]
+struct RuleBuilder {
+    captures: Vec<Capture>,
+    selected: usize,
+    focus_detail: bool,
+    line: usize,
+    draft: Option<Draft>,
+    status: Option<(String, bool)>,
+}
 
 pub struct TuiApp {
     stats: EngineStats,
     latency_history: Vec<u64>, // Mock data for sparkline for now
+    view: View,
+    builder: RuleBuilder,
+    /// Config file promoted rules are appended to; live-only without one.
+    config_path: Option<String>,
 }
 
 impl TuiApp {
@@ -24,41 +156,81 @@ impl TuiApp {
         Self {
             stats,
             latency_history: vec![0; 100],
+            view: View::Dashboard,
+            builder: RuleBuilder::default(),
+            config_path: None,
         }
     }
 
-    pub async fn run(&mut self) -> anyhow::Result<()> {
+    pub fn with_config_path(mut self, path: Option<String>) -> Self {
+        self.config_path = path;
+        self
+    }
+
+    pub async fn run(&mut self) -> anyhow::Result<TuiExit> {
         // Setup terminal
+        install_panic_hook();
         enable_raw_mode()?;
+        TERMINAL_ACTIVE.store(true, Ordering::SeqCst);
+        let _guard = TerminalGuard;
         let mut stdout = io::stdout();
-        execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+        execute!(stdout, EnterAlternateScreen)?;
+        // Some Windows consoles (legacy conhost, certain ConPTY hosts) reject mouse mode;
+        // the dashboard is keyboard-driven, so carry on without it
+        match execute!(stdout, EnableMouseCapture) {
+            Ok(()) => MOUSE_CAPTURED.store(true, Ordering::SeqCst),
+            Err(e) => log::warn!("Mouse capture unavailable, continuing without it: {}", e),
+        }
         let backend = CrosstermBackend::new(stdout);
-        let mut terminal = Terminal::new(backend)?;
+        // Legacy conhost can report 0x0 (or fail) when attached oddly; pin a fixed
+        // viewport then instead of letting the layout collapse
+        let mut terminal = match crossterm::terminal::size() {
+            Ok((w, h)) if w > 0 && h > 0 => Terminal::new(backend)?,
+            _ => Terminal::with_options(
+                backend,
+                TerminalOptions {
+                    viewport: Viewport::Fixed(Rect::new(0, 0, FALLBACK_SIZE.0, FALLBACK_SIZE.1)),
+                },
+            )?,
+        };
 
         let res = self.run_app(&mut terminal).await;
 
         // Restore terminal
-        disable_raw_mode()?;
-        execute!(
-            terminal.backend_mut(),
-            LeaveAlternateScreen,
-            DisableMouseCapture
-        )?;
-        terminal.show_cursor()?;
-
-        if let Err(err) = res {
-            println!("{:?}", err)
-        }
+        restore_terminal();
 
-        Ok(())
+        match res {
+            Ok(exit) => Ok(exit),
+            Err(err) => {
+                println!("{:?}", err);
+                Ok(TuiExit::Graceful)
+            }
+        }
     }
 
-    async fn run_app<B: Backend>(&mut self, terminal: &mut Terminal<B>) -> io::Result<()> {
+    async fn run_app<B: Backend>(&mut self, terminal: &mut Terminal<B>) -> io::Result<TuiExit> {
         let mut last_tick = Instant::now();
         let tick_rate = Duration::from_millis(250);
 
         loop {
             terminal.draw(|f| {
+                let area = f.size();
+                if area.width < MIN_WIDTH || area.height < MIN_HEIGHT {
+                    let notice = Paragraph::new(vec![
+                        Line::from(format!("Console too small ({}x{}), need {}x{}", area.width, area.height, MIN_WIDTH, MIN_HEIGHT)),
+                        Line::from(format!(
+                            "Requests: {}  Success: {}",
+                            self.stats.total_requests.load(Ordering::Relaxed),
+                            self.stats.successful_requests.load(Ordering::Relaxed)
+                        )),
+                    ]);
+                    f.render_widget(notice, area);
+                    return;
+                }
+                if self.view == View::Rules {
+                    self.draw_rules(f, area);
+                    return;
+                }
                 let chunks = Layout::default()
                     .direction(Direction::Vertical)
                     .margin(2)
@@ -71,7 +243,7 @@ impl TuiApp {
                         ]
                         .as_ref(),
                     )
-                    .split(f.size());
+                    .split(area);
 
                 // 1. KPI Banner
                 let total = self.stats.total_requests.load(Ordering::Relaxed);
@@ -80,18 +252,43 @@ impl TuiApp {
                 let failed = self.stats.failed_requests.load(Ordering::Relaxed);
                 let rps = if total > 0 { total / 10 } else { 0 }; // Mock RPS calculation
 
-                let kpi_text = vec![
-                    Line::from(vec![
-                        Span::styled(format!("Total: {} ", total), Style::default().fg(Color::White)),
-                        Span::styled(format!("Success: {} ", success), Style::default().fg(Color::Green)),
-                        Span::styled(format!("Blocked: {} ", blocked), Style::default().fg(Color::Yellow)),
-                        Span::styled(format!("Failed: {} ", failed), Style::default().fg(Color::Red)),
-                        Span::styled(format!("RPS: ~{} ", rps), Style::default().fg(Color::Cyan)),
-                    ]),
+                let mut kpi_spans = vec![
+                    Span::styled(format!("Total: {} ", total), Style::default().fg(Color::White)),
+                    Span::styled(format!("Success: {} ", success), Style::default().fg(Color::Green)),
+                    Span::styled(format!("Blocked: {} ", blocked), Style::default().fg(Color::Yellow)),
+                    Span::styled(format!("Failed: {} ", failed), Style::default().fg(Color::Red)),
+                    Span::styled(format!("RPS: ~{} ", rps), Style::default().fg(Color::Cyan)),
                 ];
 
+                for (category, count) in self.stats.categories.counts() {
+                    let color = category.color.as_deref().and_then(|c| c.parse::<Color>().ok()).unwrap_or(Color::White);
+                    kpi_spans.push(Span::styled(format!("{}: {} ", category.name, count), Style::default().fg(color)));
+                }
+
+                let shadow_total = self.stats.shadow.total_requests.load(Ordering::Relaxed);
+                if shadow_total > 0 {
+                    let shadow_success = self.stats.shadow.successful_requests.load(Ordering::Relaxed);
+                    let shadow_blocked = self.stats.shadow.blocked_requests.load(Ordering::Relaxed)
+                        + self.stats.shadow.challenged_requests.load(Ordering::Relaxed);
+                    kpi_spans.push(Span::styled(
+                        format!("| Shadow: {} ok / {} blocked of {} ", shadow_success, shadow_blocked, shadow_total),
+                        Style::default().fg(Color::Magenta),
+                    ));
+                }
+
+                let cache_hits = self.stats.cache.hits.load(Ordering::Relaxed);
+                let cache_misses = self.stats.cache.misses.load(Ordering::Relaxed);
+                if cache_hits + cache_misses > 0 {
+                    kpi_spans.push(Span::styled(
+                        format!("| Cache HIT/MISS: {}/{} ", cache_hits, cache_misses),
+                        Style::default().fg(Color::LightBlue),
+                    ));
+                }
+
+                let kpi_text = vec![Line::from(kpi_spans)];
+
                 let kpi_paragraph = Paragraph::new(kpi_text)
-                    .block(Block::default().borders(Borders::ALL).title("KPI Banner"));
+                    .block(Block::default().borders(Borders::ALL).title("KPI Banner (r: rule builder, p: reload proxies, X: emergency stop)"));
                 f.render_widget(kpi_paragraph, chunks[0]);
 
                 // 2. Latency Sparkline
@@ -120,16 +317,83 @@ impl TuiApp {
                     .percent(health as u16);
                 f.render_widget(gauge, chunks[2]);
 
+                // 4. Shutdown Progress
+                let phase = self.stats.shutdown.phase();
+                if self.stats.shutdown.is_requested() || phase == ShutdownPhase::Done {
+                    let in_flight = self.stats.in_flight.load(Ordering::Relaxed);
+                    let browsers = BrowserSolver::active_count();
+                    let (phase_text, color) = match phase {
+                        ShutdownPhase::Running | ShutdownPhase::Draining => ("Draining workers...", Color::Yellow),
+                        ShutdownPhase::Flushing => ("Flushing logs and report...", Color::Yellow),
+                        ShutdownPhase::Done => ("Done", Color::Green),
+                        ShutdownPhase::Halted => ("HALTED: requests cancelled, browsers killed, state frozen", Color::Red),
+                    };
+                    let hint = if phase == ShutdownPhase::Halted {
+                        "Inspect the nodes and logs; press q to exit"
+                    } else if self.stats.shutdown.is_requested() {
+                        "Press Ctrl-C / q again to force exit"
+                    } else {
+                        "Run complete. Press q to exit"
+                    };
+                    let shutdown_text = vec![
+                        Line::from(Span::styled(phase_text, Style::default().fg(color).add_modifier(Modifier::BOLD))),
+                        Line::from(format!("In-flight requests: {}", in_flight)),
+                        Line::from(format!("Open browsers: {}", browsers)),
+                        Line::from(Span::styled(hint, Style::default().fg(Color::DarkGray))),
+                    ];
+                    let shutdown_paragraph = Paragraph::new(shutdown_text)
+                        .block(Block::default().borders(Borders::ALL).title("Shutdown"));
+                    f.render_widget(shutdown_paragraph, chunks[3]);
+                } else {
+                    self.draw_nodes(f, chunks[3]);
+                }
+
             })?;
 
+            if self.stats.shutdown.is_requested() && self.stats.shutdown.phase() == ShutdownPhase::Done {
+                return Ok(TuiExit::Graceful);
+            }
+
             let timeout = tick_rate
                 .checked_sub(last_tick.elapsed())
                 .unwrap_or_else(|| Duration::from_secs(0));
 
             if crossterm::event::poll(timeout)? {
                 if let Event::Key(key) = event::read()? {
-                    if let KeyCode::Char('q') = key.code {
-                        return Ok(());
+                    // Windows reports key releases too; one press must count once
+                    if key.kind != KeyEventKind::Press {
+                        continue;
+                    }
+                    let ctrl_c = key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL);
+                    // The rule builder takes typed text, so only Ctrl-C stops the run from there
+                    if self.view == View::Rules && !ctrl_c {
+                        self.rules_key(key);
+                        continue;
+                    }
+                    if key.code == KeyCode::Char('p') {
+                        self.stats.control.reload_proxies.notify_one();
+                        continue;
+                    }
+                    if key.code == KeyCode::Char('r') {
+                        self.builder.captures = self.stats.captures.snapshot();
+                        self.builder.selected = 0;
+                        self.builder.line = 0;
+                        self.view = View::Rules;
+                        continue;
+                    }
+                    // Shift-X: the big red button
+                    if key.code == KeyCode::Char('X') {
+                        self.stats.shutdown.halt("TUI");
+                        continue;
+                    }
+                    // Nothing is left to drain once halted
+                    if self.stats.shutdown.is_halted() && (key.code == KeyCode::Char('q') || ctrl_c) {
+                        return Ok(TuiExit::Graceful);
+                    }
+                    let stop_pressed = key.code == KeyCode::Char('q') || ctrl_c;
+                    // First press drains gracefully, the second forces exit
+                    if stop_pressed && !self.stats.shutdown.request() {
+                        return Ok(TuiExit::Forced);
                     }
                 }
             }
@@ -139,4 +403,216 @@ impl TuiApp {
             }
         }
     }
+
+    /// Per-proxy counters, busiest nodes first.
+    fn draw_nodes(&self, f: &mut Frame, area: Rect) {
+        let mut nodes = self.stats.grid.get().snapshot();
+        nodes.sort_by(|a, b| b.requests.cmp(&a.requests));
+        let rows = nodes.iter().map(|n| {
+            let color = match n.state.as_str() {
+                "dead" => Color::Red,
+                "evicted" => Color::DarkGray,
+                "cooldown" => Color::Yellow,
+                _ => Color::Green,
+            };
+            Row::new(vec![
+                Cell::from(n.node.clone()),
+                Cell::from(n.state.clone()).style(Style::default().fg(color)),
+                Cell::from(n.requests.to_string()),
+                Cell::from(n.successes.to_string()),
+                Cell::from(n.blocks.to_string()),
+                Cell::from(n.errors.to_string()),
+                Cell::from(n.latency_ms.map(|l| format!("{:.0}", l)).unwrap_or_else(|| "-".into())),
+                Cell::from(format!("{:.1}", n.bytes as f64 / (1024.0 * 1024.0))),
+                Cell::from(n.idle_secs.map(|s| format!("{}s", s)).unwrap_or_else(|| "-".into())),
+            ])
+        });
+        let widths = [
+            Constraint::Min(20),
+            Constraint::Length(8),
+            Constraint::Length(7),
+            Constraint::Length(7),
+            Constraint::Length(7),
+            Constraint::Length(6),
+            Constraint::Length(7),
+            Constraint::Length(8),
+            Constraint::Length(6),
+        ];
+        let header = Row::new(vec!["Node", "State", "Req", "OK", "Blocked", "Err", "ms", "MiB", "Idle"])
+            .style(Style::default().add_modifier(Modifier::BOLD));
+        let table = Table::new(rows, widths)
+            .header(header)
+            .block(Block::default().borders(Borders::ALL).title(format!(
+                "Proxy Grid ({} nodes, {} evicted)",
+                nodes.len(),
+                nodes.iter().filter(|n| n.state == "evicted").count()
+            )));
+        f.render_widget(table, area);
+    }
+
+    /// Captures on the left, the selected capture's headers and body lines on the right,
+    /// and the draft editor (or key help) along the bottom.
+    fn draw_rules(&self, f: &mut Frame, area: Rect) {
+        let b = &self.builder;
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(4)].as_ref())
+            .split(area);
+        let panes = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(35), Constraint::Percentage(65)].as_ref())
+            .split(rows[0]);
+        let highlight = Style::default().fg(Color::Black).bg(Color::Yellow);
+        // Keeps the selected row on screen inside a bordered pane of `height` rows
+        let offset = |selected: usize, height: u16| selected.saturating_sub(height.saturating_sub(3) as usize) as u16;
+
+        let list: Vec<Line> = if b.captures.is_empty() {
+            vec![Line::from("No blocked or challenged responses yet (u: refresh)")]
+        } else {
+            b.captures
+                .iter()
+                .enumerate()
+                .map(|(i, c)| {
+                    let text = format!("{} {} {}", c.status, c.verdict, c.url);
+                    if i == b.selected {
+                        Line::from(Span::styled(text, if b.focus_detail { Style::default().add_modifier(Modifier::BOLD) } else { highlight }))
+                    } else {
+                        Line::from(text)
+                    }
+                })
+                .collect()
+        };
+        f.render_widget(
+            Paragraph::new(list)
+                .scroll((offset(b.selected, panes[0].height), 0))
+                .block(Block::default().borders(Borders::ALL).title(format!("Captured ({})", b.captures.len()))),
+            panes[0],
+        );
+
+        let details = b.captures.get(b.selected).map(DetailLine::of).unwrap_or_default();
+        let lines: Vec<Line> = details
+            .iter()
+            .enumerate()
+            .map(|(i, d)| {
+                let style = match d {
+                    _ if b.focus_detail && i == b.line => highlight,
+                    DetailLine::Header(..) => Style::default().fg(Color::Cyan),
+                    DetailLine::Body(_) => Style::default(),
+                };
+                Line::from(Span::styled(d.text(), style))
+            })
+            .collect();
+        f.render_widget(
+            Paragraph::new(lines)
+                .scroll((offset(b.line, panes[1].height), 0))
+                .block(Block::default().borders(Borders::ALL).title("Headers / body")),
+            panes[1],
+        );
+
+        let mut footer = Vec::new();
+        match &b.draft {
+            Some(d) => {
+                let (before, after): (String, String) = (d.text[..d.cursor].iter().collect(), d.text[d.cursor..].iter().collect());
+                let target = d.header.as_ref().map(|h| format!("header {} contains", h)).unwrap_or_else(|| "body contains".into());
+                footer.push(Line::from(vec![
+                    Span::styled(format!("{} -> {}: ", target, d.verdict.as_str()), Style::default().fg(Color::Yellow)),
+                    Span::raw(before),
+                    Span::styled("|", Style::default().fg(Color::Yellow)),
+                    Span::raw(after),
+                ]));
+                footer.push(Line::from(Span::styled(
+                    "type/backspace/arrows: trim to the phrase  Tab: verdict  Enter: save rule  Esc: cancel",
+                    Style::default().fg(Color::DarkGray),
+                )));
+            }
+            None => footer.push(Line::from(Span::styled(
+                "Up/Down: select  Tab: switch pane  Enter: rule from line  u: refresh  Esc/r: dashboard",
+                Style::default().fg(Color::DarkGray),
+            ))),
+        }
+        if let Some((msg, ok)) = &b.status {
+            footer.push(Line::from(Span::styled(msg.clone(), Style::default().fg(if *ok { Color::Green } else { Color::Red }))));
+        }
+        f.render_widget(
+            Paragraph::new(footer)
+                .wrap(Wrap { trim: false })
+                .block(Block::default().borders(Borders::ALL).title(format!("Rule builder ({} rules live)", self.stats.rules.len()))),
+            rows[1],
+        );
+    }
+
+    fn rules_key(&mut self, key: KeyEvent) {
+        let b = &mut self.builder;
+        if let Some(d) = b.draft.as_mut() {
+            match key.code {
+                KeyCode::Esc => b.draft = None,
+                KeyCode::Tab => d.verdict = d.verdict.next(),
+                KeyCode::Left => d.cursor = d.cursor.saturating_sub(1),
+                KeyCode::Right => d.cursor = (d.cursor + 1).min(d.text.len()),
+                KeyCode::Home => d.cursor = 0,
+                KeyCode::End => d.cursor = d.text.len(),
+                KeyCode::Backspace if d.cursor > 0 => {
+                    d.cursor -= 1;
+                    d.text.remove(d.cursor);
+                }
+                KeyCode::Delete if d.cursor < d.text.len() => {
+                    d.text.remove(d.cursor);
+                }
+                KeyCode::Char(c) => {
+                    d.text.insert(d.cursor, c);
+                    d.cursor += 1;
+                }
+                KeyCode::Enter => {
+                    let rule = d.rule();
+                    b.status = Some(match self.stats.rules.push(rule.clone()) {
+                        Err(e) => (format!("{:#}", e), false),
+                        Ok(()) => {
+                            b.draft = None;
+                            match &self.config_path {
+                                Some(path) => match rules::persist(path, &rule) {
+                                    Ok(()) => (format!("Rule '{}' is live and saved to {}", rule.name, path), true),
+                                    Err(e) => (format!("Rule is live but not saved: {:#}", e), false),
+                                },
+                                None => (format!("Rule '{}' is live (no config file to save it to)", rule.name), true),
+                            }
+                        }
+                    });
+                }
+                _ => {}
+            }
+            return;
+        }
+
+        let details = b.captures.get(b.selected).map(DetailLine::of).unwrap_or_default();
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('r') => self.view = View::Dashboard,
+            KeyCode::Char('u') => {
+                b.captures = self.stats.captures.snapshot();
+                b.selected = 0;
+                b.line = 0;
+            }
+            KeyCode::Tab => b.focus_detail = !b.focus_detail && !details.is_empty(),
+            KeyCode::Up if b.focus_detail => b.line = b.line.saturating_sub(1),
+            KeyCode::Down if b.focus_detail => b.line = (b.line + 1).min(details.len().saturating_sub(1)),
+            KeyCode::Up => {
+                b.selected = b.selected.saturating_sub(1);
+                b.line = 0;
+            }
+            KeyCode::Down => {
+                b.selected = (b.selected + 1).min(b.captures.len().saturating_sub(1));
+                b.line = 0;
+            }
+            KeyCode::Enter if b.focus_detail => {
+                let (header, text) = match details.get(b.line) {
+                    Some(DetailLine::Header(k, v)) => (Some(k.clone()), v.clone()),
+                    Some(DetailLine::Body(t)) => (None, t.clone()),
+                    None => return,
+                };
+                let text: Vec<char> = text.chars().collect();
+                b.draft = Some(Draft { header, cursor: text.len(), text, verdict: RuleVerdict::Blocked });
+                b.status = None;
+            }
+            _ => {}
+        }
+    }
 }
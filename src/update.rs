@@ -0,0 +1,191 @@
+use anyhow::{anyhow, Context, Result};
+use log::debug;
+use sha2::{Digest, Sha256};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// Release checks and `spectre self-update` against the project's GitHub releases. Each
+// release carries the emulation presets current at the time; a stale fingerprint doesn't
+// fail loudly, it just starts getting challenged, so runs point out when they are behind.
+
+const LATEST_RELEASE_URL: &str = "https://api.github.com/repos/id-root/spectre/releases/latest";
+const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+/// The startup check reuses its last answer for a day.
+const CHECK_CACHE: &str = "logs/.latest_release.json";
+const CHECK_INTERVAL_SECS: u64 = 24 * 3600;
+
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct Release {
+    pub tag_name: String,
+    pub html_url: String,
+    #[serde(default)]
+    pub assets: Vec<Asset>,
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct Asset {
+    pub name: String,
+    pub browser_download_url: String,
+}
+
+impl Release {
+    pub fn version(&self) -> &str {
+        self.tag_name.trim_start_matches('v')
+    }
+
+    pub fn is_newer(&self) -> bool {
+        is_newer(self.version(), CURRENT_VERSION)
+    }
+
+    /// Name of this platform's binary in a release, e.g. `spectre-x86_64-linux`.
+    pub fn asset_name() -> String {
+        format!("spectre-{}-{}{}", std::env::consts::ARCH, std::env::consts::OS, std::env::consts::EXE_SUFFIX)
+    }
+
+    fn asset(&self, name: &str) -> Option<&Asset> {
+        self.assets.iter().find(|a| a.name == name)
+    }
+}
+
+pub enum UpdateOutcome {
+    UpToDate,
+    /// Newer release found; nothing installed (`--check`).
+    Available(Release),
+    Installed(Release),
+}
+
+/// `x.y.z` (leading `v` and any `-pre` suffix ignored); `None` for anything else.
+fn parse_version(v: &str) -> Option<(u64, u64, u64)> {
+    let core = v.trim_start_matches('v').split(['-', '+']).next()?;
+    let mut parts = core.split('.').map(|p| p.parse::<u64>().ok());
+    Some((parts.next()??, parts.next().unwrap_or(Some(0))?, parts.next().unwrap_or(Some(0))?))
+}
+
+fn is_newer(candidate: &str, current: &str) -> bool {
+    match (parse_version(candidate), parse_version(current)) {
+        (Some(a), Some(b)) => a > b,
+        _ => false,
+    }
+}
+
+fn client(timeout: Duration) -> Result<rquest::Client> {
+    rquest::Client::builder()
+        .timeout(timeout)
+        .redirect(rquest::redirect::Policy::limited(5))
+        .build()
+        .context("Failed to build update client")
+}
+
+async fn fetch(client: &rquest::Client, url: &str) -> Result<rquest::Response> {
+    client
+        .get(url)
+        // GitHub rejects API requests without a User-Agent
+        .header("user-agent", format!("spectre/{}", CURRENT_VERSION))
+        .header("accept", "application/vnd.github+json")
+        .send()
+        .await
+        .context(format!("Request to {} failed", url))?
+        .error_for_status()
+        .context(format!("Request to {} failed", url))
+}
+
+pub async fn latest_release(timeout: Duration) -> Result<Release> {
+    let client = client(timeout)?;
+    fetch(&client, LATEST_RELEASE_URL)
+        .await?
+        .json()
+        .await
+        .context("Unexpected GitHub release response")
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct CheckCache {
+    checked_at: u64,
+    tag_name: String,
+}
+
+/// One line for the operator when a newer release exists. Best effort: a cached answer
+/// younger than a day is reused, and network errors just skip the notice.
+pub async fn startup_notice() -> Option<String> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+    let cached = std::fs::read_to_string(CHECK_CACHE)
+        .ok()
+        .and_then(|c| serde_json::from_str::<CheckCache>(&c).ok())
+        .filter(|c| now.saturating_sub(c.checked_at) < CHECK_INTERVAL_SECS);
+    let tag_name = match cached {
+        Some(c) => c.tag_name,
+        None => {
+            let release = match latest_release(Duration::from_secs(3)).await {
+                Ok(r) => r,
+                Err(e) => {
+                    debug!("Version check skipped: {:#}", e);
+                    return None;
+                }
+            };
+            let cache = CheckCache { checked_at: now, tag_name: release.tag_name.clone() };
+            if std::fs::create_dir_all("logs").is_ok() {
+                let _ = std::fs::write(CHECK_CACHE, serde_json::to_string(&cache).unwrap_or_default());
+            }
+            release.tag_name
+        }
+    };
+    is_newer(&tag_name, CURRENT_VERSION).then(|| {
+        format!(
+            "Spectre {} is available (running {}); newer releases ship updated browser emulation profiles. Run `spectre self-update`.",
+            tag_name.trim_start_matches('v'),
+            CURRENT_VERSION
+        )
+    })
+}
+
+/// Replaces the running binary with the latest release's build for this platform. The
+/// download must match the `<asset>.sha256` published next to it.
+pub async fn self_update(check_only: bool) -> Result<UpdateOutcome> {
+    let release = latest_release(Duration::from_secs(30)).await?;
+    if !release.is_newer() {
+        return Ok(UpdateOutcome::UpToDate);
+    }
+    if check_only {
+        return Ok(UpdateOutcome::Available(release));
+    }
+
+    let name = Release::asset_name();
+    let binary = release
+        .asset(&name)
+        .ok_or_else(|| anyhow!("Release {} has no {} build ({})", release.tag_name, name, release.html_url))?;
+    let checksum = release
+        .asset(&format!("{}.sha256", name))
+        .ok_or_else(|| anyhow!("Release {} publishes no checksum for {}; refusing to install it", release.tag_name, name))?;
+
+    let client = client(Duration::from_secs(300))?;
+    let bytes = fetch(&client, &binary.browser_download_url).await?.bytes().await?;
+    let expected = fetch(&client, &checksum.browser_download_url).await?.text().await?;
+    let expected = expected.split_whitespace().next().unwrap_or("").to_lowercase();
+    let actual = hex::encode(Sha256::digest(&bytes));
+    if actual != expected {
+        return Err(anyhow!("Checksum mismatch for {} (expected {}, got {})", name, expected, actual));
+    }
+
+    install(&bytes)?;
+    Ok(UpdateOutcome::Installed(release))
+}
+
+/// Stages the new binary next to the current one and renames it into place.
+fn install(bytes: &[u8]) -> Result<()> {
+    let exe = std::env::current_exe().context("Cannot locate the running binary")?;
+    let staged = exe.with_extension("new");
+    std::fs::write(&staged, bytes).context(format!("Failed to write {}", staged.display()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&staged, std::fs::Permissions::from_mode(0o755))?;
+    }
+    // Windows can't replace a running executable, but it can rename it out of the way
+    #[cfg(windows)]
+    {
+        let old = exe.with_extension("old");
+        let _ = std::fs::remove_file(&old);
+        std::fs::rename(&exe, &old).context(format!("Failed to move {} aside", exe.display()))?;
+    }
+    std::fs::rename(&staged, &exe).context(format!("Failed to replace {}", exe.display()))?;
+    Ok(())
+}
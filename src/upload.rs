@@ -0,0 +1,151 @@
+use crate::dates::UtcDateTime;
+use anyhow::{anyhow, Context, Result};
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Post-run upload of the session's artifacts to an S3-compatible bucket (AWS, MinIO, R2,
+// ...), so results from ephemeral cloud agents survive the instance. Plain SigV4 PUTs,
+// path-style addressing.
+
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct UploadConfig {
+    /// e.g. `https://s3.eu-central-1.amazonaws.com` or `http://minio.internal:9000`.
+    pub endpoint: String,
+    pub bucket: String,
+    /// Key prefix; each run goes under `<prefix>/<session id>/`.
+    #[serde(default = "default_prefix")]
+    pub prefix: String,
+    #[serde(default = "default_region")]
+    pub region: String,
+    /// Falls back to `AWS_ACCESS_KEY_ID` / `AWS_SECRET_ACCESS_KEY`.
+    #[serde(default)]
+    pub access_key: Option<String>,
+    #[serde(default)]
+    pub secret_key: Option<String>,
+}
+
+fn default_prefix() -> String {
+    "spectre".to_string()
+}
+
+fn default_region() -> String {
+    "us-east-1".to_string()
+}
+
+pub struct ArtifactUploader {
+    config: UploadConfig,
+    access_key: String,
+    secret_key: String,
+    client: rquest::Client,
+}
+
+impl ArtifactUploader {
+    pub fn new(config: UploadConfig) -> Result<Self> {
+        let access_key = config
+            .access_key
+            .clone()
+            .or_else(|| std::env::var("AWS_ACCESS_KEY_ID").ok())
+            .ok_or_else(|| anyhow!("upload.access_key not set and AWS_ACCESS_KEY_ID missing"))?;
+        let secret_key = config
+            .secret_key
+            .clone()
+            .or_else(|| std::env::var("AWS_SECRET_ACCESS_KEY").ok())
+            .ok_or_else(|| anyhow!("upload.secret_key not set and AWS_SECRET_ACCESS_KEY missing"))?;
+        Ok(Self { config, access_key, secret_key, client: rquest::Client::builder().build()? })
+    }
+
+    /// Uploads every existing file under `<prefix>/<session_id>/<file name>`; returns the keys.
+    pub async fn upload(&self, session_id: &str, files: &[String]) -> Result<Vec<String>> {
+        let mut keys = Vec::new();
+        for file in files {
+            let path = Path::new(file);
+            if !path.is_file() {
+                continue;
+            }
+            let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("artifact");
+            let key = format!("{}/{}/{}", self.config.prefix.trim_matches('/'), session_id, name);
+            let body = std::fs::read(path).context(format!("Failed to read {}", file))?;
+            self.put(&key, body).await.context(format!("Failed to upload {}", file))?;
+            keys.push(key);
+        }
+        Ok(keys)
+    }
+
+    async fn put(&self, key: &str, body: Vec<u8>) -> Result<()> {
+        let endpoint = rquest::Url::parse(&self.config.endpoint).context("Invalid upload.endpoint")?;
+        let host = match (endpoint.host_str(), endpoint.port()) {
+            (Some(h), Some(p)) => format!("{}:{}", h, p),
+            (Some(h), None) => h.to_string(),
+            (None, _) => return Err(anyhow!("upload.endpoint has no host")),
+        };
+        let canonical_uri = format!(
+            "/{}/{}",
+            uri_encode(&self.config.bucket, false),
+            uri_encode(key, true)
+        );
+        let (amz_date, date) = amz_timestamps(SystemTime::now());
+        let payload_hash = hex::encode(Sha256::digest(&body));
+
+        let canonical_request = format!(
+            "PUT\n{}\n\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\nhost;x-amz-content-sha256;x-amz-date\n{}",
+            canonical_uri, host, payload_hash, amz_date, payload_hash
+        );
+        let scope = format!("{}/{}/s3/aws4_request", date, self.config.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date,
+            scope,
+            hex::encode(Sha256::digest(canonical_request.as_bytes()))
+        );
+        let signing_key = [date.as_str(), self.config.region.as_str(), "s3", "aws4_request"]
+            .iter()
+            .fold(format!("AWS4{}", self.secret_key).into_bytes(), |key, part| hmac(&key, part.as_bytes()));
+        let signature = hex::encode(hmac(&signing_key, string_to_sign.as_bytes()));
+        let authorization = format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders=host;x-amz-content-sha256;x-amz-date, Signature={}",
+            self.access_key, scope, signature
+        );
+
+        let url = format!("{}{}", self.config.endpoint.trim_end_matches('/'), canonical_uri);
+        let resp = self
+            .client
+            .put(&url)
+            .header("x-amz-date", &amz_date)
+            .header("x-amz-content-sha256", &payload_hash)
+            .header("authorization", authorization)
+            .body(body)
+            .send()
+            .await?;
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let text = resp.text().await.unwrap_or_default();
+            return Err(anyhow!("S3 answered {}: {}", status, text.chars().take(300).collect::<String>()));
+        }
+        Ok(())
+    }
+}
+
+fn hmac(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// SigV4 URI encoding: unreserved characters stay, everything else is %XX (`/` kept in keys).
+fn uri_encode(value: &str, keep_slash: bool) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+            b'/' if keep_slash => "/".to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// (`YYYYMMDDTHHMMSSZ`, `YYYYMMDD`) in UTC.
+pub(crate) fn amz_timestamps(now: SystemTime) -> (String, String) {
+    UtcDateTime::from_unix(now.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()).compact()
+}
@@ -0,0 +1,197 @@
+use crate::engine::{Config, ResponseAnalyzer, TemplateEngine};
+use crate::origin::PathVerdicts;
+use crate::payloads::{PayloadConfig, PayloadManager};
+use crate::raw::{RawClient, RawTarget};
+use crate::tamper::{TamperEngine, TamperType};
+use anyhow::Result;
+
+// Raw-socket request variants: the same logical request serialized in unusual but
+// valid ways (header casing, obs-fold, ordering, whitespace) that rquest would
+// normalize away. Each variant gets its own verdict tally.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RawVariant {
+    Canonical,
+    LowercaseHeaders,
+    UppercaseHeaders,
+    MixedCaseHeaders,
+    HostLast,
+    FoldedHeader,
+    TabSeparator,
+    NoSpaceSeparator,
+    AbsoluteForm,
+}
+
+impl RawVariant {
+    pub const ALL: [RawVariant; 9] = [
+        RawVariant::Canonical,
+        RawVariant::LowercaseHeaders,
+        RawVariant::UppercaseHeaders,
+        RawVariant::MixedCaseHeaders,
+        RawVariant::HostLast,
+        RawVariant::FoldedHeader,
+        RawVariant::TabSeparator,
+        RawVariant::NoSpaceSeparator,
+        RawVariant::AbsoluteForm,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            RawVariant::Canonical => "canonical",
+            RawVariant::LowercaseHeaders => "lowercase-headers",
+            RawVariant::UppercaseHeaders => "uppercase-headers",
+            RawVariant::MixedCaseHeaders => "mixed-case-headers",
+            RawVariant::HostLast => "host-last",
+            RawVariant::FoldedHeader => "folded-header",
+            RawVariant::TabSeparator => "tab-separator",
+            RawVariant::NoSpaceSeparator => "no-space-separator",
+            RawVariant::AbsoluteForm => "absolute-form",
+        }
+    }
+
+    fn header_name(&self, name: &str) -> String {
+        match self {
+            RawVariant::LowercaseHeaders => name.to_lowercase(),
+            RawVariant::UppercaseHeaders => name.to_uppercase(),
+            RawVariant::MixedCaseHeaders => name
+                .chars()
+                .enumerate()
+                .map(|(i, c)| if i % 2 == 0 { c.to_ascii_uppercase() } else { c.to_ascii_lowercase() })
+                .collect(),
+            _ => name.to_string(),
+        }
+    }
+
+    fn separator(&self) -> &'static str {
+        match self {
+            RawVariant::TabSeparator => ":\t",
+            RawVariant::NoSpaceSeparator => ":",
+            _ => ": ",
+        }
+    }
+
+    /// Serializes one request; `headers` excludes Host, which is placed per variant.
+    pub fn render(&self, method: &str, url: &str, target: &RawTarget, headers: &[(String, String)], body: Option<&str>) -> Vec<u8> {
+        let request_target = match self {
+            RawVariant::AbsoluteForm => url.to_string(),
+            _ => target.path.clone(),
+        };
+        let mut out = format!("{} {} HTTP/1.1\r\n", method, request_target);
+        let sep = self.separator();
+        let host_line = format!("{}{}{}\r\n", self.header_name("Host"), sep, target.host_header());
+
+        if *self != RawVariant::HostLast {
+            out.push_str(&host_line);
+        }
+        for (name, value) in headers {
+            if *self == RawVariant::FoldedHeader && name.eq_ignore_ascii_case("User-Agent") {
+                // obs-fold: continuation line starting with whitespace
+                match value.split_once(' ') {
+                    Some((head, tail)) => out.push_str(&format!("{}{}{}\r\n {}\r\n", name, sep, head, tail)),
+                    None => out.push_str(&format!("{}{}{}\r\n", name, sep, value)),
+                }
+            } else {
+                out.push_str(&format!("{}{}{}\r\n", self.header_name(name), sep, value));
+            }
+        }
+        if let Some(body) = body {
+            out.push_str(&format!("{}{}{}\r\n", self.header_name("Content-Length"), sep, body.len()));
+        }
+        out.push_str(&format!("{}{}close\r\n", self.header_name("Connection"), sep));
+        if *self == RawVariant::HostLast {
+            out.push_str(&host_line);
+        }
+        out.push_str("\r\n");
+        if let Some(body) = body {
+            out.push_str(body);
+        }
+        out.into_bytes()
+    }
+}
+
+impl std::fmt::Display for RawVariant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct VariantResult {
+    pub variant: RawVariant,
+    pub verdicts: PathVerdicts,
+}
+
+pub struct RawVariantScanner {
+    config: Config,
+    client: RawClient,
+}
+
+impl RawVariantScanner {
+    pub fn new(config: Config) -> Self {
+        Self { config, client: RawClient::default() }
+    }
+
+    fn headers(&self, payload: &str) -> Vec<(String, String)> {
+        let mut headers = vec![
+            (
+                "User-Agent".to_string(),
+                "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/130.0.0.0 Safari/537.36".to_string(),
+            ),
+            (
+                "Accept".to_string(),
+                "text/html,application/xhtml+xml,application/xml;q=0.9,image/avif,image/webp,*/*;q=0.8".to_string(),
+            ),
+            ("Accept-Language".to_string(), "en-US,en;q=0.9".to_string()),
+        ];
+        for h in &self.config.general.headers {
+            if let Some((k, v)) = h.split_once(':') {
+                headers.push((k.trim().to_string(), TemplateEngine::inject(v.trim(), payload)));
+            }
+        }
+        headers
+    }
+
+    /// Sends `samples` requests per variant and tallies the analyzer verdicts.
+    pub async fn run(&self, samples: usize) -> Result<Vec<VariantResult>> {
+        let payload_conf = self.config.general.payload_file.as_ref().map(|p| PayloadConfig {
+            file_path: p.clone(),
+        });
+        let payloads = PayloadManager::new(payload_conf)?;
+        let tampers: Vec<TamperType> = self
+            .config
+            .general
+            .tampers
+            .iter()
+            .map(|t| t.parse().unwrap_or(TamperType::None))
+            .collect();
+        let method = self.config.general.method.to_uppercase();
+
+        let mut results = Vec::new();
+        for variant in RawVariant::ALL {
+            let mut verdicts = PathVerdicts::default();
+            for raw_payload in payloads.get_payloads().iter().cycle().take(samples.max(1)) {
+                let payload = TamperEngine::apply(raw_payload, &tampers);
+                let url = TemplateEngine::inject(&self.config.general.target_url, &payload);
+                let target = RawTarget::parse(&url)?;
+                let body = self
+                    .config
+                    .general
+                    .raw_body
+                    .as_deref()
+                    .map(|b| TemplateEngine::inject(b, &payload));
+                let request = variant.render(&method, &url, &target, &self.headers(&payload), body.as_deref());
+
+                let verdict = match self.client.send(&target, &request).await {
+                    Ok(resp) => match resp.status {
+                        Some(status) => Some(ResponseAnalyzer::analyze(status, &String::from_utf8_lossy(&resp.body), None)),
+                        None => None,
+                    },
+                    Err(_) => None,
+                };
+                verdicts.record(verdict.as_ref());
+            }
+            results.push(VariantResult { variant, verdicts });
+        }
+        Ok(results)
+    }
+}
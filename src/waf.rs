@@ -1,5 +1,14 @@
 use anyhow::Result;
 use rquest::Client;
+use rquest::header::{HeaderMap, SET_COOKIE};
+
+// Protection-vendor fingerprinting. Every response is attributed to the WAF or bot manager
+// in front of the target, so block rates can be broken down by vendor and `vendor_policies`
+// can react per vendor. Bot managers usually sit behind a CDN that announces itself too
+// (DataDome behind Cloudflare), so their own headers and cookies are checked first; the
+// CDNs and WAFs follow in their long-standing order, so existing targets keep their vendor.
+// Headers and cookies count before body keywords, which also appear in unrelated pages.
+// Header names match exactly, except for the families a vendor varies the suffix of.
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum WafType {
@@ -9,6 +18,9 @@ pub enum WafType {
     Imperva,
     Azure,
     BunkerWeb,
+    DataDome,
+    PerimeterX,
+    Kasada,
     Unknown,
     #[allow(dead_code)]
     None,
@@ -22,7 +34,12 @@ impl std::fmt::Display for WafType {
 
 pub struct WafSignature {
     pub name: WafType,
+    /// Response header names.
     pub headers: Vec<&'static str>,
+    /// Response header name prefixes, for vendors that send a family of headers.
+    pub header_prefixes: Vec<&'static str>,
+    /// Name prefixes of cookies the response sets.
+    pub cookies: Vec<&'static str>,
     pub body_keywords: Vec<&'static str>,
 }
 
@@ -34,40 +51,102 @@ impl WafDetector {
     pub fn new() -> Self {
         Self {
             signatures: vec![
+                WafSignature {
+                    name: WafType::DataDome,
+                    headers: vec!["x-dd-b"],
+                    header_prefixes: vec!["x-datadome"],
+                    cookies: vec!["datadome"],
+                    body_keywords: vec!["captcha-delivery.com", "datadome"],
+                },
+                WafSignature {
+                    name: WafType::PerimeterX,
+                    headers: vec![],
+                    header_prefixes: vec!["x-px-"],
+                    cookies: vec!["_px"],
+                    body_keywords: vec!["perimeterx", "px-captcha", "_pxappid"],
+                },
+                WafSignature {
+                    name: WafType::Kasada,
+                    headers: vec![],
+                    header_prefixes: vec!["x-kpsdk"],
+                    cookies: vec!["kp_uidz"],
+                    body_keywords: vec!["ips.js?kp_", "kpsdk"],
+                },
                 WafSignature {
                     name: WafType::Cloudflare,
-                    headers: vec!["cf-ray", "__cfduid", "cf-cache-status"],
+                    headers: vec!["cf-ray", "cf-mitigated", "cf-cache-status"],
+                    header_prefixes: vec![],
+                    cookies: vec!["__cf_bm", "cf_clearance", "__cfduid", "__cflb"],
                     body_keywords: vec!["cloudflare"],
                 },
                 WafSignature {
                     name: WafType::CloudFront,
                     headers: vec!["x-amz-cf-id", "via"],
+                    header_prefixes: vec![],
+                    cookies: vec![],
                     body_keywords: vec!["cloudfront"],
                 },
                 WafSignature {
                     name: WafType::Akamai,
-                    headers: vec!["x-akamai-transformed", "akamai-origin-hop"],
+                    headers: vec!["x-akamai-transformed", "akamai-origin-hop", "akamai-grn"],
+                    header_prefixes: vec![],
+                    cookies: vec!["_abck", "bm_sz", "ak_bmsc", "bm_sv"],
                     body_keywords: vec!["akamai"],
                 },
                 WafSignature {
                     name: WafType::Imperva,
-                    headers: vec!["x-cdn", "incap-ses"],
+                    headers: vec!["x-cdn", "x-iinfo", "incap-ses"],
+                    header_prefixes: vec![],
+                    cookies: vec!["incap_ses_", "visid_incap_", "nlbi_", "reese84"],
                     body_keywords: vec!["incapsula"],
                 },
                 WafSignature {
                     name: WafType::Azure,
                     headers: vec!["x-azure-ref", "x-fd-ref"],
+                    header_prefixes: vec![],
+                    cookies: vec![],
                     body_keywords: vec!["azure"],
                 },
-                 WafSignature {
+                WafSignature {
                     name: WafType::BunkerWeb,
                     headers: vec!["x-bunkerweb"],
+                    header_prefixes: vec![],
+                    cookies: vec![],
                     body_keywords: vec!["bunkerweb", "bw_id"],
                 },
             ],
         }
     }
 
+    /// Identifies the vendor from an already-received response (headers + body).
+    pub fn identify(&self, headers: &HeaderMap, body: &str) -> WafType {
+        // Header names come lowercase from the HeaderMap; cookie names are compared lowercased
+        let cookies: Vec<String> = headers
+            .get_all(SET_COOKIE)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .filter_map(|v| v.split('=').next())
+            .map(|name| name.trim().to_lowercase())
+            .collect();
+        for sig in &self.signatures {
+            if sig.headers.iter().any(|h| headers.contains_key(*h))
+                || sig.header_prefixes.iter().any(|p| headers.keys().any(|k| k.as_str().starts_with(p)))
+            {
+                return sig.name.clone();
+            }
+            if sig.cookies.iter().any(|c| cookies.iter().any(|name| name.starts_with(c))) {
+                return sig.name.clone();
+            }
+        }
+        let body = body.to_lowercase();
+        for sig in &self.signatures {
+            if sig.body_keywords.iter().any(|k| body.contains(k)) {
+                return sig.name.clone();
+            }
+        }
+        WafType::Unknown
+    }
+
     pub async fn detect(&self, url: &str) -> Result<WafType> {
         let client = Client::builder()
             .build()?;
@@ -79,21 +158,11 @@ impl WafDetector {
             Ok(resp) => {
                 let headers = resp.headers().clone(); // Clone headers to keep ownership
                 let body_bytes = resp.bytes().await.unwrap_or_default();
-                let body = String::from_utf8_lossy(&body_bytes).to_lowercase();
+                let body = String::from_utf8_lossy(&body_bytes);
                 
-                for sig in &self.signatures {
-                    // Check Headers
-                    for h in &sig.headers {
-                        if headers.contains_key(*h) {
-                            return Ok(sig.name.clone());
-                        }
-                    }
-                    // Check Body
-                    for k in &sig.body_keywords {
-                        if body.contains(k) {
-                            return Ok(sig.name.clone());
-                        }
-                    }
+                let waf_type = self.identify(&headers, &body);
+                if waf_type != WafType::Unknown {
+                    return Ok(waf_type);
                 }
             }
             Err(_) => return Ok(WafType::Unknown),
@@ -106,3 +175,51 @@ impl WafDetector {
         Ok(WafType::Unknown)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{WafDetector, WafType};
+    use rquest::header::{HeaderMap, HeaderName, HeaderValue};
+
+    fn identify(headers: &[(&'static str, &'static str)], body: &str) -> WafType {
+        let mut map = HeaderMap::new();
+        for (name, value) in headers {
+            map.append(HeaderName::from_static(name), HeaderValue::from_static(value));
+        }
+        WafDetector::new().identify(&map, body)
+    }
+
+    #[test]
+    fn old_header_names_match_exactly() {
+        assert_eq!(identify(&[("via", "1.1 abc.cloudfront.net (CloudFront)")], ""), WafType::CloudFront);
+        assert_eq!(identify(&[("via-proxy", "1")], ""), WafType::Unknown);
+        assert_eq!(identify(&[("x-cdn", "Imperva")], ""), WafType::Imperva);
+        assert_eq!(identify(&[("x-cdn-provider", "fastly")], ""), WafType::Unknown);
+    }
+
+    #[test]
+    fn bot_manager_header_families_match_by_prefix() {
+        assert_eq!(identify(&[("x-datadome-cid", "abc")], ""), WafType::DataDome);
+        assert_eq!(identify(&[("x-px-block", "1")], ""), WafType::PerimeterX);
+        assert_eq!(identify(&[("x-kpsdk-ct", "abc")], ""), WafType::Kasada);
+    }
+
+    #[test]
+    fn cloudflare_ranks_above_other_cdns() {
+        assert_eq!(identify(&[("cf-ray", "8a1b2c3d4e5f-AMS"), ("x-cdn", "Imperva")], ""), WafType::Cloudflare);
+        assert_eq!(identify(&[("akamai-grn", "0.1"), ("cf-cache-status", "HIT")], ""), WafType::Cloudflare);
+    }
+
+    #[test]
+    fn bot_manager_behind_cloudflare_is_named() {
+        let headers = [("cf-ray", "8a1b2c3d4e5f-AMS"), ("set-cookie", "datadome=abc; Path=/")];
+        assert_eq!(identify(&headers, ""), WafType::DataDome);
+    }
+
+    #[test]
+    fn body_keywords_only_count_without_headers_or_cookies() {
+        assert_eq!(identify(&[], "<p>Request unsuccessful. Incapsula incident ID</p>"), WafType::Imperva);
+        assert_eq!(identify(&[("cf-ray", "8a1b2c3d4e5f-AMS")], "powered by incapsula"), WafType::Cloudflare);
+        assert_eq!(identify(&[], "<h1>Hello</h1>"), WafType::Unknown);
+    }
+}